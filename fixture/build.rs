@@ -0,0 +1,222 @@
+//! Generates the calling-convention torture functions `src/abi.rs` includes: random signatures
+//! mixing u128 and i128 with narrower integers, each forwarding its arguments to a second function
+//! that takes them in a shuffled order. The seed is fixed so every build probes the same
+//! signatures; set `ABI_TORTURE_SEED` to try others.
+//!
+//! Signatures stay within the five BPF argument registers, a 128-bit argument taking a pair: the
+//! backend rejects calls that would pass arguments on the stack.
+//!
+//! Also records which toolchain built the program, from the variables `cargo xtask build` sets,
+//! as the `U128_TOOLCHAIN_INFO` string `src/build_info.rs` embeds, and computes the self-test
+//! vectors `src/self_test.rs` includes with the build host's own u128 arithmetic.
+
+use std::env;
+use std::fmt::Write;
+use std::fs;
+use std::path::PathBuf;
+
+const DEFAULT_SEED: u64 = 0x0123_4567_89ab_cdef;
+const FUNCTIONS: usize = 48;
+const ARGUMENT_REGISTERS: usize = 5;
+const WIDE_TYPES: [&str; 2] = ["u128", "i128"];
+const NARROW_TYPES: [&str; 8] = ["u8", "u16", "u32", "u64", "i8", "i32", "i64", "bool"];
+const RETURN_TYPES: [&str; 2] = ["u128", "i128"];
+/// Operands of the self-test vectors, each paired with another of them per operation
+const SELF_TEST_VALUES: [u128; 8] = [
+    0,
+    1,
+    3,
+    u64::MAX as u128,
+    1 << 64,
+    1 << 127,
+    u128::MAX,
+    0x0123_4567_89ab_cdef_fedc_ba98_7654_3210,
+];
+/// The host's result of a self-test operation, `None` for a division by zero
+type Reference = fn(u128, u128) -> Option<u128>;
+/// The self-test operations by their `self_test::Op` variant
+const SELF_TEST_OPS: [(&str, Reference); 7] = [
+    ("Add", |a, b| Some(a.wrapping_add(b))),
+    ("Sub", |a, b| Some(a.wrapping_sub(b))),
+    ("Mul", |a, b| Some(a.wrapping_mul(b))),
+    ("Div", u128::checked_div),
+    ("Rem", u128::checked_rem),
+    ("Shl", |a, b| Some(a << (b % 128))),
+    ("Shr", |a, b| Some(a >> (b % 128))),
+];
+/// The variables behind `U128_TOOLCHAIN_INFO`, by the key each is recorded under
+const TOOLCHAIN_ENV: [(&str, &str); 3] = [
+    ("llvm", "U128_LLVM_COMMIT"),
+    ("linker", "U128_LINKER_COMMIT"),
+    ("built", "SOURCE_DATE_EPOCH"),
+];
+
+/// splitmix64, so the generator needs no dependencies
+struct Rng(u64);
+
+impl Rng {
+    fn next(&mut self) -> u64 {
+        self.0 = self.0.wrapping_add(0x9e37_79b9_7f4a_7c15);
+        let mut z = self.0;
+        z = (z ^ (z >> 30)).wrapping_mul(0xbf58_476d_1ce4_e5b9);
+        z = (z ^ (z >> 27)).wrapping_mul(0x94d0_49bb_1331_11eb);
+        z ^ (z >> 31)
+    }
+
+    fn below(&mut self, n: usize) -> usize {
+        (self.next() % n as u64) as usize
+    }
+
+    /// Fisher-Yates
+    fn shuffle<T>(&mut self, items: &mut [T]) {
+        for i in (1..items.len()).rev() {
+            items.swap(i, self.below(i + 1));
+        }
+    }
+}
+
+fn main() {
+    println!("cargo:rerun-if-changed=build.rs");
+    println!("cargo:rerun-if-env-changed=ABI_TORTURE_SEED");
+    let seed = match env::var("ABI_TORTURE_SEED") {
+        Ok(seed) => parse_seed(&seed).unwrap_or_else(|| panic!("ABI_TORTURE_SEED is not a u64: {seed}")),
+        Err(_) => DEFAULT_SEED,
+    };
+    let mut rng = Rng(seed);
+
+    let mut out = format!("// Generated by build.rs from seed {seed:#x}\n\n");
+    writeln!(out, "pub const FUNCTIONS: usize = {FUNCTIONS};").unwrap();
+    let mut through = String::new();
+    let mut direct = String::new();
+    for index in 0..FUNCTIONS {
+        // One 128-bit argument at least, then up to three more of any width that still fit
+        let mut types = vec![WIDE_TYPES[rng.below(WIDE_TYPES.len())]];
+        let mut registers = 2;
+        for _ in 0..rng.below(4) {
+            let wide = registers + 2 <= ARGUMENT_REGISTERS && rng.below(3) == 0;
+            let ty = if wide {
+                WIDE_TYPES[rng.below(WIDE_TYPES.len())]
+            } else if registers < ARGUMENT_REGISTERS {
+                NARROW_TYPES[rng.below(NARROW_TYPES.len())]
+            } else {
+                break;
+            };
+            registers += if wide { 2 } else { 1 };
+            types.push(ty);
+        }
+        rng.shuffle(&mut types);
+        let arity = types.len();
+        let ret = RETURN_TYPES[rng.below(RETURN_TYPES.len())];
+        // So the forwarded arguments land in different registers
+        let mut order: Vec<usize> = (0..arity).collect();
+        rng.shuffle(&mut order);
+
+        let params = |order: &mut dyn Iterator<Item = usize>| {
+            order.map(|i| format!("a{i}: {}", types[i])).collect::<Vec<_>>().join(", ")
+        };
+        let signature = |order: &mut dyn Iterator<Item = usize>, ret: &str| {
+            let types: Vec<_> = order.map(|i| types[i]).collect();
+            format!("fn({}) -> {ret}", types.join(", "))
+        };
+        let args = |order: &mut dyn Iterator<Item = usize>| {
+            order.map(|i| format!("a{i}")).collect::<Vec<_>>().join(", ")
+        };
+        let widened = (0..arity).map(|i| format!("a{i}.widen()")).collect::<Vec<_>>().join(", ");
+        // Between u128 and the return type, which only needs a cast if it's i128
+        let cast = |to: &str| if ret == "u128" { String::new() } else { format!(" as {to}") };
+        writeln!(
+            out,
+            "\n#[inline(never)]\nfn abi_{index}({}) -> {ret} {{\n    \
+             opaque(abi_{index}_forward as {})({}){}\n}}\n",
+            params(&mut (0..arity)),
+            signature(&mut order.iter().copied(), "u128"),
+            args(&mut order.iter().copied()),
+            cast(ret),
+        )
+        .unwrap();
+        writeln!(
+            out,
+            "#[inline(never)]\nfn abi_{index}_forward({}) -> u128 {{\n    digest(&[{widened}])\n}}",
+            params(&mut order.iter().copied()),
+        )
+        .unwrap();
+
+        let position = |i: usize| index * ARGUMENT_REGISTERS + i;
+        let from_seed = (0..arity)
+            .map(|i| format!("Arg::from_seed(seed, {})", position(i)))
+            .collect::<Vec<_>>()
+            .join(", ");
+        writeln!(
+            through,
+            "        {index} => opaque(abi_{index} as {})({from_seed}){},",
+            signature(&mut (0..arity), ret),
+            cast("u128"),
+        )
+        .unwrap();
+        let values = (0..arity)
+            .map(|i| format!("<{} as Arg>::from_seed(seed, {}).widen()", types[i], position(i)))
+            .collect::<Vec<_>>()
+            .join(", ");
+        writeln!(direct, "        {index} => digest(&[{values}]),").unwrap();
+    }
+    writeln!(
+        out,
+        "\n/// The digest function `index` returns for arguments derived from `seed`, through the \
+         generated calls\npub fn through(index: usize, seed: u128) -> Option<u128> {{\n    \
+         Some(match index {{\n{through}        _ => return None,\n    }})\n}}"
+    )
+    .unwrap();
+    writeln!(
+        out,
+        "\n/// The same digest computed from the arguments directly\npub fn direct(index: usize, \
+         seed: u128) -> Option<u128> {{\n    Some(match index {{\n{direct}        _ => return None,\n    \
+         }})\n}}"
+    )
+    .unwrap();
+
+    let path = PathBuf::from(env::var_os("OUT_DIR").unwrap()).join("abi_torture.rs");
+    fs::write(path, out).unwrap();
+
+    let mut toolchain_info = Vec::new();
+    for (key, name) in TOOLCHAIN_ENV {
+        println!("cargo:rerun-if-env-changed={name}");
+        let value = env::var(name).unwrap_or_else(|_| "unknown".to_string());
+        toolchain_info.push(format!("{key}={value}"));
+    }
+    println!("cargo:rustc-env=U128_TOOLCHAIN_INFO={}", toolchain_info.join(" "));
+
+    write_self_test_vectors();
+}
+
+/// Write `self_test.rs`: every operation on each value paired with the one three places further
+/// on, skipping divisions by zero
+fn write_self_test_vectors() {
+    let mut vectors = String::new();
+    let mut count = 0;
+    for (op, reference) in SELF_TEST_OPS {
+        for (index, &a) in SELF_TEST_VALUES.iter().enumerate() {
+            let b = SELF_TEST_VALUES[(3 * index + 1) % SELF_TEST_VALUES.len()];
+            let Some(expected) = reference(a, b) else {
+                continue;
+            };
+            writeln!(
+                vectors,
+                "    Vector {{ op: Op::{op}, a: {a:#x}, b: {b:#x}, expected: {expected:#x} }},"
+            )
+            .unwrap();
+            count += 1;
+        }
+    }
+    let out = format!(
+        "// Generated by build.rs\n\npub static VECTORS: [Vector; {count}] = [\n{vectors}];\n"
+    );
+    let path = PathBuf::from(env::var_os("OUT_DIR").unwrap()).join("self_test.rs");
+    fs::write(path, out).unwrap();
+}
+
+fn parse_seed(seed: &str) -> Option<u64> {
+    match seed.strip_prefix("0x") {
+        Some(hex) => u64::from_str_radix(hex, 16).ok(),
+        None => seed.parse().ok(),
+    }
+}
@@ -0,0 +1,104 @@
+//! Calling-convention torture functions, generated by `build.rs`: dozens of signatures mixing
+//! u128 and i128 with narrower integers and bools, filling up to all five argument registers with
+//! the 128-bit values in register pairs at any position. Each function forwards its arguments,
+//! shuffled, to a second one that digests them, and [`direct`] computes the same digest without
+//! any call, so a value the modified calling convention loses or splits wrongly shows up as a
+//! mismatch.
+
+#[allow(clippy::too_many_arguments)]
+mod generated {
+    use super::{digest, opaque, Arg};
+
+    include!(concat!(env!("OUT_DIR"), "/abi_torture.rs"));
+}
+
+pub use generated::{direct, through, FUNCTIONS};
+
+/// An argument type of the generated functions
+trait Arg: Copy {
+    /// A value derived from `seed` for the argument at `position` across all the functions
+    fn from_seed(seed: u128, position: usize) -> Self;
+    /// The value zero- or sign-extended to 128 bits
+    fn widen(self) -> u128;
+}
+
+macro_rules! impl_arg_for_integers {
+    ($($integer:ty),*) => {$(
+        impl Arg for $integer {
+            fn from_seed(seed: u128, position: usize) -> Self {
+                mix(seed, position) as Self
+            }
+
+            fn widen(self) -> u128 {
+                self as i128 as u128
+            }
+        }
+    )*};
+}
+
+// Through i128 first, so the signed types sign-extend and the unsigned ones zero-extend
+impl_arg_for_integers!(u8, u16, u32, u64, i8, i32, i64, u128, i128);
+
+impl Arg for bool {
+    fn from_seed(seed: u128, position: usize) -> Self {
+        mix(seed, position) & 1 == 1
+    }
+
+    fn widen(self) -> u128 {
+        self.into()
+    }
+}
+
+/// A different value for every position, with all 128 bits depending on the seed
+fn mix(seed: u128, position: usize) -> u128 {
+    let position = position as u128;
+    (seed ^ position.wrapping_mul(0x9e37_79b9_7f4a_7c15_f39c_c060_5ced_c835)).rotate_left(position as u32 % 128)
+}
+
+/// Order-sensitive, and every bit of every value reaches the result
+fn digest(values: &[u128]) -> u128 {
+    // Odd, so the multiply is a bijection and loses nothing
+    const MULTIPLIER: u128 = 0x2545_f491_4f6c_dd1d_0000_0000_0000_0001;
+    values.iter().enumerate().fold(0, |digest, (position, &value)| {
+        (digest.rotate_left(29) ^ value).wrapping_mul(MULTIPLIER) ^ position as u128
+    })
+}
+
+/// `f` read back through a volatile load, so LTO can neither inline the call nor rewrite the
+/// callee's signature
+fn opaque<F: Copy>(f: F) -> F {
+    unsafe { core::ptr::read_volatile(&f) }
+}
+
+/// The digests of every generated function for `seed` XORed together, or the index of the first
+/// function whose call returned a different digest than computing it directly
+pub fn check(seed: u128) -> Result<u128, usize> {
+    (0..FUNCTIONS).try_fold(0, |digests, index| {
+        let expected = direct(index, seed).ok_or(index)?;
+        if through(index, seed) != Some(expected) {
+            return Err(index);
+        }
+        Ok(digests ^ expected)
+    })
+}
+
+/// The XOR of the direct digests, what [`check`] returns when every call agrees
+pub fn expected(seed: u128) -> u128 {
+    (0..FUNCTIONS).filter_map(|index| direct(index, seed)).fold(0, |digests, digest| digests ^ digest)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn every_value_survives() {
+        for seed in [0, 1, u64::MAX as u128, 1 << 127, 0x0123456789abcdef_fedcba9876543210, u128::MAX] {
+            assert_eq!(check(seed), Ok(expected(seed)), "{seed:#x}");
+        }
+        assert_eq!(through(FUNCTIONS, 0), None);
+        // A digest that ignored an argument or its position would let a lost value through
+        assert_ne!(digest(&[1, 2]), digest(&[2, 1]));
+        assert_ne!(digest(&[1 << 127]), digest(&[0]));
+    }
+}
@@ -0,0 +1,137 @@
+//! Just enough of the Borsh encoding for structs of integers: each field in declaration order,
+//! integers little-endian at their full width, with no padding or alignment. u128 and i128
+//! fields are 16 bytes at whatever offset the fields before them leave, which is how most
+//! existing programs store them.
+
+/// Encodes into a byte buffer, failing once the buffer is full
+pub struct Writer<'a> {
+    buffer: &'a mut [u8],
+    written: usize,
+}
+
+impl<'a> Writer<'a> {
+    pub fn new(buffer: &'a mut [u8]) -> Self {
+        Self { buffer, written: 0 }
+    }
+
+    pub fn write(&mut self, bytes: &[u8]) -> Option<()> {
+        let end = self.written.checked_add(bytes.len())?;
+        self.buffer.get_mut(self.written..end)?.copy_from_slice(bytes);
+        self.written = end;
+        Some(())
+    }
+
+    pub fn written(&self) -> usize {
+        self.written
+    }
+}
+
+/// Decodes from a byte buffer, failing once it runs out
+pub struct Reader<'a> {
+    buffer: &'a [u8],
+}
+
+impl<'a> Reader<'a> {
+    pub fn new(buffer: &'a [u8]) -> Self {
+        Self { buffer }
+    }
+
+    pub fn read<const N: usize>(&mut self) -> Option<[u8; N]> {
+        let (bytes, rest) = self.buffer.split_first_chunk()?;
+        self.buffer = rest;
+        Some(*bytes)
+    }
+}
+
+/// A value with a Borsh encoding
+pub trait Borsh: Sized {
+    fn encode(&self, writer: &mut Writer) -> Option<()>;
+    fn decode(reader: &mut Reader) -> Option<Self>;
+}
+
+macro_rules! impl_borsh_for_integers {
+    ($($integer:ty),*) => {$(
+        impl Borsh for $integer {
+            fn encode(&self, writer: &mut Writer) -> Option<()> {
+                writer.write(&self.to_le_bytes())
+            }
+
+            fn decode(reader: &mut Reader) -> Option<Self> {
+                reader.read().map(Self::from_le_bytes)
+            }
+        }
+    )*};
+}
+
+impl_borsh_for_integers!(u8, u16, u32, u64, u128, i8, i16, i32, i64, i128);
+
+/// Encode `value` at the start of `buffer`, returning the bytes written
+pub fn to_slice<T: Borsh>(value: &T, buffer: &mut [u8]) -> Option<usize> {
+    let mut writer = Writer::new(buffer);
+    value.encode(&mut writer)?;
+    Some(writer.written())
+}
+
+/// Decode a `T` from the start of `buffer`, ignoring any bytes after it, as for account data
+/// with room to spare
+pub fn from_slice<T: Borsh>(buffer: &[u8]) -> Option<T> {
+    T::decode(&mut Reader::new(buffer))
+}
+
+/// A lending position as a program might keep it in an account: the leading byte leaves both
+/// u128 fields at odd offsets
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct Position {
+    pub bump: u8,
+    pub collateral: u128,
+    /// Negative once the position is overpaid
+    pub debt: i128,
+    pub updated_slot: u64,
+}
+
+impl Position {
+    /// Length of the encoding
+    pub const LEN: usize = 1 + 16 + 16 + 8;
+}
+
+impl Borsh for Position {
+    fn encode(&self, writer: &mut Writer) -> Option<()> {
+        self.bump.encode(writer)?;
+        self.collateral.encode(writer)?;
+        self.debt.encode(writer)?;
+        self.updated_slot.encode(writer)
+    }
+
+    fn decode(reader: &mut Reader) -> Option<Self> {
+        Some(Self {
+            bump: Borsh::decode(reader)?,
+            collateral: Borsh::decode(reader)?,
+            debt: Borsh::decode(reader)?,
+            updated_slot: Borsh::decode(reader)?,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn position_round_trips() {
+        let position = Position {
+            bump: 0xfe,
+            collateral: 0x0f0e0d0c0b0a09080706050403020100,
+            debt: -2,
+            updated_slot: 7,
+        };
+        let mut buffer = [0u8; Position::LEN + 1];
+        assert_eq!(to_slice(&position, &mut buffer), Some(Position::LEN));
+        assert_eq!(buffer[0], 0xfe);
+        assert_eq!(buffer[1..17], core::array::from_fn::<u8, 16, _>(|i| i as u8));
+        assert_eq!(buffer[17..33], (-2i128).to_le_bytes());
+        assert_eq!(buffer[33..41], 7u64.to_le_bytes());
+        assert_eq!(from_slice(&buffer), Some(position));
+        assert_eq!(to_slice(&position, &mut buffer[..Position::LEN - 1]), None);
+        assert_eq!(from_slice::<Position>(&buffer[..Position::LEN - 1]), None);
+    }
+}
@@ -0,0 +1,32 @@
+//! Which toolchain built the program, embedded in .rodata at build time and logged by
+//! [`ToolchainInfo`](crate::instruction::FixtureInstruction::ToolchainInfo), so a captured log
+//! says which LLVM and sbpf-linker produced the code that wrote it.
+
+/// Starts the embedded record, to find it in the ELF without running the program
+pub const MARKER: &str = "u128-fixture toolchain: ";
+
+/// The record as embedded: the marker, then `llvm=<commit> linker=<commit> built=<unix seconds>`
+/// from the variables `cargo xtask build` sets, `unknown` for any a plain cargo build leaves
+/// unset, then a NUL ending it for readers of the ELF. The timestamp is `SOURCE_DATE_EPOCH`,
+/// which xtask sets to the commit time of the checkout unless it is already set, so rebuilding a
+/// commit still reproduces the program byte for byte.
+const RECORD: &str = concat!("u128-fixture toolchain: ", env!("U128_TOOLCHAIN_INFO"), "\0");
+
+/// The record without its NUL, as the program logs it
+pub fn toolchain_info() -> &'static str {
+    RECORD.strip_suffix('\0').unwrap_or(RECORD)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn record_starts_with_the_marker() {
+        let info = toolchain_info();
+        assert!(info.starts_with(MARKER), "{info}");
+        let fields: Vec<_> = info[MARKER.len()..].split(' ').map(|field| field.split_once('=')).collect();
+        let keys: Vec<_> = fields.iter().map(|field| field.map(|(key, _)| key)).collect();
+        assert_eq!(keys, [Some("llvm"), Some("linker"), Some("built")]);
+    }
+}
@@ -0,0 +1,41 @@
+//! Interest-style u128 arithmetic on the Clock sysvar, shared by the program and the hosts that
+//! check its return data against the clock they gave it.
+
+use crate::error::FixtureError;
+
+/// Fixed-point scale of an interest rate: `RATE_SCALE` is 100% per period
+pub const RATE_SCALE: u128 = 1_000_000_000_000_000_000;
+
+/// The Clock sysvar, laid out as `sol_get_clock_sysvar` writes it
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+#[repr(C)]
+pub struct Clock {
+    pub slot: u64,
+    pub epoch_start_timestamp: i64,
+    pub epoch: u64,
+    pub leader_schedule_epoch: u64,
+    pub unix_timestamp: i64,
+}
+
+impl Clock {
+    /// Seconds since the current epoch started, or zero if the timestamps run backwards
+    pub fn seconds_into_epoch(&self) -> u64 {
+        self.unix_timestamp.saturating_sub(self.epoch_start_timestamp).max(0) as u64
+    }
+}
+
+/// Simple interest on `principal` at `rate` (scaled by [`RATE_SCALE`]) over `periods`, or
+/// [`FixtureError::ArithmeticOverflow`] if the intermediate product overflows
+pub fn interest(principal: u128, rate: u128, periods: u64) -> Result<u128, FixtureError> {
+    let product = principal.checked_mul(rate).and_then(|product| product.checked_mul(u128::from(periods)));
+    Ok(product.ok_or(FixtureError::ArithmeticOverflow)? / RATE_SCALE)
+}
+
+/// The interest a [`ClockInterest`](crate::instruction::FixtureInstruction::ClockInterest)
+/// instruction returns for `clock`: per second since the epoch started, then per slot
+pub fn accrued(principal: u128, rate: u128, clock: &Clock) -> Result<[u128; 2], FixtureError> {
+    Ok([
+        interest(principal, rate, clock.seconds_into_epoch())?,
+        interest(principal, rate, clock.slot)?,
+    ])
+}
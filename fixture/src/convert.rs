@@ -0,0 +1,40 @@
+//! Narrowing u128 and i128 results back to 64 bits, failing with the program error the fixture
+//! returns for an out-of-range value instead of truncating.
+
+use crate::error::FixtureError;
+
+/// `value` as a u64, or [`FixtureError::ArithmeticOverflow`] if any of its high 64 bits is set
+pub fn try_into_u64(value: u128) -> Result<u64, FixtureError> {
+    if value >> 64 != 0 {
+        return Err(FixtureError::ArithmeticOverflow);
+    }
+    Ok(value as u64)
+}
+
+/// `value` as an i64, or [`FixtureError::ArithmeticOverflow`] if truncating it loses more than
+/// sign bits
+pub fn try_into_i64(value: i128) -> Result<i64, FixtureError> {
+    let narrowed = value as i64;
+    if i128::from(narrowed) != value {
+        return Err(FixtureError::ArithmeticOverflow);
+    }
+    Ok(narrowed)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn range_boundaries() {
+        for value in [0, 1, u64::MAX as u128, u64::MAX as u128 + 1, 1 << 127, u128::MAX] {
+            assert_eq!(try_into_u64(value).ok(), u64::try_from(value).ok(), "{value:#x}");
+        }
+        let edges = [i64::MIN as i128, i64::MAX as i128, 0];
+        let values = edges.into_iter().flat_map(|edge| [edge - 1, edge, edge + 1]);
+        for value in values.chain([i128::MIN, i128::MAX]) {
+            assert_eq!(try_into_i64(value).ok(), i64::try_from(value).ok(), "{value}");
+        }
+        assert_eq!(try_into_i64(i128::MIN), Err(FixtureError::ArithmeticOverflow));
+    }
+}
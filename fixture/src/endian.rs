@@ -0,0 +1,36 @@
+//! u128 decoding with the byte order spelled out: each helper assembles the value from single
+//! bytes with shifts instead of reinterpreting memory, so its result can't depend on how the
+//! target or the compiler lays out a 16-byte load.
+
+/// The first byte is the least significant
+pub fn u128_from_le(bytes: &[u8; 16]) -> u128 {
+    bytes.iter().rev().fold(0, |value, &byte| (value << 8) | u128::from(byte))
+}
+
+/// The first byte is the most significant
+pub fn u128_from_be(bytes: &[u8; 16]) -> u128 {
+    bytes.iter().fold(0, |value, &byte| (value << 8) | u128::from(byte))
+}
+
+/// The target's own byte order, little-endian on SBPF
+pub fn u128_from_ne(bytes: &[u8; 16]) -> u128 {
+    if cfg!(target_endian = "little") {
+        u128_from_le(bytes)
+    } else {
+        u128_from_be(bytes)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn matches_core() {
+        let bytes = core::array::from_fn(|i| (i as u8) << 4 | i as u8);
+        assert_eq!(u128_from_le(&bytes), u128::from_le_bytes(bytes));
+        assert_eq!(u128_from_be(&bytes), u128::from_be_bytes(bytes));
+        assert_eq!(u128_from_ne(&bytes), u128::from_ne_bytes(bytes));
+        assert_eq!(u128_from_le(&bytes), 0xffeeddccbbaa99887766554433221100);
+    }
+}
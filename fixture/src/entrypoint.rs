@@ -0,0 +1,458 @@
+//! The program itself: decode the instruction header and dispatch to the operation.
+
+use crate::abi;
+use crate::borsh::{self, Position};
+use crate::build_info;
+use crate::clock;
+use crate::endian;
+use crate::error::{return_code, FixtureError};
+use crate::execute;
+use crate::fold::fold;
+use crate::input::{read_u128, write_u128, Account, Input, InstructionData};
+use crate::instruction::{
+    opcode, Entry, EntryOp, Lowering, Reduction, Region, COPY_REGION_LEN, HEADER_LEN, MAX_COPY_VALUES,
+};
+use crate::interest;
+use crate::self_test;
+use crate::sqrt_price;
+use crate::state::{Oracle, PoolState, WorkQueue};
+use crate::syscalls;
+
+#[cfg(all(target_arch = "bpf", not(feature = "panic-log")))]
+#[panic_handler]
+fn panic(_: &core::panic::PanicInfo) -> ! {
+    unsafe { core::hint::unreachable_unchecked() }
+}
+
+/// Called by the loader with the serialized input region, whose layout the program trusts
+#[unsafe(no_mangle)]
+#[allow(clippy::not_unsafe_ptr_arg_deref)]
+pub fn entrypoint(input: *mut u8) -> u64 {
+    let input = unsafe { Input::deserialize(input) };
+    let result = match input.data.header_byte(0) {
+        Some(opcode::ENTRY_LIBCALL) => entry_libcall(&input.data),
+        Some(opcode::ENTRY_SOFT) => entry_soft(&input.data),
+        _ => dispatch(&input),
+    };
+    return_code(result)
+}
+
+/// The operation and operands of an instruction for one of the A/B entries, which only take the
+/// native lowering byte
+fn entry_operands(data: &InstructionData) -> Option<(EntryOp, u128, u128)> {
+    if Lowering::from_u8(data.header_byte(1)?)? != Lowering::Native {
+        return None;
+    }
+    Some((EntryOp::from_u8(data.header_byte(2)?)?, data.operand(0)?, data.operand(1)?))
+}
+
+/// The A/B entry computing everything with the compiler-builtins libcalls. Exported and never
+/// inlined, like [`entry_soft`], so each keeps a symbol of its own to disassemble and profile.
+#[unsafe(no_mangle)]
+#[inline(never)]
+pub fn entry_libcall(data: &InstructionData) -> Result<u64, FixtureError> {
+    let (op, a, b) = entry_operands(data).ok_or(FixtureError::InvalidInstructionData)?;
+    execute::entry(Entry::Libcall, op, a, b)
+}
+
+/// The A/B entry computing everything with the u64-pair emulation, without any u128 libcall
+#[unsafe(no_mangle)]
+#[inline(never)]
+pub fn entry_soft(data: &InstructionData) -> Result<u64, FixtureError> {
+    let (op, a, b) = entry_operands(data).ok_or(FixtureError::InvalidInstructionData)?;
+    execute::entry(Entry::Soft, op, a, b)
+}
+
+fn dispatch(input: &Input) -> Result<u64, FixtureError> {
+    let invalid = FixtureError::InvalidInstructionData;
+    let data = &input.data;
+    let opcode = data.header_byte(0).ok_or(invalid)?;
+    let lowering = data.header_byte(1).and_then(Lowering::from_u8).ok_or(invalid)?;
+    match (opcode, lowering) {
+        (opcode::TOOLCHAIN_INFO, Lowering::Native) => {
+            syscalls::log(build_info::toolchain_info());
+            return Ok(0);
+        }
+        (opcode::SELF_TEST, Lowering::Native) => return self_test(),
+        (opcode::PROCESS_CHUNK, Lowering::Native) => {
+            let batch = header_u32(data).ok_or(invalid)?;
+            return process_chunk(input.account(0).ok_or(invalid)?, batch);
+        }
+        _ => {}
+    }
+    let a = data.operand(0).ok_or(invalid)?;
+    match (opcode, lowering) {
+        (opcode::RETURN_DATA, Lowering::Native) => return return_data_round_trip(a, &data.program_id()),
+        (opcode::MEMCPY | opcode::MEMMOVE, Lowering::Native) => return copy(input, opcode, a),
+        (opcode::ECHO, Lowering::Native) => return Ok(echo(data, a, data.operand(1).ok_or(invalid)?)),
+        (opcode::SELF_CPI, Lowering::Native) => return self_cpi(data, a),
+        (opcode::DECODE_ENDIAN, Lowering::Native) => {
+            return decode_endian(&data.operand_bytes(0).ok_or(invalid)?, a)
+        }
+        (opcode::FIND_PDA, Lowering::Native) => return find_pda(a, &data.program_id()),
+        (opcode::FOLD_ACCOUNTS, Lowering::Native) => return fold_accounts(input, a),
+        (opcode::ACCRUE_INTEREST, Lowering::Native) => return accrue_interest(data, a),
+        (opcode::ABI_TORTURE, Lowering::Native) => return abi_torture(a),
+        #[cfg(feature = "panic-log")]
+        (opcode::PANIC, Lowering::Native) => panic!("deliberate panic"),
+        (opcode::POOL_SWAP, Lowering::Native) => {
+            let amount_out = unsafe { pool(input.account(0).ok_or(invalid)?) }?.swap(a)?;
+            syscalls::set_return_data(&amount_out.to_le_bytes());
+            return Ok(0);
+        }
+        (opcode::ORACLE_UPDATE, Lowering::Native) => {
+            let state = unsafe { writable_data(input.account(0).ok_or(invalid)?) }.ok_or(invalid)?;
+            let oracle = Oracle::load_mut(state).ok_or(invalid)?;
+            let now = syscalls::get_clock().ok_or(invalid)?.unix_timestamp;
+            oracle.update(a, now).ok_or(invalid)?;
+            syscalls::set_return_data(&oracle.cumulative_price.to_le_bytes());
+            return Ok(0);
+        }
+        (opcode::SUM_LAMPORTS, Lowering::Native) => {
+            let total = input
+                .unique_accounts()
+                .try_fold(a, |total, account| total.checked_add(u128::from(account.lamports())))
+                .ok_or(FixtureError::ArithmeticOverflow)?;
+            return Ok(fold(total));
+        }
+        _ => {}
+    }
+    if let Some(b) = data.operand(1) {
+        match (opcode, lowering) {
+            (opcode::MEMCMP, Lowering::Native) => return memcmp_cross_check(a, b),
+            (opcode::POOL_DEPOSIT, Lowering::Native) => {
+                return unsafe { pool(input.account(0).ok_or(invalid)?) }?.deposit(a, b).map(|()| 0)
+            }
+            (opcode::CLOCK_INTEREST, Lowering::Native) => return clock_interest(a, b),
+            (opcode::BORSH_POSITION, Lowering::Native) => {
+                return borsh_position(input.account(0).ok_or(invalid)?, a, b as i128)
+            }
+            (opcode::SQRT_PRICE_STEP, Lowering::Native) => return sqrt_price_step(data, a, b),
+            _ => {}
+        }
+    }
+    execute::run(&data.header().ok_or(invalid)?, |index| data.operand(index))
+}
+
+/// Header bytes 4..8 as a little-endian u32
+fn header_u32(data: &InstructionData) -> Option<u32> {
+    Some(u32::from_le_bytes([
+        data.header_byte(4)?,
+        data.header_byte(5)?,
+        data.header_byte(6)?,
+        data.header_byte(7)?,
+    ]))
+}
+
+fn return_data_round_trip(a: u128, program_id: &[u8; 32]) -> Result<u64, FixtureError> {
+    syscalls::set_return_data(&a.to_le_bytes());
+    // Twice the payload, to check the runtime copies no more than it holds
+    let mut buffer = [[0u8; 16]; 2];
+    let mut setter = [0u8; 32];
+    let length = syscalls::get_return_data(buffer.as_flattened_mut(), &mut setter);
+    if length != 16 || setter != *program_id || buffer[1] != [0; 16] {
+        return Err(FixtureError::CrossCheckFailed);
+    }
+    Ok(fold(u128::from_le_bytes(buffer[0])))
+}
+
+fn memcmp_cross_check(a: u128, b: u128) -> Result<u64, FixtureError> {
+    let native = if a == b {
+        1
+    } else if a < b {
+        0
+    } else {
+        2
+    };
+    // Big-endian bytes order like the numbers they encode
+    let bytes = syscalls::memcmp(&a.to_be_bytes(), &b.to_be_bytes());
+    let runtime = (bytes.signum() + 1) as u64;
+    if native == runtime {
+        syscalls::log("u128 comparison: native and sol_memcmp agree");
+        Ok(native)
+    } else {
+        syscalls::log("u128 comparison: native and sol_memcmp disagree");
+        syscalls::log_64(native, runtime, bytes as u64, 0, 0);
+        Err(FixtureError::CrossCheckFailed)
+    }
+}
+
+/// The outer half invokes the program itself with `a` and checks what the inner half returns
+fn self_cpi(data: &InstructionData, a: u128) -> Result<u64, FixtureError> {
+    let program_id = data.program_id();
+    match data.header_byte(2) {
+        Some(0) => {}
+        Some(1) => {
+            syscalls::set_return_data(&a.to_le_bytes());
+            return Ok(0);
+        }
+        _ => return Err(FixtureError::InvalidInstructionData),
+    }
+
+    let mut inner = [0u8; HEADER_LEN + 16];
+    inner[..3].copy_from_slice(&[opcode::SELF_CPI, Lowering::Native as u8, 1]);
+    inner[HEADER_LEN..].copy_from_slice(&a.to_le_bytes());
+    if syscalls::invoke(&program_id, &inner) != 0 {
+        return Err(FixtureError::CrossCheckFailed);
+    }
+    let mut echoed = [0u8; 16];
+    let mut setter = [0u8; 32];
+    let length = syscalls::get_return_data(&mut echoed, &mut setter);
+    if length != 16 || setter != program_id || u128::from_le_bytes(echoed) != a {
+        syscalls::log("u128 self-CPI: the callee's return data differs from the operand sent");
+        return Err(FixtureError::CrossCheckFailed);
+    }
+    Ok(fold(a))
+}
+
+fn find_pda(seed: u128, program_id: &[u8; 32]) -> Result<u64, FixtureError> {
+    let seed = seed.to_le_bytes();
+    let (address, bump) =
+        syscalls::try_find_program_address(&[&seed], program_id).ok_or(FixtureError::InvalidInstructionData)?;
+    if syscalls::create_program_address(&[&seed, &[bump]], program_id) != Some(address) {
+        syscalls::log("u128 PDA: sol_create_program_address disagrees with the address found");
+        return Err(FixtureError::CrossCheckFailed);
+    }
+    let mut return_data = [0u8; 33];
+    return_data[..32].copy_from_slice(&address);
+    return_data[32] = bump;
+    syscalls::set_return_data(&return_data);
+    Ok(0)
+}
+
+fn clock_interest(principal: u128, rate: u128) -> Result<u64, FixtureError> {
+    let clock = syscalls::get_clock().ok_or(FixtureError::InvalidInstructionData)?;
+    let [per_second, per_slot] = clock::accrued(principal, rate, &clock)?;
+    let mut return_data = [0u8; 32];
+    return_data[..16].copy_from_slice(&per_second.to_le_bytes());
+    return_data[16..].copy_from_slice(&per_slot.to_le_bytes());
+    syscalls::set_return_data(&return_data);
+    Ok(0)
+}
+
+/// Combine the u128 field the header locates in each of the first accounts into `a`
+fn fold_accounts(input: &Input, a: u128) -> Result<u64, FixtureError> {
+    let invalid = FixtureError::InvalidInstructionData;
+    let data = &input.data;
+    let reduction = data.header_byte(2).and_then(Reduction::from_u8).ok_or(invalid)?;
+    let count = data.header_byte(3).ok_or(invalid)?;
+    let offset = data
+        .header_byte(4)
+        .zip(data.header_byte(5))
+        .map(|(lo, hi)| usize::from(u16::from_le_bytes([lo, hi])))
+        .ok_or(invalid)?;
+    let mut accumulator = a;
+    for index in 0..usize::from(count) {
+        let account = input.account(index).ok_or(invalid)?;
+        if account.data_len() < offset + 16 {
+            return Err(invalid);
+        }
+        let field = unsafe { *(account.data_ptr().add(offset) as *const [u8; 16]) };
+        accumulator = reduction.apply(accumulator, u128::from_le_bytes(field))?;
+    }
+    Ok(fold(accumulator))
+}
+
+/// `account`'s data, if the account is writable
+///
+/// # Safety
+/// Nothing else may reference the account data while the slice does
+unsafe fn writable_data<'a>(account: Account) -> Option<&'a mut [u8]> {
+    account
+        .is_writable()
+        .then(|| unsafe { core::slice::from_raw_parts_mut(account.data_ptr(), account.data_len()) })
+}
+
+/// The pool state in `account`'s data, if the account is writable and holds one
+///
+/// # Safety
+/// As for [`writable_data`]
+unsafe fn pool<'a>(account: Account) -> Result<&'a mut PoolState, FixtureError> {
+    unsafe { writable_data(account) }
+        .and_then(PoolState::load_mut)
+        .ok_or(FixtureError::InvalidInstructionData)
+}
+
+fn process_chunk(account: Account, batch: u32) -> Result<u64, FixtureError> {
+    let invalid = FixtureError::InvalidInstructionData;
+    if batch == 0 {
+        return Err(invalid);
+    }
+    let data = unsafe { writable_data(account) }.ok_or(invalid)?;
+    let (queue, items) = WorkQueue::load_mut(data).ok_or(invalid)?;
+    let left = queue.process(items, batch).ok_or(invalid)?;
+    syscalls::log_64(queue.cursor, left, 0, 0, 0);
+    syscalls::set_return_data(&queue.accumulator.to_le_bytes());
+    Ok(0)
+}
+
+fn borsh_position(account: Account, collateral: u128, debt: i128) -> Result<u64, FixtureError> {
+    let (invalid, overflow) = (FixtureError::InvalidInstructionData, FixtureError::ArithmeticOverflow);
+    let data = unsafe { writable_data(account) }.ok_or(invalid)?;
+    let mut position: Position = borsh::from_slice(data).ok_or(invalid)?;
+    position.collateral = position.collateral.checked_add(collateral).ok_or(overflow)?;
+    position.debt = position.debt.checked_add(debt).ok_or(overflow)?;
+    borsh::to_slice(&position, data).ok_or(invalid)?;
+    if borsh::from_slice(data) != Some(position) {
+        syscalls::log("u128 Borsh: the position decoded differs from the one encoded");
+        return Err(FixtureError::CrossCheckFailed);
+    }
+    Ok(0)
+}
+
+/// Decode `bytes` under each byte order and check them against `loaded`, the same bytes read
+/// with the aligned 64-bit loads every other operand goes through
+fn decode_endian(bytes: &[u8; 16], loaded: u128) -> Result<u64, FixtureError> {
+    let decoded = [
+        endian::u128_from_le(bytes),
+        endian::u128_from_be(bytes),
+        endian::u128_from_ne(bytes),
+    ];
+    let mut return_data = [[0u8; 16]; 3];
+    for (convention, (value, data)) in decoded.iter().zip(&mut return_data).enumerate() {
+        syscalls::log_64(convention as u64, (value >> 64) as u64, *value as u64, 0, 0);
+        *data = value.to_le_bytes();
+    }
+    syscalls::set_return_data(return_data.as_flattened());
+    let [le, be, ne] = decoded;
+    if le != loaded || be != le.swap_bytes() || ne != le {
+        syscalls::log("u128 byte order: decodes disagree with the operand load");
+        return Err(FixtureError::CrossCheckFailed);
+    }
+    Ok(fold(le))
+}
+
+fn accrue_interest(data: &InstructionData, principal: u128) -> Result<u64, FixtureError> {
+    let invalid = FixtureError::InvalidInstructionData;
+    let rate_bps = data
+        .header_byte(2)
+        .zip(data.header_byte(3))
+        .map(|(lo, hi)| u16::from_le_bytes([lo, hi]))
+        .ok_or(invalid)?;
+    let periods = header_u32(data).ok_or(invalid)?;
+    let balance = interest::compound(principal, rate_bps, periods);
+    syscalls::log_64(u64::from(rate_bps), u64::from(periods), (balance >> 64) as u64, balance as u64, 0);
+    Ok(fold(balance))
+}
+
+fn sqrt_price_step(data: &InstructionData, sqrt_price: u128, liquidity: u128) -> Result<u64, FixtureError> {
+    let invalid = FixtureError::InvalidInstructionData;
+    let a_to_b = match data.header_byte(2) {
+        Some(0) => false,
+        Some(1) => true,
+        _ => return Err(invalid),
+    };
+    let liquidity = u64::try_from(liquidity).map_err(|_| invalid)?;
+    let amount_in = data.operand(2).ok_or(invalid)?;
+    let (next, amount_out) = sqrt_price::swap_step(sqrt_price, liquidity, amount_in, a_to_b)?;
+    let mut return_data = [0u8; 32];
+    return_data[..16].copy_from_slice(&next.to_le_bytes());
+    return_data[16..].copy_from_slice(&amount_out.to_le_bytes());
+    syscalls::set_return_data(&return_data);
+    Ok(fold(next) ^ fold(amount_out))
+}
+
+fn abi_torture(seed: u128) -> Result<u64, FixtureError> {
+    match abi::check(seed) {
+        Ok(digests) => {
+            syscalls::set_return_data(&digests.to_le_bytes());
+            Ok(0)
+        }
+        Err(index) => {
+            syscalls::log("u128 ABI: a generated function returned the wrong digest");
+            syscalls::log_64(index as u64, 0, 0, 0, 0);
+            Err(FixtureError::CrossCheckFailed)
+        }
+    }
+}
+
+fn echo(data: &InstructionData, a: u128, b: u128) -> u64 {
+    syscalls::set_return_data(data.bytes());
+    syscalls::log_64(a as u64, (a >> 64) as u64, b as u64, (b >> 64) as u64, data.bytes().len() as u64);
+    0
+}
+
+fn self_test() -> Result<u64, FixtureError> {
+    match self_test::check() {
+        Ok(()) => Ok(0),
+        Err(index) => {
+            syscalls::log("u128 self-test: a vector computed the wrong result");
+            syscalls::log_64(index as u64, 0, 0, 0, 0);
+            Err(FixtureError::SelfTestFailed)
+        }
+    }
+}
+
+/// Start of the heap region the runtime maps for every invocation
+#[cfg(target_arch = "bpf")]
+fn heap() -> *mut u8 {
+    0x3_0000_0000 as *mut u8
+}
+
+/// A thread-local buffer standing in for the heap region in host runs
+#[cfg(not(target_arch = "bpf"))]
+fn heap() -> *mut u8 {
+    use std::cell::UnsafeCell;
+
+    thread_local! {
+        static HEAP: UnsafeCell<[u64; COPY_REGION_LEN / 8]> =
+            const { UnsafeCell::new([0; COPY_REGION_LEN / 8]) };
+    }
+    HEAP.with(|heap| heap.get().cast())
+}
+
+/// Write `count` values to one region, copy them with `sol_memcpy_` or `sol_memmove_`, and read
+/// them back as u128s
+fn copy(input: &Input, opcode: u8, a: u128) -> Result<u64, FixtureError> {
+    let invalid = FixtureError::InvalidInstructionData;
+    let data = &input.data;
+    let region = |index| data.header_byte(index).and_then(Region::from_u8).ok_or(invalid);
+    let src = region(2)?;
+    let dst = if opcode == opcode::MEMCPY {
+        region(3)?
+    } else {
+        src
+    };
+    let count = data.header_byte(4).filter(|&count| count <= MAX_COPY_VALUES).ok_or(invalid)?;
+
+    // u64 elements keep the buffer 8-byte aligned, like the heap and account data
+    let mut stack = [0u64; COPY_REGION_LEN / 8];
+    let stack = stack.as_mut_ptr().cast::<u8>();
+    let base = |region| match region {
+        Region::Stack => Some(stack),
+        Region::Heap => Some(heap()),
+        Region::Account => {
+            let account = input.account(0)?;
+            (account.is_writable() && account.data_len() >= COPY_REGION_LEN).then(|| account.data_ptr())
+        }
+    };
+    let (src, dst) = (base(src).ok_or(invalid)?, base(dst).ok_or(invalid)?);
+
+    let value = |i: u8| a.wrapping_add(u128::from(i));
+    let slot = |base: *mut u8, i: u8| unsafe { base.add(16 * usize::from(i)) };
+    let mut folded = 0;
+    for i in 0..count {
+        unsafe { write_u128(slot(src, i), value(i)) };
+        folded ^= fold(value(i));
+    }
+    let len = 16 * usize::from(count);
+    let holds_values = |base| (0..count).all(|i| unsafe { read_u128(slot(base, i)) } == value(i));
+    let copied = if opcode == opcode::MEMCPY {
+        // The second half of the region, so a copy within one region doesn't overlap
+        let dst = unsafe { dst.add(COPY_REGION_LEN / 2) };
+        unsafe { syscalls::memcpy(dst, src, len) };
+        holds_values(dst) && holds_values(src)
+    } else {
+        // Half a value forward: the ranges overlap, and the copies straddle 16-byte boundaries
+        let dst = unsafe { src.add(8) };
+        unsafe { syscalls::memmove(dst, src, len) };
+        holds_values(dst)
+    };
+    if copied {
+        Ok(folded)
+    } else {
+        syscalls::log("u128 copy: values read back differ from those written");
+        syscalls::log_64(u64::from(opcode), u64::from(count), 0, 0, 0);
+        Err(FixtureError::CrossCheckFailed)
+    }
+}
@@ -0,0 +1,136 @@
+//! The error codes the program returns from `entrypoint`, a stable taxonomy by range: 1xx for
+//! input it can't use, 2xx for arithmetic whose result doesn't fit, and 3xx for internal
+//! invariants that don't hold, like a syscall disagreeing with native code. The runtime reports
+//! each as `Custom(code)`. A code keeps its meaning across versions; new errors take new codes in
+//! their class's range.
+//!
+//! Operations return a successful result as the return code too, moved out of these ranges by
+//! [`result_code`], so a code in them is always an error.
+
+use core::ops::Range;
+
+/// A class of errors, each owning a range of codes
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum ErrorClass {
+    Input,
+    Arithmetic,
+    Internal,
+}
+
+impl ErrorClass {
+    pub const ALL: [Self; 3] = [Self::Input, Self::Arithmetic, Self::Internal];
+
+    /// The codes reserved for the class
+    pub const fn codes(self) -> Range<u64> {
+        match self {
+            Self::Input => 100..200,
+            Self::Arithmetic => 200..300,
+            Self::Internal => 300..400,
+        }
+    }
+
+    /// The class whose range `code` is in
+    pub fn of(code: u64) -> Option<Self> {
+        Self::ALL.into_iter().find(|class| class.codes().contains(&code))
+    }
+}
+
+/// Every code the error classes own between them, which no successful result is returned as
+pub const RESERVED: Range<u64> = ErrorClass::Input.codes().start..ErrorClass::Internal.codes().end;
+
+/// Set on a successful result that would otherwise land in [`RESERVED`]
+pub const RESULT_MOVED: u64 = 1 << 63;
+
+/// Every error the program returns, by its code
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+#[repr(u64)]
+pub enum FixtureError {
+    /// The instruction data can't be decoded, or an account it needs is missing, read-only, too
+    /// short, or misaligned
+    InvalidInstructionData = 100,
+    /// A result doesn't fit the width it is narrowed to, or a checked computation overflows
+    ArithmeticOverflow = 200,
+    /// A syscall disagreed with the native code checking it
+    CrossCheckFailed = 300,
+    /// A self-test vector computed the wrong result
+    SelfTestFailed = 301,
+}
+
+impl FixtureError {
+    pub const ALL: [Self; 4] = [
+        Self::InvalidInstructionData,
+        Self::ArithmeticOverflow,
+        Self::CrossCheckFailed,
+        Self::SelfTestFailed,
+    ];
+
+    pub const fn code(self) -> u64 {
+        self as u64
+    }
+
+    pub fn from_code(code: u64) -> Option<Self> {
+        Self::ALL.into_iter().find(|error| error.code() == code)
+    }
+
+    pub const fn class(self) -> ErrorClass {
+        match self {
+            Self::InvalidInstructionData => ErrorClass::Input,
+            Self::ArithmeticOverflow => ErrorClass::Arithmetic,
+            Self::CrossCheckFailed | Self::SelfTestFailed => ErrorClass::Internal,
+        }
+    }
+}
+
+/// The return code of a successful `result`: the result itself, or with [`RESULT_MOVED`] set if
+/// it lands in [`RESERVED`]. A moved result returns the same code as the result it lands on,
+/// which a check comparing return codes doesn't mind; one reading as an error would be.
+pub const fn result_code(result: u64) -> u64 {
+    if result >= RESERVED.start && result < RESERVED.end {
+        result | RESULT_MOVED
+    } else {
+        result
+    }
+}
+
+/// What `entrypoint` returns for an instruction's `result`: [`result_code`] of the folded result,
+/// or the error's code
+pub fn return_code(result: Result<u64, FixtureError>) -> u64 {
+    result.map_or_else(FixtureError::code, result_code)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn codes_are_in_their_class_range() {
+        for error in FixtureError::ALL {
+            assert!(error.class().codes().contains(&error.code()), "{error:?}");
+            assert_eq!(ErrorClass::of(error.code()), Some(error.class()));
+            assert_eq!(FixtureError::from_code(error.code()), Some(error));
+        }
+        // The ranges don't overlap, and the runtime reports every code as `Custom`
+        for class in ErrorClass::ALL {
+            assert!(class.codes().all(|code| ErrorClass::of(code) == Some(class)));
+            assert!(class.codes().end <= u64::from(u32::MAX));
+        }
+        assert_eq!(ErrorClass::of(0), None);
+        assert_eq!(ErrorClass::of(400), None);
+        assert!(ErrorClass::ALL.iter().all(|class| RESERVED.contains(&class.codes().start)));
+        assert!(ErrorClass::ALL.iter().all(|class| RESERVED.end >= class.codes().end));
+    }
+
+    #[test]
+    fn results_never_read_as_errors() {
+        for result in [0, 1, 99, 100, 200, 300, 301, 399, 400, RESULT_MOVED | 200, u64::MAX] {
+            let code = return_code(Ok(result));
+            assert_eq!(ErrorClass::of(code), None, "{result}");
+            assert_eq!(code & !RESULT_MOVED, result & !RESULT_MOVED, "{result}");
+        }
+        assert_eq!(return_code(Ok(99)), 99);
+        assert_eq!(return_code(Ok(200)), RESULT_MOVED | 200);
+        for error in FixtureError::ALL {
+            assert_eq!(return_code(Err(error)), error.code());
+        }
+    }
+}
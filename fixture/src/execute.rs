@@ -0,0 +1,252 @@
+//! The opcodes that compute on the instruction data alone, without syscalls or accounts. The
+//! entrypoint handles the ones that need the runtime and hands every other instruction here, so
+//! the host runs exactly the code the program ships in unit tests, Miri, fuzzers, and Kani
+//! harnesses. Results come back folded, as the program returns them.
+
+use crate::convert;
+use crate::error::FixtureError;
+use crate::fold::{fold, repeat};
+use crate::instruction::{
+    opcode, BatchedOp, Entry, EntryOp, Lowering, Rounding, HEADER_LEN, MAX_ORDERS, MUL_LOOP_ITERATIONS,
+};
+use crate::interest;
+use crate::intrinsics;
+use crate::muldiv;
+use crate::orderbook::{self, Order};
+use crate::pair::U64Pair;
+use crate::state;
+
+/// The folded result of the instruction with `header`, reading its operands through `operand`,
+/// or the error it fails with. An opcode that needs the runtime is invalid here.
+// Inlined into the dispatch, so `codegen/entrypoint.check` keeps finding the multiply loop's
+// `__multi3` call under `entrypoint`
+#[inline(always)]
+pub fn run(header: &[u8; HEADER_LEN], operand: impl Fn(usize) -> Option<u128>) -> Result<u64, FixtureError> {
+    let invalid = FixtureError::InvalidInstructionData;
+    let (opcode, lowering) = (header[0], Lowering::from_u8(header[1]).ok_or(invalid)?);
+    let a = operand(0).ok_or(invalid)?;
+    match (opcode, lowering) {
+        (opcode::NARROW, Lowering::Native) => return narrow(header[2], a),
+        (opcode::NOTIONAL, Lowering::Native) => return notional(header[2], &operand),
+        (opcode::COMPOUND_FACTOR, Lowering::Native) => {
+            let periods = u32::from_le_bytes([header[4], header[5], header[6], header[7]]);
+            return interest::compound_factor(a, periods).map(fold);
+        }
+        _ => {}
+    }
+    let b = operand(1).ok_or(invalid)?;
+    let (x, y) = (U64Pair::from(a), U64Pair::from(b));
+    let result = match (opcode, lowering) {
+        (opcode::MUL_LOOP, Lowering::Native) => return Ok(mul_loop(a, b)),
+        (opcode::MUL, Lowering::Native) => a.wrapping_mul(b),
+        (opcode::MUL, Lowering::Libcall) => intrinsics::mul_libcall(a, b),
+        (opcode::MUL, Lowering::Limbs) => intrinsics::mul_limbs(a, b),
+        (opcode::MUL, Lowering::Pair) => x.wrapping_mul(y).into(),
+        (opcode::DIV, Lowering::Native) => a.checked_div(b).ok_or(invalid)?,
+        (opcode::DIV, Lowering::Libcall) if b != 0 => intrinsics::div_libcall(a, b),
+        (opcode::DIV, Lowering::Pair) => x.checked_div_rem(y).ok_or(invalid)?.0.into(),
+        (opcode::REM, Lowering::Native) => a.checked_rem(b).ok_or(invalid)?,
+        (opcode::REM, Lowering::Libcall) if b != 0 => intrinsics::rem_libcall(a, b),
+        (opcode::REM, Lowering::Pair) => x.checked_div_rem(y).ok_or(invalid)?.1.into(),
+        (opcode::SHL, Lowering::Native) => a << (b % 128),
+        (opcode::SHL, Lowering::Libcall) => intrinsics::shl_libcall(a, (b % 128) as u32),
+        (opcode::SHL, Lowering::Pair) => x.wrapping_shl(y.lo as u32).into(),
+        (opcode::SHR, Lowering::Native) => a >> (b % 128),
+        (opcode::SHR, Lowering::Libcall) => intrinsics::shr_libcall(a, (b % 128) as u32),
+        (opcode::SHR, Lowering::Pair) => x.wrapping_shr(y.lo as u32).into(),
+        (opcode::DIV_ROUND, Lowering::Native) => {
+            Rounding::from_u8(header[2]).ok_or(invalid)?.div(a, b).ok_or(invalid)?
+        }
+        (opcode::MUL_DIV, Lowering::Native) => {
+            let c = operand(2).filter(|&c| c != 0).ok_or(invalid)?;
+            // With a nonzero divisor, only a quotient that doesn't fit fails
+            let quotient = match header[2] {
+                0 => muldiv::mul_div_floor(a, b, c),
+                1 => muldiv::mul_div_ceil(a, b, c),
+                _ => return Err(invalid),
+            };
+            quotient.ok_or(FixtureError::ArithmeticOverflow)?
+        }
+        (opcode::CMP, Lowering::Native) => return Ok((a.cmp(&b) as i8 + 1) as u64),
+        (opcode::CMP, Lowering::Pair) => return Ok((x.cmp(&y) as i8 + 1) as u64),
+        (opcode::ADD, Lowering::Native) => a.wrapping_add(b),
+        (opcode::ADD, Lowering::Pair) => x.wrapping_add(y).into(),
+        (opcode::SUB, Lowering::Native) => a.wrapping_sub(b),
+        (opcode::SUB, Lowering::Pair) => x.wrapping_sub(y).into(),
+        (opcode::CONSTANT_PRODUCT, Lowering::Native) => {
+            let fee_bps = u16::from_le_bytes([header[2], header[3]]);
+            state::swap_amounts(a, b, operand(2).ok_or(invalid)?, fee_bps.into())?.0
+        }
+        (opcode::BATCH, _) => return batch(header, a, b, lowering),
+        _ => return Err(invalid),
+    };
+    Ok(fold(result))
+}
+
+/// `op` on `a` and `b` the way `entry` computes it: every u128 operation a compiler-builtins
+/// libcall, or all of them u64-pair emulation. Inlined into each exported A/B entry, so the two
+/// keep separate code.
+#[inline(always)]
+pub fn entry(entry: Entry, op: EntryOp, a: u128, b: u128) -> Result<u64, FixtureError> {
+    let invalid = FixtureError::InvalidInstructionData;
+    let result = match entry {
+        Entry::Libcall => match op {
+            EntryOp::Mul => intrinsics::mul_libcall(a, b),
+            EntryOp::Div if b != 0 => intrinsics::div_libcall(a, b),
+            EntryOp::Rem if b != 0 => intrinsics::rem_libcall(a, b),
+            EntryOp::Shl => intrinsics::shl_libcall(a, (b % 128) as u32),
+            EntryOp::Shr => intrinsics::shr_libcall(a, (b % 128) as u32),
+            EntryOp::Div | EntryOp::Rem => return Err(invalid),
+        },
+        Entry::Soft => {
+            let (x, y) = (U64Pair::from(a), U64Pair::from(b));
+            let result = match op {
+                EntryOp::Mul => x.wrapping_mul(y),
+                EntryOp::Div => x.checked_div_rem(y).ok_or(invalid)?.0,
+                EntryOp::Rem => x.checked_div_rem(y).ok_or(invalid)?.1,
+                EntryOp::Shl => x.wrapping_shl(y.lo as u32),
+                EntryOp::Shr => x.wrapping_shr(y.lo as u32),
+            };
+            result.into()
+        }
+    };
+    Ok(fold(result))
+}
+
+fn batch(header: &[u8; HEADER_LEN], a: u128, b: u128, lowering: Lowering) -> Result<u64, FixtureError> {
+    let invalid = FixtureError::InvalidInstructionData;
+    let op = BatchedOp::from_u8(header[2]).ok_or(invalid)?;
+    let count = u32::from_le_bytes([header[4], header[5], header[6], header[7]]);
+    if op == BatchedOp::Div && b == 0 {
+        return Err(invalid);
+    }
+    Ok(match (op, lowering) {
+        (BatchedOp::Mul, Lowering::Native) => repeat(count, a, b, u128::wrapping_mul),
+        (BatchedOp::Mul, Lowering::Libcall) => repeat(count, a, b, intrinsics::mul_libcall),
+        (BatchedOp::Mul, Lowering::Limbs) => repeat(count, a, b, intrinsics::mul_limbs),
+        (BatchedOp::Mul, Lowering::Pair) => repeat(count, a, b, |a, b| {
+            U64Pair::from(a).wrapping_mul(b.into()).into()
+        }),
+        (BatchedOp::Div, Lowering::Native) => repeat(count, a, b, |a, b| a / b),
+        (BatchedOp::Div, Lowering::Libcall) => repeat(count, a, b, intrinsics::div_libcall),
+        (BatchedOp::Div, Lowering::Pair) => repeat(count, a, b, |a, b| {
+            U64Pair::from(a).checked_div_rem(b.into()).map_or(0, |(quotient, _)| quotient.into())
+        }),
+        _ => return Err(invalid),
+    })
+}
+
+// Inlined so `codegen/entrypoint.check` keeps finding the loop's multiply under `entrypoint`
+#[inline(always)]
+fn mul_loop(mut a: u128, b: u128) -> u64 {
+    for _ in 0..MUL_LOOP_ITERATIONS {
+        // reassign a to avoid multiply being optimized away
+        a *= b;
+    }
+
+    (a >> 64) as u64
+}
+
+fn narrow(signed: u8, a: u128) -> Result<u64, FixtureError> {
+    match signed {
+        0 => convert::try_into_u64(a),
+        1 => convert::try_into_i64(a as i128).map(|narrowed| narrowed as u64),
+        _ => Err(FixtureError::InvalidInstructionData),
+    }
+}
+
+/// The total notional of the `count` orders in the operands, folded
+fn notional(count: u8, operand: impl Fn(usize) -> Option<u128>) -> Result<u64, FixtureError> {
+    if count == 0 || count > MAX_ORDERS {
+        return Err(FixtureError::InvalidInstructionData);
+    }
+    // Reject data too short for the orders before reading any
+    operand(usize::from(count) - 1).ok_or(FixtureError::InvalidInstructionData)?;
+    let orders = (0..usize::from(count)).filter_map(operand).map(Order::from);
+    orderbook::total_notional(orders).map(fold)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::error::{return_code, ErrorClass};
+    use crate::instruction::{FixtureInstruction, ARITHMETIC_OVERFLOW};
+
+    const VALUES: [u128; 7] = [0, 1, 3, u64::MAX as u128, 1 << 64, i128::MAX as u128, u128::MAX];
+
+    /// The return code of `result`, or `None` for an instruction the program rejects as invalid,
+    /// which has no expected return
+    fn returned(result: Result<u64, FixtureError>) -> Option<u64> {
+        (result != Err(FixtureError::InvalidInstructionData)).then(|| return_code(result))
+    }
+
+    /// What the program returns for `instruction`, reading it from its packed bytes
+    fn run_packed(instruction: FixtureInstruction) -> Option<u64> {
+        let data = instruction.pack();
+        let operand = |index: usize| {
+            let offset = HEADER_LEN + 16 * index;
+            Some(u128::from_le_bytes(data.get(offset..offset + 16)?.try_into().ok()?))
+        };
+        returned(run(data[..HEADER_LEN].try_into().unwrap(), operand))
+    }
+
+    #[test]
+    fn agrees_with_the_reference() {
+        for a in VALUES {
+            for b in VALUES {
+                let mut instructions = vec![
+                    FixtureInstruction::Narrow { a, signed: false },
+                    FixtureInstruction::Narrow { a, signed: true },
+                    FixtureInstruction::CompoundFactor { rate: a, periods: b as u32 % 16 },
+                    FixtureInstruction::MulDiv { a, b, c: b ^ 5, ceil: a > b },
+                ];
+                // Including the lowerings the program rejects, which have no expected return
+                for lowering in Lowering::ALL {
+                    instructions.extend([
+                        FixtureInstruction::Mul { a, b, lowering },
+                        FixtureInstruction::Div { a, b, lowering },
+                        FixtureInstruction::Rem { a, b, lowering },
+                        FixtureInstruction::Shl { a, b, lowering },
+                        FixtureInstruction::Shr { a, b, lowering },
+                        FixtureInstruction::Cmp { a, b, lowering },
+                        FixtureInstruction::Add { a, b, lowering },
+                        FixtureInstruction::Sub { a, b, lowering },
+                        FixtureInstruction::Batch { op: BatchedOp::Mul, count: 4, a, b, lowering },
+                        FixtureInstruction::Batch { op: BatchedOp::Div, count: 4, a, b, lowering },
+                    ]);
+                }
+                for instruction in instructions {
+                    let expected = instruction.expected_return();
+                    assert_eq!(run_packed(instruction), expected, "{:02x?}", instruction.pack());
+                }
+                for op in EntryOp::ALL {
+                    let instruction = FixtureInstruction::Entry { entry: Entry::Libcall, op, a, b };
+                    let expected = instruction.expected_return();
+                    for kind in Entry::ALL {
+                        let returned = returned(entry(kind, op, a, b));
+                        assert_eq!(returned, expected, "{kind:?} {op:?} on ({a:#x}, {b:#x})");
+                    }
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn results_in_the_error_ranges_are_moved_out() {
+        let narrowed = run_packed(FixtureInstruction::Narrow { a: ARITHMETIC_OVERFLOW.into(), signed: false });
+        assert_ne!(narrowed, Some(ARITHMETIC_OVERFLOW));
+        assert_eq!(narrowed.and_then(ErrorClass::of), None);
+        let overflowed = run_packed(FixtureInstruction::Narrow { a: u128::MAX, signed: false });
+        assert_eq!(overflowed, Some(ARITHMETIC_OVERFLOW));
+    }
+
+    #[test]
+    fn leaves_runtime_opcodes_to_the_entrypoint() {
+        assert_eq!(run_packed(FixtureInstruction::ReturnData { a: 1 }), None);
+        assert_eq!(run_packed(FixtureInstruction::SelfTest), None);
+        let truncated = FixtureInstruction::Mul { a: 1, b: 2, lowering: Lowering::Native }.pack();
+        let header = truncated[..HEADER_LEN].try_into().unwrap();
+        let operand = |index| (index == 0).then_some(1);
+        assert_eq!(run(header, operand), Err(FixtureError::InvalidInstructionData));
+    }
+}
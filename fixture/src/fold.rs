@@ -0,0 +1,39 @@
+//! How the program turns u128 results into its 64-bit return code. The entrypoint and
+//! [`FixtureInstruction::expected_return`](crate::instruction::FixtureInstruction::expected_return)
+//! share these, so the host reference folds results exactly the way the program does.
+
+/// Fold the halves so the whole result decides the return code
+#[inline(always)]
+pub fn fold(result: u128) -> u64 {
+    (result >> 64) as u64 ^ result as u64
+}
+
+/// `op` on `(a ^ i, b)` for every `i` below `count`, with the folded results XORed together. The
+/// XOR keeps LLVM from strength-reducing the operations into a running sum.
+#[inline(always)]
+pub fn repeat(count: u32, a: u128, b: u128, op: impl Fn(u128, u128) -> u128) -> u64 {
+    let mut folded = 0;
+    for i in 0..count {
+        folded ^= fold(op(a ^ u128::from(i), b));
+    }
+    folded
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn folds_both_halves() {
+        assert_eq!(fold(0), 0);
+        assert_eq!(fold(u128::MAX), 0);
+        assert_eq!(fold(1 << 64 | 1), 0);
+        assert_eq!(fold(0x0123_4567_89ab_cdef_0000_0000_0000_0000), 0x0123_4567_89ab_cdef);
+        assert_eq!(fold(0xffff_0000_0000_0000_0000_0000_0000_ffff), 0xffff_0000_0000_ffff);
+
+        let (a, b) = (u128::MAX - 5, 3);
+        let expected = (0..4).fold(0, |folded, i| folded ^ fold((a ^ i).wrapping_mul(b)));
+        assert_eq!(repeat(4, a, b, u128::wrapping_mul), expected);
+        assert_eq!(repeat(0, a, b, u128::wrapping_mul), 0);
+    }
+}
@@ -0,0 +1,193 @@
+//! The input region the loader serializes for the program: the accounts, then the instruction
+//! data and the program id. Everything is read in place, and only the parts the fixture uses are
+//! decoded.
+
+use crate::instruction::{HEADER_LEN, MAX_ACCOUNTS};
+
+/// Room the loader leaves after each account's data for it to grow during the instruction
+const MAX_PERMITTED_DATA_INCREASE: usize = 10 * 1024;
+
+/// Duplicate marker of an account serialized in full, rather than as the index of an earlier one
+const NON_DUP_MARKER: u8 = u8::MAX;
+
+/// Offset of the lamports in a serialized account
+const ACCOUNT_LAMPORTS_OFFSET: usize = 72;
+
+/// Offset of the data length in a serialized account; the data follows it
+const ACCOUNT_DATA_LEN_OFFSET: usize = 80;
+
+/// Read a u128 as two 64-bit halves, which only needs `ptr` to be 8-byte aligned
+///
+/// # Safety
+/// `ptr` must be 8-byte aligned and valid for reading 16 bytes
+pub unsafe fn read_u128(ptr: *const u8) -> u128 {
+    let halves = ptr as *const u64;
+    let (lo, hi) = unsafe { (*halves, *halves.add(1)) };
+    (u128::from(hi) << 64) | u128::from(lo)
+}
+
+/// Write a u128 as two 64-bit halves, which only needs `ptr` to be 8-byte aligned
+///
+/// # Safety
+/// `ptr` must be 8-byte aligned and valid for writing 16 bytes
+pub unsafe fn write_u128(ptr: *mut u8, value: u128) {
+    let halves = ptr as *mut u64;
+    unsafe {
+        *halves = value as u64;
+        *halves.add(1) = (value >> 64) as u64;
+    }
+}
+
+/// One account passed to the instruction
+#[derive(Clone, Copy)]
+pub struct Account {
+    /// Start of the serialized account, 8-byte aligned
+    ptr: *mut u8,
+}
+
+impl Account {
+    pub fn is_writable(&self) -> bool {
+        unsafe { *self.ptr.add(2) != 0 }
+    }
+
+    pub fn lamports(&self) -> u64 {
+        unsafe { *(self.ptr.add(ACCOUNT_LAMPORTS_OFFSET) as *const u64) }
+    }
+
+    pub fn data_len(&self) -> usize {
+        unsafe { *(self.ptr.add(ACCOUNT_DATA_LEN_OFFSET) as *const u64) as usize }
+    }
+
+    /// Start of the account data, 8-byte aligned
+    pub fn data_ptr(&self) -> *mut u8 {
+        unsafe { self.ptr.add(ACCOUNT_DATA_LEN_OFFSET + 8) }
+    }
+}
+
+/// Instruction data, read in place from the input region
+pub struct InstructionData {
+    /// 8-byte aligned, like everything the loader serializes
+    ptr: *const u8,
+    len: usize,
+}
+
+impl InstructionData {
+    /// The header in front of the operands
+    pub fn header(&self) -> Option<[u8; HEADER_LEN]> {
+        (self.len >= HEADER_LEN).then(|| unsafe { *(self.ptr as *const [u8; HEADER_LEN]) })
+    }
+
+    pub fn header_byte(&self, index: usize) -> Option<u8> {
+        (self.len >= HEADER_LEN).then(|| unsafe { *self.ptr.add(index) })
+    }
+
+    /// The u128 operand at `index`
+    pub fn operand(&self, index: usize) -> Option<u128> {
+        let offset = HEADER_LEN + 16 * index;
+        if offset + 16 > self.len {
+            return None;
+        }
+        Some(unsafe { read_u128(self.ptr.add(offset)) })
+    }
+
+    /// The 16 bytes of the operand at `index`, undecoded
+    pub fn operand_bytes(&self, index: usize) -> Option<[u8; 16]> {
+        let offset = HEADER_LEN + 16 * index;
+        if offset + 16 > self.len {
+            return None;
+        }
+        Some(unsafe { *(self.ptr.add(offset) as *const [u8; 16]) })
+    }
+
+    /// The whole instruction data, header included, undecoded
+    pub fn bytes(&self) -> &[u8] {
+        unsafe { core::slice::from_raw_parts(self.ptr, self.len) }
+    }
+
+    /// The id of the program being invoked, which the loader serializes right after the data
+    pub fn program_id(&self) -> [u8; 32] {
+        unsafe { *(self.ptr.add(self.len) as *const [u8; 32]) }
+    }
+}
+
+/// An entry of the serialized account list
+enum Entry {
+    Account(Account),
+    /// A repeat of the account at this index
+    Duplicate(u8),
+}
+
+/// The entry at `ptr` and its serialized length
+///
+/// # Safety
+/// `ptr` must point to an entry of the account list in the input region
+unsafe fn entry(ptr: *mut u8) -> (Entry, usize) {
+    let marker = unsafe { *ptr };
+    if marker != NON_DUP_MARKER {
+        return (Entry::Duplicate(marker), 8);
+    }
+    let account = Account { ptr };
+    let len = ACCOUNT_DATA_LEN_OFFSET + 8 + account.data_len() + MAX_PERMITTED_DATA_INCREASE;
+    // Padding to 8 bytes, then the rent epoch
+    (Entry::Account(account), len.next_multiple_of(8) + 8)
+}
+
+pub struct Input {
+    /// The first [`MAX_ACCOUNTS`] accounts, duplicates resolved to the account they repeat
+    accounts: [Option<Account>; MAX_ACCOUNTS],
+    /// Start of the account list, and the number of entries in it
+    entries: *mut u8,
+    entry_count: usize,
+    pub data: InstructionData,
+}
+
+impl Input {
+    /// # Safety
+    /// `input` must point to the input region the loader passed to the entrypoint
+    pub unsafe fn deserialize(input: *mut u8) -> Self {
+        let mut accounts = [None; MAX_ACCOUNTS];
+        let entry_count = unsafe { *(input as *const u64) } as usize;
+        let entries = unsafe { input.add(8) };
+        let mut offset = 0;
+        for index in 0..entry_count {
+            let (entry, len) = unsafe { entry(entries.add(offset)) };
+            offset += len;
+            let account = match entry {
+                Entry::Account(account) => Some(account),
+                Entry::Duplicate(original) => accounts.get(original as usize).copied().flatten(),
+            };
+            if let Some(slot) = accounts.get_mut(index) {
+                *slot = account;
+            }
+        }
+        let data = unsafe { entries.add(offset) };
+        let data = InstructionData {
+            ptr: unsafe { data.add(8) },
+            len: unsafe { *(data as *const u64) } as usize,
+        };
+        Self {
+            accounts,
+            entries,
+            entry_count,
+            data,
+        }
+    }
+
+    pub fn account(&self, index: usize) -> Option<Account> {
+        self.accounts.get(index).copied().flatten()
+    }
+
+    /// Every account passed, each once however often it repeats, including those past
+    /// [`MAX_ACCOUNTS`]
+    pub fn unique_accounts(&self) -> impl Iterator<Item = Account> + '_ {
+        let mut ptr = self.entries;
+        (0..self.entry_count).filter_map(move |_| {
+            let (entry, len) = unsafe { entry(ptr) };
+            ptr = unsafe { ptr.add(len) };
+            match entry {
+                Entry::Account(account) => Some(account),
+                Entry::Duplicate(_) => None,
+            }
+        })
+    }
+}
@@ -0,0 +1,1087 @@
+//! Instruction data the fixture program accepts.
+//!
+//! Every instruction starts with an 8-byte header: the opcode, a byte selecting a variant of the
+//! operation, and six parameter bytes, zero unless the opcode documents them. The u128 operands
+//! follow, 16 bytes little-endian each. Keeping them at 8-byte offsets lets the program read them
+//! with aligned 64-bit loads wherever the loader places the instruction data.
+//!
+//! The encoding is stable within a major version of this crate: a new operation is a new
+//! opcode, and existing opcodes keep their layout.
+
+extern crate alloc;
+
+use alloc::vec;
+use alloc::vec::Vec;
+
+use crate::error::{return_code, FixtureError};
+use crate::fold::{fold, repeat};
+use crate::interest;
+use crate::muldiv;
+use crate::orderbook::{self, Order};
+use crate::rounding;
+use crate::sqrt_price;
+use crate::state;
+
+/// Iterations of the multiply loop behind [`FixtureInstruction::MulLoop`]
+pub const MUL_LOOP_ITERATIONS: usize = 10_000;
+
+/// Offset of the instruction data in the input region of an invocation without accounts: the
+/// program skips the account count and the data length, 8 bytes each
+pub const INSTRUCTION_DATA_OFFSET: usize = 0x10;
+
+/// Length of the header in front of the operands
+pub const HEADER_LEN: usize = 8;
+
+/// Returned for data the program can't decode ([`FixtureError::InvalidInstructionData`])
+pub const INVALID_INSTRUCTION_DATA: u64 = FixtureError::InvalidInstructionData.code();
+
+/// Returned when a result doesn't fit the width it is narrowed to, or a checked computation
+/// overflows ([`FixtureError::ArithmeticOverflow`])
+pub const ARITHMETIC_OVERFLOW: u64 = FixtureError::ArithmeticOverflow.code();
+
+/// Returned by the instructions that check a runtime syscall against native code when the two
+/// disagree, e.g. `sol_get_return_data` reporting the wrong length
+/// ([`FixtureError::CrossCheckFailed`])
+pub const CROSS_CHECK_FAILED: u64 = FixtureError::CrossCheckFailed.code();
+
+/// Returned by [`FixtureInstruction::SelfTest`] when a vector fails
+/// ([`FixtureError::SelfTestFailed`])
+pub const SELF_TEST_FAILED: u64 = FixtureError::SelfTestFailed.code();
+
+/// First byte of the instruction data
+pub mod opcode {
+    pub const MUL_LOOP: u8 = 0;
+    pub const MUL: u8 = 1;
+    pub const DIV: u8 = 2;
+    pub const REM: u8 = 3;
+    pub const SHL: u8 = 4;
+    pub const SHR: u8 = 5;
+    pub const CMP: u8 = 6;
+    pub const BATCH: u8 = 7;
+    pub const RETURN_DATA: u8 = 8;
+    pub const MEMCMP: u8 = 9;
+    pub const MEMCPY: u8 = 10;
+    pub const MEMMOVE: u8 = 11;
+    pub const CLOCK_INTEREST: u8 = 12;
+    pub const SELF_CPI: u8 = 13;
+    pub const FIND_PDA: u8 = 14;
+    pub const SUM_LAMPORTS: u8 = 15;
+    pub const FOLD_ACCOUNTS: u8 = 16;
+    pub const POOL_DEPOSIT: u8 = 17;
+    pub const POOL_SWAP: u8 = 18;
+    pub const BORSH_POSITION: u8 = 19;
+    pub const DECODE_ENDIAN: u8 = 20;
+    pub const DIV_ROUND: u8 = 21;
+    pub const NARROW: u8 = 22;
+    pub const MUL_DIV: u8 = 23;
+    pub const ACCRUE_INTEREST: u8 = 24;
+    pub const ORACLE_UPDATE: u8 = 25;
+    pub const SQRT_PRICE_STEP: u8 = 26;
+    pub const CONSTANT_PRODUCT: u8 = 27;
+    pub const NOTIONAL: u8 = 28;
+    pub const COMPOUND_FACTOR: u8 = 29;
+    pub const ABI_TORTURE: u8 = 30;
+    /// Panics on purpose in a build with the `panic-log` feature, to check the panic gets logged;
+    /// other builds reject it as invalid instruction data
+    pub const PANIC: u8 = 31;
+    pub const ENTRY_LIBCALL: u8 = 32;
+    pub const ENTRY_SOFT: u8 = 33;
+    pub const TOOLCHAIN_INFO: u8 = 34;
+    pub const SELF_TEST: u8 = 35;
+    pub const ECHO: u8 = 36;
+    pub const PROCESS_CHUNK: u8 = 37;
+    pub const ADD: u8 = 38;
+    pub const SUB: u8 = 39;
+}
+
+/// Accounts the program resolves by index; any beyond are only reachable by walking the input
+pub const MAX_ACCOUNTS: usize = 8;
+
+/// Most u128 values a [`FixtureInstruction::Memcpy`] or [`FixtureInstruction::Memmove`] copies
+pub const MAX_COPY_VALUES: u8 = 32;
+
+/// Most orders a [`FixtureInstruction::Notional`] carries
+pub const MAX_ORDERS: u8 = 8;
+
+/// Bytes of a region the copy instructions use: room for the values and, past them, for their
+/// copy
+pub const COPY_REGION_LEN: usize = 2 * MAX_COPY_VALUES as usize * 16;
+
+/// How an arithmetic instruction computes its result, selected by the second header byte. Not
+/// every operation has every lowering; the program rejects the combinations it lacks.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord)]
+#[repr(u8)]
+pub enum Lowering {
+    /// The plain Rust operator, lowered however the backend chooses
+    Native = 0,
+    /// An explicit call to the compiler-builtins routine the linker provides (`__multi3`,
+    /// `__udivti3`, ...)
+    Libcall = 1,
+    /// A hand-scheduled implementation from [`intrinsics`](crate::intrinsics)
+    Limbs = 2,
+    /// [`U64Pair`](crate::pair::U64Pair) emulation using only 64-bit operations, the workaround
+    /// programs use without u128 support
+    Pair = 3,
+}
+
+impl Lowering {
+    pub const ALL: [Self; 4] = [Self::Native, Self::Libcall, Self::Limbs, Self::Pair];
+
+    pub fn from_u8(value: u8) -> Option<Self> {
+        Self::ALL.into_iter().find(|lowering| *lowering as u8 == value)
+    }
+
+    pub fn name(self) -> &'static str {
+        match self {
+            Self::Native => "native",
+            Self::Libcall => "libcall",
+            Self::Limbs => "limbs",
+            Self::Pair => "pair",
+        }
+    }
+}
+
+/// The operation a [`FixtureInstruction::Batch`] repeats
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+#[repr(u8)]
+pub enum BatchedOp {
+    Mul = opcode::MUL,
+    Div = opcode::DIV,
+}
+
+impl BatchedOp {
+    pub const ALL: [Self; 2] = [Self::Mul, Self::Div];
+
+    pub fn from_u8(value: u8) -> Option<Self> {
+        Self::ALL.into_iter().find(|op| *op as u8 == value)
+    }
+}
+
+/// One of the program's exported A/B entry functions, each compiled with a single lowering of the
+/// u128 arithmetic and selected by the opcode of a [`FixtureInstruction::Entry`]
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+#[repr(u8)]
+pub enum Entry {
+    /// `entry_libcall`: the compiler-builtins routines the linker provides
+    Libcall = opcode::ENTRY_LIBCALL,
+    /// `entry_soft`: [`U64Pair`](crate::pair::U64Pair) emulation using only 64-bit operations
+    Soft = opcode::ENTRY_SOFT,
+}
+
+impl Entry {
+    pub const ALL: [Self; 2] = [Self::Libcall, Self::Soft];
+
+    pub fn from_u8(value: u8) -> Option<Self> {
+        Self::ALL.into_iter().find(|entry| *entry as u8 == value)
+    }
+
+    /// The name the function is exported under
+    pub fn symbol(self) -> &'static str {
+        match self {
+            Self::Libcall => "entry_libcall",
+            Self::Soft => "entry_soft",
+        }
+    }
+}
+
+/// The operation a [`FixtureInstruction::Entry`] computes
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+#[repr(u8)]
+pub enum EntryOp {
+    Mul = opcode::MUL,
+    Div = opcode::DIV,
+    Rem = opcode::REM,
+    Shl = opcode::SHL,
+    Shr = opcode::SHR,
+}
+
+impl EntryOp {
+    pub const ALL: [Self; 5] = [Self::Mul, Self::Div, Self::Rem, Self::Shl, Self::Shr];
+
+    pub fn from_u8(value: u8) -> Option<Self> {
+        Self::ALL.into_iter().find(|op| *op as u8 == value)
+    }
+}
+
+/// Memory a copy instruction reads from or writes to
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+#[repr(u8)]
+pub enum Region {
+    /// A buffer in the program's stack frame
+    Stack = 0,
+    /// The start of the heap
+    Heap = 1,
+    /// The data of the first account, which must be writable and at least [`COPY_REGION_LEN`]
+    /// bytes long
+    Account = 2,
+}
+
+impl Region {
+    pub const ALL: [Self; 3] = [Self::Stack, Self::Heap, Self::Account];
+
+    pub fn from_u8(value: u8) -> Option<Self> {
+        Self::ALL.into_iter().find(|region| *region as u8 == value)
+    }
+
+    pub fn name(self) -> &'static str {
+        match self {
+            Self::Stack => "stack",
+            Self::Heap => "heap",
+            Self::Account => "account",
+        }
+    }
+}
+
+/// Which way a [`FixtureInstruction::DivRound`] rounds, see [`rounding`]
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+#[repr(u8)]
+pub enum Rounding {
+    Floor = 0,
+    Ceil = 1,
+    /// To the nearest integer, halves up
+    Nearest = 2,
+}
+
+impl Rounding {
+    pub const ALL: [Self; 3] = [Self::Floor, Self::Ceil, Self::Nearest];
+
+    pub fn from_u8(value: u8) -> Option<Self> {
+        Self::ALL.into_iter().find(|rounding| *rounding as u8 == value)
+    }
+
+    pub fn name(self) -> &'static str {
+        match self {
+            Self::Floor => "floor",
+            Self::Ceil => "ceil",
+            Self::Nearest => "nearest",
+        }
+    }
+
+    /// `a / b` rounded this way, or `None` for a zero divisor
+    pub fn div(self, a: u128, b: u128) -> Option<u128> {
+        match self {
+            Self::Floor => rounding::div_floor(a, b),
+            Self::Ceil => rounding::div_ceil(a, b),
+            Self::Nearest => rounding::div_round(a, b),
+        }
+    }
+}
+
+/// How a [`FixtureInstruction::FoldAccounts`] combines the fields it reads
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+#[repr(u8)]
+pub enum Reduction {
+    /// Checked addition; overflow fails with [`FixtureError::ArithmeticOverflow`]
+    Sum = 0,
+    Xor = 1,
+    Max = 2,
+}
+
+impl Reduction {
+    pub const ALL: [Self; 3] = [Self::Sum, Self::Xor, Self::Max];
+
+    pub fn from_u8(value: u8) -> Option<Self> {
+        Self::ALL.into_iter().find(|reduction| *reduction as u8 == value)
+    }
+
+    /// Combine the accumulator with one more value, or [`FixtureError::ArithmeticOverflow`] if a
+    /// sum overflows
+    pub fn apply(self, accumulator: u128, value: u128) -> Result<u128, FixtureError> {
+        match self {
+            Self::Sum => accumulator.checked_add(value).ok_or(FixtureError::ArithmeticOverflow),
+            Self::Xor => Ok(accumulator ^ value),
+            Self::Max => Ok(accumulator.max(value)),
+        }
+    }
+}
+
+/// An operation the fixture program performs on its u128 operands.
+///
+/// Unless noted otherwise, an operation with a u128 result returns its two 64-bit halves XORed
+/// together. A result in an error code's range comes back with bit 63 set, so the two never mix
+/// ([`result_code`](crate::error::result_code)).
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum FixtureInstruction {
+    /// Multiply `a` by `b` [`MUL_LOOP_ITERATIONS`] times and return the high 64 bits of the
+    /// product. The result is undefined if any step overflows.
+    MulLoop { a: u128, b: u128 },
+    /// `a * b`, wrapping; every lowering
+    Mul { a: u128, b: u128, lowering: Lowering },
+    /// `a / b`; native, libcall, or pair. Division by zero is rejected as invalid instruction data.
+    Div { a: u128, b: u128, lowering: Lowering },
+    /// `a % b`; native, libcall, or pair. Division by zero is rejected as invalid instruction data.
+    Rem { a: u128, b: u128, lowering: Lowering },
+    /// `a << (b % 128)`; native, libcall, or pair
+    Shl { a: u128, b: u128, lowering: Lowering },
+    /// `a >> (b % 128)`, logical; native, libcall, or pair
+    Shr { a: u128, b: u128, lowering: Lowering },
+    /// `a.cmp(&b)` as 0 (less), 1 (equal), or 2 (greater); native or pair
+    Cmp { a: u128, b: u128, lowering: Lowering },
+    /// `a + b`, wrapping; native or pair
+    Add { a: u128, b: u128, lowering: Lowering },
+    /// `a - b`, wrapping; native or pair
+    Sub { a: u128, b: u128, lowering: Lowering },
+    /// `op` applied `count` times to the independent operand pairs `(a ^ i, b)` for `i` in
+    /// `0..count`, returning the XOR of the folded results. The lowerings are those of `op`; the
+    /// header carries `op` in byte 2 and `count` in bytes 4..8 (little-endian). Division by zero
+    /// is rejected as invalid instruction data, and a large enough `count` exhausts the compute
+    /// budget.
+    Batch {
+        op: BatchedOp,
+        count: u32,
+        a: u128,
+        b: u128,
+        lowering: Lowering,
+    },
+    /// Set `a` (16 bytes little-endian) as return data with `sol_set_return_data`, read it back
+    /// with `sol_get_return_data` into a larger buffer, and return the folded value read back, or
+    /// [`CROSS_CHECK_FAILED`]. Native only.
+    ReturnData { a: u128 },
+    /// Compare `a` and `b` with the native `==` and `<` and with `sol_memcmp_` on their big-endian
+    /// bytes, log whether the two agree, and return the comparison as for [`Cmp`](Self::Cmp), or
+    /// [`CROSS_CHECK_FAILED`]. Native only.
+    MemCmp { a: u128, b: u128 },
+    /// Write the `count` values `a + i` (wrapping) to the start of `src`, copy them with
+    /// `sol_memcpy_` to the second half of `dst`, check both copies, and return the XOR of the
+    /// folded values, or [`CROSS_CHECK_FAILED`]. The header carries `src` in byte 2, `dst` in
+    /// byte 3 and `count` in byte 4; a `count` above [`MAX_COPY_VALUES`], or an account region
+    /// without a suitable account, is rejected as invalid instruction data. Native only.
+    Memcpy { src: Region, dst: Region, count: u8, a: u128 },
+    /// Like [`Memcpy`](Self::Memcpy) within one region, but with `sol_memmove_` moving the values
+    /// 8 bytes forward, so the source and destination overlap and the destination is only 8-byte
+    /// aligned. The header carries `region` in byte 2 and `count` in byte 4.
+    Memmove { region: Region, count: u8, a: u128 },
+    /// Read the Clock sysvar with `sol_get_clock_sysvar`, compute the interest on `principal` at
+    /// `rate` per second since the epoch started and per slot ([`clock::accrued`]), and set the
+    /// two as 32 bytes of return data. Returns 0, or [`ARITHMETIC_OVERFLOW`] if an interest
+    /// overflows. Native only.
+    ///
+    /// [`clock::accrued`]: crate::clock::accrued
+    ClockInterest { principal: u128, rate: u128 },
+    /// Invoke the program itself with `sol_invoke_signed_c`, passing `a` in the inner
+    /// instruction's data, and return `a` folded once the callee's return data matches it, or
+    /// [`CROSS_CHECK_FAILED`]. The callee, marked `inner` (header byte 2 set to 1), sets the
+    /// operand it decoded as return data and returns 0. The outer instruction needs the program
+    /// account among its accounts for the runtime to resolve the callee. Native only.
+    SelfCpi { a: u128, inner: bool },
+    /// Derive the program address with `seed`'s 16 little-endian bytes as the only seed using
+    /// `sol_try_find_program_address`, check `sol_create_program_address` agrees given the bump,
+    /// and set the address followed by the bump as 33 bytes of return data. Returns 0, or
+    /// [`CROSS_CHECK_FAILED`]. Native only.
+    FindPda { seed: u128 },
+    /// Add the lamports of every account passed to `a` in a u128 accumulator, counting a repeated
+    /// account once. Accumulating past `u128::MAX` returns [`ARITHMETIC_OVERFLOW`]. Native only;
+    /// the expected return assumes no accounts, leaving `a` alone.
+    SumLamports { a: u128 },
+    /// Read the u128 (little-endian, at any alignment) at `offset` in the data of each of the
+    /// first `count` accounts and combine them into `a` with `reduction`, returning the folded
+    /// accumulator. The header carries `reduction` in byte 2, `count` in byte 3, and `offset` in
+    /// bytes 4..6 (little-endian). A `count` above [`MAX_ACCOUNTS`] or the accounts passed, or data
+    /// too short for the field, is rejected as invalid instruction data, and a sum overflowing
+    /// returns [`ARITHMETIC_OVERFLOW`]. Native only; the expected return is only known without
+    /// accounts, for a `count` of 0.
+    FoldAccounts {
+        reduction: Reduction,
+        count: u8,
+        offset: u16,
+        a: u128,
+    },
+    /// Add `a` and `b` to the reserves of the [`PoolState`] viewed in place over the first
+    /// account's data, which must be writable, at least [`PoolState::LEN`] bytes long, and aligned
+    /// for it. Returns 0, or [`ARITHMETIC_OVERFLOW`] if a reserve overflows; an unusable account
+    /// is rejected as invalid instruction data. Native only; the expected return depends on the
+    /// account.
+    ///
+    /// [`PoolState`]: crate::state::PoolState
+    /// [`PoolState::LEN`]: crate::state::PoolState::LEN
+    PoolDeposit { a: u128, b: u128 },
+    /// Swap `amount_in` through the pool of [`PoolDeposit`](Self::PoolDeposit) with
+    /// [`PoolState::swap`](crate::state::PoolState::swap), and set the amount out as 16 bytes of
+    /// return data. Returns 0, failing as [`ConstantProduct`](Self::ConstantProduct) does and
+    /// with the same rejections of the account.
+    PoolSwap { amount_in: u128 },
+    /// Decode the Borsh [`Position`] at the start of the first account's data, add `collateral`
+    /// and `debt` to it (checked), encode it back in place, and decode it again to check the
+    /// round trip. Returns 0, [`ARITHMETIC_OVERFLOW`] if a sum overflows, or
+    /// [`CROSS_CHECK_FAILED`]; an account that isn't writable or too short is rejected as invalid
+    /// instruction data. Native only; the expected return depends on the account.
+    ///
+    /// [`Position`]: crate::borsh::Position
+    BorshPosition { collateral: u128, debt: i128 },
+    /// Decode the operand's 16 bytes little-endian, big-endian, and native-endian with the
+    /// [`endian`](crate::endian) helpers, log the three, and set them as 48 bytes of return data
+    /// (each little-endian). Returns the little-endian value folded, or [`CROSS_CHECK_FAILED`] if
+    /// the decodes disagree with the program's aligned operand load or with each other. Native
+    /// only.
+    DecodeEndian { a: u128 },
+    /// `a / b` rounded as `rounding` says, carried in header byte 2. Division by zero is rejected
+    /// as invalid instruction data. Native only.
+    DivRound { a: u128, b: u128, rounding: Rounding },
+    /// `a` narrowed to a u64 with [`try_into_u64`], or, when `signed` (header byte 2 set to 1),
+    /// reinterpreted as an i128 and narrowed with [`try_into_i64`], returning the 64 bits. A value
+    /// out of range returns [`ARITHMETIC_OVERFLOW`]. Native only.
+    ///
+    /// [`try_into_u64`]: crate::convert::try_into_u64
+    /// [`try_into_i64`]: crate::convert::try_into_i64
+    Narrow { a: u128, signed: bool },
+    /// `a * b / c` through a 256-bit product ([`muldiv`]), rounded up when `ceil` (header byte 2
+    /// set to 1) and down otherwise. A zero `c` is rejected as invalid instruction data, and a
+    /// quotient that doesn't fit in a u128 returns [`ARITHMETIC_OVERFLOW`]. Native only.
+    MulDiv { a: u128, b: u128, c: u128, ceil: bool },
+    /// Compound `principal` at `rate_bps` per period over `periods` periods with
+    /// [`interest::compound`], log the balance, and return it folded. The header carries
+    /// `rate_bps` in bytes 2..4 and `periods` in bytes 4..8 (little-endian); a large enough
+    /// `periods` exhausts the compute budget before the balance saturates. Native only.
+    ///
+    /// [`interest::compound`]: crate::interest::compound
+    AccrueInterest { principal: u128, rate_bps: u16, periods: u32 },
+    /// Add `price` times the seconds since the last update, by the Clock sysvar's
+    /// `unix_timestamp`, to the [`Oracle`] viewed in place over the first account's data, and set
+    /// the new cumulative price as 16 bytes of return data. Returns 0; a clock that runs backwards
+    /// is rejected as invalid instruction data, like an account that isn't writable, is too
+    /// short, or isn't aligned. Native only; the expected return depends on the account.
+    ///
+    /// [`Oracle`]: crate::state::Oracle
+    OracleUpdate { price: u128 },
+    /// One concentrated-liquidity swap step with [`sqrt_price::swap_step`]: `amount_in` of token A
+    /// (`a_to_b`, header byte 2 set to 1) or B comes in at the Q64.64 `sqrt_price` and
+    /// `liquidity`, the latter a u128 operand with its high half zero. Sets the next square root
+    /// price and the amount out as 32 bytes of return data and returns the two folded and XORed
+    /// together. A zero price or liquidity is rejected as invalid instruction data, and a result
+    /// that doesn't fit returns [`ARITHMETIC_OVERFLOW`]. Native only.
+    ///
+    /// [`sqrt_price::swap_step`]: crate::sqrt_price::swap_step
+    SqrtPriceStep {
+        sqrt_price: u128,
+        liquidity: u64,
+        amount_in: u128,
+        a_to_b: bool,
+    },
+    /// The amount out of a constant-product swap of `amount_in` against `reserve_in` and
+    /// `reserve_out` with a fee of `fee_bps`, carried in header bytes 2..4 (little-endian), as
+    /// [`swap_amounts`] computes it. A fee over 100% or an empty reserve in is rejected as invalid
+    /// instruction data, and an overflowing product returns [`ARITHMETIC_OVERFLOW`]. Native only.
+    ///
+    /// [`swap_amounts`]: crate::state::swap_amounts
+    ConstantProduct {
+        reserve_in: u128,
+        reserve_out: u128,
+        amount_in: u128,
+        fee_bps: u16,
+    },
+    /// The total notional of the first `count` orders with [`total_notional`], each order one
+    /// 16-byte operand, returned folded, or [`ARITHMETIC_OVERFLOW`] if the total overflows. The
+    /// header carries `count` in byte 2; a `count` of 0 or above [`MAX_ORDERS`] is rejected as
+    /// invalid instruction data, and the orders past it are left out of the data and zero once
+    /// decoded. Build one with [`notional`](Self::notional). Native only.
+    ///
+    /// [`total_notional`]: crate::orderbook::total_notional
+    Notional { count: u8, orders: [Order; MAX_ORDERS as usize] },
+    /// `(1 + rate)^periods` in Q64.64 with [`interest::compound_factor`], returned folded, or
+    /// [`ARITHMETIC_OVERFLOW`] if it doesn't fit. The header carries `periods` in bytes 4..8
+    /// (little-endian). Native only.
+    CompoundFactor { rate: u128, periods: u32 },
+    /// Call every generated [`abi`](crate::abi) function with arguments derived from `seed`,
+    /// check each digest it returns against the one computed directly, and set the digests XORed
+    /// together as 16 bytes of return data ([`abi::expected`]). Returns 0, or
+    /// [`CROSS_CHECK_FAILED`] after logging the index of the first function that disagrees.
+    /// Native only.
+    ///
+    /// [`abi::expected`]: crate::abi::expected
+    AbiTorture { seed: u128 },
+    /// `op` on `a` and `b` as for [`Mul`](Self::Mul), [`Div`](Self::Div), [`Rem`](Self::Rem),
+    /// [`Shl`](Self::Shl), or [`Shr`](Self::Shr), computed by the exported function `entry`
+    /// selects instead of the shared dispatch, so the two lowerings can be compared in one binary
+    /// under identical runtime conditions. The opcode is the entry's, and the header carries `op`
+    /// in byte 2. Division by zero is rejected as invalid instruction data.
+    Entry { entry: Entry, op: EntryOp, a: u128, b: u128 },
+    /// Log which toolchain built the program ([`build_info::toolchain_info`]) and return 0. Takes
+    /// no operands. Native only.
+    ///
+    /// [`build_info::toolchain_info`]: crate::build_info::toolchain_info
+    ToolchainInfo,
+    /// Check every vector of [`self_test::VECTORS`] inside the program and return 0 if all pass,
+    /// or [`SELF_TEST_FAILED`] after logging the index of the first that fails. Takes no operands.
+    /// Native only.
+    ///
+    /// [`self_test::VECTORS`]: crate::self_test::VECTORS
+    SelfTest,
+    /// Set the raw instruction data, header included, as return data, log `a` and `b` as the
+    /// program decoded them (low then high half of each) followed by the data length, and return 0.
+    /// A harness sends it before anything else to confirm the input offsets, endianness, and
+    /// serialization it assumes, so a mismatch there isn't taken for a codegen bug. Native only.
+    Echo { a: u128, b: u128 },
+    /// Fold up to `batch` items of the [`WorkQueue`] in the first account's data, from its cursor
+    /// on, into its accumulator, set the accumulator as 16 bytes of return data, log the new cursor
+    /// and the items left, and return 0. The cursor stays in the account, so a workload too long
+    /// for one transaction's compute budget is split across invocations, each sized by `batch`.
+    /// A zero `batch` or a cursor past the end is rejected as invalid instruction data, like an
+    /// account that isn't writable, is too short for its items, or isn't aligned. Takes no
+    /// operands; `batch` is in header bytes 4 to 7. Native only; the expected return depends on
+    /// the account.
+    ///
+    /// [`WorkQueue`]: crate::state::WorkQueue
+    ProcessChunk { batch: u32 },
+}
+
+impl FixtureInstruction {
+    /// A [`Notional`](Self::Notional) over `orders`, if there are 1 to [`MAX_ORDERS`] of them
+    pub fn notional(orders: &[Order]) -> Option<Self> {
+        if orders.is_empty() || orders.len() > usize::from(MAX_ORDERS) {
+            return None;
+        }
+        let mut padded = [Order::default(); MAX_ORDERS as usize];
+        padded[..orders.len()].copy_from_slice(orders);
+        Some(Self::Notional {
+            count: orders.len() as u8,
+            orders: padded,
+        })
+    }
+
+    /// The header and the operands
+    fn parts(&self) -> ([u8; HEADER_LEN], Vec<u128>) {
+        let header = |opcode, lowering: Lowering| [opcode, lowering as u8, 0, 0, 0, 0, 0, 0];
+        match *self {
+            Self::MulLoop { a, b } => (header(opcode::MUL_LOOP, Lowering::Native), vec![a, b]),
+            Self::Mul { a, b, lowering } => (header(opcode::MUL, lowering), vec![a, b]),
+            Self::Div { a, b, lowering } => (header(opcode::DIV, lowering), vec![a, b]),
+            Self::Rem { a, b, lowering } => (header(opcode::REM, lowering), vec![a, b]),
+            Self::Shl { a, b, lowering } => (header(opcode::SHL, lowering), vec![a, b]),
+            Self::Shr { a, b, lowering } => (header(opcode::SHR, lowering), vec![a, b]),
+            Self::Cmp { a, b, lowering } => (header(opcode::CMP, lowering), vec![a, b]),
+            Self::Add { a, b, lowering } => (header(opcode::ADD, lowering), vec![a, b]),
+            Self::Sub { a, b, lowering } => (header(opcode::SUB, lowering), vec![a, b]),
+            Self::DivRound { a, b, rounding } => {
+                let mut header = header(opcode::DIV_ROUND, Lowering::Native);
+                header[2] = rounding as u8;
+                (header, vec![a, b])
+            }
+            Self::MulDiv { a, b, c, ceil } => {
+                let mut header = header(opcode::MUL_DIV, Lowering::Native);
+                header[2] = ceil as u8;
+                (header, vec![a, b, c])
+            }
+            Self::Batch {
+                op,
+                count,
+                a,
+                b,
+                lowering,
+            } => {
+                let mut header = header(opcode::BATCH, lowering);
+                header[2] = op as u8;
+                header[4..].copy_from_slice(&count.to_le_bytes());
+                (header, vec![a, b])
+            }
+            Self::ReturnData { a } => (header(opcode::RETURN_DATA, Lowering::Native), vec![a]),
+            Self::MemCmp { a, b } => (header(opcode::MEMCMP, Lowering::Native), vec![a, b]),
+            Self::ClockInterest { principal, rate } => {
+                (header(opcode::CLOCK_INTEREST, Lowering::Native), vec![principal, rate])
+            }
+            Self::SelfCpi { a, inner } => {
+                let mut header = header(opcode::SELF_CPI, Lowering::Native);
+                header[2] = inner as u8;
+                (header, vec![a])
+            }
+            Self::SqrtPriceStep {
+                sqrt_price,
+                liquidity,
+                amount_in,
+                a_to_b,
+            } => {
+                let mut header = header(opcode::SQRT_PRICE_STEP, Lowering::Native);
+                header[2] = a_to_b as u8;
+                (header, vec![sqrt_price, liquidity.into(), amount_in])
+            }
+            Self::CompoundFactor { rate, periods } => {
+                let mut header = header(opcode::COMPOUND_FACTOR, Lowering::Native);
+                header[4..].copy_from_slice(&periods.to_le_bytes());
+                (header, vec![rate])
+            }
+            Self::Notional { count, orders } => {
+                let mut header = header(opcode::NOTIONAL, Lowering::Native);
+                header[2] = count;
+                let orders = orders.iter().take(count.into()).map(|&order| order.into());
+                (header, orders.collect())
+            }
+            Self::ConstantProduct {
+                reserve_in,
+                reserve_out,
+                amount_in,
+                fee_bps,
+            } => {
+                let mut header = header(opcode::CONSTANT_PRODUCT, Lowering::Native);
+                header[2..4].copy_from_slice(&fee_bps.to_le_bytes());
+                (header, vec![reserve_in, reserve_out, amount_in])
+            }
+            Self::AccrueInterest {
+                principal,
+                rate_bps,
+                periods,
+            } => {
+                let mut header = header(opcode::ACCRUE_INTEREST, Lowering::Native);
+                header[2..4].copy_from_slice(&rate_bps.to_le_bytes());
+                header[4..].copy_from_slice(&periods.to_le_bytes());
+                (header, vec![principal])
+            }
+            Self::FindPda { seed } => (header(opcode::FIND_PDA, Lowering::Native), vec![seed]),
+            Self::SumLamports { a } => (header(opcode::SUM_LAMPORTS, Lowering::Native), vec![a]),
+            Self::DecodeEndian { a } => (header(opcode::DECODE_ENDIAN, Lowering::Native), vec![a]),
+            Self::AbiTorture { seed } => (header(opcode::ABI_TORTURE, Lowering::Native), vec![seed]),
+            Self::Narrow { a, signed } => {
+                let mut header = header(opcode::NARROW, Lowering::Native);
+                header[2] = signed as u8;
+                (header, vec![a])
+            }
+            Self::FoldAccounts {
+                reduction,
+                count,
+                offset,
+                a,
+            } => {
+                let mut header = header(opcode::FOLD_ACCOUNTS, Lowering::Native);
+                header[2] = reduction as u8;
+                header[3] = count;
+                header[4..6].copy_from_slice(&offset.to_le_bytes());
+                (header, vec![a])
+            }
+            Self::PoolDeposit { a, b } => (header(opcode::POOL_DEPOSIT, Lowering::Native), vec![a, b]),
+            Self::BorshPosition { collateral, debt } => {
+                (header(opcode::BORSH_POSITION, Lowering::Native), vec![collateral, debt as u128])
+            }
+            Self::OracleUpdate { price } => (header(opcode::ORACLE_UPDATE, Lowering::Native), vec![price]),
+            Self::PoolSwap { amount_in } => {
+                (header(opcode::POOL_SWAP, Lowering::Native), vec![amount_in])
+            }
+            Self::Memcpy { src, dst, count, a } => {
+                let mut header = header(opcode::MEMCPY, Lowering::Native);
+                header[2] = src as u8;
+                header[3] = dst as u8;
+                header[4] = count;
+                (header, vec![a])
+            }
+            Self::Memmove { region, count, a } => {
+                let mut header = header(opcode::MEMMOVE, Lowering::Native);
+                header[2] = region as u8;
+                header[4] = count;
+                (header, vec![a])
+            }
+            Self::Entry { entry, op, a, b } => {
+                let mut header = header(entry as u8, Lowering::Native);
+                header[2] = op as u8;
+                (header, vec![a, b])
+            }
+            Self::ToolchainInfo => (header(opcode::TOOLCHAIN_INFO, Lowering::Native), vec![]),
+            Self::SelfTest => (header(opcode::SELF_TEST, Lowering::Native), vec![]),
+            Self::Echo { a, b } => (header(opcode::ECHO, Lowering::Native), vec![a, b]),
+            Self::ProcessChunk { batch } => {
+                let mut header = header(opcode::PROCESS_CHUNK, Lowering::Native);
+                header[4..].copy_from_slice(&batch.to_le_bytes());
+                (header, vec![])
+            }
+        }
+    }
+
+    /// Instruction data for this operation
+    pub fn pack(&self) -> Vec<u8> {
+        let (header, operands) = self.parts();
+        let mut data = Vec::with_capacity(HEADER_LEN + 16 * operands.len());
+        data.extend_from_slice(&header);
+        for operand in operands {
+            data.extend_from_slice(&operand.to_le_bytes());
+        }
+        data
+    }
+
+    /// Decode instruction data produced by [`pack`](Self::pack)
+    pub fn unpack(data: &[u8]) -> Option<Self> {
+        let header = data.get(..HEADER_LEN)?;
+        let lowering = Lowering::from_u8(header[1])?;
+        let without_operands = match header[0] {
+            opcode::TOOLCHAIN_INFO => Some(Self::ToolchainInfo),
+            opcode::SELF_TEST => Some(Self::SelfTest),
+            opcode::PROCESS_CHUNK => Some(Self::ProcessChunk {
+                batch: u32::from_le_bytes(header[4..].try_into().ok()?),
+            }),
+            _ => None,
+        };
+        if let Some(instruction) = without_operands {
+            return (instruction.pack() == data).then_some(instruction);
+        }
+        let a = operand(data, 0)?;
+        let single = match header[0] {
+            opcode::RETURN_DATA => Some(Self::ReturnData { a }),
+            opcode::FIND_PDA => Some(Self::FindPda { seed: a }),
+            opcode::SUM_LAMPORTS => Some(Self::SumLamports { a }),
+            opcode::DECODE_ENDIAN => Some(Self::DecodeEndian { a }),
+            opcode::ABI_TORTURE => Some(Self::AbiTorture { seed: a }),
+            opcode::NARROW => Some(Self::Narrow {
+                a,
+                signed: header[2] == 1,
+            }),
+            opcode::POOL_SWAP => Some(Self::PoolSwap { amount_in: a }),
+            opcode::ORACLE_UPDATE => Some(Self::OracleUpdate { price: a }),
+            opcode::COMPOUND_FACTOR => Some(Self::CompoundFactor {
+                rate: a,
+                periods: u32::from_le_bytes(header[4..].try_into().ok()?),
+            }),
+            opcode::NOTIONAL => {
+                let count = usize::from(header[2]);
+                let orders = (0..count).map(|index| operand(data, index).map(Order::from));
+                Self::notional(&orders.collect::<Option<Vec<_>>>()?)
+            }
+            opcode::ACCRUE_INTEREST => Some(Self::AccrueInterest {
+                principal: a,
+                rate_bps: u16::from_le_bytes([header[2], header[3]]),
+                periods: u32::from_le_bytes(header[4..].try_into().ok()?),
+            }),
+            opcode::FOLD_ACCOUNTS => Some(Self::FoldAccounts {
+                reduction: Reduction::from_u8(header[2])?,
+                count: header[3],
+                offset: u16::from_le_bytes([header[4], header[5]]),
+                a,
+            }),
+            opcode::SELF_CPI => Some(Self::SelfCpi {
+                a,
+                inner: header[2] == 1,
+            }),
+            opcode::MEMCPY => Some(Self::Memcpy {
+                src: Region::from_u8(header[2])?,
+                dst: Region::from_u8(header[3])?,
+                count: header[4],
+                a,
+            }),
+            opcode::MEMMOVE => Some(Self::Memmove {
+                region: Region::from_u8(header[2])?,
+                count: header[4],
+                a,
+            }),
+            _ => None,
+        };
+        if let Some(instruction) = single {
+            return (instruction.pack() == data).then_some(instruction);
+        }
+        let b = operand(data, 1)?;
+        let instruction = match header[0] {
+            opcode::MUL_LOOP => Self::MulLoop { a, b },
+            opcode::MUL => Self::Mul { a, b, lowering },
+            opcode::DIV => Self::Div { a, b, lowering },
+            opcode::REM => Self::Rem { a, b, lowering },
+            opcode::SHL => Self::Shl { a, b, lowering },
+            opcode::SHR => Self::Shr { a, b, lowering },
+            opcode::CMP => Self::Cmp { a, b, lowering },
+            opcode::ADD => Self::Add { a, b, lowering },
+            opcode::SUB => Self::Sub { a, b, lowering },
+            opcode::MEMCMP => Self::MemCmp { a, b },
+            opcode::ECHO => Self::Echo { a, b },
+            opcode::POOL_DEPOSIT => Self::PoolDeposit { a, b },
+            opcode::DIV_ROUND => Self::DivRound {
+                a,
+                b,
+                rounding: Rounding::from_u8(header[2])?,
+            },
+            opcode::BORSH_POSITION => Self::BorshPosition {
+                collateral: a,
+                debt: b as i128,
+            },
+            opcode::CLOCK_INTEREST => Self::ClockInterest { principal: a, rate: b },
+            opcode::MUL_DIV => Self::MulDiv {
+                a,
+                b,
+                c: operand(data, 2)?,
+                ceil: header[2] == 1,
+            },
+            opcode::SQRT_PRICE_STEP => Self::SqrtPriceStep {
+                sqrt_price: a,
+                liquidity: u64::try_from(b).ok()?,
+                amount_in: operand(data, 2)?,
+                a_to_b: header[2] == 1,
+            },
+            opcode::CONSTANT_PRODUCT => Self::ConstantProduct {
+                reserve_in: a,
+                reserve_out: b,
+                amount_in: operand(data, 2)?,
+                fee_bps: u16::from_le_bytes([header[2], header[3]]),
+            },
+            opcode::BATCH => Self::Batch {
+                op: BatchedOp::from_u8(header[2])?,
+                count: u32::from_le_bytes(header[4..].try_into().ok()?),
+                a,
+                b,
+                lowering,
+            },
+            opcode::ENTRY_LIBCALL | opcode::ENTRY_SOFT => Self::Entry {
+                entry: Entry::from_u8(header[0])?,
+                op: EntryOp::from_u8(header[2])?,
+                a,
+                b,
+            },
+            _ => return None,
+        };
+        // Reject trailing bytes and anything in the unused header bytes
+        (instruction.pack() == data).then_some(instruction)
+    }
+
+    /// Whether the program implements the instruction's lowering; it rejects the other
+    /// combinations of operation and lowering as invalid instruction data
+    pub fn lowering_implemented(&self) -> bool {
+        match *self {
+            Self::Div { lowering, .. }
+            | Self::Rem { lowering, .. }
+            | Self::Shl { lowering, .. }
+            | Self::Shr { lowering, .. }
+            | Self::Batch { op: BatchedOp::Div, lowering, .. } => lowering != Lowering::Limbs,
+            Self::Cmp { lowering, .. } | Self::Add { lowering, .. } | Self::Sub { lowering, .. } => {
+                matches!(lowering, Lowering::Native | Lowering::Pair)
+            }
+            _ => true,
+        }
+    }
+
+    /// The return code a correct toolchain and VM produce, computed natively, or `None` when the
+    /// result is undefined or depends on runtime state, like the clock or account data, or the
+    /// program rejects the instruction as invalid, including a lowering it doesn't implement
+    pub fn expected_return(&self) -> Option<u64> {
+        self.expected_result().map(return_code)
+    }
+
+    /// The folded result or the error behind [`expected_return`](Self::expected_return)
+    fn expected_result(&self) -> Option<Result<u64, FixtureError>> {
+        if !self.lowering_implemented() {
+            return None;
+        }
+        let result = match *self {
+            Self::MulLoop { mut a, b } => {
+                for _ in 0..MUL_LOOP_ITERATIONS {
+                    a = a.checked_mul(b)?;
+                }
+                Some((a >> 64) as u64)
+            }
+            Self::Mul { a, b, .. } | Self::Entry { op: EntryOp::Mul, a, b, .. } => {
+                Some(fold(a.wrapping_mul(b)))
+            }
+            Self::Div { a, b, .. } | Self::Entry { op: EntryOp::Div, a, b, .. } => a.checked_div(b).map(fold),
+            Self::Rem { a, b, .. } | Self::Entry { op: EntryOp::Rem, a, b, .. } => a.checked_rem(b).map(fold),
+            Self::DivRound { a, b, rounding } => rounding.div(a, b).map(fold),
+            Self::MulDiv { c: 0, .. } => None,
+            Self::MulDiv { a, b, c, ceil } => {
+                let quotient = if ceil {
+                    muldiv::mul_div_ceil(a, b, c)
+                } else {
+                    muldiv::mul_div_floor(a, b, c)
+                };
+                return Some(quotient.map(fold).ok_or(FixtureError::ArithmeticOverflow));
+            }
+            Self::AccrueInterest {
+                principal,
+                rate_bps,
+                periods,
+            } => Some(fold(interest::compound(principal, rate_bps, periods))),
+            Self::SqrtPriceStep {
+                sqrt_price,
+                liquidity,
+                amount_in,
+                a_to_b,
+            } => {
+                let step = sqrt_price::swap_step(sqrt_price, liquidity, amount_in, a_to_b);
+                return unless_invalid(step.map(|(next, amount_out)| fold(next) ^ fold(amount_out)));
+            }
+            Self::ConstantProduct {
+                reserve_in,
+                reserve_out,
+                amount_in,
+                fee_bps,
+            } => {
+                let swap = state::swap_amounts(reserve_in, reserve_out, amount_in, fee_bps.into());
+                return unless_invalid(swap.map(|(amount_out, _)| fold(amount_out)));
+            }
+            Self::CompoundFactor { rate, periods } => {
+                return Some(interest::compound_factor(rate, periods).map(fold));
+            }
+            Self::Notional { count, orders } => {
+                return (1..=MAX_ORDERS).contains(&count).then(|| {
+                    orderbook::total_notional(orders[..usize::from(count)].iter().copied()).map(fold)
+                });
+            }
+            Self::Narrow { a, signed } => {
+                let narrowed = if signed {
+                    i64::try_from(a as i128).map(|narrowed| narrowed as u64)
+                } else {
+                    u64::try_from(a)
+                };
+                return Some(narrowed.map_err(|_| FixtureError::ArithmeticOverflow));
+            }
+            Self::Shl { a, b, .. } | Self::Entry { op: EntryOp::Shl, a, b, .. } => Some(fold(a << (b % 128))),
+            Self::Shr { a, b, .. } | Self::Entry { op: EntryOp::Shr, a, b, .. } => Some(fold(a >> (b % 128))),
+            Self::Cmp { a, b, .. } | Self::MemCmp { a, b } => Some((a.cmp(&b) as i8 + 1) as u64),
+            Self::Add { a, b, .. } => Some(fold(a.wrapping_add(b))),
+            Self::Sub { a, b, .. } => Some(fold(a.wrapping_sub(b))),
+            Self::Batch { op, count, a, b, .. } => {
+                // A zero divisor is rejected up front, even for an empty batch
+                (op == BatchedOp::Mul || b != 0).then(|| match op {
+                    BatchedOp::Mul => repeat(count, a, b, u128::wrapping_mul),
+                    BatchedOp::Div => repeat(count, a, b, |a, b| a / b),
+                })
+            }
+            Self::ReturnData { a }
+            | Self::SelfCpi { a, inner: false }
+            | Self::SumLamports { a }
+            | Self::DecodeEndian { a } => {
+                Some(fold(a))
+            }
+            Self::SelfCpi { inner: true, .. }
+            | Self::FindPda { .. }
+            | Self::AbiTorture { .. }
+            | Self::ToolchainInfo
+            | Self::SelfTest
+            | Self::Echo { .. } => Some(0),
+            Self::Memcpy { count, a, .. } | Self::Memmove { count, a, .. } => (count <= MAX_COPY_VALUES)
+                .then(|| (0..count).fold(0, |folded, i| folded ^ fold(a.wrapping_add(u128::from(i))))),
+            Self::ClockInterest { .. }
+            | Self::PoolDeposit { .. }
+            | Self::PoolSwap { .. }
+            | Self::OracleUpdate { .. }
+            | Self::ProcessChunk { .. }
+            | Self::BorshPosition { .. } => None,
+            Self::FoldAccounts { count, a, .. } => (count == 0).then(|| fold(a)),
+        };
+        result.map(Ok)
+    }
+}
+
+/// `result`, unless it is the program rejecting the instruction, which has no expected return
+fn unless_invalid(result: Result<u64, FixtureError>) -> Option<Result<u64, FixtureError>> {
+    (result != Err(FixtureError::InvalidInstructionData)).then_some(result)
+}
+
+/// The u128 operand at `index` in instruction data, if the data is long enough
+pub fn operand(data: &[u8], index: usize) -> Option<u128> {
+    let start = HEADER_LEN + 16 * index;
+    Some(u128::from_le_bytes(data.get(start..start + 16)?.try_into().ok()?))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn mul_loop_layout_is_stable() {
+        let instruction = FixtureInstruction::MulLoop {
+            a: 0x0f0e0d0c0b0a09080706050403020100,
+            b: u128::MAX,
+        };
+        let data = instruction.pack();
+        assert_eq!(data[..HEADER_LEN], [opcode::MUL_LOOP, 0, 0, 0, 0, 0, 0, 0]);
+        assert_eq!(data[HEADER_LEN..HEADER_LEN + 16], core::array::from_fn::<u8, 16, _>(|i| i as u8));
+        assert_eq!(data[HEADER_LEN + 16..], [0xff; 16]);
+        assert_eq!(FixtureInstruction::unpack(&data), Some(instruction));
+    }
+
+    #[test]
+    fn entry_opcode_selects_the_entry() {
+        let instruction = FixtureInstruction::Entry {
+            entry: Entry::Soft,
+            op: EntryOp::Rem,
+            a: 7,
+            b: 0,
+        };
+        let mut data = instruction.pack();
+        assert_eq!(data[..HEADER_LEN], [opcode::ENTRY_SOFT, 0, opcode::REM, 0, 0, 0, 0, 0]);
+        assert_eq!(FixtureInstruction::unpack(&data), Some(instruction));
+        assert_eq!(instruction.expected_return(), None);
+        // Only the arithmetic the entries implement
+        data[2] = opcode::CMP;
+        assert_eq!(FixtureInstruction::unpack(&data), None);
+    }
+
+    /// The native references behind [`FixtureInstruction::expected_return`], recomputed with
+    /// `ethnum`'s U256 and `primitive-types`' U128, so a mistake in the reference can't hide a
+    /// backend bug that makes the same mistake
+    #[test]
+    fn references_agree_with_other_implementations() {
+        use crate::error::result_code;
+        use ethnum::U256;
+        use primitive_types::U128;
+
+        let values = [
+            0,
+            1,
+            3,
+            u64::MAX as u128,
+            u64::MAX as u128 + 1,
+            0x0123456789abcdef_fedcba9876543210,
+            i128::MAX as u128,
+            i128::MIN as u128,
+            u128::MAX - 1,
+            u128::MAX,
+        ];
+        for a in values {
+            for b in values {
+                let (wide_a, wide_b) = (U256::new(a), U256::new(b));
+                let (alt_a, alt_b) = (U128::from(a), U128::from(b));
+                let shift = (b % 128) as u32;
+                let cases = [
+                    (
+                        FixtureInstruction::Add { a, b, lowering: Lowering::Native },
+                        (wide_a + wide_b).low().to_owned(),
+                        alt_a.overflowing_add(alt_b).0.as_u128(),
+                    ),
+                    (
+                        FixtureInstruction::Sub { a, b, lowering: Lowering::Native },
+                        wide_a.wrapping_sub(wide_b).low().to_owned(),
+                        alt_a.overflowing_sub(alt_b).0.as_u128(),
+                    ),
+                    (
+                        FixtureInstruction::Mul { a, b, lowering: Lowering::Native },
+                        (wide_a * wide_b).low().to_owned(),
+                        alt_a.overflowing_mul(alt_b).0.as_u128(),
+                    ),
+                    (
+                        FixtureInstruction::Shl { a, b, lowering: Lowering::Native },
+                        (wide_a << shift).low().to_owned(),
+                        (alt_a << shift).as_u128(),
+                    ),
+                    (
+                        FixtureInstruction::Shr { a, b, lowering: Lowering::Native },
+                        (wide_a >> shift).as_u128(),
+                        (alt_a >> shift).as_u128(),
+                    ),
+                ];
+                for (instruction, ethnum, primitive) in cases {
+                    assert_eq!(ethnum, primitive, "{instruction:?}");
+                    let expected = Some(result_code(fold(ethnum)));
+                    assert_eq!(instruction.expected_return(), expected, "{instruction:?}");
+                }
+
+                let divided = (b != 0).then(|| ((wide_a / wide_b).as_u128(), (wide_a % wide_b).as_u128()));
+                let alt_divided = alt_a.checked_div(alt_b).zip(alt_a.checked_rem(alt_b));
+                assert_eq!(divided, alt_divided.map(|(q, r)| (q.as_u128(), r.as_u128())));
+                let div = FixtureInstruction::Div { a, b, lowering: Lowering::Native };
+                let rem = FixtureInstruction::Rem { a, b, lowering: Lowering::Native };
+                assert_eq!(div.expected_return(), divided.map(|(q, _)| result_code(fold(q))), "{div:?}");
+                assert_eq!(rem.expected_return(), divided.map(|(_, r)| result_code(fold(r))), "{rem:?}");
+
+                assert_eq!(wide_a.cmp(&wide_b), alt_a.cmp(&alt_b));
+                let cmp = FixtureInstruction::Cmp { a, b, lowering: Lowering::Native };
+                assert_eq!(cmp.expected_return(), Some((wide_a.cmp(&wide_b) as i8 + 1) as u64));
+
+                for c in values {
+                    // The full product in 256 bits, then divided down; the result must fit again
+                    let product = wide_a * wide_b;
+                    let exact = |ceil: bool| {
+                        let c = U256::new(c);
+                        let quotient = (c != 0).then(|| product / c + U256::from(ceil && product % c != 0))?;
+                        (*quotient.high() == 0).then(|| quotient.as_u128())
+                    };
+                    for ceil in [false, true] {
+                        let mul_div = FixtureInstruction::MulDiv { a, b, c, ceil };
+                        // A zero `c` is invalid, and a quotient that doesn't fit overflows
+                        let expected = (c != 0)
+                            .then(|| exact(ceil).map_or(ARITHMETIC_OVERFLOW, |q| result_code(fold(q))));
+                        assert_eq!(mul_div.expected_return(), expected, "{mul_div:?}");
+                    }
+                }
+            }
+        }
+    }
+}
@@ -0,0 +1,97 @@
+//! Compound interest on a u128 balance the way interest-bearing token extensions accrue it: a
+//! basis-point rate applied once per period, each period's interest rounded down and added to the
+//! principal. A balance that would grow past `u128::MAX` saturates there instead of failing, so
+//! reading a balance can't abort.
+//!
+//! [`compound_factor`] computes the growth factor itself in Q64.64 instead, by repeated squaring,
+//! and fails rather than saturates: a factor past the u128 range has no useful approximation.
+
+use crate::error::FixtureError;
+use crate::muldiv;
+use crate::sqrt_price::Q64;
+use crate::state::BPS_SCALE;
+
+/// `principal` compounded at `rate_bps` per period over `periods` periods
+pub fn compound(principal: u128, rate_bps: u16, periods: u32) -> u128 {
+    let mut balance = principal;
+    for _ in 0..periods {
+        // Nothing changes once the balance is zero or saturated, so a long accrual ends early
+        if balance == 0 || balance == u128::MAX || rate_bps == 0 {
+            break;
+        }
+        let interest = muldiv::mul_div_floor(balance, rate_bps.into(), BPS_SCALE).unwrap_or(u128::MAX);
+        balance = balance.saturating_add(interest);
+    }
+    balance
+}
+
+/// `(1 + rate)^periods` in Q64.64, with `rate` in Q64.64 too, or
+/// [`FixtureError::ArithmeticOverflow`] if the factor reaches 2^64. Each multiply rounds down, so
+/// the factor errs low by at most one unit in the last place per multiply, scaled by the growth
+/// after it.
+pub fn compound_factor(rate: u128, periods: u32) -> Result<u128, FixtureError> {
+    let mul = |a, b| muldiv::mul_div_floor(a, b, Q64).ok_or(FixtureError::ArithmeticOverflow);
+    let mut base = Q64.checked_add(rate).ok_or(FixtureError::ArithmeticOverflow)?;
+    let mut factor = Q64;
+    let mut exponent = periods;
+    while exponent != 0 {
+        if exponent & 1 == 1 {
+            factor = mul(factor, base)?;
+        }
+        exponent >>= 1;
+        // Squaring once more than the exponent needs can overflow where the factor doesn't
+        if exponent == 0 {
+            break;
+        }
+        base = mul(base, base)?;
+    }
+    Ok(factor)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use num_bigint::BigUint;
+
+    #[test]
+    fn matches_bigint() {
+        let limit = BigUint::from(u128::MAX);
+        let principals = [0, 1, 9_999, 10_000, 1_000_000_000, u64::MAX as u128, u128::MAX / 3, u128::MAX];
+        for principal in principals {
+            for rate_bps in [0, 1, 30, 500, 10_000, u16::MAX] {
+                let mut reference = BigUint::from(principal);
+                for periods in 0..64 {
+                    assert_eq!(
+                        compound(principal, rate_bps, periods),
+                        u128::try_from(&reference).unwrap(),
+                        "{principal} at {rate_bps} bps over {periods} periods"
+                    );
+                    let interest = &reference * rate_bps / BPS_SCALE;
+                    reference = (reference + interest).min(limit.clone());
+                }
+            }
+        }
+        assert_eq!(compound(10_000, 100, 2), 10_201);
+        assert_eq!(compound(u128::MAX / 2, 10_000, 2), u128::MAX);
+    }
+
+    #[test]
+    fn compound_factor_guard() {
+        assert_eq!(compound_factor(Q64, 63), Ok(1 << 127));
+        assert_eq!(compound_factor(Q64, 64), Err(FixtureError::ArithmeticOverflow));
+        // 2^32 to the first power fits, though squaring it once more would not
+        let base = 1 << 96;
+        assert_eq!(compound_factor(base - Q64, 1), Ok(base));
+        assert_eq!(compound_factor(base - Q64, 2), Err(FixtureError::ArithmeticOverflow));
+        assert_eq!(compound_factor(u128::MAX - Q64 + 1, 1), Err(FixtureError::ArithmeticOverflow));
+        assert_eq!(compound_factor(u128::MAX, 0), Err(FixtureError::ArithmeticOverflow));
+        assert_eq!(compound_factor(0, u32::MAX), Ok(Q64));
+        assert_eq!(compound_factor(1, 0), Ok(Q64));
+
+        // 1% a period for a year of months, and 5% a year compounded daily
+        for (rate, periods, expected) in [(0.01, 12, 1.01f64.powi(12)), (0.05 / 365.0, 365, 1.051267496467)] {
+            let factor = compound_factor((rate * Q64 as f64) as u128, periods).unwrap();
+            assert!((factor as f64 / Q64 as f64 - expected).abs() < 1e-9, "{rate} over {periods}");
+        }
+    }
+}
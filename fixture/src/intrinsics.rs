@@ -0,0 +1,89 @@
+//! Alternatives to the code LLVM emits for 128-bit arithmetic, so their cost can be compared
+//! with the backend's own lowering in the same program.
+
+// compiler-builtins' 128-bit routines, which sbpf-linker links into the program
+unsafe extern "C" {
+    fn __multi3(a: u128, b: u128) -> u128;
+    fn __udivti3(n: u128, d: u128) -> u128;
+    fn __umodti3(n: u128, d: u128) -> u128;
+    fn __ashlti3(a: u128, b: u32) -> u128;
+    fn __lshrti3(a: u128, b: u32) -> u128;
+}
+
+/// `a * b` (wrapping) through an explicit call to the linker-provided `__multi3`, whatever the
+/// backend would have chosen for a plain multiply
+#[inline(never)]
+pub fn mul_libcall(a: u128, b: u128) -> u128 {
+    unsafe { __multi3(a, b) }
+}
+
+/// `n / d` through the linker-provided `__udivti3`. `d` must not be zero.
+#[inline(never)]
+pub fn div_libcall(n: u128, d: u128) -> u128 {
+    unsafe { __udivti3(n, d) }
+}
+
+/// `n % d` through the linker-provided `__umodti3`. `d` must not be zero.
+#[inline(never)]
+pub fn rem_libcall(n: u128, d: u128) -> u128 {
+    unsafe { __umodti3(n, d) }
+}
+
+/// `a << b` through the linker-provided `__ashlti3`. `b` must be below 128.
+#[inline(never)]
+pub fn shl_libcall(a: u128, b: u32) -> u128 {
+    unsafe { __ashlti3(a, b) }
+}
+
+/// `a >> b` (logical) through the linker-provided `__lshrti3`. `b` must be below 128.
+#[inline(never)]
+pub fn shr_libcall(a: u128, b: u32) -> u128 {
+    unsafe { __lshrti3(a, b) }
+}
+
+/// `a * b` (wrapping) over 64-bit limbs, scheduled by hand for SBPF, which has no high-half
+/// multiply before v2: the low limbs' full product comes from four 32x32-bit products, and the
+/// cross terms only contribute their low 64 bits to the high limb
+#[inline(never)]
+pub fn mul_limbs(a: u128, b: u128) -> u128 {
+    let (a_lo, a_hi) = (a as u64, (a >> 64) as u64);
+    let (b_lo, b_hi) = (b as u64, (b >> 64) as u64);
+    let cross = a_lo.wrapping_mul(b_hi).wrapping_add(a_hi.wrapping_mul(b_lo));
+
+    let (x0, x1) = (a_lo & 0xffff_ffff, a_lo >> 32);
+    let (y0, y1) = (b_lo & 0xffff_ffff, b_lo >> 32);
+    let p00 = x0 * y0;
+    let p01 = x0 * y1;
+    let p10 = x1 * y0;
+    let p11 = x1 * y1;
+    // Three terms below 2^32 each, so the middle column cannot overflow
+    let middle = (p00 >> 32) + (p01 & 0xffff_ffff) + (p10 & 0xffff_ffff);
+    let lo = (middle << 32) | (p00 & 0xffff_ffff);
+    let hi = p11 + (p01 >> 32) + (p10 >> 32) + (middle >> 32);
+
+    ((hi.wrapping_add(cross) as u128) << 64) | lo as u128
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn lowerings_match_native_operators() {
+        let values = [0, 1, 2, 0xffff_ffff, u64::MAX as u128, 1 << 64, 0x1234_5678_9abc_def0_0fed_cba9_8765_4321, u128::MAX];
+        for a in values {
+            for b in values {
+                assert_eq!(mul_limbs(a, b), a.wrapping_mul(b), "{a:#x} * {b:#x}");
+                assert_eq!(mul_libcall(a, b), a.wrapping_mul(b), "{a:#x} * {b:#x}");
+                if b != 0 {
+                    assert_eq!(div_libcall(a, b), a / b, "{a:#x} / {b:#x}");
+                    assert_eq!(rem_libcall(a, b), a % b, "{a:#x} % {b:#x}");
+                }
+            }
+        }
+        for shift in [0, 1, 63, 64, 65, 127] {
+            assert_eq!(shl_libcall(values[6], shift), values[6] << shift, "<< {shift}");
+            assert_eq!(shr_libcall(values[6], shift), values[6] >> shift, ">> {shift}");
+        }
+    }
+}
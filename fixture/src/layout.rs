@@ -0,0 +1,77 @@
+//! Compile-time checks of the layouts the program's offset math relies on: the size and
+//! alignment of u128, and the size, alignment, and field offsets of every `#[repr(C)]` struct
+//! viewed over account data or handed to a syscall. A target or compiler that lays one out
+//! differently fails the build instead of reading the wrong bytes at runtime.
+
+use crate::clock::Clock;
+use crate::state::{Oracle, PoolState, WorkQueue};
+
+/// Fail the build unless `$ty` is `$size` bytes and aligned to `$align`
+macro_rules! assert_layout {
+    ($ty:ty, size = $size:expr, align = $align:expr) => {
+        const _: () = assert!(
+            core::mem::size_of::<$ty>() == $size,
+            concat!("unexpected size of ", stringify!($ty))
+        );
+        const _: () = assert!(
+            core::mem::align_of::<$ty>() == $align,
+            concat!("unexpected alignment of ", stringify!($ty))
+        );
+    };
+}
+
+/// Fail the build unless each field of `$ty` is at the given byte offset
+macro_rules! assert_offsets {
+    ($ty:ty { $($field:ident: $offset:expr),+ $(,)? }) => {
+        $(
+            const _: () = assert!(
+                core::mem::offset_of!($ty, $field) == $offset,
+                concat!("unexpected offset of ", stringify!($ty), "::", stringify!($field))
+            );
+        )+
+    };
+}
+
+// For the syscall structs, which `no-entrypoint` and host `no_std` builds leave out
+#[cfg(all(not(feature = "no-entrypoint"), any(target_arch = "bpf", feature = "std")))]
+pub(crate) use {assert_layout, assert_offsets};
+
+/// Alignment of u128 and i128. Every data layout the fixture is built for has `i128:128`:
+/// `bpfel-unknown-none`, and x86_64 and aarch64 hosts since Rust 1.77.
+pub const U128_ALIGN: usize = 16;
+
+assert_layout!(u128, size = 16, align = U128_ALIGN);
+assert_layout!(i128, size = 16, align = U128_ALIGN);
+
+// The layouts account data is written in; a change here breaks existing accounts
+assert_layout!(PoolState, size = 80, align = U128_ALIGN);
+assert_offsets!(PoolState {
+    reserve_a: 0,
+    reserve_b: 16,
+    fees_a: 32,
+    fees_b: 48,
+    fee_bps: 64,
+    padding: 72,
+});
+assert_layout!(Oracle, size = 32, align = U128_ALIGN);
+assert_offsets!(Oracle {
+    cumulative_price: 0,
+    last_timestamp: 16,
+    padding: 24,
+});
+assert_layout!(WorkQueue, size = 32, align = U128_ALIGN);
+assert_offsets!(WorkQueue {
+    accumulator: 0,
+    cursor: 16,
+    len: 24,
+});
+
+// The layout `sol_get_clock_sysvar` writes
+assert_layout!(Clock, size = 40, align = 8);
+assert_offsets!(Clock {
+    slot: 0,
+    epoch_start_timestamp: 8,
+    epoch: 16,
+    leader_schedule_epoch: 24,
+    unix_timestamp: 32,
+});
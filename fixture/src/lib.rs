@@ -0,0 +1,45 @@
+//! An SBPF program exercising 128-bit integer arithmetic, and the encoding of the instructions
+//! it accepts.
+//!
+//! Build the program for `bpfel-unknown-none` with an i128-capable toolchain and load the
+//! resulting `libu128_bpf_fixture.so` into the VM under test. Depend on the crate with the
+//! `no-entrypoint` feature to use [`instruction::FixtureInstruction`] from the host side.
+//!
+//! Everything the program computes lives in modules that build for the host as well, and
+//! [`execute`] runs the opcodes that need neither syscalls nor accounts exactly as the program
+//! does, so unit tests, Miri, fuzzers, and Kani harnesses check the code that ships. Only the
+//! entrypoint, the input parser, and the syscall shims are BPF-specific, and their host stand-ins
+//! for the runtime need the default `std` feature. Without it the library is `no_std` on the host
+//! too, as on BPF, and leaves those three out, which checks the shipped code needs nothing from
+//! std. Only the rlib builds that way: linking the host cdylib needs std's panic handler and
+//! allocator. Unit tests always build with std.
+#![cfg_attr(any(target_arch = "bpf", not(any(test, feature = "std"))), no_std)]
+
+pub mod abi;
+pub mod borsh;
+pub mod build_info;
+pub mod clock;
+pub mod convert;
+pub mod endian;
+pub mod error;
+#[cfg(all(not(feature = "no-entrypoint"), any(target_arch = "bpf", feature = "std")))]
+mod entrypoint;
+pub mod execute;
+pub mod fold;
+#[cfg(all(not(feature = "no-entrypoint"), any(target_arch = "bpf", feature = "std")))]
+mod input;
+pub mod instruction;
+pub mod interest;
+pub mod intrinsics;
+pub mod layout;
+pub mod muldiv;
+pub mod orderbook;
+pub mod pair;
+#[cfg(any(test, all(target_arch = "bpf", feature = "panic-log", not(feature = "no-entrypoint"))))]
+mod panic_log;
+pub mod rounding;
+pub mod self_test;
+pub mod sqrt_price;
+pub mod state;
+#[cfg(all(not(feature = "no-entrypoint"), any(target_arch = "bpf", feature = "std")))]
+mod syscalls;
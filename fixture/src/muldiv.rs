@@ -0,0 +1,108 @@
+//! `a * b / c` at full precision: the product is kept as 256 bits, so the result is exact
+//! whenever the quotient itself fits in a u128, however large the product. Fee, price, and share
+//! computations all reduce to this, and the naive `a * b / c` overflows long before the quotient
+//! does.
+
+/// The full product `a * b` as `(high, low)` 128-bit halves
+pub fn widening_mul(a: u128, b: u128) -> (u128, u128) {
+    let (a_hi, a_lo) = (a >> 64, a as u64 as u128);
+    let (b_hi, b_lo) = (b >> 64, b as u64 as u128);
+    let lo_lo = a_lo * b_lo;
+    let lo_hi = a_lo * b_hi;
+    let hi_lo = a_hi * b_lo;
+    let hi_hi = a_hi * b_hi;
+    // Three 64-bit terms and the carry out of the low word; at most 3 * (2^64 - 1), so no overflow
+    let middle = (lo_lo >> 64) + (lo_hi as u64 as u128) + (hi_lo as u64 as u128);
+    let low = (middle << 64) | (lo_lo as u64 as u128);
+    let high = hi_hi + (lo_hi >> 64) + (hi_lo >> 64) + (middle >> 64);
+    (high, low)
+}
+
+/// `(high, low) / divisor` as the quotient and remainder, or `None` for a zero divisor or a
+/// quotient wider than 128 bits
+pub fn div_rem_wide((high, low): (u128, u128), divisor: u128) -> Option<(u128, u128)> {
+    if divisor == 0 || high >= divisor {
+        return None;
+    }
+    // Restoring division one bit of `low` at a time; the remainder stays below `divisor`, and
+    // the bit shifted out of it accounts for remainders past 2^127
+    let mut remainder = high;
+    let mut quotient = 0;
+    for bit in (0..128).rev() {
+        let carry = remainder >> 127;
+        remainder = (remainder << 1) | ((low >> bit) & 1);
+        quotient <<= 1;
+        if carry != 0 || remainder >= divisor {
+            remainder = remainder.wrapping_sub(divisor);
+            quotient |= 1;
+        }
+    }
+    Some((quotient, remainder))
+}
+
+/// `a * b / c` rounded down, or `None` for a zero `c` or a quotient that overflows
+pub fn mul_div_floor(a: u128, b: u128, c: u128) -> Option<u128> {
+    div_rem_wide(widening_mul(a, b), c).map(|(quotient, _)| quotient)
+}
+
+/// `a * b / c` rounded up, or `None` for a zero `c` or a quotient that overflows
+pub fn mul_div_ceil(a: u128, b: u128, c: u128) -> Option<u128> {
+    let (quotient, remainder) = div_rem_wide(widening_mul(a, b), c)?;
+    quotient.checked_add(u128::from(remainder != 0))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use num_bigint::BigUint;
+    use proptest::prelude::*;
+
+    /// `a * b / c` in arbitrary precision, rounded up if `ceil`, if it fits
+    fn reference(a: u128, b: u128, c: u128, ceil: bool) -> Option<u128> {
+        if c == 0 {
+            return None;
+        }
+        let product = BigUint::from(a) * BigUint::from(b);
+        let c = BigUint::from(c);
+        let quotient = if ceil {
+            (product + &c - 1u8) / c
+        } else {
+            product / c
+        };
+        u128::try_from(quotient).ok()
+    }
+
+    /// Operands biased toward the edges of the range, where the carries are
+    fn operand() -> impl Strategy<Value = u128> {
+        prop_oneof![
+            any::<u128>(),
+            any::<u64>().prop_map(u128::from),
+            (0..128u32, any::<u128>()).prop_map(|(shift, noise)| (u128::MAX >> shift) ^ (noise & 0xff)),
+            Just(0),
+            Just(1),
+            Just(u128::MAX),
+        ]
+    }
+
+    #[test]
+    fn widening_mul_edges() {
+        assert_eq!(widening_mul(u128::MAX, u128::MAX), (u128::MAX - 1, 1));
+        assert_eq!(widening_mul(1 << 64, 1 << 64), (1, 0));
+        assert_eq!(widening_mul(u64::MAX as u128, u64::MAX as u128), (0, (u64::MAX as u128).pow(2)));
+        assert_eq!(mul_div_floor(u128::MAX, u128::MAX, u128::MAX), Some(u128::MAX));
+        assert_eq!(mul_div_ceil(u128::MAX, u128::MAX - 1, u128::MAX), Some(u128::MAX - 1));
+        assert_eq!(mul_div_floor(u128::MAX, 2, 1), None);
+        assert_eq!(mul_div_ceil(1, 1, 0), None);
+    }
+
+    proptest! {
+        #[test]
+        fn matches_bigint(a in operand(), b in operand(), c in operand()) {
+            let (high, low) = widening_mul(a, b);
+            let product = (BigUint::from(high) << 128u32) + BigUint::from(low);
+            prop_assert_eq!(product, BigUint::from(a) * BigUint::from(b));
+            prop_assert_eq!(mul_div_floor(a, b, c), reference(a, b, c, false));
+            prop_assert_eq!(mul_div_ceil(a, b, c), reference(a, b, c, true));
+        }
+    }
+}
@@ -0,0 +1,63 @@
+//! Order notionals the way a central limit order book computes them: prices and sizes stay u64,
+//! as they are stored, and each `price * size` widens to a u128 that can't overflow. Only the
+//! running total can, and it is checked.
+
+use crate::error::FixtureError;
+
+/// A resting order, encoded in instruction data as 16 bytes: the price, then the size, each
+/// little-endian. Read as a little-endian u128, that is `price | size << 64`.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct Order {
+    pub price: u64,
+    pub size: u64,
+}
+
+impl Order {
+    /// `price * size`, exact
+    pub fn notional(&self) -> u128 {
+        u128::from(self.price) * u128::from(self.size)
+    }
+}
+
+impl From<u128> for Order {
+    fn from(value: u128) -> Self {
+        Self {
+            price: value as u64,
+            size: (value >> 64) as u64,
+        }
+    }
+}
+
+impl From<Order> for u128 {
+    fn from(order: Order) -> Self {
+        u128::from(order.price) | u128::from(order.size) << 64
+    }
+}
+
+/// The notionals of `orders` added up, or [`FixtureError::ArithmeticOverflow`] if the total
+/// passes `u128::MAX`
+pub fn total_notional(orders: impl IntoIterator<Item = Order>) -> Result<u128, FixtureError> {
+    orders
+        .into_iter()
+        .try_fold(0u128, |total, order| total.checked_add(order.notional()))
+        .ok_or(FixtureError::ArithmeticOverflow)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn totals() {
+        let order = Order { price: 0x0706050403020100, size: 0x0f0e0d0c0b0a0908 };
+        assert_eq!(u128::from(order).to_le_bytes(), core::array::from_fn(|i| i as u8));
+        assert_eq!(Order::from(u128::from(order)), order);
+
+        let largest = Order { price: u64::MAX, size: u64::MAX };
+        assert_eq!(largest.notional(), u128::MAX - 2 * u128::from(u64::MAX));
+        assert_eq!(total_notional([]), Ok(0));
+        assert_eq!(total_notional([largest, Order { price: u64::MAX, size: 2 }]), Ok(u128::MAX));
+        let overflowing = [largest, Order { price: 3, size: u64::MAX }];
+        assert_eq!(total_notional(overflowing), Err(FixtureError::ArithmeticOverflow));
+    }
+}
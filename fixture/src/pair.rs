@@ -0,0 +1,162 @@
+//! 128-bit arithmetic over an explicit pair of u64 limbs, the way programs work around a toolchain
+//! without u128 support today. It only uses 64-bit operations, so it serves as the baseline the
+//! native u128 lowerings are benchmarked against.
+
+/// A 128-bit unsigned integer as two u64 limbs
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord)]
+pub struct U64Pair {
+    // `hi` first, so the derived ordering compares the high limbs before the low ones
+    pub hi: u64,
+    pub lo: u64,
+}
+
+impl U64Pair {
+    pub const ZERO: Self = Self { hi: 0, lo: 0 };
+
+    /// The full 128-bit product of two u64 values, from four 32x32-bit products
+    pub fn widening_mul(a: u64, b: u64) -> Self {
+        let (a0, a1) = (a & 0xffff_ffff, a >> 32);
+        let (b0, b1) = (b & 0xffff_ffff, b >> 32);
+        let p00 = a0 * b0;
+        let p01 = a0 * b1;
+        let p10 = a1 * b0;
+        let p11 = a1 * b1;
+        let middle = (p00 >> 32) + (p01 & 0xffff_ffff) + (p10 & 0xffff_ffff);
+        Self {
+            hi: p11 + (p01 >> 32) + (p10 >> 32) + (middle >> 32),
+            lo: (middle << 32) | (p00 & 0xffff_ffff),
+        }
+    }
+
+    pub fn wrapping_mul(self, rhs: Self) -> Self {
+        let low = Self::widening_mul(self.lo, rhs.lo);
+        let cross = self.lo.wrapping_mul(rhs.hi).wrapping_add(self.hi.wrapping_mul(rhs.lo));
+        Self {
+            hi: low.hi.wrapping_add(cross),
+            lo: low.lo,
+        }
+    }
+
+    pub fn wrapping_add(self, rhs: Self) -> Self {
+        let (lo, carry) = self.lo.overflowing_add(rhs.lo);
+        Self {
+            hi: self.hi.wrapping_add(rhs.hi).wrapping_add(carry as u64),
+            lo,
+        }
+    }
+
+    pub fn wrapping_sub(self, rhs: Self) -> Self {
+        let (lo, borrow) = self.lo.overflowing_sub(rhs.lo);
+        Self {
+            hi: self.hi.wrapping_sub(rhs.hi).wrapping_sub(borrow as u64),
+            lo,
+        }
+    }
+
+    /// `self << (shift % 128)`
+    pub fn wrapping_shl(self, shift: u32) -> Self {
+        match shift % 128 {
+            0 => self,
+            shift @ 1..=63 => Self {
+                hi: (self.hi << shift) | (self.lo >> (64 - shift)),
+                lo: self.lo << shift,
+            },
+            shift => Self {
+                hi: self.lo << (shift - 64),
+                lo: 0,
+            },
+        }
+    }
+
+    /// `self >> (shift % 128)`, logical
+    pub fn wrapping_shr(self, shift: u32) -> Self {
+        match shift % 128 {
+            0 => self,
+            shift @ 1..=63 => Self {
+                hi: self.hi >> shift,
+                lo: (self.lo >> shift) | (self.hi << (64 - shift)),
+            },
+            shift => Self {
+                hi: 0,
+                lo: self.hi >> (shift - 64),
+            },
+        }
+    }
+
+    pub fn leading_zeros(self) -> u32 {
+        if self.hi == 0 {
+            64 + self.lo.leading_zeros()
+        } else {
+            self.hi.leading_zeros()
+        }
+    }
+
+    /// Quotient and remainder by binary long division, or `None` for a zero divisor
+    pub fn checked_div_rem(self, rhs: Self) -> Option<(Self, Self)> {
+        if rhs == Self::ZERO {
+            return None;
+        }
+        // Both fit in a u64: one native division
+        if self.hi == 0 && rhs.hi == 0 {
+            let quotient = Self { hi: 0, lo: self.lo / rhs.lo };
+            let remainder = Self { hi: 0, lo: self.lo % rhs.lo };
+            return Some((quotient, remainder));
+        }
+        let mut quotient = Self::ZERO;
+        let mut remainder = Self::ZERO;
+        for bit in (0..128 - self.leading_zeros()).rev() {
+            let (limb, offset) = if bit < 64 { (self.lo, bit) } else { (self.hi, bit - 64) };
+            remainder = remainder.wrapping_shl(1);
+            remainder.lo |= (limb >> offset) & 1;
+            if remainder >= rhs {
+                remainder = remainder.wrapping_sub(rhs);
+                if bit < 64 {
+                    quotient.lo |= 1 << offset;
+                } else {
+                    quotient.hi |= 1 << offset;
+                }
+            }
+        }
+        Some((quotient, remainder))
+    }
+}
+
+impl From<u128> for U64Pair {
+    fn from(value: u128) -> Self {
+        Self {
+            hi: (value >> 64) as u64,
+            lo: value as u64,
+        }
+    }
+}
+
+impl From<U64Pair> for u128 {
+    fn from(pair: U64Pair) -> Self {
+        (u128::from(pair.hi) << 64) | u128::from(pair.lo)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn matches_u128() {
+        let values = [0, 1, 3, 0xffff_ffff, u64::MAX as u128, 1 << 64, 0x1234_5678_9abc_def0_0fed_cba9_8765_4321, u128::MAX];
+        for a in values {
+            for b in values {
+                let (x, y) = (U64Pair::from(a), U64Pair::from(b));
+                assert_eq!(u128::from(x.wrapping_add(y)), a.wrapping_add(b), "{a:#x} + {b:#x}");
+                assert_eq!(u128::from(x.wrapping_sub(y)), a.wrapping_sub(b), "{a:#x} - {b:#x}");
+                assert_eq!(u128::from(x.wrapping_mul(y)), a.wrapping_mul(b), "{a:#x} * {b:#x}");
+                assert_eq!(x.cmp(&y), a.cmp(&b), "{a:#x} cmp {b:#x}");
+                let expected = a.checked_div(b).zip(a.checked_rem(b));
+                let actual = x.checked_div_rem(y).map(|(q, r)| (u128::from(q), u128::from(r)));
+                assert_eq!(actual, expected, "{a:#x} / {b:#x}");
+                let shift = (b % 128) as u32;
+                assert_eq!(u128::from(x.wrapping_shl(shift)), a << shift, "{a:#x} << {shift}");
+                assert_eq!(u128::from(x.wrapping_shr(shift)), a >> shift, "{a:#x} >> {shift}");
+            }
+        }
+    }
+}
@@ -0,0 +1,95 @@
+//! The panic handler of a `panic-log` build: log where the program panicked with `sol_log`, then
+//! abort. The message is assembled by hand rather than with `core::fmt`, so the diagnostic costs a
+//! few hundred bytes of .text instead of the formatting machinery.
+
+/// Longest diagnostic logged; longer messages are truncated
+const MESSAGE_CAPACITY: usize = 192;
+
+/// A fixed-size buffer the diagnostic is assembled in, without allocating
+struct Message {
+    bytes: [u8; MESSAGE_CAPACITY],
+    len: usize,
+}
+
+impl Message {
+    const fn new() -> Self {
+        Self { bytes: [0; MESSAGE_CAPACITY], len: 0 }
+    }
+
+    /// Append as much of `text` as fits, cut at a char boundary
+    fn push_str(&mut self, text: &str) {
+        let mut end = text.len().min(MESSAGE_CAPACITY - self.len);
+        while !text.is_char_boundary(end) {
+            end -= 1;
+        }
+        self.bytes[self.len..self.len + end].copy_from_slice(&text.as_bytes()[..end]);
+        self.len += end;
+    }
+
+    /// Append `value` in decimal
+    fn push_u32(&mut self, mut value: u32) {
+        let mut digits = [0u8; 10];
+        let mut start = digits.len();
+        loop {
+            start -= 1;
+            digits[start] = b'0' + (value % 10) as u8;
+            value /= 10;
+            if value == 0 {
+                break;
+            }
+        }
+        // ASCII digits are always valid UTF-8
+        self.push_str(unsafe { core::str::from_utf8_unchecked(&digits[start..]) });
+    }
+
+    fn as_str(&self) -> &str {
+        // Only whole `str`s, cut at char boundaries, are ever pushed
+        unsafe { core::str::from_utf8_unchecked(&self.bytes[..self.len]) }
+    }
+}
+
+/// `panicked at <file>:<line>:<column>`, followed by `: <message>` when the panic message is a
+/// plain string; a formatted one would need `core::fmt` to render
+fn diagnostic(location: Option<(&str, u32, u32)>, message: Option<&str>) -> Message {
+    let mut diagnostic = Message::new();
+    diagnostic.push_str("panicked");
+    if let Some((file, line, column)) = location {
+        diagnostic.push_str(" at ");
+        diagnostic.push_str(file);
+        diagnostic.push_str(":");
+        diagnostic.push_u32(line);
+        diagnostic.push_str(":");
+        diagnostic.push_u32(column);
+    }
+    if let Some(message) = message {
+        diagnostic.push_str(": ");
+        diagnostic.push_str(message);
+    }
+    diagnostic
+}
+
+#[cfg(target_arch = "bpf")]
+#[panic_handler]
+fn panic(info: &core::panic::PanicInfo) -> ! {
+    let location = info.location().map(|location| (location.file(), location.line(), location.column()));
+    crate::syscalls::log(diagnostic(location, info.message().as_str()).as_str());
+    crate::syscalls::abort()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn formats_location_and_message() {
+        let logged = diagnostic(Some(("src/entrypoint.rs", 58, 46)), Some("deliberate panic"));
+        assert_eq!(logged.as_str(), "panicked at src/entrypoint.rs:58:46: deliberate panic");
+        assert_eq!(diagnostic(None, None).as_str(), "panicked");
+
+        // Truncated at the last char boundary that fits
+        let long = "é".repeat(MESSAGE_CAPACITY);
+        let logged = diagnostic(Some(("a.rs", 0, u32::MAX)), Some(&long));
+        assert!(logged.as_str().starts_with("panicked at a.rs:0:4294967295: éé"));
+        assert!(logged.as_str().len() > MESSAGE_CAPACITY - 2);
+    }
+}
@@ -0,0 +1,71 @@
+//! u128 division with the rounding direction explicit. Token math rounds in whichever direction
+//! favors the protocol, so an off-by-one in the remainder handling, whether in a program or in
+//! the backend's lowering of `/` and `%`, moves value between users.
+
+/// `a / b` rounded down, or `None` for a zero divisor
+pub fn div_floor(a: u128, b: u128) -> Option<u128> {
+    a.checked_div(b)
+}
+
+/// `a / b` rounded up, or `None` for a zero divisor
+pub fn div_ceil(a: u128, b: u128) -> Option<u128> {
+    let quotient = a.checked_div(b)?;
+    let remainder = a % b;
+    Some(quotient + u128::from(remainder != 0))
+}
+
+/// `a / b` rounded to the nearest integer, halves up, or `None` for a zero divisor
+pub fn div_round(a: u128, b: u128) -> Option<u128> {
+    let quotient = a.checked_div(b)?;
+    let remainder = a % b;
+    // `2 * remainder >= b` without overflowing, since `remainder < b`
+    Some(quotient + u128::from(remainder >= b - remainder))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn small_operands_exhaustively() {
+        for a in 0..512u128 {
+            for b in 1..64u128 {
+                assert_eq!(div_floor(a, b), Some(a / b));
+                assert_eq!(div_ceil(a, b), Some(a.div_ceil(b)));
+                assert_eq!(div_round(a, b), Some((2 * a + b) / (2 * b)), "{a} / {b}");
+            }
+        }
+        assert_eq!((div_floor(1, 0), div_ceil(1, 0), div_round(1, 0)), (None, None, None));
+    }
+
+    #[test]
+    fn remainder_boundaries() {
+        let divisors = [
+            1,
+            2,
+            3,
+            10,
+            u64::MAX as u128,
+            u64::MAX as u128 + 1,
+            u128::MAX / 3,
+            u128::MAX - 1,
+            u128::MAX,
+        ];
+        for b in divisors {
+            let half = b / 2;
+            let remainders = [0, 1, half.saturating_sub(1), half, half + 1, b - 1];
+            for q in [0, 1, 2, u128::MAX / b - 1, u128::MAX / b] {
+                for r in remainders.into_iter().filter(|&r| r < b) {
+                    // Built as q * b + r, so the exact quotient is q + r / b
+                    let Some(a) = q.checked_mul(b).and_then(|qb| qb.checked_add(r)) else {
+                        continue;
+                    };
+                    assert_eq!(div_floor(a, b), Some(q), "{a:#x} / {b:#x}");
+                    assert_eq!(div_ceil(a, b), Some(q + u128::from(r > 0)), "{a:#x} / {b:#x} up");
+                    let nearest = q + u128::from(r >= b.div_ceil(2));
+                    assert_eq!(div_round(a, b), Some(nearest), "{a:#x} / {b:#x} nearest");
+                }
+            }
+        }
+    }
+}
@@ -0,0 +1,74 @@
+//! A table of u128 test vectors, generated by `build.rs` with the results the build host computed
+//! and stored in .rodata, that [`SelfTest`](crate::instruction::FixtureInstruction::SelfTest)
+//! checks entirely inside the program. One instruction then validates a deployed binary's
+//! arithmetic without a host harness to compare against.
+
+mod generated {
+    use super::{Op, Vector};
+
+    include!(concat!(env!("OUT_DIR"), "/self_test.rs"));
+}
+
+pub use generated::VECTORS;
+
+/// An operation of the self-test, wrapping like the unchecked operators of a release build
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Op {
+    Add,
+    Sub,
+    Mul,
+    Div,
+    Rem,
+    /// By the shift amount modulo 128
+    Shl,
+    /// By the shift amount modulo 128
+    Shr,
+}
+
+impl Op {
+    /// `a` and `b` under the operation, `None` for a division by zero
+    pub fn apply(self, a: u128, b: u128) -> Option<u128> {
+        match self {
+            Op::Add => Some(a.wrapping_add(b)),
+            Op::Sub => Some(a.wrapping_sub(b)),
+            Op::Mul => Some(a.wrapping_mul(b)),
+            Op::Div => a.checked_div(b),
+            Op::Rem => a.checked_rem(b),
+            Op::Shl => Some(a.wrapping_shl(b as u32)),
+            Op::Shr => Some(a.wrapping_shr(b as u32)),
+        }
+    }
+}
+
+/// `op` on `a` and `b` must give `expected`
+#[derive(Clone, Copy, Debug)]
+pub struct Vector {
+    pub op: Op,
+    pub a: u128,
+    pub b: u128,
+    pub expected: u128,
+}
+
+/// The index of the first vector whose result differs from the one it expects. Each is read
+/// through a volatile load, so the compiler can't evaluate the table at build time and every
+/// operation runs on the target.
+pub fn check() -> Result<(), usize> {
+    let failure = VECTORS.iter().position(|vector| {
+        let Vector { op, a, b, expected } = unsafe { core::ptr::read_volatile(vector) };
+        op.apply(a, b) != Some(expected)
+    });
+    failure.map_or(Ok(()), Err)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn host_agrees_with_the_table() {
+        assert_eq!(check(), Ok(()));
+        for op in [Op::Add, Op::Sub, Op::Mul, Op::Div, Op::Rem, Op::Shl, Op::Shr] {
+            assert!(VECTORS.iter().any(|vector| vector.op == op), "no vector for {op:?}");
+        }
+    }
+}
@@ -0,0 +1,154 @@
+//! One swap step of a concentrated-liquidity pool, with the price kept as its square root in
+//! Q64.64 fixed point (64 integer bits, 64 fractional bits) as Uniswap v3 style pools on Solana
+//! store it. Every step rounds in the pool's favor: the next price toward the side that gives the
+//! trader less, and the amount out down.
+
+use crate::error::FixtureError;
+use crate::muldiv;
+use crate::rounding;
+
+/// 1.0 in Q64.64
+pub const Q64: u128 = 1 << 64;
+
+/// The square root price after `amount` of token A comes in, which moves the price down:
+/// `L * sqrt_price / (L + amount * sqrt_price)`, rounded up. `None` for a zero price or
+/// liquidity, or a result that doesn't fit.
+pub fn next_sqrt_price_from_a_in(sqrt_price: u128, liquidity: u64, amount: u128) -> Option<u128> {
+    if sqrt_price == 0 || liquidity == 0 {
+        return None;
+    }
+    if amount == 0 {
+        return Some(sqrt_price);
+    }
+    // L in Q64.64, which fits because liquidity is 64 bits
+    let numerator = u128::from(liquidity) << 64;
+    if let Some(denominator) = amount.checked_mul(sqrt_price).and_then(|product| numerator.checked_add(product)) {
+        return muldiv::mul_div_ceil(numerator, sqrt_price, denominator);
+    }
+    // The same price divided through by `sqrt_price`, less precise but free of the overflowing
+    // product; the floor in the denominator only rounds the result further up
+    rounding::div_ceil(numerator, (numerator / sqrt_price).checked_add(amount)?)
+}
+
+/// The square root price after `amount` of token B comes in, which moves the price up:
+/// `sqrt_price + amount / L`, rounded down. `None` for a zero liquidity or a result that doesn't
+/// fit.
+pub fn next_sqrt_price_from_b_in(sqrt_price: u128, liquidity: u64, amount: u128) -> Option<u128> {
+    let quotient = muldiv::mul_div_floor(amount, Q64, liquidity.into())?;
+    sqrt_price.checked_add(quotient)
+}
+
+/// Token A between two square root prices at `liquidity`:
+/// `L * (upper - lower) / (upper * lower)`, rounded down
+pub fn amount_a_delta(lower: u128, upper: u128, liquidity: u64) -> Option<u128> {
+    if lower == 0 || lower > upper {
+        return None;
+    }
+    let numerator = u128::from(liquidity) << 64;
+    Some(muldiv::mul_div_floor(numerator, upper - lower, upper)? / lower)
+}
+
+/// Token B between two square root prices at `liquidity`: `L * (upper - lower)`, rounded down
+pub fn amount_b_delta(lower: u128, upper: u128, liquidity: u64) -> Option<u128> {
+    muldiv::mul_div_floor(liquidity.into(), upper.checked_sub(lower)?, Q64)
+}
+
+/// Swap `amount_in` of token A for B (`a_to_b`) or B for A within a single liquidity range,
+/// returning the next square root price and the amount out.
+/// [`FixtureError::InvalidInstructionData`] for a zero price or liquidity, and
+/// [`FixtureError::ArithmeticOverflow`] for a price or amount that doesn't fit.
+pub fn swap_step(
+    sqrt_price: u128,
+    liquidity: u64,
+    amount_in: u128,
+    a_to_b: bool,
+) -> Result<(u128, u128), FixtureError> {
+    if sqrt_price == 0 || liquidity == 0 {
+        return Err(FixtureError::InvalidInstructionData);
+    }
+    // With both nonzero, every step below fails only on a result that doesn't fit
+    let step = || {
+        if a_to_b {
+            let next = next_sqrt_price_from_a_in(sqrt_price, liquidity, amount_in)?;
+            Some((next, amount_b_delta(next, sqrt_price, liquidity)?))
+        } else {
+            let next = next_sqrt_price_from_b_in(sqrt_price, liquidity, amount_in)?;
+            Some((next, amount_a_delta(sqrt_price, next, liquidity)?))
+        }
+    };
+    step().ok_or(FixtureError::ArithmeticOverflow)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use num_bigint::BigUint;
+    use proptest::prelude::*;
+
+    /// The bounds of the square root price in the pools this models, about 2^32 and 2^96
+    const MIN_SQRT_PRICE: u128 = 4_295_048_016;
+    const MAX_SQRT_PRICE: u128 = 79_226_673_515_401_279_992_447_579_055;
+
+    fn big(value: impl Into<BigUint>) -> BigUint {
+        value.into()
+    }
+
+    fn ceil_div(a: BigUint, b: &BigUint) -> BigUint {
+        (a + b - 1u8) / b
+    }
+
+    /// [`swap_step`] in arbitrary precision, taking the same fallback where the fixed-width
+    /// version's product overflows
+    fn reference(sqrt_price: u128, liquidity: u64, amount_in: u128, a_to_b: bool) -> Option<(u128, u128)> {
+        if sqrt_price == 0 || liquidity == 0 {
+            return None;
+        }
+        let (price, numerator) = (big(sqrt_price), big(liquidity) << 64u32);
+        let (next, amount_out) = if a_to_b {
+            let product = big(amount_in) * &price;
+            let next = if amount_in == 0 {
+                price.clone()
+            } else if &numerator + &product <= big(u128::MAX) {
+                ceil_div(&numerator * &price, &(&numerator + product))
+            } else {
+                ceil_div(numerator.clone(), &(&numerator / &price + amount_in))
+            };
+            let amount_out = (big(liquidity) * (&price - &next)) >> 64u32;
+            (next, amount_out)
+        } else {
+            let next = &price + (big(amount_in) << 64u32) / liquidity;
+            let amount_out = numerator * (&next - &price) / (&next * &price);
+            (next, amount_out)
+        };
+        Some((u128::try_from(next).ok()?, u128::try_from(amount_out).ok()?))
+    }
+
+    #[test]
+    fn one_to_one_price() {
+        // At a price of 1, with liquidity L, 1% of L in moves the square root price by about 1%
+        let liquidity = 1_000_000_000_000;
+        let (next, out) = swap_step(Q64, liquidity, 10_000_000_000, false).unwrap();
+        assert_eq!(next, Q64 + Q64 / 100);
+        assert_eq!(out, 9_900_990_099);
+        let (next, out) = swap_step(Q64, liquidity, 10_000_000_000, true).unwrap();
+        assert_eq!(next, rounding::div_ceil(Q64 * 100, 101).unwrap());
+        assert_eq!(out, 9_900_990_099);
+        assert_eq!(swap_step(Q64, 0, 1, true), Err(FixtureError::InvalidInstructionData));
+        assert_eq!(swap_step(u128::MAX, 1, 1, false), Err(FixtureError::ArithmeticOverflow));
+    }
+
+    proptest! {
+        #[test]
+        fn matches_bigint(
+            sqrt_price in MIN_SQRT_PRICE..=MAX_SQRT_PRICE,
+            liquidity in any::<u64>(),
+            amount_in in prop_oneof![any::<u64>().prop_map(u128::from), any::<u128>()],
+            a_to_b in any::<bool>(),
+        ) {
+            prop_assert_eq!(
+                swap_step(sqrt_price, liquidity, amount_in, a_to_b).ok(),
+                reference(sqrt_price, liquidity, amount_in, a_to_b)
+            );
+        }
+    }
+}
@@ -0,0 +1,328 @@
+//! Account state the program reads and writes in place, the zero-copy way programs keep u128
+//! balances: a `#[repr(C)]` struct viewed directly over the account data.
+
+use core::mem::size_of;
+
+use crate::error::FixtureError;
+
+/// Fees are charged in basis points of the amount swapped in
+pub const BPS_SCALE: u128 = 10_000;
+
+/// A constant-product pool with two u128 reserves and the fees it has collected
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+#[repr(C)]
+pub struct PoolState {
+    pub reserve_a: u128,
+    pub reserve_b: u128,
+    pub fees_a: u128,
+    pub fees_b: u128,
+    /// At most [`BPS_SCALE`]
+    pub fee_bps: u64,
+    // Explicit, so every byte of the struct is defined and the size stays a multiple of 16
+    pub padding: u64,
+}
+
+/// View the start of `data` as a `T`, or `None` if it is too short or not aligned for `T`
+fn load<T>(data: &[u8]) -> Option<&T> {
+    let data = data.get(..size_of::<T>())?;
+    data.as_ptr()
+        .cast::<T>()
+        .is_aligned()
+        .then(|| unsafe { &*data.as_ptr().cast::<T>() })
+}
+
+/// The mutable counterpart of [`load`]
+fn load_mut<T>(data: &mut [u8]) -> Option<&mut T> {
+    let data = data.get_mut(..size_of::<T>())?;
+    data.as_mut_ptr()
+        .cast::<T>()
+        .is_aligned()
+        .then(|| unsafe { &mut *data.as_mut_ptr().cast::<T>() })
+}
+
+impl PoolState {
+    pub const LEN: usize = size_of::<Self>();
+
+    /// View `data` as a pool, or `None` if it is too short or not aligned for the u128 fields
+    pub fn load(data: &[u8]) -> Option<&Self> {
+        load(data)
+    }
+
+    /// View `data` as a mutable pool; see [`load`](Self::load)
+    pub fn load_mut(data: &mut [u8]) -> Option<&mut Self> {
+        load_mut(data)
+    }
+
+    /// The pool as the bytes it occupies in account data
+    pub fn as_bytes(&self) -> &[u8; Self::LEN] {
+        unsafe { &*(self as *const Self).cast() }
+    }
+
+    /// Add `a` and `b` to the reserves, or [`FixtureError::ArithmeticOverflow`] if either
+    /// overflows
+    pub fn deposit(&mut self, a: u128, b: u128) -> Result<(), FixtureError> {
+        self.reserve_a = self.reserve_a.checked_add(a).ok_or(FixtureError::ArithmeticOverflow)?;
+        self.reserve_b = self.reserve_b.checked_add(b).ok_or(FixtureError::ArithmeticOverflow)?;
+        Ok(())
+    }
+
+    /// Swap `amount_in` of token A for B at the constant-product price, keeping the fee, and
+    /// return the amount of B out. Fails as [`swap_amounts`] does, or with
+    /// [`FixtureError::ArithmeticOverflow`] if the fees collected overflow.
+    pub fn swap(&mut self, amount_in: u128) -> Result<u128, FixtureError> {
+        let (amount_out, fee) = swap_amounts(self.reserve_a, self.reserve_b, amount_in, self.fee_bps)?;
+        self.reserve_a += amount_in - fee;
+        self.reserve_b -= amount_out;
+        self.fees_a = self.fees_a.checked_add(fee).ok_or(FixtureError::ArithmeticOverflow)?;
+        Ok(amount_out)
+    }
+}
+
+/// The constant-product swap of `amount_in` against the two reserves with a fee of `fee_bps`,
+/// all in u128: the amount out, rounded down, and the fee taken from the amount in, also rounded
+/// down. [`FixtureError::InvalidInstructionData`] if the fee is over [`BPS_SCALE`] or the
+/// reserve in stays empty, and [`FixtureError::ArithmeticOverflow`] if an intermediate product
+/// overflows.
+pub fn swap_amounts(
+    reserve_in: u128,
+    reserve_out: u128,
+    amount_in: u128,
+    fee_bps: u64,
+) -> Result<(u128, u128), FixtureError> {
+    if u128::from(fee_bps) > BPS_SCALE {
+        return Err(FixtureError::InvalidInstructionData);
+    }
+    let overflow = FixtureError::ArithmeticOverflow;
+    let fee = amount_in.checked_mul(fee_bps.into()).ok_or(overflow)? / BPS_SCALE;
+    let net = amount_in - fee;
+    let product = reserve_out.checked_mul(net).ok_or(overflow)?;
+    let amount_out = product
+        .checked_div(reserve_in.checked_add(net).ok_or(overflow)?)
+        .ok_or(FixtureError::InvalidInstructionData)?;
+    Ok((amount_out, fee))
+}
+
+/// A time-weighted average price oracle: every update adds the reported price times the seconds
+/// since the previous update to a u128 accumulator
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+#[repr(C)]
+pub struct Oracle {
+    /// Wraps on overflow, like the accumulators it models; differences between two readings stay
+    /// exact as long as the true difference fits in a u128
+    pub cumulative_price: u128,
+    /// Unix timestamp of the last update, or 0 before the first
+    pub last_timestamp: i64,
+    pub padding: u64,
+}
+
+impl Oracle {
+    pub const LEN: usize = size_of::<Self>();
+
+    /// View `data` as an oracle, or `None` if it is too short or not aligned for the u128 field
+    pub fn load(data: &[u8]) -> Option<&Self> {
+        load(data)
+    }
+
+    /// View `data` as a mutable oracle; see [`load`](Self::load)
+    pub fn load_mut(data: &mut [u8]) -> Option<&mut Self> {
+        load_mut(data)
+    }
+
+    /// The oracle as the bytes it occupies in account data
+    pub fn as_bytes(&self) -> &[u8; Self::LEN] {
+        unsafe { &*(self as *const Self).cast() }
+    }
+
+    /// Accumulate `price` over the seconds since the last update, which only records the time if
+    /// it is the first. `None` if `now` is before the last update.
+    pub fn update(&mut self, price: u128, now: i64) -> Option<()> {
+        if self.last_timestamp != 0 {
+            let elapsed = u64::try_from(now.checked_sub(self.last_timestamp)?).ok()?;
+            self.cumulative_price = self.cumulative_price.wrapping_add(price.wrapping_mul(elapsed.into()));
+        }
+        self.last_timestamp = now;
+        Some(())
+    }
+
+    /// The average price between `earlier` and this reading, or `None` if no time passed
+    pub fn twap(&self, earlier: &Self) -> Option<u128> {
+        let elapsed = u64::try_from(self.last_timestamp.checked_sub(earlier.last_timestamp)?).ok()?;
+        self.cumulative_price.wrapping_sub(earlier.cumulative_price).checked_div(elapsed.into())
+    }
+}
+
+/// A queue of u128 work items too long to process in one invocation: each call folds the next
+/// batch into the accumulator and moves the cursor past it, so the work resumes where the last
+/// transaction stopped. The items follow this header in the account data.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+#[repr(C)]
+pub struct WorkQueue {
+    /// The items before the cursor, folded in order with [`WorkQueue::fold`]
+    pub accumulator: u128,
+    /// Index of the next item to process
+    pub cursor: u64,
+    /// Number of items after the header
+    pub len: u64,
+}
+
+impl WorkQueue {
+    pub const LEN: usize = size_of::<Self>();
+
+    /// Multiplier of [`fold`](Self::fold), the 128-bit LCG multiplier of PCG
+    pub const MULTIPLIER: u128 = 0x2360_ed05_1fc6_5da4_4385_df64_9fcc_f645;
+
+    /// `accumulator` with `item` folded in. Order matters, so an item skipped or processed twice
+    /// across invocations changes the result.
+    pub fn fold(accumulator: u128, item: u128) -> u128 {
+        accumulator.wrapping_mul(Self::MULTIPLIER).wrapping_add(item)
+    }
+
+    /// View `data` as a mutable queue and the items after it, or `None` if it is too short for
+    /// them or not aligned for the u128 fields
+    pub fn load_mut(data: &mut [u8]) -> Option<(&mut Self, &[u128])> {
+        let (header, items) = data.split_at_mut_checked(Self::LEN)?;
+        let queue: &mut Self = load_mut(header)?;
+        let len = usize::try_from(queue.len).ok()?;
+        let items = items.get(..len.checked_mul(size_of::<u128>())?)?;
+        // Aligned, since the header is and its length is a multiple of 16
+        let items = unsafe { core::slice::from_raw_parts(items.as_ptr().cast::<u128>(), len) };
+        Some((queue, items))
+    }
+
+    /// The queue as the bytes it occupies in account data, before the items
+    pub fn as_bytes(&self) -> &[u8; Self::LEN] {
+        unsafe { &*(self as *const Self).cast() }
+    }
+
+    /// Fold up to `batch` of `items` from the cursor on and return how many are left, or `None` if
+    /// the cursor is past the end
+    pub fn process(&mut self, items: &[u128], batch: u32) -> Option<u64> {
+        let pending = items.get(usize::try_from(self.cursor).ok()?..)?;
+        let count = pending.len().min(batch as usize);
+        self.accumulator = pending[..count].iter().fold(self.accumulator, |accumulator, &item| {
+            Self::fold(accumulator, item)
+        });
+        self.cursor += count as u64;
+        Some((pending.len() - count) as u64)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn layout_is_stable() {
+        let pool = PoolState {
+            reserve_a: 0x0f0e0d0c0b0a09080706050403020100,
+            reserve_b: 1,
+            fees_a: 2,
+            fees_b: u128::MAX,
+            fee_bps: 30,
+            padding: 0,
+        };
+        let bytes = pool.as_bytes();
+        assert_eq!(bytes[..16], core::array::from_fn::<u8, 16, _>(|i| i as u8));
+        assert_eq!(bytes[16], 1);
+        assert_eq!(bytes[32], 2);
+        assert_eq!(bytes[48..64], [0xff; 16]);
+        assert_eq!(bytes[64..72], 30u64.to_le_bytes());
+
+        // u128 words, so the buffer is aligned for the struct and 8 bytes into it isn't
+        let mut words = [0u128; PoolState::LEN / 16 + 1];
+        let buffer: &mut [u8] =
+            unsafe { core::slice::from_raw_parts_mut(words.as_mut_ptr().cast(), 16 * words.len()) };
+        buffer[..PoolState::LEN].copy_from_slice(bytes);
+        assert_eq!(PoolState::load(buffer), Some(&pool));
+        if core::mem::align_of::<PoolState>() > 8 {
+            assert!(PoolState::load(&buffer[8..]).is_none());
+        }
+        assert!(PoolState::load(&buffer[..PoolState::LEN - 1]).is_none());
+    }
+
+    #[test]
+    fn twap_across_wrapping() {
+        // Start near the top of the range so the accumulator wraps between the two readings
+        let mut oracle = Oracle {
+            cumulative_price: u128::MAX - 5,
+            last_timestamp: 1_700_000_000,
+            padding: 0,
+        };
+        let earlier = oracle;
+        oracle.update(u128::MAX / 4, 1_700_000_002).unwrap();
+        oracle.update(1 << 100, 1_700_000_004).unwrap();
+        assert!(oracle.cumulative_price < earlier.cumulative_price);
+        assert_eq!(oracle.twap(&earlier), Some((u128::MAX / 4 + (1 << 100)) / 2));
+        assert_eq!(oracle.update(1, 1_699_999_999), None);
+        assert_eq!(oracle.twap(&oracle), None);
+
+        let mut fresh = Oracle::default();
+        fresh.update(7, 1_700_000_000).unwrap();
+        assert_eq!((fresh.cumulative_price, fresh.last_timestamp), (0, 1_700_000_000));
+    }
+
+    #[test]
+    fn work_queue_resumes_at_the_cursor() {
+        let items: Vec<u128> = (0..10).map(|i| u128::MAX / 11 * i).collect();
+        let mut words = vec![0u128; WorkQueue::LEN / 16 + items.len()];
+        words[WorkQueue::LEN / 16..].copy_from_slice(&items);
+        let buffer: &mut [u8] =
+            unsafe { core::slice::from_raw_parts_mut(words.as_mut_ptr().cast(), 16 * words.len()) };
+        let queue = WorkQueue { len: items.len() as u64, ..WorkQueue::default() };
+        buffer[..WorkQueue::LEN].copy_from_slice(queue.as_bytes());
+
+        // In batches of 4, each resuming from the state the last one left in the buffer
+        for left in [6, 2, 0, 0] {
+            let (queue, loaded) = WorkQueue::load_mut(buffer).unwrap();
+            assert_eq!(queue.process(loaded, 4), Some(left));
+        }
+        let (queue, _) = WorkQueue::load_mut(buffer).unwrap();
+        assert_eq!(queue.cursor, 10);
+        let expected = items.iter().fold(0, |accumulator, &item| WorkQueue::fold(accumulator, item));
+        assert_eq!(queue.accumulator, expected);
+
+        queue.cursor = 11;
+        assert_eq!(queue.process(&items, 4), None);
+        queue.len = 11;
+        assert!(WorkQueue::load_mut(buffer).is_none());
+    }
+
+    #[test]
+    fn swap_amounts_at_boundary_reserves() {
+        use num_bigint::BigUint;
+
+        let limit = BigUint::from(u128::MAX);
+        // `None` wherever an intermediate exceeds u128, as the checked version does
+        let reference = |reserve_in: u128, reserve_out: u128, amount_in: u128, fee_bps: u64| {
+            if u128::from(fee_bps) > BPS_SCALE {
+                return None;
+            }
+            let fee_product = BigUint::from(amount_in) * fee_bps;
+            let fee = (fee_product <= limit).then(|| fee_product / BPS_SCALE)?;
+            let net = BigUint::from(amount_in) - &fee;
+            let (product, reserve) = (BigUint::from(reserve_out) * &net, BigUint::from(reserve_in) + &net);
+            if product > limit || reserve > limit || reserve == BigUint::ZERO {
+                return None;
+            }
+            Some((u128::try_from(product / reserve).ok()?, u128::try_from(fee).ok()?))
+        };
+        let values = [0, 1, 2, 9_999, 10_000, u64::MAX as u128, 1 << 64, u128::MAX / 10_000, u128::MAX];
+        for reserve_in in values {
+            for reserve_out in values {
+                for amount_in in values {
+                    for fee_bps in [0, 30, 10_000, 10_001] {
+                        assert_eq!(
+                            swap_amounts(reserve_in, reserve_out, amount_in, fee_bps).ok(),
+                            reference(reserve_in, reserve_out, amount_in, fee_bps),
+                            "{amount_in} into {reserve_in} / {reserve_out} at {fee_bps} bps"
+                        );
+                    }
+                }
+            }
+        }
+        // Input no swap can use fails apart from a product that doesn't fit
+        assert_eq!(swap_amounts(1, 1, 1, 10_001), Err(FixtureError::InvalidInstructionData));
+        assert_eq!(swap_amounts(0, 1, 0, 30), Err(FixtureError::InvalidInstructionData));
+        assert_eq!(swap_amounts(1, u128::MAX, 2, 0), Err(FixtureError::ArithmeticOverflow));
+    }
+}
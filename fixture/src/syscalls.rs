@@ -0,0 +1,283 @@
+//! Safe wrappers around the runtime syscalls the program uses. Off BPF, where there is no
+//! runtime, they are emulated in memory so the program logic can run in host tests.
+
+use crate::clock::Clock;
+use crate::layout::{assert_layout, assert_offsets};
+
+#[cfg(target_arch = "bpf")]
+mod sys {
+    unsafe extern "C" {
+        pub fn sol_set_return_data(data: *const u8, length: u64);
+        pub fn sol_get_return_data(data: *mut u8, length: u64, program_id: *mut [u8; 32]) -> u64;
+        pub fn sol_memcmp_(s1: *const u8, s2: *const u8, n: u64, result: *mut i32);
+        pub fn sol_memcpy_(dst: *mut u8, src: *const u8, n: u64);
+        pub fn sol_memmove_(dst: *mut u8, src: *const u8, n: u64);
+        pub fn sol_get_clock_sysvar(clock: *mut u8) -> u64;
+        pub fn sol_invoke_signed_c(
+            instruction: *const u8,
+            account_infos: *const u8,
+            account_infos_len: u64,
+            signers_seeds: *const u8,
+            signers_seeds_len: u64,
+        ) -> u64;
+        pub fn sol_log_(message: *const u8, length: u64);
+        pub fn sol_log_64_(arg1: u64, arg2: u64, arg3: u64, arg4: u64, arg5: u64);
+        pub fn abort() -> !;
+    }
+}
+
+#[cfg(not(target_arch = "bpf"))]
+mod sys {
+    use crate::clock::Clock;
+    use std::cell::RefCell;
+
+    /// The program id host runs report as the setter of return data
+    const HOST_PROGRAM_ID: [u8; 32] = [0x02; 32];
+
+    /// The clock host runs read: slot 1000, 400 seconds into an epoch
+    pub const HOST_CLOCK: Clock = Clock {
+        slot: 1_000,
+        epoch_start_timestamp: 1_700_000_000,
+        epoch: 2,
+        leader_schedule_epoch: 3,
+        unix_timestamp: 1_700_000_400,
+    };
+
+    thread_local! {
+        static RETURN_DATA: RefCell<Vec<u8>> = const { RefCell::new(Vec::new()) };
+    }
+
+    pub unsafe fn sol_set_return_data(data: *const u8, length: u64) {
+        let data = unsafe { std::slice::from_raw_parts(data, length as usize) };
+        RETURN_DATA.with(|return_data| *return_data.borrow_mut() = data.to_vec());
+    }
+
+    pub unsafe fn sol_get_return_data(data: *mut u8, length: u64, program_id: *mut [u8; 32]) -> u64 {
+        RETURN_DATA.with(|return_data| {
+            let return_data = return_data.borrow();
+            let copied = return_data.len().min(length as usize);
+            if copied > 0 {
+                unsafe {
+                    std::ptr::copy_nonoverlapping(return_data.as_ptr(), data, copied);
+                    *program_id = HOST_PROGRAM_ID;
+                }
+            }
+            return_data.len() as u64
+        })
+    }
+
+    pub unsafe fn sol_memcmp_(s1: *const u8, s2: *const u8, n: u64, result: *mut i32) {
+        let s1 = unsafe { std::slice::from_raw_parts(s1, n as usize) };
+        let s2 = unsafe { std::slice::from_raw_parts(s2, n as usize) };
+        let difference = s1
+            .iter()
+            .zip(s2)
+            .find(|(x, y)| x != y)
+            .map_or(0, |(&x, &y)| i32::from(x) - i32::from(y));
+        unsafe { *result = difference };
+    }
+
+    pub unsafe fn sol_memcpy_(dst: *mut u8, src: *const u8, n: u64) {
+        unsafe { std::ptr::copy_nonoverlapping(src, dst, n as usize) }
+    }
+
+    pub unsafe fn sol_memmove_(dst: *mut u8, src: *const u8, n: u64) {
+        unsafe { std::ptr::copy(src, dst, n as usize) }
+    }
+
+    pub unsafe fn sol_get_clock_sysvar(clock: *mut u8) -> u64 {
+        unsafe { *(clock as *mut Clock) = HOST_CLOCK };
+        0
+    }
+
+    /// Run the callee in-process on an input region serialized without accounts
+    pub unsafe fn sol_invoke_signed_c(
+        instruction: *const u8,
+        _account_infos: *const u8,
+        _account_infos_len: u64,
+        _signers_seeds: *const u8,
+        _signers_seeds_len: u64,
+    ) -> u64 {
+        let instruction = unsafe { &*(instruction as *const super::SolInstruction) };
+        let data = unsafe { std::slice::from_raw_parts(instruction.data, instruction.data_len as usize) };
+        let mut bytes = Vec::new();
+        bytes.extend_from_slice(&0u64.to_le_bytes());
+        bytes.extend_from_slice(&(data.len() as u64).to_le_bytes());
+        bytes.extend_from_slice(data);
+        bytes.extend_from_slice(unsafe { &*instruction.program_id });
+        // u64 words, for the alignment the loader guarantees
+        let mut input = vec![0u64; bytes.len().div_ceil(8)];
+        unsafe { std::ptr::copy_nonoverlapping(bytes.as_ptr(), input.as_mut_ptr().cast(), bytes.len()) };
+        crate::entrypoint::entrypoint(input.as_mut_ptr().cast())
+    }
+
+    // Deriving an address means checking it is off the ed25519 curve, which the host side has no
+    // implementation of; both report the derivation as failed
+
+    pub unsafe fn sol_create_program_address(
+        _seeds: *const u8,
+        _seeds_len: u64,
+        _program_id: *const [u8; 32],
+        _address: *mut [u8; 32],
+    ) -> u64 {
+        1
+    }
+
+    pub unsafe fn sol_try_find_program_address(
+        _seeds: *const u8,
+        _seeds_len: u64,
+        _program_id: *const [u8; 32],
+        _address: *mut [u8; 32],
+        _bump_seed: *mut u8,
+    ) -> u64 {
+        1
+    }
+
+    /// Logs have nowhere to go on the host
+    pub unsafe fn sol_log_(_message: *const u8, _length: u64) {}
+
+    pub unsafe fn sol_log_64_(_arg1: u64, _arg2: u64, _arg3: u64, _arg4: u64, _arg5: u64) {}
+}
+
+/// Set the instruction's return data
+pub fn set_return_data(data: &[u8]) {
+    unsafe { sys::sol_set_return_data(data.as_ptr(), data.len() as u64) }
+}
+
+/// Copy the current return data into `buffer`, as much as fits, and the id of the program that
+/// set it into `program_id`. Returns the full length of the return data; neither output is
+/// written when there is none.
+pub fn get_return_data(buffer: &mut [u8], program_id: &mut [u8; 32]) -> usize {
+    unsafe { sys::sol_get_return_data(buffer.as_mut_ptr(), buffer.len() as u64, program_id) as usize }
+}
+
+/// Compare two buffers like `memcmp`: zero when equal, otherwise the difference of the first
+/// bytes that differ
+pub fn memcmp<const N: usize>(s1: &[u8; N], s2: &[u8; N]) -> i32 {
+    let mut result = 0;
+    unsafe { sys::sol_memcmp_(s1.as_ptr(), s2.as_ptr(), N as u64, &mut result) };
+    result
+}
+
+/// Copy `n` bytes from `src` to `dst` with `sol_memcpy_`, which fails the instruction if they
+/// overlap
+///
+/// # Safety
+/// `src` must be valid for reading and `dst` for writing `n` bytes
+pub unsafe fn memcpy(dst: *mut u8, src: *const u8, n: usize) {
+    unsafe { sys::sol_memcpy_(dst, src, n as u64) }
+}
+
+/// Copy `n` bytes from `src` to `dst` with `sol_memmove_`, which allows them to overlap
+///
+/// # Safety
+/// `src` must be valid for reading and `dst` for writing `n` bytes
+pub unsafe fn memmove(dst: *mut u8, src: *const u8, n: usize) {
+    unsafe { sys::sol_memmove_(dst, src, n as u64) }
+}
+
+/// The Clock sysvar, or `None` if the runtime fails to provide it
+pub fn get_clock() -> Option<Clock> {
+    let mut clock = Clock::default();
+    let result = unsafe { sys::sol_get_clock_sysvar((&mut clock as *mut Clock).cast()) };
+    (result == 0).then_some(clock)
+}
+
+/// An instruction as `sol_invoke_signed_c` reads it
+#[repr(C)]
+struct SolInstruction {
+    program_id: *const [u8; 32],
+    accounts: *const u8,
+    account_len: u64,
+    data: *const u8,
+    data_len: u64,
+}
+
+assert_layout!(SolInstruction, size = 40, align = 8);
+assert_offsets!(SolInstruction {
+    program_id: 0,
+    accounts: 8,
+    account_len: 16,
+    data: 24,
+    data_len: 32,
+});
+
+/// Invoke `program_id` with `data`, and no accounts or signers, through `sol_invoke_signed_c`.
+/// Returns zero on success; the runtime aborts the caller outright when the callee fails.
+pub fn invoke(program_id: &[u8; 32], data: &[u8]) -> u64 {
+    let instruction = SolInstruction {
+        program_id,
+        accounts: core::ptr::null(),
+        account_len: 0,
+        data: data.as_ptr(),
+        data_len: data.len() as u64,
+    };
+    unsafe {
+        sys::sol_invoke_signed_c(
+            (&instruction as *const SolInstruction).cast(),
+            core::ptr::null(),
+            0,
+            core::ptr::null(),
+            0,
+        )
+    }
+}
+
+/// A seed as the PDA syscalls read it
+#[repr(C)]
+struct SolSignerSeed {
+    addr: *const u8,
+    len: u64,
+}
+
+assert_layout!(SolSignerSeed, size = 16, align = 8);
+assert_offsets!(SolSignerSeed { addr: 0, len: 8 });
+
+fn signer_seeds<const N: usize>(seeds: &[&[u8]; N]) -> [SolSignerSeed; N] {
+    core::array::from_fn(|i| SolSignerSeed {
+        addr: seeds[i].as_ptr(),
+        len: seeds[i].len() as u64,
+    })
+}
+
+/// The program address `seeds` derive for `program_id` with `sol_create_program_address`, or
+/// `None` if it lands on the curve
+pub fn create_program_address<const N: usize>(
+    seeds: &[&[u8]; N],
+    program_id: &[u8; 32],
+) -> Option<[u8; 32]> {
+    let seeds = signer_seeds(seeds);
+    let mut address = [0u8; 32];
+    let result =
+        unsafe { sys::sol_create_program_address(seeds.as_ptr().cast(), N as u64, program_id, &mut address) };
+    (result == 0).then_some(address)
+}
+
+/// The first program address off the curve for `seeds` followed by a bump seed counting down
+/// from 255, and that bump, with `sol_try_find_program_address`
+pub fn try_find_program_address<const N: usize>(
+    seeds: &[&[u8]; N],
+    program_id: &[u8; 32],
+) -> Option<([u8; 32], u8)> {
+    let seeds = signer_seeds(seeds);
+    let mut address = [0u8; 32];
+    let mut bump = 0;
+    let result = unsafe {
+        sys::sol_try_find_program_address(seeds.as_ptr().cast(), N as u64, program_id, &mut address, &mut bump)
+    };
+    (result == 0).then_some((address, bump))
+}
+
+pub fn log(message: &str) {
+    unsafe { sys::sol_log_(message.as_ptr(), message.len() as u64) }
+}
+
+pub fn log_64(arg1: u64, arg2: u64, arg3: u64, arg4: u64, arg5: u64) {
+    unsafe { sys::sol_log_64_(arg1, arg2, arg3, arg4, arg5) }
+}
+
+/// Stop the program; the runtime fails the instruction
+#[cfg(all(target_arch = "bpf", feature = "panic-log"))]
+pub fn abort() -> ! {
+    unsafe { sys::abort() }
+}
@@ -1,41 +1,946 @@
-#![cfg_attr(target_arch = "bpf", no_std)]
-
-#[cfg(target_arch = "bpf")]
-#[panic_handler]
-fn panic(_: &core::panic::PanicInfo) -> ! {
-    unsafe { core::hint::unreachable_unchecked() }
-}
-
-#[unsafe(no_mangle)]
-pub fn entrypoint(i: *mut u8) -> u64 {
-    let mut a = unsafe { *(i.add(0x0010) as *const u128) };
-    let b = unsafe { *((i.add(0x0010) as *const u128).wrapping_add(1)) };
-    
-    for _ in 0..10000 {
-        // reassign a to avoid multiply being optimized away
-        a = a * b;
-    }
-    
-    (a >> 64) as u64
-}
+//! Mollusk tests of the u128 fixture program built from the `u128-bpf-fixture` crate.
 
 #[cfg(test)]
 mod tests {
-    use mollusk_svm::{Mollusk, result::Check};
-    use solana_instruction::Instruction;
+    use mollusk_svm::{Mollusk, result::{Check, ProgramResult}};
+    use solana_keypair::Keypair;
+    use solana_program_error::ProgramError;
+    use solana_loader_v3_interface::{
+        get_program_data_address, instruction as loader, state::UpgradeableLoaderState,
+    };
+    use solana_program_test::{ProgramTest, ProgramTestContext};
+    use solana_signer::Signer;
+    use solana_transaction::Transaction;
+    use borsh::BorshSerialize;
+    use object::{Object, ObjectSymbol};
+    use std::collections::BTreeSet;
+    use u128_bpf_fixture::abi;
+    use u128_bpf_fixture::clock;
+    use u128_bpf_fixture::error::{ErrorClass, FixtureError};
+    use u128_bpf_fixture::orderbook::Order;
+    use u128_bpf_fixture::state::{Oracle, PoolState, WorkQueue};
+    use u128_bpf_fixture::instruction::{
+        Entry, EntryOp, FixtureInstruction, Lowering, Reduction, Region, COPY_REGION_LEN, HEADER_LEN,
+    };
 
     const PROGRAM_ID: [u8; 32] = [0x02; 32];
 
+    /// Compute units each invocation of the work-queue test may spend, well below what the whole
+    /// queue takes, so the work has to be split
+    const CHUNK_CU_THRESHOLD: u64 = 2_000;
+
+    /// The 128-bit compiler-rt routines the `u128_mul_libcall` sbpf-linker branch resolves, by the
+    /// names it matches on
+    const LINKER_LIBCALLS: &[&str] = &["__multi3", "__udivti3", "__umodti3", "__ashlti3", "__lshrti3"];
+
+    /// The program failed with `error`, which the runtime reports as a custom error
+    fn fixture_err(error: FixtureError) -> Check<'static> {
+        Check::err(ProgramError::Custom(error.code() as u32))
+    }
+
+    /// Built program for the profile named by `BPF_PROFILE` (`release`, `debug`, or `release-dwarf`),
+    /// release by default
+    fn program_path() -> String {
+        let profile = std::env::var("BPF_PROFILE").unwrap_or_else(|_| "release".to_string());
+        format!("target/bpfel-unknown-none/{profile}/libu128_bpf_fixture")
+    }
+
     #[test]
     pub fn test() {
         let mollusk = Mollusk::new(&PROGRAM_ID.into(), // 
-            "target/bpfel-unknown-none/release/libupstream_u128_test");
-        let input_data : [i128; 2] = [10, 20];
+            &program_path());
+        let instruction = solana_instruction::Instruction {
+            program_id: PROGRAM_ID.into(),
+            accounts: vec![],
+            data: FixtureInstruction::MulLoop { a: 10, b: 20 }.pack(),
+        };
+        mollusk.process_and_validate_instruction(&instruction, &[], &[Check::success()]);
+    }
+
+    #[test]
+    fn return_data_round_trip() {
+        let mollusk = Mollusk::new(&PROGRAM_ID.into(), &program_path());
+        // Equal halves fold to 0, so the instruction succeeds when the value read back folds like
+        // the one set
+        let a: u128 = 0x0123_4567_89ab_cdef_0123_4567_89ab_cdef;
+        let instruction = solana_instruction::Instruction {
+            program_id: PROGRAM_ID.into(),
+            accounts: vec![],
+            data: FixtureInstruction::ReturnData { a }.pack(),
+        };
+        mollusk.process_and_validate_instruction(
+            &instruction,
+            &[],
+            &[Check::success(), Check::return_data(&a.to_le_bytes())],
+        );
+    }
+
+    #[test]
+    fn memcpy_into_account_data() {
+        let mollusk = Mollusk::new(&PROGRAM_ID.into(), &program_path());
+        let key = [0x03; 32].into();
+        let account = solana_account::Account::new(
+            mollusk.sysvars.rent.minimum_balance(COPY_REGION_LEN),
+            COPY_REGION_LEN,
+            &PROGRAM_ID.into(),
+        );
+        // 0..4 XOR to 0, so the instruction succeeds when every value survives the copy
+        let (a, count) = (0u128, 4);
+        let instruction = solana_instruction::Instruction {
+            program_id: PROGRAM_ID.into(),
+            accounts: vec![solana_instruction::AccountMeta::new(key, false)],
+            data: FixtureInstruction::Memcpy {
+                src: Region::Stack,
+                dst: Region::Account,
+                count,
+                a,
+            }
+            .pack(),
+        };
+        // The copy lands in the second half of the data
+        let mut data = vec![0; COPY_REGION_LEN];
+        for i in 0..count {
+            let offset = COPY_REGION_LEN / 2 + 16 * usize::from(i);
+            data[offset..offset + 16].copy_from_slice(&(a + u128::from(i)).to_le_bytes());
+        }
+        mollusk.process_and_validate_instruction(
+            &instruction,
+            &[(key, account)],
+            &[Check::success(), Check::account(&key).data(&data).build()],
+        );
+    }
+
+    #[test]
+    fn clock_interest() {
+        let mut mollusk = Mollusk::new(&PROGRAM_ID.into(), &program_path());
+        let clock = clock::Clock {
+            slot: 250_000_000,
+            epoch_start_timestamp: 1_700_000_000,
+            epoch: 578,
+            leader_schedule_epoch: 579,
+            unix_timestamp: 1_700_172_800,
+        };
+        mollusk.sysvars.clock.slot = clock.slot;
+        mollusk.sysvars.clock.epoch_start_timestamp = clock.epoch_start_timestamp;
+        mollusk.sysvars.clock.epoch = clock.epoch;
+        mollusk.sysvars.clock.leader_schedule_epoch = clock.leader_schedule_epoch;
+        mollusk.sysvars.clock.unix_timestamp = clock.unix_timestamp;
+
+        // A principal past u64, at 5% a year as a per-period rate
+        let (principal, rate) = (10u128.pow(20), clock::RATE_SCALE / 20 / 31_536_000);
+        let [per_second, per_slot] = clock::accrued(principal, rate, &clock).unwrap();
+        let mut return_data = per_second.to_le_bytes().to_vec();
+        return_data.extend_from_slice(&per_slot.to_le_bytes());
+        let instruction = solana_instruction::Instruction {
+            program_id: PROGRAM_ID.into(),
+            accounts: vec![],
+            data: FixtureInstruction::ClockInterest { principal, rate }.pack(),
+        };
+        mollusk.process_and_validate_instruction(
+            &instruction,
+            &[],
+            &[Check::success(), Check::return_data(&return_data)],
+        );
+    }
+
+    #[test]
+    fn self_cpi() {
+        let mollusk = Mollusk::new(&PROGRAM_ID.into(), &program_path());
+        // Equal halves fold to 0, so the instruction succeeds when the callee echoes the value back
+        let a: u128 = 0x0123_4567_89ab_cdef_0123_4567_89ab_cdef;
+        let program_id = PROGRAM_ID.into();
+        // The runtime resolves the callee among the caller's accounts
+        let instruction = solana_instruction::Instruction {
+            program_id,
+            accounts: vec![solana_instruction::AccountMeta::new_readonly(program_id, false)],
+            data: FixtureInstruction::SelfCpi { a, inner: false }.pack(),
+        };
+        mollusk.process_and_validate_instruction(
+            &instruction,
+            &[(program_id, mollusk_svm::program::create_program_account_loader_v3(&program_id))],
+            &[Check::success(), Check::return_data(&a.to_le_bytes())],
+        );
+    }
+
+    #[test]
+    fn find_pda_from_u128_seed() {
+        let mollusk = Mollusk::new(&PROGRAM_ID.into(), &program_path());
+        let seed: u128 = 0x0f0e_0d0c_0b0a_0908_0706_0504_0302_0100;
+        let (address, bump) =
+            solana_pubkey::Pubkey::find_program_address(&[&seed.to_le_bytes()], &PROGRAM_ID.into());
+        let mut return_data = address.to_bytes().to_vec();
+        return_data.push(bump);
+        let instruction = solana_instruction::Instruction {
+            program_id: PROGRAM_ID.into(),
+            accounts: vec![],
+            data: FixtureInstruction::FindPda { seed }.pack(),
+        };
+        mollusk.process_and_validate_instruction(
+            &instruction,
+            &[],
+            &[Check::success(), Check::return_data(&return_data)],
+        );
+    }
+
+    #[test]
+    fn sum_lamports() {
+        let mollusk = Mollusk::new(&PROGRAM_ID.into(), &program_path());
+        let (first, second) = ([0x04; 32].into(), [0x05; 32].into());
+        let accounts = [
+            (first, solana_account::Account::new(1_000_000_000, 0, &PROGRAM_ID.into())),
+            (second, solana_account::Account::new(2_000_000_000, 0, &PROGRAM_ID.into())),
+        ];
+        // The lamports fill the low half to match the high one, folding to 0, only if the repeated
+        // account counts once
+        let a = 3_000_000_000u128 << 64;
+        let instruction = solana_instruction::Instruction {
+            program_id: PROGRAM_ID.into(),
+            accounts: vec![
+                solana_instruction::AccountMeta::new_readonly(first, false),
+                solana_instruction::AccountMeta::new_readonly(second, false),
+                solana_instruction::AccountMeta::new_readonly(first, false),
+            ],
+            data: FixtureInstruction::SumLamports { a }.pack(),
+        };
+        mollusk.process_and_validate_instruction(&instruction, &accounts, &[Check::success()]);
+
+        // Past `u128::MAX`, the sum overflows rather than reading as bad input
+        let instruction = solana_instruction::Instruction {
+            data: FixtureInstruction::SumLamports { a: u128::MAX }.pack(),
+            ..instruction
+        };
+        mollusk.process_and_validate_instruction(
+            &instruction,
+            &accounts,
+            &[fixture_err(FixtureError::ArithmeticOverflow)],
+        );
+    }
+
+    #[test]
+    fn fold_account_fields() {
+        let mollusk = Mollusk::new(&PROGRAM_ID.into(), &program_path());
+        // Each account holds an 8-byte discriminator, then the u128 field
+        let offset = 8;
+        let values: [u128; 3] = [u64::MAX as u128 + 1, 0x0123_4567_89ab_cdef_0011_2233_4455_6677, 42];
+        let accounts: Vec<_> = values
+            .iter()
+            .enumerate()
+            .map(|(i, value)| {
+                let mut account = solana_account::Account::new(1_000_000_000, 24, &PROGRAM_ID.into());
+                account.data[..8].copy_from_slice(b"u128stat");
+                account.data[offset..].copy_from_slice(&value.to_le_bytes());
+                ([0x10 + i as u8; 32].into(), account)
+            })
+            .collect();
+        let metas: Vec<_> = accounts
+            .iter()
+            .map(|(key, _)| solana_instruction::AccountMeta::new_readonly(*key, false))
+            .collect();
+
+        // Pick the starting accumulator so that a correct fold ends on `target`, whose equal
+        // halves fold to 0 and let the instruction succeed
+        let target = u128::MAX;
+        let sum: u128 = values.iter().sum();
+        let xor = values.iter().fold(0, |xor, value| xor ^ value);
+        for (reduction, a) in [
+            (Reduction::Sum, target - sum),
+            (Reduction::Xor, target ^ xor),
+            (Reduction::Max, target),
+        ] {
+            let instruction = solana_instruction::Instruction {
+                program_id: PROGRAM_ID.into(),
+                accounts: metas.clone(),
+                data: FixtureInstruction::FoldAccounts {
+                    reduction,
+                    count: values.len() as u8,
+                    offset: offset as u16,
+                    a,
+                }
+                .pack(),
+            };
+            mollusk.process_and_validate_instruction(&instruction, &accounts, &[Check::success()]);
+        }
+    }
+
+    #[test]
+    fn pool_state_in_account_data() {
+        let mollusk = Mollusk::new(&PROGRAM_ID.into(), &program_path());
+        let key = [0x06; 32].into();
+        let mut pool = PoolState {
+            reserve_a: 1 << 70,
+            reserve_b: 1 << 72,
+            fee_bps: 30,
+            ..PoolState::default()
+        };
+        let mut account = solana_account::Account::new(
+            mollusk.sysvars.rent.minimum_balance(PoolState::LEN),
+            PoolState::LEN,
+            &PROGRAM_ID.into(),
+        );
+        account.data.copy_from_slice(pool.as_bytes());
+        let instruction = |data: FixtureInstruction| solana_instruction::Instruction {
+            program_id: PROGRAM_ID.into(),
+            accounts: vec![solana_instruction::AccountMeta::new(key, false)],
+            data: data.pack(),
+        };
+
+        // The first account's data is the first in the input region, 16-byte aligned, so the
+        // program views it in place
+        let (a, b) = (u64::MAX as u128, 3);
+        pool.deposit(a, b).unwrap();
+        let result = mollusk.process_and_validate_instruction(
+            &instruction(FixtureInstruction::PoolDeposit { a, b }),
+            &[(key, account)],
+            &[Check::success(), Check::account(&key).data(pool.as_bytes()).build()],
+        );
+
+        let amount_in = 1 << 40;
+        let amount_out = pool.swap(amount_in).unwrap();
+        mollusk.process_and_validate_instruction(
+            &instruction(FixtureInstruction::PoolSwap { amount_in }),
+            &result.resulting_accounts,
+            &[
+                Check::success(),
+                Check::return_data(&amount_out.to_le_bytes()),
+                Check::account(&key).data(pool.as_bytes()).build(),
+            ],
+        );
+    }
+
+    #[test]
+    fn oracle_accumulates_across_invocations() {
+        let mut mollusk = Mollusk::new(&PROGRAM_ID.into(), &program_path());
+        let key = [0x07; 32].into();
+        let account = solana_account::Account::new(
+            mollusk.sysvars.rent.minimum_balance(Oracle::LEN),
+            Oracle::LEN,
+            &PROGRAM_ID.into(),
+        );
+        let mut accounts = vec![(key, account)];
+        let mut oracle = Oracle::default();
+        let mut readings = Vec::new();
+
+        // Prices past u64, a few seconds apart; each call starts from the account the last left
+        let updates = [
+            (1_700_000_000, 1u128 << 80),
+            (1_700_000_012, 3 << 90),
+            (1_700_000_030, u64::MAX as u128),
+        ];
+        for (now, price) in updates {
+            mollusk.sysvars.clock.unix_timestamp = now;
+            oracle.update(price, now).unwrap();
+            let instruction = solana_instruction::Instruction {
+                program_id: PROGRAM_ID.into(),
+                accounts: vec![solana_instruction::AccountMeta::new(key, false)],
+                data: FixtureInstruction::OracleUpdate { price }.pack(),
+            };
+            let result = mollusk.process_and_validate_instruction(
+                &instruction,
+                &accounts,
+                &[
+                    Check::success(),
+                    Check::return_data(&oracle.cumulative_price.to_le_bytes()),
+                    Check::account(&key).data(oracle.as_bytes()).build(),
+                ],
+            );
+            accounts = result.resulting_accounts;
+            readings.push(oracle);
+        }
+        assert_eq!(readings[2].twap(&readings[0]), Some(((3 << 90) * 12 + (u64::MAX as u128) * 18) / 30));
+
+        // A clock running backwards leaves the account as it was
+        mollusk.sysvars.clock.unix_timestamp = 1_700_000_029;
+        let instruction = solana_instruction::Instruction {
+            program_id: PROGRAM_ID.into(),
+            accounts: vec![solana_instruction::AccountMeta::new(key, false)],
+            data: FixtureInstruction::OracleUpdate { price: 1 }.pack(),
+        };
+        mollusk.process_and_validate_instruction(
+            &instruction,
+            &accounts,
+            &[fixture_err(FixtureError::InvalidInstructionData)],
+        );
+    }
+
+    #[test]
+    fn work_queue_splits_across_invocations() {
+        let mollusk = Mollusk::new(&PROGRAM_ID.into(), &program_path());
+        let key = [0x08; 32].into();
+        let items: Vec<u128> = (1..=256).map(|i| u128::MAX / i).collect();
+        let queue_data = |queue: WorkQueue| {
+            let mut data = queue.as_bytes().to_vec();
+            data.extend(items.iter().flat_map(|item| item.to_le_bytes()));
+            data
+        };
+        let mut queue = WorkQueue { len: items.len() as u64, ..WorkQueue::default() };
+        let mut account = solana_account::Account::new(
+            mollusk.sysvars.rent.minimum_balance(WorkQueue::LEN + 16 * items.len()),
+            WorkQueue::LEN + 16 * items.len(),
+            &PROGRAM_ID.into(),
+        );
+        account.data = queue_data(queue);
+        let mut accounts = vec![(key, account)];
+        let instruction = |batch| solana_instruction::Instruction {
+            program_id: PROGRAM_ID.into(),
+            accounts: vec![solana_instruction::AccountMeta::new(key, false)],
+            data: FixtureInstruction::ProcessChunk { batch }.pack(),
+        };
+
+        // Size the batch to the threshold from what one and two items cost
+        let cost = |batch| mollusk.process_instruction(&instruction(batch), &accounts).compute_units_consumed;
+        let per_item = cost(2) - cost(1);
+        let batch = u32::try_from((CHUNK_CU_THRESHOLD - cost(1)) / per_item + 1).unwrap();
+        assert!((batch as usize) < items.len(), "{batch} items fit in {CHUNK_CU_THRESHOLD} CU");
+
+        // Each call starts from the cursor the last left in the account
+        let mut invocations = 0;
+        while queue.cursor < queue.len {
+            let left = queue.process(&items, batch).unwrap();
+            let result = mollusk.process_and_validate_instruction(
+                &instruction(batch),
+                &accounts,
+                &[
+                    Check::success(),
+                    Check::return_data(&queue.accumulator.to_le_bytes()),
+                    Check::account(&key).data(&queue_data(queue)).build(),
+                ],
+            );
+            assert!(result.compute_units_consumed <= CHUNK_CU_THRESHOLD, "{left} left");
+            accounts = result.resulting_accounts;
+            invocations += 1;
+        }
+        assert_eq!(invocations, items.len().div_ceil(batch as usize));
+        let expected = items.iter().fold(0, |accumulator, &item| WorkQueue::fold(accumulator, item));
+        assert_eq!(queue.accumulator, expected);
+
+        // A finished queue stays finished, and an empty batch makes no progress
+        mollusk.process_and_validate_instruction(
+            &instruction(batch),
+            &accounts,
+            &[Check::success(), Check::account(&key).data(&queue_data(queue)).build()],
+        );
+        mollusk.process_and_validate_instruction(
+            &instruction(0),
+            &accounts,
+            &[fixture_err(FixtureError::InvalidInstructionData)],
+        );
+    }
+
+    #[test]
+    fn decode_endian() {
+        let mollusk = Mollusk::new(&PROGRAM_ID.into(), &program_path());
+        // Equal halves fold to 0; each half is asymmetric, so a swapped decode can't pass
+        let a: u128 = 0x0011_2233_4455_6677_0011_2233_4455_6677;
+        let bytes = a.to_le_bytes();
+        let mut return_data = Vec::new();
+        for value in [
+            u128::from_le_bytes(bytes),
+            u128::from_be_bytes(bytes),
+            // The program is little-endian whatever the host is
+            u128::from_le_bytes(bytes),
+        ] {
+            return_data.extend_from_slice(&value.to_le_bytes());
+        }
+        let instruction = solana_instruction::Instruction {
+            program_id: PROGRAM_ID.into(),
+            accounts: vec![],
+            data: FixtureInstruction::DecodeEndian { a }.pack(),
+        };
+        mollusk.process_and_validate_instruction(
+            &instruction,
+            &[],
+            &[Check::success(), Check::return_data(&return_data)],
+        );
+    }
+
+    #[test]
+    fn narrow() {
+        let mollusk = Mollusk::new(&PROGRAM_ID.into(), &program_path());
+        // A value in range comes back as the return code, which the runtime reports as a custom
+        // error unless it is 0; one out of range comes back as `ArithmeticOverflow` (200)
+        let cases = [
+            (0, false, Check::success()),
+            (7, false, Check::err(ProgramError::Custom(7))),
+            (u64::MAX as u128 + 1, false, fixture_err(FixtureError::ArithmeticOverflow)),
+            (9, true, Check::err(ProgramError::Custom(9))),
+            (i64::MAX as u128 + 1, true, fixture_err(FixtureError::ArithmeticOverflow)),
+            ((i64::MIN as i128 - 1) as u128, true, fixture_err(FixtureError::ArithmeticOverflow)),
+        ];
+        for (a, signed, check) in cases {
+            let instruction = solana_instruction::Instruction {
+                program_id: PROGRAM_ID.into(),
+                accounts: vec![],
+                data: FixtureInstruction::Narrow { a, signed }.pack(),
+            };
+            mollusk.process_and_validate_instruction(&instruction, &[], &[check]);
+        }
+    }
+
+    #[test]
+    fn add_sub_carry_between_halves() {
+        let mollusk = Mollusk::new(&PROGRAM_ID.into(), &program_path());
+        // The folded result is the return code, which the runtime reports as a custom error
+        // unless it is 0
+        let cases = [
+            // Carries into the high half: 1 << 64
+            (true, u64::MAX as u128, 1, 1),
+            // Wraps to 0
+            (true, u128::MAX, 1, 0),
+            // Borrows from the high half: 1 << 64 | 1
+            (false, 2 << 64, u64::MAX as u128, 0),
+            // Wraps to u128::MAX - 2
+            (false, 1 << 64, 1 << 64 | 3, 2),
+        ];
+        for lowering in [Lowering::Native, Lowering::Pair] {
+            for (add, a, b, code) in cases {
+                let data = if add {
+                    FixtureInstruction::Add { a, b, lowering }
+                } else {
+                    FixtureInstruction::Sub { a, b, lowering }
+                };
+                let instruction = solana_instruction::Instruction {
+                    program_id: PROGRAM_ID.into(),
+                    accounts: vec![],
+                    data: data.pack(),
+                };
+                let check = match code {
+                    0 => Check::success(),
+                    code => Check::err(ProgramError::Custom(code)),
+                };
+                mollusk.process_and_validate_instruction(&instruction, &[], &[check]);
+            }
+        }
+    }
+
+    #[test]
+    fn order_notionals() {
+        let mollusk = Mollusk::new(&PROGRAM_ID.into(), &program_path());
+        let largest = Order {
+            price: u64::MAX,
+            size: u64::MAX,
+        };
+        // The two notionals add up to exactly u128::MAX, whose halves fold to 0; one more lot
+        // overflows the total
+        let cases = [
+            (vec![largest, Order { price: u64::MAX, size: 2 }], Check::success()),
+            (vec![largest, Order { price: u64::MAX, size: 3 }], fixture_err(FixtureError::ArithmeticOverflow)),
+        ];
+        for (orders, check) in cases {
+            let instruction = solana_instruction::Instruction {
+                program_id: PROGRAM_ID.into(),
+                accounts: vec![],
+                data: FixtureInstruction::notional(&orders).unwrap().pack(),
+            };
+            mollusk.process_and_validate_instruction(&instruction, &[], &[check]);
+        }
+    }
+
+    #[test]
+    fn compound_factor() {
+        let mollusk = Mollusk::new(&PROGRAM_ID.into(), &program_path());
+        // In Q64.64, a factor of 1 folds to 1 and one of 2 to 2; doubling 64 times reaches 2^64,
+        // past the range
+        let cases = [
+            (0, 1_000, Check::err(ProgramError::Custom(1))),
+            (1 << 64, 1, Check::err(ProgramError::Custom(2))),
+            (1 << 64, 64, fixture_err(FixtureError::ArithmeticOverflow)),
+        ];
+        for (rate, periods, check) in cases {
+            let instruction = solana_instruction::Instruction {
+                program_id: PROGRAM_ID.into(),
+                accounts: vec![],
+                data: FixtureInstruction::CompoundFactor { rate, periods }.pack(),
+            };
+            mollusk.process_and_validate_instruction(&instruction, &[], &[check]);
+        }
+    }
+
+    /// Each class of failure comes back as a custom error in the range the taxonomy gives it. An
+    /// internal failure needs a broken runtime or backend, so only its codes are checked, on the
+    /// host, by the fixture crate.
+    #[test]
+    fn error_codes_by_class() {
+        let mollusk = Mollusk::new(&PROGRAM_ID.into(), &program_path());
+        let mut unknown_opcode = FixtureInstruction::ReturnData { a: 1 }.pack();
+        unknown_opcode[0] = u8::MAX;
+        let cases = [
+            (unknown_opcode, ErrorClass::Input),
+            (FixtureInstruction::ReturnData { a: 1 }.pack()[..HEADER_LEN].to_vec(), ErrorClass::Input),
+            (FixtureInstruction::Narrow { a: u128::MAX, signed: false }.pack(), ErrorClass::Arithmetic),
+            (FixtureInstruction::CompoundFactor { rate: 1 << 64, periods: 64 }.pack(), ErrorClass::Arithmetic),
+            (
+                FixtureInstruction::MulDiv { a: 1 << 127, b: 2, c: 1, ceil: false }.pack(),
+                ErrorClass::Arithmetic,
+            ),
+        ];
+        for (data, class) in cases {
+            let instruction = solana_instruction::Instruction {
+                program_id: PROGRAM_ID.into(),
+                accounts: vec![],
+                data: data.clone(),
+            };
+            let result = mollusk.process_instruction(&instruction, &[]).program_result;
+            let ProgramResult::Failure(ProgramError::Custom(code)) = result else {
+                panic!("{data:02x?} did not fail with a custom error: {result:?}");
+            };
+            assert_eq!(ErrorClass::of(code.into()), Some(class), "{data:02x?} failed with {code}");
+        }
+    }
+
+    #[test]
+    fn abi_torture() {
+        let mollusk = Mollusk::new(&PROGRAM_ID.into(), &program_path());
+        for seed in [0, 0x0123456789abcdef_fedcba9876543210, u128::MAX] {
+            let instruction = solana_instruction::Instruction {
+                program_id: PROGRAM_ID.into(),
+                accounts: vec![],
+                data: FixtureInstruction::AbiTorture { seed }.pack(),
+            };
+            mollusk.process_and_validate_instruction(
+                &instruction,
+                &[],
+                &[Check::success(), Check::return_data(&abi::expected(seed).to_le_bytes())],
+            );
+        }
+    }
+
+    /// Both A/B entries keep an exported symbol of their own, and compute what the shared dispatch
+    /// does for the same operation
+    #[test]
+    fn ab_entries_agree_with_the_dispatch() {
+        let elf = std::fs::read(format!("{}.so", program_path()))
+            .expect("build the program with `cargo xtask build` first");
+        let file = object::File::parse(&*elf).unwrap();
+        let symbols: BTreeSet<_> = file.symbols().filter_map(|symbol| symbol.name().ok()).collect();
+        for entry in Entry::ALL {
+            assert!(symbols.contains(entry.symbol()), "{} is not exported", entry.symbol());
+        }
+
+        let mollusk = Mollusk::new(&PROGRAM_ID.into(), &program_path());
+        let run = |instruction: FixtureInstruction| {
+            let instruction = solana_instruction::Instruction {
+                program_id: PROGRAM_ID.into(),
+                accounts: vec![],
+                data: instruction.pack(),
+            };
+            mollusk.process_instruction(&instruction, &[]).program_result
+        };
+        let operands = [(0, 0), (u128::MAX, 3), (1 << 64 | 5, 1 << 70), (0x0123_4567_89ab_cdef << 60, 67)];
+        for op in EntryOp::ALL {
+            for (a, b) in operands {
+                let lowering = Lowering::Native;
+                let dispatched = run(match op {
+                    EntryOp::Mul => FixtureInstruction::Mul { a, b, lowering },
+                    EntryOp::Div => FixtureInstruction::Div { a, b, lowering },
+                    EntryOp::Rem => FixtureInstruction::Rem { a, b, lowering },
+                    EntryOp::Shl => FixtureInstruction::Shl { a, b, lowering },
+                    EntryOp::Shr => FixtureInstruction::Shr { a, b, lowering },
+                });
+                for entry in Entry::ALL {
+                    let result = run(FixtureInstruction::Entry { entry, op, a, b });
+                    assert_eq!(result, dispatched, "{entry:?} {op:?} on ({a:#x}, {b:#x})");
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn toolchain_info() {
+        let mollusk = Mollusk::new(&PROGRAM_ID.into(), &program_path());
         let instruction = solana_instruction::Instruction {
             program_id: PROGRAM_ID.into(),
             accounts: vec![],
-            data: input_data.iter().flat_map(|x| x.to_le_bytes()).collect(),
+            data: FixtureInstruction::ToolchainInfo.pack(),
         };
         mollusk.process_and_validate_instruction(&instruction, &[], &[Check::success()]);
     }
+
+    #[test]
+    fn self_test() {
+        let mollusk = Mollusk::new(&PROGRAM_ID.into(), &program_path());
+        let instruction = solana_instruction::Instruction {
+            program_id: PROGRAM_ID.into(),
+            accounts: vec![],
+            data: FixtureInstruction::SelfTest.pack(),
+        };
+        mollusk.process_and_validate_instruction(&instruction, &[], &[Check::success()]);
+    }
+
+    #[test]
+    fn echo() {
+        let mollusk = Mollusk::new(&PROGRAM_ID.into(), &program_path());
+        let data = FixtureInstruction::Echo {
+            a: 0x0f0e_0d0c_0b0a_0908_0706_0504_0302_0100,
+            b: u128::MAX - 1,
+        }
+        .pack();
+        let instruction = solana_instruction::Instruction {
+            program_id: PROGRAM_ID.into(),
+            accounts: vec![],
+            data: data.clone(),
+        };
+        mollusk.process_and_validate_instruction(
+            &instruction,
+            &[],
+            &[Check::success(), Check::return_data(&data)],
+        );
+    }
+
+    /// Whether `name` is a compiler-rt routine on 128-bit (TI mode) integers, like `__multi3`,
+    /// `__muloti4`, or `__udivmodti4`
+    fn is_ti_mode_libcall(name: &str) -> bool {
+        name.strip_prefix("__")
+            .map(|rest| rest.trim_end_matches(|c: char| c.is_ascii_digit()))
+            .is_some_and(|rest| rest.len() < name.len() - 2 && rest.ends_with("ti"))
+    }
+
+    /// The program references exactly the 128-bit libcalls the linker resolves: a name LLVM
+    /// spells differently, or a routine it starts calling that the linker doesn't know, fails
+    /// here rather than as an unknown syscall at load time
+    #[test]
+    fn libcall_names_match_the_linker() {
+        let elf = std::fs::read(format!("{}.so", program_path()))
+            .expect("build the program with `cargo xtask build` first");
+        let file = object::File::parse(&*elf).unwrap();
+        let referenced: BTreeSet<_> = file
+            .symbols()
+            .chain(file.dynamic_symbols())
+            .filter_map(|symbol| symbol.name().ok())
+            .filter(|name| is_ti_mode_libcall(name))
+            .collect();
+        assert_eq!(referenced, LINKER_LIBCALLS.iter().copied().collect());
+        assert!(!is_ti_mode_libcall("__floattidf") && !is_ti_mode_libcall("__multi"));
+    }
+
+    /// Paths of the core machinery a release program must not pull in: formatting, the panic paths
+    /// that format their messages, and the Unicode and float tables. `cargo xtask build` runs the
+    /// same audit, with per-package opt-ins, in xtask/src/footprint.rs.
+    const HEAVYWEIGHT_CORE: &[&str] = &[
+        "core::fmt::",
+        "core::panicking::",
+        "core::result::unwrap_failed",
+        "core::option::expect_failed",
+        "core::slice::index::slice_",
+        "core::str::slice_error_fail",
+        "core::unicode::",
+        "core::num::flt2dec::",
+        "core::num::dec2flt::",
+    ];
+
+    /// The panic handler ignores its `PanicInfo`, so optimized panic paths fold away with the
+    /// formatting behind them; a `format!` or a printed `unwrap` brings them back
+    #[test]
+    fn no_heavyweight_core_machinery() {
+        // Unoptimized builds keep every panic path
+        if std::env::var("BPF_PROFILE").is_ok_and(|profile| profile == "debug") {
+            return;
+        }
+        let elf = std::fs::read(format!("{}.so", program_path()))
+            .expect("build the program with `cargo xtask build` first");
+        let file = object::File::parse(&*elf).unwrap();
+        let pulled: Vec<_> = file
+            .symbols()
+            .filter_map(|symbol| symbol.name().ok())
+            .map(|name| format!("{:#}", rustc_demangle::demangle(name)))
+            .filter(|name| HEAVYWEIGHT_CORE.iter().any(|path| name.contains(path)))
+            .collect();
+        assert!(pulled.is_empty(), "the program pulls in heavyweight core machinery: {pulled:#?}");
+    }
+
+    /// `u128_bpf_fixture::borsh::Position`, encoded by the `borsh` crate
+    #[derive(BorshSerialize)]
+    struct Position {
+        bump: u8,
+        collateral: u128,
+        debt: i128,
+        updated_slot: u64,
+    }
+
+    #[test]
+    fn borsh_position_in_account_data() {
+        let mollusk = Mollusk::new(&PROGRAM_ID.into(), &program_path());
+        let key = [0x07; 32].into();
+        let before = Position {
+            bump: 254,
+            collateral: u64::MAX as u128 + 1,
+            debt: 1_000,
+            updated_slot: 42,
+        };
+        // Room to spare after the encoding, which the program leaves alone
+        let mut account = solana_account::Account::new(1_000_000_000, 64, &PROGRAM_ID.into());
+        let encoded = borsh::to_vec(&before).unwrap();
+        account.data[..encoded.len()].copy_from_slice(&encoded);
+
+        // Repay past the debt, so it goes negative
+        let (collateral, debt) = (1 << 100, -5_000);
+        let after = Position {
+            collateral: before.collateral + collateral,
+            debt: before.debt + debt,
+            ..before
+        };
+        let mut data = account.data.clone();
+        data[..encoded.len()].copy_from_slice(&borsh::to_vec(&after).unwrap());
+        let instruction = solana_instruction::Instruction {
+            program_id: PROGRAM_ID.into(),
+            accounts: vec![solana_instruction::AccountMeta::new(key, false)],
+            data: FixtureInstruction::BorshPosition { collateral, debt }.pack(),
+        };
+        mollusk.process_and_validate_instruction(
+            &instruction,
+            &[(key, account)],
+            &[Check::success(), Check::account(&key).data(&data).build()],
+        );
+    }
+
+    /// Bytes per loader `Write`, small enough to fit a transaction with its signatures
+    const WRITE_CHUNK: usize = 900;
+
+    async fn send(
+        context: &mut ProgramTestContext,
+        instructions: &[solana_instruction::Instruction],
+        signers: &[&Keypair],
+    ) -> Result<(), solana_program_test::BanksClientError> {
+        let blockhash = context.get_new_latest_blockhash().await.unwrap();
+        let mut all_signers = vec![&context.payer];
+        all_signers.extend(signers);
+        let transaction = Transaction::new_signed_with_payer(
+            instructions,
+            Some(&context.payer.pubkey()),
+            &all_signers,
+            blockhash,
+        );
+        context.banks_client.process_transaction(transaction).await
+    }
+
+    /// Create a buffer owned by `authority` and fill it with `elf`, one `Write` per chunk
+    async fn write_buffer(
+        context: &mut ProgramTestContext,
+        elf: &[u8],
+        authority: &Keypair,
+    ) -> Keypair {
+        let buffer = Keypair::new();
+        let rent = context.banks_client.get_rent().await.unwrap();
+        let create = loader::create_buffer(
+            &context.payer.pubkey(),
+            &buffer.pubkey(),
+            &authority.pubkey(),
+            rent.minimum_balance(UpgradeableLoaderState::size_of_buffer(elf.len())),
+            elf.len(),
+        )
+        .unwrap();
+        send(context, &create, &[&buffer]).await.unwrap();
+        for (index, chunk) in elf.chunks(WRITE_CHUNK).enumerate() {
+            let write = loader::write(
+                &buffer.pubkey(),
+                &authority.pubkey(),
+                (index * WRITE_CHUNK) as u32,
+                chunk.to_vec(),
+            );
+            send(context, &[write], &[authority]).await.unwrap();
+        }
+        buffer
+    }
+
+    /// Programs deployed or upgraded in a slot only become invocable in a later one
+    async fn next_slots(context: &mut ProgramTestContext) {
+        let root = context.banks_client.get_root_slot().await.unwrap();
+        context.warp_to_slot(root + 10).unwrap();
+    }
+
+    async fn invoke(context: &mut ProgramTestContext, program_id: &Keypair) {
+        let instruction = solana_instruction::Instruction {
+            program_id: program_id.pubkey(),
+            accounts: vec![],
+            data: FixtureInstruction::MulLoop { a: 10, b: 20 }.pack(),
+        };
+        send(context, &[instruction], &[]).await.unwrap();
+    }
+
+    /// Deploy through the upgradeable loader the way `solana program deploy` does, so the ELF goes
+    /// through program-data accounts and the validator's deployment checks rather than being
+    /// loaded directly
+    #[tokio::test]
+    async fn deploy_through_upgradeable_loader() {
+        let elf = std::fs::read(format!("{}.so", program_path()))
+            .expect("build the program with `cargo xtask build` first");
+        let mut program_test = ProgramTest::default();
+        // Mollusk's default budget; the multiply loop needs more than the per-instruction default
+        program_test.set_compute_max_units(1_400_000);
+        let mut context = program_test.start_with_context().await;
+
+        let authority = Keypair::new();
+        let program = Keypair::new();
+        let buffer = write_buffer(&mut context, &elf, &authority).await;
+        let rent = context.banks_client.get_rent().await.unwrap();
+        let deploy = loader::deploy_with_max_program_len(
+            &context.payer.pubkey(),
+            &program.pubkey(),
+            &buffer.pubkey(),
+            &authority.pubkey(),
+            rent.minimum_balance(UpgradeableLoaderState::size_of_program()),
+            elf.len() * 2,
+        )
+        .unwrap();
+        send(&mut context, &deploy, &[&program, &authority])
+            .await
+            .unwrap();
+
+        // The program-data account holds the authority and the ELF exactly as written
+        let program_data_address = get_program_data_address(&program.pubkey());
+        let program_data = context
+            .banks_client
+            .get_account(program_data_address)
+            .await
+            .unwrap()
+            .unwrap();
+        let metadata_len = UpgradeableLoaderState::size_of_programdata_metadata();
+        match bincode::deserialize(&program_data.data[..metadata_len]).unwrap() {
+            UpgradeableLoaderState::ProgramData {
+                upgrade_authority_address,
+                ..
+            } => {
+                assert_eq!(upgrade_authority_address, Some(authority.pubkey()));
+            }
+            state => panic!("unexpected program-data state {state:?}"),
+        }
+        assert_eq!(
+            &program_data.data[metadata_len..metadata_len + elf.len()],
+            &elf[..]
+        );
+
+        next_slots(&mut context).await;
+        invoke(&mut context, &program).await;
+
+        // Hand the upgrade authority over, then redeploy: only the new authority may upgrade
+        let new_authority = Keypair::new();
+        let set_authority = loader::set_upgrade_authority(
+            &program.pubkey(),
+            &authority.pubkey(),
+            Some(&new_authority.pubkey()),
+        );
+        send(&mut context, &[set_authority], &[&authority])
+            .await
+            .unwrap();
+
+        let buffer = write_buffer(&mut context, &elf, &new_authority).await;
+        let stale = loader::upgrade(
+            &program.pubkey(),
+            &buffer.pubkey(),
+            &authority.pubkey(),
+            &context.payer.pubkey(),
+        );
+        assert!(send(&mut context, &[stale], &[&authority]).await.is_err());
+        let upgrade = loader::upgrade(
+            &program.pubkey(),
+            &buffer.pubkey(),
+            &new_authority.pubkey(),
+            &context.payer.pubkey(),
+        );
+        send(&mut context, &[upgrade], &[&new_authority])
+            .await
+            .unwrap();
+
+        next_slots(&mut context).await;
+        invoke(&mut context, &program).await;
+    }
 }
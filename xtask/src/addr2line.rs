@@ -0,0 +1,211 @@
+//! `cargo xtask addr2line`: the Rust source lines behind a program counter the VM reported, read
+//! from the DWARF of a `release-dwarf` build with the patched LLVM's llvm-addr2line. u128 code is
+//! inlined heavily, so every inlined frame at the address is printed, innermost first.
+
+use crate::elf;
+use anyhow::{bail, Context, Result};
+use std::fs;
+use std::io::Write;
+use std::path::Path;
+use std::process::{Command, Stdio};
+
+/// Parse a pc given in decimal or as `0x` hex
+pub fn parse_pc(pc: &str) -> Result<u64, String> {
+    match pc.strip_prefix("0x") {
+        Some(hex) => u64::from_str_radix(hex, 16),
+        None => pc.parse(),
+    }
+    .map_err(|err| format!("invalid program counter `{pc}`: {err}"))
+}
+
+/// A pretty-printed llvm-addr2line frame, `[(inlined by) ]<symbol> at <file>:<line>`, with the
+/// symbol demangled the way rustc mangles it and without its hash
+fn demangle_frame(frame: &str) -> String {
+    let (inlined, frame) = match frame.strip_prefix("(inlined by) ") {
+        Some(frame) => ("(inlined by) ", frame),
+        None => ("", frame),
+    };
+    match frame.split_once(" at ") {
+        Some((symbol, location)) => {
+            format!("{inlined}{:#} at {location}", rustc_demangle::demangle(symbol))
+        }
+        None => format!("{inlined}{frame}"),
+    }
+}
+
+/// The innermost source line of an address, as llvm-addr2line reports it
+#[derive(Clone, PartialEq, Eq, Debug)]
+pub struct SourceLine {
+    pub file: String,
+    pub line: u32,
+}
+
+/// Parse a `<file>:<line>` llvm-addr2line line, `None` for the `??:0` of an address without line
+/// info
+fn parse_source_line(line: &str) -> Option<SourceLine> {
+    let location = line.split(" (discriminator").next()?.trim();
+    let (file, line) = location.rsplit_once(':')?;
+    match line.parse() {
+        Ok(0) | Err(_) => None,
+        Ok(line) if file != "??" => Some(SourceLine { file: file.to_string(), line }),
+        Ok(_) => None,
+    }
+}
+
+/// Fail unless `program` carries DWARF
+fn check_dwarf(info: &elf::ElfInfo, program: &Path) -> Result<()> {
+    if info.section(".debug_info").is_none() {
+        bail!(
+            "{} has no DWARF. Build it with `cargo xtask build --profile release-dwarf`.",
+            program.display()
+        );
+    }
+    Ok(())
+}
+
+/// The patched LLVM's llvm-addr2line
+fn tool() -> Result<std::path::PathBuf> {
+    let tool = crate::LlvmDirs::selected()?.install.join("bin/llvm-addr2line");
+    if !tool.exists() {
+        bail!("{} not found. Run `cargo xtask build-llvm` first.", tool.display());
+    }
+    Ok(tool)
+}
+
+/// The llvm-addr2line output for each of `addresses` (ELF virtual addresses) in `program`, its
+/// lines grouped per address, from one run with `args`
+fn resolve_all(
+    program: &Path,
+    info: &elf::ElfInfo,
+    addresses: &[u64],
+    args: &[&str],
+) -> Result<Vec<Vec<String>>> {
+    check_dwarf(info, program)?;
+    let tool = tool()?;
+    let mut child = Command::new(&tool)
+        .args(args)
+        .args(["--addresses", "--exe"])
+        .arg(program)
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .spawn()
+        .with_context(|| format!("failed to run {}", tool.display()))?;
+    let input: String = addresses.iter().map(|address| format!("{address:#x}\n")).collect();
+    child.stdin.take().expect("stdin is piped").write_all(input.as_bytes())?;
+    let output = child.wait_with_output()?;
+    if !output.status.success() {
+        bail!("llvm-addr2line failed: {}", String::from_utf8_lossy(&output.stderr).trim());
+    }
+
+    // With --addresses every address is echoed before its lines
+    let mut groups: Vec<Vec<String>> = Vec::with_capacity(addresses.len());
+    for line in String::from_utf8_lossy(&output.stdout).lines() {
+        if line.starts_with("0x") {
+            groups.push(Vec::new());
+        } else if let Some(group) = groups.last_mut() {
+            group.push(line.to_string());
+        } else {
+            bail!("unexpected llvm-addr2line output `{line}`");
+        }
+    }
+    if groups.len() != addresses.len() {
+        bail!("llvm-addr2line resolved {} of {} addresses", groups.len(), addresses.len());
+    }
+    Ok(groups)
+}
+
+/// The innermost source line of each of `addresses` (ELF virtual addresses) in `program`, in one
+/// llvm-addr2line run
+pub fn source_lines(
+    program: &Path,
+    info: &elf::ElfInfo,
+    addresses: &[u64],
+) -> Result<Vec<Option<SourceLine>>> {
+    Ok(resolve_all(program, info, addresses, &[])?
+        .into_iter()
+        .map(|lines| lines.first().and_then(|line| parse_source_line(line)))
+        .collect())
+}
+
+/// The functions inlined at each of `addresses` in `program`, outermost (the function the symbol
+/// table names) first and demangled without hashes, empty where the DWARF has nothing
+pub fn inlined_functions(
+    program: &Path,
+    info: &elf::ElfInfo,
+    addresses: &[u64],
+) -> Result<Vec<Vec<String>>> {
+    let groups = resolve_all(program, info, addresses, &["--functions", "--inlines", "--no-demangle"])?;
+    Ok(groups
+        .into_iter()
+        .map(|lines| {
+            // A function name and its location per frame, innermost first
+            let mut frames: Vec<_> = lines
+                .iter()
+                .step_by(2)
+                .filter(|function| function.as_str() != "??")
+                .map(|function| format!("{:#}", rustc_demangle::demangle(function)))
+                .collect();
+            frames.reverse();
+            frames
+        })
+        .collect())
+}
+
+/// Print the source lines of each of `pcs` in `program`. A pc is the instruction index into
+/// .text the VM reports, or with `addresses`, the ELF virtual address.
+pub fn addr2line(program: &Path, pcs: &[u64], addresses: bool) -> Result<()> {
+    let data = fs::read(program).with_context(|| format!("failed to read {}", program.display()))?;
+    let info = elf::inspect(&data)?;
+    check_dwarf(&info, program)?;
+    let text = info.section(".text").context("the program has no .text section")?;
+
+    let tool = tool()?;
+    for &pc in pcs {
+        let address = if addresses { pc } else { text.address + pc * solana_sbpf::ebpf::INSN_SIZE as u64 };
+        if !(text.address..text.address + text.size).contains(&address) {
+            bail!(
+                "{address:#x} is outside .text ({:#x}..{:#x})",
+                text.address,
+                text.address + text.size
+            );
+        }
+        let output = Command::new(&tool)
+            .args(["--functions", "--inlines", "--no-demangle", "--pretty-print", "--exe"])
+            .arg(program)
+            .arg(format!("{address:#x}"))
+            .output()
+            .with_context(|| format!("failed to run {}", tool.display()))?;
+        if !output.status.success() {
+            bail!("llvm-addr2line failed: {}", String::from_utf8_lossy(&output.stderr).trim());
+        }
+        if addresses {
+            println!("{address:#x}:");
+        } else {
+            println!("pc {pc} ({address:#x}):");
+        }
+        for frame in String::from_utf8_lossy(&output.stdout).lines() {
+            println!("  {}", demangle_frame(frame.trim()));
+        }
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn demangles_inlined_frames() {
+        let frame = "(inlined by) _ZN16u128_bpf_fixture4fold4fold17h0123456789abcdefE at src/fold.rs:7:5";
+        assert_eq!(demangle_frame(frame), "(inlined by) u128_bpf_fixture::fold::fold at src/fold.rs:7:5");
+        assert_eq!(demangle_frame("entrypoint at ??:0"), "entrypoint at ??:0");
+        assert_eq!(parse_pc("0x10"), Ok(16));
+        assert!(parse_pc("pc").is_err());
+        assert_eq!(
+            parse_source_line("/src/fold.rs:7 (discriminator 2)"),
+            Some(SourceLine { file: "/src/fold.rs".to_string(), line: 7 })
+        );
+        assert_eq!(parse_source_line("??:0"), None);
+    }
+}
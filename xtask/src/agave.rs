@@ -0,0 +1,83 @@
+//! Load the program the way an Agave validator does when it is deployed, rather than with the
+//! permissive loader the VM commands use.
+
+use crate::elf::{SbpfVersion, KNOWN_SYSCALLS};
+use crate::vm::RunContext;
+use anyhow::{anyhow, Context, Result};
+use solana_sbpf::{
+    declare_builtin_function,
+    elf::Executable,
+    memory_region::MemoryMapping,
+    program::{BuiltinProgram, SBPFVersion},
+    verifier::RequisiteVerifier,
+    vm::Config,
+};
+use std::fs;
+use std::path::Path;
+use std::sync::Arc;
+
+declare_builtin_function!(
+    /// Stands in for every runtime syscall; only the name matters when loading
+    SyscallStub,
+    fn rust(
+        _context: &mut RunContext,
+        _arg1: u64,
+        _arg2: u64,
+        _arg3: u64,
+        _arg4: u64,
+        _arg5: u64,
+        _memory_mapping: &mut MemoryMapping,
+    ) -> Result<u64, Box<dyn std::error::Error>> {
+        Ok(0)
+    }
+);
+
+/// The runtime environment Agave builds for program deployment
+/// (`create_program_runtime_environment_v1` with deployment checks on and debugging off)
+fn deployment_loader(max_version: SBPFVersion) -> Result<Arc<BuiltinProgram<RunContext>>> {
+    let config = Config {
+        max_call_depth: 64,
+        stack_frame_size: 4096,
+        enable_address_translation: true,
+        enable_stack_frame_gaps: true,
+        instruction_meter_checkpoint_distance: 10_000,
+        enable_instruction_meter: true,
+        enable_instruction_tracing: false,
+        enable_symbol_and_section_labels: false,
+        reject_broken_elfs: true,
+        noop_instruction_rate: 256,
+        sanitize_user_provided_values: true,
+        optimize_rodata: false,
+        aligned_memory_mapping: true,
+        enabled_sbpf_versions: SBPFVersion::V0..=max_version,
+    };
+    let mut loader = BuiltinProgram::new_loader(config);
+    for name in KNOWN_SYSCALLS {
+        loader.register_function(name, SyscallStub::vm)?;
+    }
+    Ok(Arc::new(loader))
+}
+
+/// Check that `program` passes the ELF loader and verifier a validator runs on deployment
+pub fn agave_check(program: &Path, max_version: SbpfVersion) -> Result<()> {
+    let elf = fs::read(program).with_context(|| {
+        format!(
+            "failed to read {}. Run `cargo xtask build` first.",
+            program.display()
+        )
+    })?;
+
+    let executable = Executable::from_elf(&elf, deployment_loader(max_version.into())?)
+        .map_err(|err| anyhow!("the Agave ELF loader rejected {}: {err}", program.display()))?;
+    executable
+        .verify::<RequisiteVerifier>()
+        .map_err(|err| anyhow!("the Agave verifier rejected {}: {err}", program.display()))?;
+
+    println!(
+        "{} is loadable by Agave (SBPF {:?}, {} bytes of text)",
+        program.display(),
+        executable.get_sbpf_version(),
+        executable.get_text_bytes().1.len()
+    );
+    Ok(())
+}
@@ -0,0 +1,560 @@
+//! CU benchmarks of the fixture's alternative lowerings, run in the solana-sbpf VM.
+
+use crate::{elf, fixtures, ops, summary, vm};
+use anyhow::{bail, Context, Result};
+use serde::Serialize;
+use std::collections::BTreeMap;
+use std::fmt::Write as _;
+use std::fs;
+use std::path::Path;
+use u128_bpf_fixture::instruction::{BatchedOp, Entry, EntryOp, FixtureInstruction, Lowering};
+
+/// How each lowering of the multiply is described in the output
+fn describe(lowering: Lowering) -> &'static str {
+    match lowering {
+        Lowering::Native => "native (backend lowering)",
+        Lowering::Libcall => "__multi3 libcall",
+        Lowering::Limbs => "hand-scheduled limbs",
+        Lowering::Pair => "u64-pair emulation",
+    }
+}
+
+/// CU of `op` on every boundary input with a defined result, after checking each result against
+/// the native reference
+fn measure(elf: &[u8], program: &Path, op: &ops::Op) -> Result<Vec<u64>> {
+    let mut compute_units = Vec::new();
+    for operands in op.boundary_inputs() {
+        let Some(expected) = op.reference(&operands) else {
+            continue;
+        };
+        let outcome = vm::execute(elf, &op.encode(&operands), None)?;
+        if outcome.result.as_ref().ok() != Some(&expected) {
+            bail!(
+                "{} computed {} wrong on {operands:#x?}: expected {expected:#x}, got {:?}",
+                program.display(),
+                op.name,
+                outcome.result
+            );
+        }
+        compute_units.push(outcome.instruction_count);
+    }
+    Ok(compute_units)
+}
+
+fn mean(compute_units: &[u64]) -> f64 {
+    compute_units.iter().sum::<u64>() as f64 / compute_units.len() as f64
+}
+
+/// The u128 operations measured one per invocation. The other ops repeat an operation or
+/// exercise syscalls.
+const ARITHMETIC_OPERATIONS: &[&str] = &["mul", "div", "rem", "shl", "shr", "cmp", "add", "sub"];
+
+/// Batch sizes swept by [`batch_sweep`]
+const BATCH_SIZES: &[u32] = &[0, 1, 2, 4, 8, 16, 32, 64];
+
+/// Operands of the batch sweep: a token amount times a price, over an 18-decimal fixed-point
+/// scale
+const BATCH_OPERANDS: (u128, u128) = (123_456_789_000_000_000_000_000_000_000, 1_000_000_000_000_000_000);
+
+/// The ops measured per operation
+fn single_ops() -> impl Iterator<Item = &'static ops::Op> {
+    ops::OPS.iter().filter(|op| ARITHMETIC_OPERATIONS.contains(&op.operation))
+}
+
+/// Compare the CU of each lowering of the multiply, then of every operation's native lowering
+/// against the u64-pair emulation programs use without u128 support
+pub fn bench(program: &Path) -> Result<()> {
+    let elf = fixtures::read_program(program)?;
+
+    println!("{:<28} {:>8} {:>8} {:>8} {:>10}", "u128 multiply", "min CU", "mean CU", "max CU", "vs libcall");
+    let mut rows = Vec::new();
+    for op in single_ops().filter(|op| op.operation == "mul") {
+        let compute_units = measure(&elf, program, op)?;
+        rows.push((
+            op.lowering,
+            *compute_units.iter().min().unwrap(),
+            mean(&compute_units),
+            *compute_units.iter().max().unwrap(),
+        ));
+    }
+    let libcall_mean = rows
+        .iter()
+        .find(|(lowering, ..)| *lowering == Lowering::Libcall)
+        .map(|&(_, _, mean, _)| mean)
+        .unwrap();
+    for (lowering, min, mean, max) in rows {
+        println!(
+            "{:<28} {min:>8} {mean:>8.1} {max:>8} {:>+10.1}",
+            describe(lowering),
+            mean - libcall_mean
+        );
+    }
+    println!();
+
+    println!("{:<28} {:>10} {:>10} {:>10} {:>8}", "native vs u64 pair", "native CU", "pair CU", "saved CU", "speedup");
+    let mut operations: Vec<_> = single_ops().map(|op| op.operation).collect();
+    operations.dedup();
+    for operation in operations {
+        let lowering = |lowering| single_ops().find(|op| op.operation == operation && op.lowering == lowering);
+        let (Some(native), Some(pair)) = (lowering(Lowering::Native), lowering(Lowering::Pair)) else {
+            continue;
+        };
+        let native = mean(&measure(&elf, program, native)?);
+        let pair = mean(&measure(&elf, program, pair)?);
+        println!(
+            "{operation:<28} {native:>10.1} {pair:>10.1} {:>10.1} {:>7.2}x",
+            pair - native,
+            pair / native
+        );
+    }
+    println!();
+    println!(
+        "Mean CU per invocation, including the shared dispatch overhead, over the boundary inputs with a defined result"
+    );
+    Ok(())
+}
+
+/// Check the worst-case CU of every single op over the boundary inputs against the checked-in
+/// baseline, or rewrite the baseline when `bless` is set. An op that costs more than `tolerance`
+/// percent over its baseline fails, and so does one the baseline is missing or still lists
+/// after it's gone. The ops whose CU changed are appended to `summary_md` as markdown if given.
+pub fn check_baseline(
+    program: &Path,
+    baseline: &Path,
+    tolerance: f64,
+    bless: bool,
+    summary_md: Option<&Path>,
+) -> Result<()> {
+    let elf = fixtures::read_program(program)?;
+    let mut current = BTreeMap::new();
+    for op in single_ops() {
+        let compute_units = measure(&elf, program, op)?;
+        current.insert(op.name.to_string(), *compute_units.iter().max().unwrap());
+    }
+
+    if bless {
+        if let Some(dir) = baseline.parent() {
+            fs::create_dir_all(dir)?;
+        }
+        fs::write(baseline, serde_json::to_string_pretty(&current)? + "\n")
+            .with_context(|| format!("failed to write {}", baseline.display()))?;
+        println!("Wrote the CU baseline of {} ops to {}", current.len(), baseline.display());
+        return Ok(());
+    }
+
+    let contents = fs::read_to_string(baseline).with_context(|| {
+        format!(
+            "failed to read {}. Record it with `cargo xtask bench --baseline --bless`.",
+            baseline.display()
+        )
+    })?;
+    let expected: BTreeMap<String, u64> =
+        serde_json::from_str(&contents).with_context(|| format!("failed to parse {}", baseline.display()))?;
+
+    println!("{:<24} {:>10} {:>10} {:>8}", "op (max CU)", "baseline", "current", "change");
+    let (mut failed, mut improved, mut changed) = (Vec::new(), false, Vec::new());
+    for (name, &compute_units) in &current {
+        let Some(&base) = expected.get(name) else {
+            println!("{name:<24} {:>10} {compute_units:>10}  MISSING", "-");
+            failed.push(name.as_str());
+            changed.push(vec![format!("`{name}`"), "-".into(), compute_units.to_string(), "missing".into()]);
+            continue;
+        };
+        let change = 100.0 * (compute_units as f64 - base as f64) / base as f64;
+        let status = if change > tolerance {
+            failed.push(name.as_str());
+            "  REGRESSED"
+        } else {
+            improved |= compute_units < base;
+            ""
+        };
+        println!("{name:<24} {base:>10} {compute_units:>10} {change:>+7.1}%{status}");
+        if compute_units != base {
+            let change = format!("{change:+.1}%{}", if status.is_empty() { "" } else { " **regressed**" });
+            changed.push(vec![format!("`{name}`"), base.to_string(), compute_units.to_string(), change]);
+        }
+    }
+    for (name, base) in expected.iter().filter(|(name, _)| !current.contains_key(*name)) {
+        println!("{name:<24} {base:>10} {:>10}  GONE", "-");
+        failed.push(name.as_str());
+        changed.push(vec![format!("`{name}`"), base.to_string(), "-".into(), "gone".into()]);
+    }
+    println!();
+
+    if let Some(path) = summary_md {
+        let mut markdown = format!(
+            "### CU vs baseline\n\n{} ops checked against `{}` (tolerance {tolerance}%): **{} failed**, {} \
+             changed\n\n",
+            current.len(),
+            baseline.file_name().unwrap_or_default().to_string_lossy(),
+            failed.len(),
+            changed.len()
+        );
+        if !changed.is_empty() {
+            markdown.push_str(&summary::table(&["op (max CU)", "baseline", "current", "change"], &changed));
+        }
+        summary::append(path, &markdown)?;
+    }
+    if !failed.is_empty() {
+        bail!(
+            "{} op(s) regressed beyond {tolerance}% or no longer match the baseline: {}. Re-run with \
+             --bless if the change is intended.",
+            failed.len(),
+            failed.join(", ")
+        );
+    }
+    if improved {
+        println!("Some ops got cheaper; re-run with --bless to lower the baseline and lock the gain in");
+    }
+    println!("No op costs more than {tolerance}% over its baseline");
+    Ok(())
+}
+
+/// Executed-instruction counts per op, from the VM's instruction trace: a measure of codegen
+/// quality that doesn't depend on the runtime's compute or fee model
+pub fn instruction_counts(program: &Path) -> Result<()> {
+    let elf = fixtures::read_program(program)?;
+
+    println!(
+        "{:<14} {:>12} {:>10} {:>8} {:>9} {:>10}",
+        "op", "mean insns", "max insns", "calls", "syscalls", "mean CU"
+    );
+    for op in single_ops() {
+        let (mut instructions, mut calls, mut syscalls, mut compute_units) = (Vec::new(), 0, 0, Vec::new());
+        for operands in op.boundary_inputs() {
+            let Some(expected) = op.reference(&operands) else {
+                continue;
+            };
+            let data = op.encode(&operands);
+            let counts = vm::count_executed(&elf, &data)?;
+            if counts.result.as_ref().ok() != Some(&expected) {
+                bail!(
+                    "{} computed {} wrong on {operands:#x?}: expected {expected:#x}, got {:?}",
+                    program.display(),
+                    op.name,
+                    counts.result
+                );
+            }
+            instructions.push(counts.instructions);
+            calls += counts.calls;
+            syscalls += counts.syscalls;
+            compute_units.push(vm::execute(&elf, &data, None)?.instruction_count);
+        }
+        let samples = instructions.len() as f64;
+        println!(
+            "{:<14} {:>12.1} {:>10} {:>8.1} {:>9.1} {:>10.1}",
+            op.name,
+            mean(&instructions),
+            instructions.iter().max().unwrap(),
+            calls as f64 / samples,
+            syscalls as f64 / samples,
+            mean(&compute_units)
+        );
+    }
+    println!();
+    println!("Per invocation over the boundary inputs with a defined result; calls include libcalls");
+    Ok(())
+}
+
+/// Run every op on each boundary input `runs` times and report the spread of its CU. The same
+/// input must cost the same CU on every run; a spread across inputs means the lowered sequence
+/// branches on its operands, so its cost depends on the data a program feeds it.
+pub fn variance(program: &Path, runs: usize) -> Result<()> {
+    if runs == 0 {
+        bail!("--runs must be at least 1");
+    }
+    let elf = fixtures::read_program(program)?;
+
+    println!(
+        "{:<14} {:>8} {:>8} {:>8} {:>10} {:>8}  flag",
+        "op", "samples", "min CU", "max CU", "mean CU", "stddev"
+    );
+    let mut flagged = 0;
+    for op in single_ops() {
+        let (mut compute_units, mut nondeterministic) = (Vec::new(), false);
+        for operands in op.boundary_inputs() {
+            let Some(expected) = op.reference(&operands) else {
+                continue;
+            };
+            let data = op.encode(&operands);
+            let mut first = None;
+            for _ in 0..runs {
+                let outcome = vm::execute(&elf, &data, None)?;
+                if outcome.result.as_ref().ok() != Some(&expected) {
+                    bail!(
+                        "{} computed {} wrong on {operands:#x?}: expected {expected:#x}, got {:?}",
+                        program.display(),
+                        op.name,
+                        outcome.result
+                    );
+                }
+                let first = *first.get_or_insert(outcome.instruction_count);
+                nondeterministic |= first != outcome.instruction_count;
+                compute_units.push(outcome.instruction_count);
+            }
+        }
+        let min = *compute_units.iter().min().unwrap();
+        let max = *compute_units.iter().max().unwrap();
+        let flag = if nondeterministic {
+            "nondeterministic"
+        } else if min != max {
+            "data-dependent"
+        } else {
+            ""
+        };
+        flagged += usize::from(!flag.is_empty());
+        println!(
+            "{:<14} {:>8} {min:>8} {max:>8} {:>10.1} {:>8.2}  {flag}",
+            op.name,
+            compute_units.len(),
+            mean(&compute_units),
+            std_dev(&compute_units)
+        );
+    }
+    println!();
+    println!(
+        "{runs} runs of every boundary input with a defined result; {flagged} ops flagged. Nondeterministic: \
+         one input cost different CU across runs. Data-dependent: the CU varies with the operands."
+    );
+    Ok(())
+}
+
+/// Population standard deviation
+fn std_dev(compute_units: &[u64]) -> f64 {
+    let mean = mean(compute_units);
+    let variance =
+        compute_units.iter().map(|&cu| (cu as f64 - mean).powi(2)).sum::<f64>() / compute_units.len() as f64;
+    variance.sqrt()
+}
+
+/// Run batches of each size in [`BATCH_SIZES`] for every batched operation and lowering, and fit
+/// CU against batch size: the slope is the marginal CU per operation, the intercept the fixed
+/// cost of an invocation
+pub fn batch_sweep(program: &Path) -> Result<()> {
+    let elf = fixtures::read_program(program)?;
+    let (a, b) = BATCH_OPERANDS;
+
+    print!("{:<16}", "batch size");
+    for count in BATCH_SIZES {
+        print!(" {count:>7}");
+    }
+    println!(" {:>10} {:>10}", "CU/op", "fixed CU");
+    for op in BatchedOp::ALL {
+        for lowering in Lowering::ALL {
+            let instruction = |count| FixtureInstruction::Batch {
+                op,
+                count,
+                a,
+                b,
+                lowering,
+            };
+            // Only the lowerings the program implements for this operation
+            if !instruction(0).lowering_implemented() {
+                continue;
+            }
+
+            let mut points = Vec::new();
+            for &count in BATCH_SIZES {
+                let instruction = instruction(count);
+                let outcome = vm::execute(&elf, &instruction.pack(), None)?;
+                let expected = instruction.expected_return();
+                if outcome.result.as_ref().ok().copied() != expected {
+                    bail!(
+                        "{} computed {instruction:?} wrong: expected {expected:#x?}, got {:?}",
+                        program.display(),
+                        outcome.result
+                    );
+                }
+                points.push((f64::from(count), outcome.instruction_count as f64));
+            }
+
+            print!("{:<16}", format!("{op:?} {}", lowering.name()).to_lowercase());
+            for &(_, compute_units) in &points {
+                print!(" {compute_units:>7}");
+            }
+            let (slope, intercept) = least_squares(&points);
+            println!(" {slope:>10.1} {intercept:>10.1}");
+        }
+    }
+    println!();
+    println!("CU per invocation on the operands ({a} ^ i, {b}); CU/op is the slope of the least-squares fit");
+    Ok(())
+}
+
+/// Run each operation through both A/B entries of one binary, `entry_libcall` and `entry_soft`,
+/// on every pair of boundary values with a defined result, checking each result, and compare
+/// their mean CU. Both run behind the same entrypoint in the same program, so only the lowering
+/// differs.
+pub fn entries(program: &Path) -> Result<()> {
+    let elf = fixtures::read_program(program)?;
+    let info = elf::inspect(&elf)?;
+    for entry in Entry::ALL {
+        if !info.symbols.iter().any(|symbol| symbol.defined && symbol.name == entry.symbol()) {
+            bail!(
+                "{} has no {} function. Rebuild it with `cargo xtask build`.",
+                program.display(),
+                entry.symbol()
+            );
+        }
+    }
+
+    println!("{:<16} {:>12} {:>12} {:>14}", "A/B entries", "libcall CU", "soft CU", "soft - libcall");
+    for op in EntryOp::ALL {
+        let mut means = Vec::new();
+        for entry in Entry::ALL {
+            let mut compute_units = Vec::new();
+            for &a in ops::BOUNDARY_VALUES {
+                for &b in ops::BOUNDARY_VALUES {
+                    let instruction = FixtureInstruction::Entry { entry, op, a, b };
+                    let Some(expected) = instruction.expected_return() else {
+                        continue;
+                    };
+                    let outcome = vm::execute(&elf, &instruction.pack(), None)?;
+                    if outcome.result.as_ref().ok() != Some(&expected) {
+                        bail!(
+                            "{} computed {instruction:?} wrong: expected {expected:#x}, got {:?}",
+                            program.display(),
+                            outcome.result
+                        );
+                    }
+                    compute_units.push(outcome.instruction_count);
+                }
+            }
+            means.push(mean(&compute_units));
+        }
+        let (libcall, soft) = (means[0], means[1]);
+        println!(
+            "{:<16} {libcall:>12.1} {soft:>12.1} {:>+14.1}",
+            format!("{op:?}").to_lowercase(),
+            soft - libcall
+        );
+    }
+    println!();
+    println!("Mean CU per invocation over the boundary inputs with a defined result");
+    Ok(())
+}
+
+/// Slope and intercept of the least-squares line through `points`
+fn least_squares(points: &[(f64, f64)]) -> (f64, f64) {
+    let n = points.len() as f64;
+    let mean_x = points.iter().map(|&(x, _)| x).sum::<f64>() / n;
+    let mean_y = points.iter().map(|&(_, y)| y).sum::<f64>() / n;
+    let covariance: f64 = points.iter().map(|&(x, y)| (x - mean_x) * (y - mean_y)).sum();
+    let variance: f64 = points.iter().map(|&(x, _)| (x - mean_x).powi(2)).sum();
+    let slope = covariance / variance;
+    (slope, mean_y - slope * mean_x)
+}
+
+#[derive(Serialize)]
+struct CostModel {
+    programs: Vec<ModelProgram>,
+    entries: Vec<CostEntry>,
+}
+
+#[derive(Serialize)]
+struct ModelProgram {
+    path: String,
+    sbpf_version: String,
+}
+
+/// CU of one operation under one lowering and SBPF version, over the boundary inputs with a
+/// defined result
+#[derive(Serialize)]
+struct CostEntry {
+    operation: &'static str,
+    lowering: &'static str,
+    sbpf_version: String,
+    samples: usize,
+    min: u64,
+    mean: f64,
+    max: u64,
+}
+
+/// Measure every operation under every lowering in each of `programs`, one build per SBPF
+/// version, and write the cost model to `cost-model.json` and `cost-model.md` in `out_dir`
+pub fn cost_model(programs: &[&Path], out_dir: &Path) -> Result<()> {
+    let mut model = CostModel {
+        programs: Vec::new(),
+        entries: Vec::new(),
+    };
+    for &program in programs {
+        let elf = fixtures::read_program(program)?;
+        let version = elf::inspect(&elf)?
+            .sbpf_version()
+            .with_context(|| format!("{} declares an unknown SBPF version", program.display()))?;
+        let version = format!("{version:?}").to_lowercase();
+        if model.programs.iter().any(|other| other.sbpf_version == version) {
+            bail!("more than one program targets SBPF {version}; pass one build per version");
+        }
+
+        for op in single_ops() {
+            let compute_units = measure(&elf, program, op)?;
+            model.entries.push(CostEntry {
+                operation: op.operation,
+                lowering: op.lowering.name(),
+                sbpf_version: version.clone(),
+                samples: compute_units.len(),
+                min: *compute_units.iter().min().unwrap(),
+                mean: mean(&compute_units),
+                max: *compute_units.iter().max().unwrap(),
+            });
+        }
+        model.programs.push(ModelProgram {
+            path: program.display().to_string(),
+            sbpf_version: version,
+        });
+    }
+
+    let markdown = markdown(&model)?;
+    fs::create_dir_all(out_dir)?;
+    let json_path = out_dir.join("cost-model.json");
+    fs::write(&json_path, serde_json::to_string_pretty(&model)?)
+        .with_context(|| format!("failed to write {}", json_path.display()))?;
+    let markdown_path = out_dir.join("cost-model.md");
+    fs::write(&markdown_path, &markdown).with_context(|| format!("failed to write {}", markdown_path.display()))?;
+
+    print!("{markdown}");
+    println!();
+    println!("Cost model written to {} and {}", markdown_path.display(), json_path.display());
+    Ok(())
+}
+
+/// The cost model as a table with one row per operation and lowering and one column per SBPF
+/// version
+fn markdown(model: &CostModel) -> Result<String> {
+    let mut out = String::new();
+    writeln!(out, "# u128 cost model")?;
+    writeln!(out)?;
+    writeln!(
+        out,
+        "Mean CU per invocation (min..max) over the boundary inputs with a defined result, including the \
+         shared decode and dispatch overhead."
+    )?;
+    writeln!(out)?;
+    write!(out, "| operation | lowering |")?;
+    for program in &model.programs {
+        write!(out, " SBPF {} |", program.sbpf_version)?;
+    }
+    writeln!(out)?;
+    writeln!(out, "|---|---|{}", "---|".repeat(model.programs.len()))?;
+    for op in single_ops() {
+        write!(out, "| {} | {} |", op.operation, op.lowering.name())?;
+        for program in &model.programs {
+            let entry = model.entries.iter().find(|entry| {
+                entry.operation == op.operation
+                    && entry.lowering == op.lowering.name()
+                    && entry.sbpf_version == program.sbpf_version
+            });
+            match entry {
+                Some(entry) => write!(out, " {:.1} ({}..{}) |", entry.mean, entry.min, entry.max)?,
+                None => write!(out, " - |")?,
+            }
+        }
+        writeln!(out)?;
+    }
+    writeln!(out)?;
+    for program in &model.programs {
+        writeln!(out, "- SBPF {}: `{}`", program.sbpf_version, program.path)?;
+    }
+    Ok(out)
+}
@@ -0,0 +1,134 @@
+//! Cross-reference the compiler-rt 128-bit builtins the fixture's ops need against the ones the
+//! stock Solana platform-tools ship, to list what the linker branch still has to provide.
+
+use crate::{elf, ops};
+use anyhow::{bail, Context, Result};
+use object::read::archive::ArchiveFile;
+use object::{Object, ObjectSymbol};
+use std::collections::BTreeSet;
+use std::fs;
+use std::path::{Path, PathBuf};
+use walkdir::WalkDir;
+
+/// Where `cargo build-sbf` installs platform-tools, one directory per release
+pub fn default_platform_tools_root() -> PathBuf {
+    dirs::cache_dir()
+        .unwrap_or_else(|| PathBuf::from("/tmp"))
+        .join("solana")
+}
+
+/// The `compiler_builtins` rlib of the SBF target in the newest platform-tools under `root`
+fn find_compiler_builtins(root: &Path) -> Result<PathBuf> {
+    let newest = WalkDir::new(root)
+        .follow_links(true)
+        .into_iter()
+        .filter_map(Result::ok)
+        .filter(|entry| {
+            let path = entry.path();
+            let is_rlib = path
+                .file_name()
+                .and_then(|name| name.to_str())
+                .is_some_and(|name| name.starts_with("libcompiler_builtins-") && name.ends_with(".rlib"));
+            let is_sbf = path
+                .components()
+                .any(|component| component.as_os_str().to_string_lossy().starts_with("sbf"));
+            is_rlib && is_sbf
+        })
+        // Several releases may be installed side by side; the last one installed is what
+        // `cargo build-sbf` uses by default
+        .max_by_key(|entry| entry.metadata().ok().and_then(|metadata| metadata.modified().ok()))
+        .map(|entry| entry.into_path());
+    newest.with_context(|| {
+        format!(
+            "no SBF compiler_builtins rlib under {}. Install platform-tools with `cargo build-sbf --force-tools-install` or pass --platform-tools.",
+            root.display()
+        )
+    })
+}
+
+/// Names of the i128 builtins defined by any object in the rlib at `path`
+fn provided_builtins(path: &Path) -> Result<BTreeSet<&'static str>> {
+    let data = fs::read(path).with_context(|| format!("failed to read {}", path.display()))?;
+    let archive = ArchiveFile::parse(&*data).with_context(|| format!("failed to parse {}", path.display()))?;
+    let mut provided = BTreeSet::new();
+    for member in archive.members() {
+        let member = member?;
+        // rlibs also carry rustc metadata and bitcode, which aren't objects
+        let Ok(file) = object::File::parse(member.data(&*data)?) else {
+            continue;
+        };
+        for symbol in file.symbols().filter(|symbol| symbol.is_definition() && symbol.is_global()) {
+            let Ok(name) = symbol.name() else {
+                continue;
+            };
+            if let Some(&name) = elf::I128_LIBCALLS.iter().find(|&&libcall| libcall == name) {
+                provided.insert(name);
+            }
+        }
+    }
+    Ok(provided)
+}
+
+/// Print, per op, the builtins it needs and whether stock platform-tools and the patched build
+/// provide them, followed by the builtins missing from platform-tools
+pub fn libcall_gaps(program: &Path, platform_tools: Option<&Path>) -> Result<()> {
+    let rlib = match platform_tools {
+        Some(root) => find_compiler_builtins(root)?,
+        None => find_compiler_builtins(&default_platform_tools_root())?,
+    };
+    let stock = provided_builtins(&rlib)?;
+
+    let elf = fs::read(program).with_context(|| {
+        format!(
+            "failed to read {}. Run `cargo xtask build` first.",
+            program.display()
+        )
+    })?;
+    let info = elf::inspect(&elf)?;
+    let libcalls = info.libcalls();
+    let linked = |name: &str| libcalls.iter().any(|libcall| libcall.name == name && libcall.defined);
+
+    println!("stock:   {}", rlib.display());
+    println!("patched: {}", program.display());
+    println!();
+    println!("{:<12} {:<14} {:<10} {:<10}", "opcode", "builtin", "stock", "patched");
+    let mut required = BTreeSet::new();
+    for op in ops::OPS {
+        for &builtin in op.builtins {
+            required.insert(builtin);
+            println!(
+                "{:<12} {:<14} {:<10} {:<10}",
+                op.name,
+                builtin,
+                if stock.contains(builtin) { "provided" } else { "MISSING" },
+                if linked(builtin) { "linked" } else { "-" }
+            );
+        }
+    }
+
+    // A libcall in the binary that no op accounts for means ops::OPS is out of date
+    let undeclared: Vec<_> = libcalls
+        .iter()
+        .map(|libcall| libcall.name)
+        .filter(|name| !required.contains(name))
+        .collect();
+    if !undeclared.is_empty() {
+        bail!(
+            "{} calls {} but no op in ops::OPS lists them in `builtins`",
+            program.display(),
+            undeclared.join(", ")
+        );
+    }
+
+    let gaps: Vec<_> = required.iter().filter(|name| !stock.contains(**name)).collect();
+    println!();
+    if gaps.is_empty() {
+        println!("Stock platform-tools provide every builtin the fixture needs");
+    } else {
+        println!("Builtins missing from stock platform-tools:");
+        for name in gaps {
+            println!("  {name}");
+        }
+    }
+    Ok(())
+}
@@ -0,0 +1,88 @@
+use crate::vm::{self, ListedInstruction};
+use anyhow::{Context, Result};
+use std::fs;
+use std::path::Path;
+
+/// One line of an instruction-level diff
+enum Edit<'a> {
+    Same(&'a ListedInstruction, &'a ListedInstruction),
+    Removed(&'a ListedInstruction),
+    Added(&'a ListedInstruction),
+}
+
+/// Print per-instruction differences between two builds, with functions matched by symbol
+pub fn bytediff(old_path: &Path, new_path: &Path) -> Result<()> {
+    let read = |path: &Path| fs::read(path).with_context(|| format!("failed to read {}", path.display()));
+    let old = vm::function_instructions(&read(old_path)?)
+        .with_context(|| format!("failed to disassemble {}", old_path.display()))?;
+    let new = vm::function_instructions(&read(new_path)?)
+        .with_context(|| format!("failed to disassemble {}", new_path.display()))?;
+
+    println!("--- {}", old_path.display());
+    println!("+++ {}", new_path.display());
+
+    let mut unchanged = 0;
+    for (name, old_insns) in &old {
+        let Some(new_insns) = new.get(name) else {
+            println!();
+            println!("- {name}: removed ({} instructions)", old_insns.len());
+            continue;
+        };
+        let edits = diff(old_insns, new_insns);
+        if edits.iter().all(|edit| matches!(edit, Edit::Same(..))) {
+            unchanged += 1;
+            continue;
+        }
+        println!();
+        println!("@ {name}: {} -> {} instructions", old_insns.len(), new_insns.len());
+        for edit in edits {
+            match edit {
+                Edit::Same(a, b) => println!("  {:>#7x} {:>#7x}  {}", a.offset, b.offset, a.text),
+                Edit::Removed(a) => println!("- {:>#7x} {:>7}  {}", a.offset, "", a.text),
+                Edit::Added(b) => println!("+ {:>7} {:>#7x}  {}", "", b.offset, b.text),
+            }
+        }
+    }
+    for (name, new_insns) in new.iter().filter(|(name, _)| !old.contains_key(*name)) {
+        println!();
+        println!("+ {name}: added ({} instructions)", new_insns.len());
+    }
+
+    println!();
+    println!("{unchanged} functions unchanged");
+    Ok(())
+}
+
+/// Longest-common-subsequence alignment of two instruction listings by their text
+fn diff<'a>(old: &'a [ListedInstruction], new: &'a [ListedInstruction]) -> Vec<Edit<'a>> {
+    // lcs[i][j] is the length of the LCS of old[i..] and new[j..]
+    let mut lcs = vec![vec![0usize; new.len() + 1]; old.len() + 1];
+    for i in (0..old.len()).rev() {
+        for j in (0..new.len()).rev() {
+            lcs[i][j] = if old[i].text == new[j].text {
+                lcs[i + 1][j + 1] + 1
+            } else {
+                lcs[i + 1][j].max(lcs[i][j + 1])
+            };
+        }
+    }
+
+    let mut edits = Vec::new();
+    let (mut i, mut j) = (0, 0);
+    while i < old.len() && j < new.len() {
+        if old[i].text == new[j].text {
+            edits.push(Edit::Same(&old[i], &new[j]));
+            i += 1;
+            j += 1;
+        } else if lcs[i + 1][j] >= lcs[i][j + 1] {
+            edits.push(Edit::Removed(&old[i]));
+            i += 1;
+        } else {
+            edits.push(Edit::Added(&new[j]));
+            j += 1;
+        }
+    }
+    edits.extend(old[i..].iter().map(Edit::Removed));
+    edits.extend(new[j..].iter().map(Edit::Added));
+    edits
+}
@@ -0,0 +1,198 @@
+//! Export and import of the built toolchain, so CI runners can keep it in their cache instead of
+//! building LLVM on every run. The archive holds the LLVM install prefix, the sbpf-linker binary
+//! and its Cargo.lock, and the `.ref` files selecting them, plus a manifest with the commits and
+//! the sha256 of every file. The source checkouts and the LLVM build tree are left out: the other
+//! commands only need the install prefix and the linker.
+
+use crate::cargo_config::CargoConfig;
+use crate::LlvmDirs;
+use anyhow::{bail, Context, Result};
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use std::collections::BTreeMap;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::process::Command;
+use walkdir::WalkDir;
+
+/// Name of the manifest at the root of the archive
+const MANIFEST: &str = "toolchain-cache.json";
+/// Where an archive is unpacked and verified before its contents replace the cache's
+const STAGING_DIR: &str = ".import";
+
+#[derive(Serialize, Deserialize)]
+struct Manifest {
+    llvm_branch: String,
+    llvm_commit: String,
+    linker_branch: String,
+    linker_commit: String,
+    /// sha256 of the linker's Cargo.lock, which pins every crate the binary was built from
+    linker_lock_sha256: String,
+    /// sha256 of every regular file in the archive, by path relative to the cache directory
+    files: BTreeMap<String, String>,
+}
+
+/// The cache entries the toolchain consists of, relative to the cache directory
+fn entries(llvm_commit: &str, linker_commit: &str) -> Vec<PathBuf> {
+    let cache_dir = crate::cache_dir();
+    let relative = |path: PathBuf| path.strip_prefix(&cache_dir).unwrap().to_path_buf();
+    let linker_src = crate::linker_src_dir(linker_commit);
+    vec![
+        relative(crate::ref_file("llvm")),
+        relative(crate::ref_file("sbpf-linker")),
+        relative(LlvmDirs::for_commit(llvm_commit.to_string()).install),
+        relative(linker_src.join("Cargo.lock")),
+        relative(crate::linker_bin(linker_commit, llvm_commit)),
+    ]
+}
+
+fn sha256_file(path: &Path) -> Result<String> {
+    let contents = fs::read(path).with_context(|| format!("failed to read {}", path.display()))?;
+    Ok(format!("{:x}", Sha256::digest(&contents)))
+}
+
+/// sha256 of every regular file under the `entries` of `root`, by path relative to `root`.
+/// Symlinks are archived as links and left out.
+fn hash_files(root: &Path, entries: &[PathBuf]) -> Result<BTreeMap<String, String>> {
+    let mut files = BTreeMap::new();
+    for entry in entries {
+        for file in WalkDir::new(root.join(entry)).follow_links(false) {
+            let file = file.with_context(|| format!("failed to walk {}", root.join(entry).display()))?;
+            if !file.file_type().is_file() {
+                continue;
+            }
+            let relative = file.path().strip_prefix(root).unwrap().to_string_lossy().replace('\\', "/");
+            files.insert(relative, sha256_file(file.path())?);
+        }
+    }
+    Ok(files)
+}
+
+/// Pack the selected LLVM install, the linker built against it, and their refs into `archive`.
+/// tar picks the compression from the extension, like `.tar.zst` or `.tar.gz`.
+pub fn export(archive: &Path) -> Result<()> {
+    let llvm_commit = crate::selected_commit("llvm", crate::LLVM_BRANCH)?;
+    let linker_commit = crate::selected_commit("sbpf-linker", crate::LINKER_BRANCH)?;
+    let cache_dir = crate::cache_dir();
+    let entries = entries(&llvm_commit, &linker_commit);
+    for entry in &entries {
+        if !cache_dir.join(entry).exists() {
+            bail!("{} not found. Run `cargo xtask setup` first.", cache_dir.join(entry).display());
+        }
+    }
+
+    println!("Hashing the toolchain in {}...", cache_dir.display());
+    let files = hash_files(&cache_dir, &entries)?;
+    let lock = crate::linker_src_dir(&linker_commit).join("Cargo.lock");
+    let manifest = Manifest {
+        llvm_branch: crate::LLVM_BRANCH.to_string(),
+        llvm_commit,
+        linker_branch: crate::LINKER_BRANCH.to_string(),
+        linker_commit,
+        linker_lock_sha256: sha256_file(&lock)?,
+        files,
+    };
+    fs::write(cache_dir.join(MANIFEST), serde_json::to_string_pretty(&manifest)?)
+        .with_context(|| format!("failed to write {}", cache_dir.join(MANIFEST).display()))?;
+
+    if let Some(dir) = archive.parent().filter(|dir| !dir.as_os_str().is_empty()) {
+        fs::create_dir_all(dir)?;
+    }
+    let archive = std::path::absolute(archive)?;
+    crate::run_command(
+        Command::new("tar")
+            .arg("-caf")
+            .arg(&archive)
+            .arg("-C")
+            .arg(&cache_dir)
+            .arg(MANIFEST)
+            .args(&entries),
+        "pack the toolchain",
+    )?;
+    fs::remove_file(cache_dir.join(MANIFEST))?;
+    println!(
+        "Exported LLVM {} and sbpf-linker {} ({} files, Cargo.lock {}) to {}",
+        crate::short_commit(&manifest.llvm_commit),
+        crate::short_commit(&manifest.linker_commit),
+        manifest.files.len(),
+        crate::short_commit(&manifest.linker_lock_sha256),
+        archive.display()
+    );
+    Ok(())
+}
+
+/// Unpack an archive written by [`export`], check every file against the manifest, move the
+/// toolchain into the cache, and point .cargo/config.toml at its linker
+pub fn import(project_root: &Path, archive: &Path) -> Result<()> {
+    let cache_dir = crate::cache_dir();
+    let staging = cache_dir.join(STAGING_DIR);
+    if staging.exists() {
+        fs::remove_dir_all(&staging).with_context(|| format!("failed to remove {}", staging.display()))?;
+    }
+    fs::create_dir_all(&staging)?;
+    crate::run_command(
+        Command::new("tar").arg("-xf").arg(std::path::absolute(archive)?).arg("-C").arg(&staging),
+        "unpack the toolchain",
+    )?;
+
+    let manifest = fs::read_to_string(staging.join(MANIFEST)).with_context(|| {
+        format!("{} has no {MANIFEST}; was it written by `cache export`?", archive.display())
+    })?;
+    let manifest: Manifest =
+        serde_json::from_str(&manifest).with_context(|| format!("failed to parse {MANIFEST}"))?;
+    if manifest.llvm_branch != crate::LLVM_BRANCH || manifest.linker_branch != crate::LINKER_BRANCH {
+        bail!(
+            "the archive holds LLVM `{}` and sbpf-linker `{}`, but this project builds with `{}` and \
+             `{}`",
+            manifest.llvm_branch,
+            manifest.linker_branch,
+            crate::LLVM_BRANCH,
+            crate::LINKER_BRANCH
+        );
+    }
+
+    let entries = entries(&manifest.llvm_commit, &manifest.linker_commit);
+    let files = hash_files(&staging, &entries)?;
+    let lock = crate::linker_src_dir(&manifest.linker_commit).join("Cargo.lock");
+    let lock = lock.strip_prefix(&cache_dir).unwrap().to_string_lossy().replace('\\', "/");
+    if files != manifest.files || files.get(&lock) != Some(&manifest.linker_lock_sha256) {
+        let differing = manifest
+            .files
+            .iter()
+            .filter(|(file, hash)| files.get(*file) != Some(hash))
+            .map(|(file, _)| file.as_str())
+            .chain(files.keys().filter(|file| !manifest.files.contains_key(*file)).map(String::as_str))
+            .chain((files.get(&lock) != Some(&manifest.linker_lock_sha256)).then_some(lock.as_str()))
+            .take(10)
+            .collect::<Vec<_>>();
+        bail!(
+            "{} does not match its manifest, starting with: {}",
+            archive.display(),
+            differing.join(", ")
+        );
+    }
+
+    for entry in &entries {
+        let target = cache_dir.join(entry);
+        if target.is_dir() {
+            fs::remove_dir_all(&target).with_context(|| format!("failed to remove {}", target.display()))?;
+        } else if target.exists() {
+            fs::remove_file(&target).with_context(|| format!("failed to remove {}", target.display()))?;
+        }
+        fs::create_dir_all(target.parent().unwrap())?;
+        fs::rename(staging.join(entry), &target)
+            .with_context(|| format!("failed to move {} into place", target.display()))?;
+    }
+    fs::remove_dir_all(&staging).with_context(|| format!("failed to remove {}", staging.display()))?;
+
+    let linker_bin = crate::linker_bin(&manifest.linker_commit, &manifest.llvm_commit);
+    CargoConfig::new(&linker_bin, crate::FIXTURE_PACKAGE).write(project_root)?;
+    println!(
+        "Imported LLVM {} and sbpf-linker {} ({} files verified) into {}",
+        crate::short_commit(&manifest.llvm_commit),
+        crate::short_commit(&manifest.linker_commit),
+        manifest.files.len(),
+        cache_dir.display()
+    );
+    Ok(())
+}
@@ -0,0 +1,128 @@
+//! The project's .cargo/config.toml, rendered from the checked-in template at
+//! `xtask/templates/cargo-config.toml`. The template holds the config policy (target rustflags,
+//! build-std, and aliases); the code only fills in its placeholders:
+//!
+//! - `{linker}`: path of the sbpf-linker binary
+//! - `{stack_size}`: BPF stack size in bytes, passed to the backend as `-bpf-stack-size`
+//! - `{sbpf_flags}`: extra `-C` codegen options, one rustflags entry pair each
+//! - `{package}`: package the `build-bpf` alias builds
+
+use anyhow::{bail, Context, Result};
+use std::fs;
+use std::path::{Path, PathBuf};
+
+const TEMPLATE: &str = include_str!("../templates/cargo-config.toml");
+
+/// Stack size the fixture is built with, the SBPF frame budget
+pub const DEFAULT_STACK_SIZE: u32 = 4096;
+
+/// Values for the template's placeholders
+pub struct CargoConfig<'a> {
+    pub linker: &'a Path,
+    pub package: &'a str,
+    pub stack_size: u32,
+    pub sbpf_flags: &'a [String],
+}
+
+impl<'a> CargoConfig<'a> {
+    /// The config setup writes: the default stack size and no extra SBPF flags
+    pub fn new(linker: &'a Path, package: &'a str) -> Self {
+        Self {
+            linker,
+            package,
+            stack_size: DEFAULT_STACK_SIZE,
+            sbpf_flags: &[],
+        }
+    }
+
+    /// The template with every placeholder filled in
+    pub fn render(&self) -> String {
+        let sbpf_flags: String = self
+            .sbpf_flags
+            .iter()
+            .map(|flag| format!("\n    \"-C\", {},", toml::Value::String(flag.clone())))
+            .collect();
+        TEMPLATE
+            .replace("{linker}", &self.linker.display().to_string())
+            .replace("{stack_size}", &self.stack_size.to_string())
+            .replace("{sbpf_flags}", &sbpf_flags)
+            .replace("{package}", self.package)
+    }
+
+    /// Render the template to `.cargo/config.toml` under `project_root`
+    pub fn write(&self, project_root: &Path) -> Result<()> {
+        let cargo_config_dir = project_root.join(".cargo");
+        fs::create_dir_all(&cargo_config_dir)?;
+        fs::write(cargo_config_dir.join("config.toml"), self.render())
+            .context("failed to write .cargo/config.toml")
+    }
+}
+
+/// `cargo xtask gen-config`: render the template with the selected linker (or `linker`), the
+/// stack size, and the SBPF flags
+pub fn gen_config(
+    project_root: &Path,
+    linker: Option<PathBuf>,
+    stack_size: u32,
+    sbpf_flags: &[String],
+) -> Result<()> {
+    if stack_size == 0 || !stack_size.is_multiple_of(8) {
+        bail!("the stack size must be a nonzero multiple of 8 bytes, not {stack_size}");
+    }
+    let linker = match linker {
+        Some(linker) => linker,
+        None => {
+            let llvm_commit = crate::selected_commit("llvm", crate::LLVM_BRANCH)?;
+            let linker_commit = crate::selected_commit("sbpf-linker", crate::LINKER_BRANCH)?;
+            crate::linker_bin(&linker_commit, &llvm_commit)
+        }
+    };
+    if !linker.exists() {
+        println!("warning: {} does not exist yet; run `cargo xtask setup` to build it", linker.display());
+    }
+
+    let config = CargoConfig {
+        linker: &linker,
+        package: crate::FIXTURE_PACKAGE,
+        stack_size,
+        sbpf_flags,
+    };
+    config.write(project_root)?;
+    println!("Wrote .cargo/config.toml (linker {}, stack size {stack_size})", linker.display());
+    for flag in sbpf_flags {
+        println!("  -C {flag}");
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn renders_every_placeholder() {
+        let flags = ["target-cpu=v3".to_string()];
+        let config = CargoConfig {
+            linker: Path::new("/cache/sbpf-linker"),
+            package: "u128-bpf-fixture",
+            stack_size: 8192,
+            sbpf_flags: &flags,
+        };
+        let rendered = config.render();
+        for placeholder in ["{linker}", "{stack_size}", "{sbpf_flags}", "{package}"] {
+            assert!(!rendered.contains(placeholder), "{placeholder} left in the config");
+        }
+
+        let config: toml::Value = toml::from_str(&rendered).unwrap();
+        let rustflags: Vec<_> = config["target"]["bpfel-unknown-none"]["rustflags"]
+            .as_array()
+            .unwrap()
+            .iter()
+            .map(|flag| flag.as_str().unwrap())
+            .collect();
+        assert!(rustflags.contains(&"linker=/cache/sbpf-linker"));
+        assert!(rustflags.contains(&"link-arg=--llvm-args=-bpf-stack-size=8192"));
+        assert_eq!(rustflags[rustflags.len() - 2..], ["-C", "target-cpu=v3"]);
+        assert!(config["alias"]["build-bpf"].as_str().unwrap().ends_with("--package u128-bpf-fixture"));
+    }
+}
@@ -0,0 +1,151 @@
+//! `cargo xtask ci`: the whole pipeline a CI workflow runs, as one command. The toolchain check
+//! and the build gate everything else; the checks after them all run even when one fails, so a
+//! single run reports every problem, and the command fails if any step did.
+
+use crate::{agave, bench, codegen, elf, isa, panic_log, purity, Profile};
+use anyhow::{bail, Result};
+use std::path::Path;
+use std::process::Command;
+use std::time::Instant;
+
+enum Status {
+    Passed,
+    Failed(anyhow::Error),
+    Skipped,
+}
+
+type Step<'a> = (&'static str, Box<dyn Fn() -> Result<()> + 'a>);
+
+/// The nightly with rust-src, and the sbpf-linker built for the selected LLVM, are installed
+fn check_toolchain(project_root: &Path) -> Result<()> {
+    crate::setup_rust_toolchain(project_root, false)?;
+    let llvm_commit = crate::selected_commit("llvm", crate::LLVM_BRANCH)?;
+    let linker_commit = crate::selected_commit("sbpf-linker", crate::LINKER_BRANCH)?;
+    let linker_bin = crate::linker_bin(&linker_commit, &llvm_commit);
+    if !linker_bin.exists() {
+        bail!("sbpf-linker not found at {}. Run `cargo xtask setup` first.", linker_bin.display());
+    }
+    println!("  sbpf-linker at {}", linker_bin.display());
+    Ok(())
+}
+
+/// The fixture library builds and passes its unit tests without the `std` feature, so the code
+/// that ships needs nothing std provides on the host. Only as an rlib: the host cdylib needs std's
+/// panic handler and allocator to link.
+fn no_std_check(project_root: &Path) -> Result<()> {
+    let package = ["-p", crate::FIXTURE_PACKAGE, "--lib", "--no-default-features"];
+    for features in [&[][..], &["--features", "no-entrypoint"]] {
+        crate::run_command_prefixed(
+            Command::new("cargo")
+                .arg("rustc")
+                .args(package)
+                .args(features)
+                .args(["--crate-type", "rlib"])
+                .current_dir(project_root),
+            "build the fixture rlib without std",
+            "no-std",
+        )?;
+    }
+    crate::run_command_prefixed(
+        Command::new("cargo").arg("test").args(package).current_dir(project_root),
+        "test the fixture without std",
+        "no-std",
+    )
+}
+
+/// Run the toolchain check, the build, the bytecode checks, the host tests, the no_std check,
+/// and the CU baseline gate, then print a summary of every step
+pub fn ci(project_root: &Path, tolerance: f64) -> Result<()> {
+    let program = Profile::Release.artifact(project_root);
+    let baseline = project_root.join(crate::CU_BASELINE);
+    let prerequisites: Vec<Step> = vec![
+        ("toolchain", Box::new(|| check_toolchain(project_root))),
+        ("build", Box::new(|| crate::build_all(project_root, Profile::Release))),
+    ];
+    let mut checks: Vec<Step> = vec![
+        ("agave-check", Box::new(|| agave::agave_check(&program, elf::SbpfVersion::V3))),
+        ("isa-check", Box::new(|| isa::isa_check(&program))),
+        ("pure-check", Box::new(|| purity::pure_check(&program))),
+        ("codegen-check", Box::new(|| codegen::codegen_check(project_root))),
+        (
+            "panic-log",
+            Box::new(|| panic_log::check(&panic_log::build(project_root, Profile::Release)?)),
+        ),
+        (
+            "tests",
+            Box::new(|| {
+                crate::run_command_prefixed(
+                    Command::new("cargo").args(["test", "--workspace"]).current_dir(project_root),
+                    "run the tests",
+                    "test",
+                )
+            }),
+        ),
+        ("no-std", Box::new(|| no_std_check(project_root))),
+    ];
+    // A checkout without a recorded baseline has nothing to gate on yet
+    let has_baseline = baseline.exists();
+    if has_baseline {
+        checks.push((
+            "cu-baseline",
+            Box::new(|| bench::check_baseline(&program, &baseline, tolerance, false, None)),
+        ));
+    }
+
+    let mut results = Vec::new();
+    let mut blocked = false;
+    for (index, (name, step)) in prerequisites.iter().chain(&checks).enumerate() {
+        if blocked {
+            results.push((*name, Status::Skipped, 0.0));
+            continue;
+        }
+        println!("==> {name}");
+        let start = Instant::now();
+        let status = match step() {
+            Ok(()) => Status::Passed,
+            Err(err) => {
+                println!("{name} failed: {err:#}");
+                // Nothing after the toolchain check and the build can run without them
+                blocked = index < prerequisites.len();
+                Status::Failed(err)
+            }
+        };
+        results.push((*name, status, start.elapsed().as_secs_f64()));
+        println!();
+    }
+    if !has_baseline {
+        println!("==> cu-baseline");
+        println!(
+            "skipped: no CU baseline at {}. Record one with `cargo xtask bench --baseline --bless` and \
+             commit it to gate on compute units.",
+            baseline.display()
+        );
+        println!();
+        results.push(("cu-baseline", Status::Skipped, 0.0));
+    }
+
+    println!("{:<16} {:<8} {:>8}", "step", "status", "time");
+    let mut failed = Vec::new();
+    for (name, status, seconds) in &results {
+        let label = match status {
+            Status::Passed => "passed",
+            Status::Failed(_) => "FAILED",
+            Status::Skipped => "skipped",
+        };
+        println!("{name:<16} {label:<8} {seconds:>7.1}s");
+        if let Status::Failed(err) = status {
+            failed.push(format!("{name}: {err:#}"));
+        }
+    }
+    println!();
+    if !failed.is_empty() {
+        bail!("{} of {} CI step(s) failed:\n  {}", failed.len(), results.len(), failed.join("\n  "));
+    }
+    let skipped = results.iter().filter(|(_, status, _)| matches!(status, Status::Skipped)).count();
+    if skipped == 0 {
+        println!("All {} CI steps passed", results.len());
+    } else {
+        println!("{} CI steps passed, {skipped} skipped", results.len() - skipped);
+    }
+    Ok(())
+}
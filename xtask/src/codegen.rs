@@ -0,0 +1,70 @@
+use anyhow::{bail, Context, Result};
+use std::fs;
+use std::path::Path;
+use std::process::Command;
+
+/// IR module sbpf-linker writes via `--dump-module=llvm_dump` (see .cargo/config.toml)
+pub const LLVM_DUMP: &str = "llvm_dump";
+/// Directory holding the FileCheck pattern files
+const CHECKS_DIR: &str = "codegen";
+const ASM_OUT: &str = "target/codegen/fixture.s";
+
+/// Lower the linked fixture module with the patched `llc` and run every `codegen/*.check` file
+/// against the resulting assembly
+pub fn codegen_check(project_root: &Path) -> Result<()> {
+    crate::build_project(project_root, crate::Profile::Release)?;
+
+    let llvm_bin = crate::LlvmDirs::selected()?.install.join("bin");
+    let llc = llvm_bin.join("llc");
+    let file_check = llvm_bin.join("FileCheck");
+    for tool in [&llc, &file_check] {
+        if !tool.exists() {
+            bail!("{} not found. Run `cargo xtask build-llvm` first.", tool.display());
+        }
+    }
+
+    let dump = project_root.join(LLVM_DUMP);
+    if !dump.exists() {
+        bail!("{} was not written by the linker; check the --dump-module link arg", dump.display());
+    }
+    let asm = project_root.join(ASM_OUT);
+    fs::create_dir_all(asm.parent().unwrap())?;
+    crate::run_command(
+        Command::new(&llc)
+            .args(["-O3", "-bpf-stack-size=4096"])
+            .arg(&dump)
+            .arg("-o")
+            .arg(&asm),
+        "lower fixture with llc",
+    )?;
+
+    let mut checks: Vec<_> = fs::read_dir(project_root.join(CHECKS_DIR))
+        .context("failed to read codegen check directory")?
+        .filter_map(|entry| entry.ok().map(|entry| entry.path()))
+        .filter(|path| path.extension().is_some_and(|ext| ext == "check"))
+        .collect();
+    checks.sort();
+
+    let mut failed = Vec::new();
+    for check in &checks {
+        let name = check.file_stem().unwrap().to_string_lossy().into_owned();
+        let status = Command::new(&file_check)
+            .arg(check)
+            .arg("--input-file")
+            .arg(&asm)
+            .status()
+            .context("failed to run FileCheck")?;
+        if status.success() {
+            println!("  ok      {name}");
+        } else {
+            println!("  FAILED  {name}");
+            failed.push(name);
+        }
+    }
+
+    if !failed.is_empty() {
+        bail!("{} codegen check(s) failed: {}", failed.len(), failed.join(", "));
+    }
+    println!("{} codegen checks passed ({})", checks.len(), asm.display());
+    Ok(())
+}
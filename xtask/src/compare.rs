@@ -0,0 +1,429 @@
+use crate::{elf, ops, vm};
+use anyhow::{Context, Result};
+use std::fmt::Write as _;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::process::Command;
+
+const STOCK_OUT_DIR: &str = "target/compare/stock";
+const REPORT_DIR: &str = "target/compare";
+/// `cargo build-sbf` names the artifact after the crate, without the `lib` prefix
+const STOCK_SO_NAME: &str = "u128_bpf_fixture.so";
+/// Compiler diagnostics quoted from the stock build log in the report
+const MAX_ERROR_LINES: usize = 60;
+
+/// One side of the A/B comparison
+struct Variant {
+    name: &'static str,
+    build_error: Option<String>,
+    elf_size: Option<u64>,
+    summary: Option<Result<vm::ProgramSummary>>,
+    outcome: Option<Result<vm::RunOutcome>>,
+}
+
+impl Variant {
+    fn failed(name: &'static str, error: String) -> Self {
+        Self {
+            name,
+            build_error: Some(error),
+            elf_size: None,
+            summary: None,
+            outcome: None,
+        }
+    }
+
+    fn inspect(name: &'static str, so: &Path, build: Result<()>, data: &[u8]) -> Self {
+        if let Err(err) = build {
+            return Self::failed(name, format!("{err:#}"));
+        }
+        let elf = match fs::read(so) {
+            Ok(elf) => elf,
+            Err(err) => return Self::failed(name, format!("missing artifact {}: {err}", so.display())),
+        };
+        Self {
+            name,
+            build_error: None,
+            elf_size: Some(elf.len() as u64),
+            summary: Some(vm::summarize(&elf)),
+            outcome: Some(vm::execute(&elf, data, None)),
+        }
+    }
+
+    fn build_status(&self) -> String {
+        match &self.build_error {
+            None => "ok".to_string(),
+            Some(_) => "FAILED".to_string(),
+        }
+    }
+
+    fn elf_size(&self) -> String {
+        self.elf_size.map_or("-".to_string(), |size| size.to_string())
+    }
+
+    fn text_size(&self) -> String {
+        match &self.summary {
+            Some(Ok(summary)) => summary.text_size.to_string(),
+            Some(Err(_)) => "load failed".to_string(),
+            None => "-".to_string(),
+        }
+    }
+
+    fn instruction_count(&self) -> String {
+        match &self.summary {
+            Some(Ok(summary)) => summary.instruction_count.to_string(),
+            Some(Err(_)) => "load failed".to_string(),
+            None => "-".to_string(),
+        }
+    }
+
+    fn result(&self) -> String {
+        match &self.outcome {
+            Some(Ok(outcome)) => match &outcome.result {
+                Ok(code) => format!("ok ({code:#x})"),
+                Err(err) => format!("error: {err}"),
+            },
+            Some(Err(_)) => "load failed".to_string(),
+            None => "-".to_string(),
+        }
+    }
+
+    fn compute_units(&self) -> String {
+        match &self.outcome {
+            Some(Ok(outcome)) => outcome.instruction_count.to_string(),
+            _ => "-".to_string(),
+        }
+    }
+}
+
+/// Build the fixture with both toolchains and report them side by side
+pub fn compare(project_root: &Path, data: &[u8]) -> Result<()> {
+    let report_dir = project_root.join(REPORT_DIR);
+    fs::create_dir_all(&report_dir)?;
+
+    println!("[1/3] Building with the patched toolchain...");
+    let patched_build = crate::build_project(project_root, crate::Profile::Release);
+    let patched = Variant::inspect(
+        "patched",
+        &crate::Profile::Release.artifact(project_root),
+        patched_build,
+        data,
+    );
+
+    println!("[2/3] Building with stock platform-tools (cargo build-sbf)...");
+    let stock_out_dir = project_root.join(STOCK_OUT_DIR);
+    let stock_build = build_stock(project_root, &stock_out_dir, &report_dir);
+    let stock = Variant::inspect(
+        "stock",
+        &stock_out_dir.join(STOCK_SO_NAME),
+        stock_build,
+        data,
+    );
+
+    println!("[3/3] Writing disassembly to {}...", report_dir.display());
+    let mut listings = Vec::new();
+    for variant in [&patched, &stock] {
+        let path = report_dir.join(format!("{}.s", variant.name));
+        match &variant.summary {
+            Some(Ok(summary)) => {
+                fs::write(&path, &summary.disassembly)
+                    .with_context(|| format!("failed to write {}", path.display()))?;
+                listings.push(path);
+            }
+            Some(Err(err)) => println!("  {}: {err:#}", variant.name),
+            None => {}
+        }
+    }
+
+    print_table(&patched, &stock);
+    for variant in [&patched, &stock] {
+        if let Some(err) = &variant.build_error {
+            println!();
+            println!("{} build failed: {err}", variant.name);
+        }
+    }
+    if !listings.is_empty() {
+        println!();
+        println!("Disassembly:");
+        for path in &listings {
+            println!("  {}", path.display());
+        }
+    }
+    Ok(())
+}
+
+/// How one build fared on one op's boundary-value matrix
+enum OpOutcome {
+    /// No program to run
+    NotBuilt,
+    Ran {
+        total: usize,
+        passed: usize,
+        /// The first disagreement with the native reference
+        first_failure: Option<String>,
+        /// Highest CU among the passing inputs
+        max_compute_units: Option<u64>,
+    },
+}
+
+impl OpOutcome {
+    fn evaluate(elf: Option<&[u8]>, op: &ops::Op) -> Result<Self> {
+        let Some(elf) = elf else {
+            return Ok(Self::NotBuilt);
+        };
+        let (mut total, mut passed, mut first_failure, mut max_compute_units) = (0, 0, None, None);
+        for operands in op.boundary_inputs() {
+            let Some(expected) = op.reference(&operands) else {
+                continue;
+            };
+            total += 1;
+            let failure = match vm::execute(elf, &op.encode(&operands), None) {
+                Err(err) => Some(format!("load failed: {err:#}")),
+                Ok(outcome) => match outcome.result {
+                    Ok(code) if code == expected => {
+                        passed += 1;
+                        max_compute_units = max_compute_units.max(Some(outcome.instruction_count));
+                        None
+                    }
+                    Ok(code) => Some(format!("{operands:x?}: returned {code:#x}, expected {expected:#x}")),
+                    Err(err) => Some(format!("{operands:x?}: {err}")),
+                },
+            };
+            if first_failure.is_none() {
+                first_failure = failure;
+            }
+        }
+        Ok(Self::Ran {
+            total,
+            passed,
+            first_failure,
+            max_compute_units,
+        })
+    }
+
+    fn status(&self) -> String {
+        match self {
+            Self::NotBuilt => "not built".to_string(),
+            Self::Ran { total, passed, .. } if passed == total => format!("pass ({passed}/{total})"),
+            Self::Ran { total, passed, .. } => format!("**FAIL** ({passed}/{total})"),
+        }
+    }
+
+    fn compute_units(&self) -> String {
+        match self {
+            Self::Ran {
+                max_compute_units: Some(cu),
+                ..
+            } => cu.to_string(),
+            _ => "-".to_string(),
+        }
+    }
+}
+
+/// Build the fixture with both toolchains, run every op's boundary-value matrix on each, and
+/// write a markdown report of where the stock toolchain fails or degrades
+pub fn report(project_root: &Path, out: &Path) -> Result<()> {
+    let report_dir = project_root.join(REPORT_DIR);
+    fs::create_dir_all(&report_dir)?;
+
+    println!("[1/3] Building with the patched toolchain...");
+    let patched_so = crate::Profile::Release.artifact(project_root);
+    let patched_build = crate::build_project(project_root, crate::Profile::Release);
+    let patched = BuiltProgram::read("patched", &patched_so, patched_build);
+
+    println!("[2/3] Building with stock platform-tools (cargo build-sbf)...");
+    let stock_out_dir = project_root.join(STOCK_OUT_DIR);
+    let stock_build = build_stock(project_root, &stock_out_dir, &report_dir);
+    let stock = BuiltProgram::read("stock", &stock_out_dir.join(STOCK_SO_NAME), stock_build);
+
+    println!("[3/3] Running the boundary-value matrix on both builds...");
+    let mut md = String::new();
+    writeln!(md, "# u128 fixture: patched toolchain vs stock platform-tools")?;
+    writeln!(md)?;
+    writeln!(md, "## Builds")?;
+    writeln!(md)?;
+    writeln!(md, "| toolchain | build | ELF size | `.text` size | i128 libcalls |")?;
+    writeln!(md, "|---|---|---|---|---|")?;
+    for program in [&patched, &stock] {
+        writeln!(
+            md,
+            "| {} | {} | {} | {} | {} |",
+            program.name,
+            if program.elf.is_some() { "ok" } else { "**FAILED**" },
+            program.elf.as_ref().map_or("-".to_string(), |elf| elf.len().to_string()),
+            program.text_size(),
+            program.libcalls()
+        )?;
+    }
+
+    for program in [&patched, &stock] {
+        if let Some(err) = &program.build_error {
+            writeln!(md)?;
+            writeln!(md, "### {} build failure", program.name)?;
+            writeln!(md)?;
+            writeln!(md, "```")?;
+            writeln!(md, "{}", err.trim_end())?;
+            writeln!(md, "```")?;
+        }
+    }
+
+    writeln!(md)?;
+    writeln!(md, "## Opcodes")?;
+    writeln!(md)?;
+    writeln!(md, "Every op runs over its boundary-value matrix; inputs the reference leaves undefined are excluded.")?;
+    writeln!(md, "CU is the most any passing input used.")?;
+    writeln!(md)?;
+    writeln!(md, "| opcode | patched | stock | patched CU | stock CU |")?;
+    writeln!(md, "|---|---|---|---|---|")?;
+    let mut failures = Vec::new();
+    for op in ops::OPS {
+        let patched_outcome = OpOutcome::evaluate(patched.elf.as_deref(), op)?;
+        let stock_outcome = OpOutcome::evaluate(stock.elf.as_deref(), op)?;
+        writeln!(
+            md,
+            "| `{}` | {} | {} | {} | {} |",
+            op.name,
+            patched_outcome.status(),
+            stock_outcome.status(),
+            patched_outcome.compute_units(),
+            stock_outcome.compute_units()
+        )?;
+        for (name, outcome) in [(patched.name, &patched_outcome), (stock.name, &stock_outcome)] {
+            if let OpOutcome::Ran {
+                first_failure: Some(failure),
+                ..
+            } = outcome
+            {
+                failures.push(format!("- `{}` ({name}): {failure}", op.name));
+            }
+        }
+    }
+    if !failures.is_empty() {
+        writeln!(md)?;
+        writeln!(md, "### First failure per opcode")?;
+        writeln!(md)?;
+        for failure in &failures {
+            writeln!(md, "{failure}")?;
+        }
+    }
+
+    if let Some(parent) = out.parent() {
+        fs::create_dir_all(parent)?;
+    }
+    fs::write(out, &md).with_context(|| format!("failed to write {}", out.display()))?;
+    println!("Report written to {}", out.display());
+    Ok(())
+}
+
+/// The artifact of one build, or why there is none
+struct BuiltProgram {
+    name: &'static str,
+    elf: Option<Vec<u8>>,
+    build_error: Option<String>,
+}
+
+impl BuiltProgram {
+    fn read(name: &'static str, so: &Path, build: Result<()>) -> Self {
+        let (elf, build_error) = match build.and_then(|()| {
+            fs::read(so).with_context(|| format!("missing artifact {}", so.display()))
+        }) {
+            Ok(elf) => (Some(elf), None),
+            Err(err) => (None, Some(format!("{err:#}"))),
+        };
+        Self {
+            name,
+            elf,
+            build_error,
+        }
+    }
+
+    fn text_size(&self) -> String {
+        self.elf
+            .as_deref()
+            .and_then(|elf| elf::inspect(elf).ok())
+            .and_then(|info| info.section(".text").map(|text| text.size.to_string()))
+            .unwrap_or_else(|| "-".to_string())
+    }
+
+    fn libcalls(&self) -> String {
+        let Some(info) = self.elf.as_deref().and_then(|elf| elf::inspect(elf).ok()) else {
+            return "-".to_string();
+        };
+        let names: Vec<_> = info
+            .libcalls()
+            .iter()
+            .map(|libcall| {
+                if libcall.defined {
+                    format!("`{}`", libcall.name)
+                } else {
+                    format!("`{}` (unresolved)", libcall.name)
+                }
+            })
+            .collect();
+        if names.is_empty() {
+            "none".to_string()
+        } else {
+            names.join(", ")
+        }
+    }
+}
+
+fn build_stock(project_root: &Path, out_dir: &Path, report_dir: &Path) -> Result<()> {
+    let log_path: PathBuf = report_dir.join("stock-build.log");
+    let output = Command::new("cargo")
+        .arg("build-sbf")
+        .arg("--manifest-path")
+        .arg(Path::new(crate::FIXTURE_DIR).join("Cargo.toml"))
+        .arg("--sbf-out-dir")
+        .arg(out_dir)
+        .current_dir(project_root)
+        .output()
+        .context("failed to run cargo build-sbf (is the Solana CLI installed?)")?;
+    fs::write(&log_path, [&output.stdout[..], &output.stderr].concat())
+        .with_context(|| format!("failed to write {}", log_path.display()))?;
+    if !output.status.success() {
+        anyhow::bail!(
+            "cargo build-sbf exited with {} (log: {})\n{}",
+            output.status,
+            log_path.display(),
+            compiler_errors(&String::from_utf8_lossy(&output.stderr))
+        );
+    }
+    Ok(())
+}
+
+/// The `error` diagnostics of a cargo build, each with the source context rustc prints under it
+fn compiler_errors(stderr: &str) -> String {
+    let mut lines = Vec::new();
+    let mut in_error = false;
+    for line in stderr.lines() {
+        if line.starts_with("error") {
+            in_error = true;
+        } else if line.starts_with(|c: char| c.is_ascii_alphabetic()) {
+            in_error = false;
+        }
+        if in_error {
+            lines.push(line);
+        }
+    }
+    if lines.len() > MAX_ERROR_LINES {
+        lines.truncate(MAX_ERROR_LINES);
+        lines.push("...");
+    }
+    lines.join("\n")
+}
+
+fn print_table(patched: &Variant, stock: &Variant) {
+    let rows = [
+        ("build", patched.build_status(), stock.build_status()),
+        ("ELF size (bytes)", patched.elf_size(), stock.elf_size()),
+        (".text size (bytes)", patched.text_size(), stock.text_size()),
+        ("instructions", patched.instruction_count(), stock.instruction_count()),
+        ("result", patched.result(), stock.result()),
+        ("CU", patched.compute_units(), stock.compute_units()),
+    ];
+    println!();
+    println!("{:<20} {:<24} {:<24}", "", patched.name, stock.name);
+    for (label, a, b) in rows {
+        println!("{label:<20} {a:<24} {b:<24}");
+    }
+}
@@ -0,0 +1,133 @@
+//! Line coverage of the fixture's arithmetic modules. Their unit tests run on the host under
+//! cargo-llvm-cov, and the program compiles the same modules for BPF, so the report shows what
+//! math reaches the VM without any host test exercising it. The build uses `no-entrypoint`: the
+//! dispatcher, input parsing, and syscall wrappers only run meaningfully in the VM.
+
+use anyhow::{bail, Context, Result};
+use std::collections::BTreeMap;
+use std::fs;
+use std::path::Path;
+use std::process::Command;
+
+/// Where the lcov report, and the HTML report when asked for, are written
+const COVERAGE_DIR: &str = "target/coverage";
+const LCOV_FILE: &str = "lcov.info";
+
+/// Run the fixture's host tests with coverage instrumentation, write an lcov report to
+/// target/coverage, and print the line coverage of each source file. Fails if the total is below
+/// `fail_under` percent.
+pub fn coverage(project_root: &Path, html: bool, fail_under: Option<f64>) -> Result<()> {
+    let installed = Command::new("cargo")
+        .args(["llvm-cov", "--version"])
+        .output()
+        .is_ok_and(|output| output.status.success());
+    if !installed {
+        bail!(
+            "cargo-llvm-cov is not installed. Install it with `cargo install cargo-llvm-cov` and \
+             `rustup component add llvm-tools-preview`."
+        );
+    }
+
+    let out = project_root.join(COVERAGE_DIR);
+    fs::create_dir_all(&out)?;
+    let lcov_path = out.join(LCOV_FILE);
+    crate::run_command_prefixed(
+        Command::new("cargo")
+            .args(["llvm-cov", "--package", crate::FIXTURE_PACKAGE, "--features", "no-entrypoint"])
+            .args(["--lcov", "--output-path"])
+            .arg(&lcov_path)
+            .current_dir(project_root),
+        "run the fixture's host tests with coverage",
+        "coverage",
+    )?;
+    if html {
+        // Reuses the profile data of the run above instead of running the tests again
+        crate::run_command(
+            Command::new("cargo")
+                .args(["llvm-cov", "report", "--html", "--output-dir"])
+                .arg(&out)
+                .current_dir(project_root),
+            "write the HTML coverage report",
+        )?;
+    }
+
+    let lcov = fs::read_to_string(&lcov_path)
+        .with_context(|| format!("failed to read {}", lcov_path.display()))?;
+    let files = line_counts(&lcov);
+    let percent = |hit: u64, found: u64| if found == 0 { 100.0 } else { 100.0 * hit as f64 / found as f64 };
+
+    println!("{:<32} {:>7} {:>7} {:>8}", "file", "lines", "hit", "covered");
+    let (mut total_found, mut total_hit) = (0, 0);
+    let root = format!("{}/", project_root.display());
+    for (file, &(found, hit)) in &files {
+        let file = file.strip_prefix(&root).unwrap_or(file);
+        println!("{file:<32} {found:>7} {hit:>7} {:>7.1}%", percent(hit, found));
+        total_found += found;
+        total_hit += hit;
+    }
+    let total = percent(total_hit, total_found);
+    println!("{:<32} {total_found:>7} {total_hit:>7} {total:>7.1}%", "total");
+    println!();
+    println!("lcov report written to {}", lcov_path.display());
+    if html {
+        println!("HTML report written to {}", out.join("html/index.html").display());
+    }
+    if let Some(minimum) = fail_under {
+        if total < minimum {
+            bail!("line coverage is {total:.1}%, below the required {minimum:.1}%");
+        }
+    }
+    Ok(())
+}
+
+/// Lines found and lines hit per source file of an lcov report
+fn line_counts(lcov: &str) -> BTreeMap<String, (u64, u64)> {
+    let mut files = BTreeMap::new();
+    let mut file = None;
+    for line in lcov.lines() {
+        let (key, value) = line.split_once(':').unwrap_or((line, ""));
+        match key {
+            "SF" => file = Some(files.entry(value.to_string()).or_insert((0, 0))),
+            "LF" => {
+                if let Some((found, _)) = file.as_mut() {
+                    *found += value.parse().unwrap_or(0);
+                }
+            }
+            "LH" => {
+                if let Some((_, hit)) = file.as_mut() {
+                    *hit += value.parse().unwrap_or(0);
+                }
+            }
+            "end_of_record" => file = None,
+            _ => {}
+        }
+    }
+    files
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn counts_lines_per_file() {
+        let lcov = [
+            "SF:/src/fixture/src/muldiv.rs",
+            "FN:4,mul_div_floor",
+            "DA:4,12",
+            "DA:5,0",
+            "LF:2",
+            "LH:1",
+            "end_of_record",
+            "SF:/src/fixture/src/fold.rs",
+            "LF:6",
+            "LH:6",
+            "end_of_record",
+        ]
+        .join("\n");
+        let counts = line_counts(&lcov);
+        assert_eq!(counts["/src/fixture/src/muldiv.rs"], (2, 1));
+        assert_eq!(counts["/src/fixture/src/fold.rs"], (6, 6));
+        assert_eq!(counts.len(), 2);
+    }
+}
@@ -0,0 +1,116 @@
+//! `cargo xtask dump`: the program's disassembly, function by function. With `--source`, each run
+//! of instructions is headed by the Rust source line it came from, read from the DWARF line tables
+//! of a `release-dwarf` build, so a suspicious instruction sequence can be traced to the expression
+//! that produced it.
+
+use crate::addr2line::{self, SourceLine};
+use crate::{elf, vm};
+use anyhow::{bail, Context, Result};
+use std::collections::HashMap;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::process::Command;
+
+/// Where DWARF built from a rustup toolchain places the standard library sources
+const RUSTC_SOURCE_PREFIX: &str = "/rustc/";
+
+/// Reads the source lines the DWARF points at, each file once
+struct Sources {
+    project_root: PathBuf,
+    /// The toolchain's rust-src, where core's `/rustc/<commit>/library/...` paths live locally
+    rust_src: Option<PathBuf>,
+    files: HashMap<String, Option<Vec<String>>>,
+}
+
+impl Sources {
+    fn new(project_root: &Path) -> Self {
+        let sysroot = crate::toolchain::nightly(project_root).ok().and_then(|nightly| {
+            Command::new("rustc")
+                .arg(format!("+{nightly}"))
+                .args(["--print", "sysroot"])
+                .output()
+                .ok()
+        });
+        let rust_src = sysroot
+            .filter(|output| output.status.success())
+            .map(|output| PathBuf::from(String::from_utf8_lossy(&output.stdout).trim()))
+            .map(|sysroot| sysroot.join("lib/rustlib/src/rust"));
+        Self {
+            project_root: project_root.to_path_buf(),
+            rust_src,
+            files: HashMap::new(),
+        }
+    }
+
+    /// Where `file` is on this machine
+    fn resolve(&self, file: &str) -> Option<PathBuf> {
+        if let Some(rest) = file.strip_prefix(RUSTC_SOURCE_PREFIX) {
+            let (_commit, path) = rest.split_once('/')?;
+            return self.rust_src.as_ref().map(|rust_src| rust_src.join(path));
+        }
+        Some(self.project_root.join(file))
+    }
+
+    /// The text of `source`, trimmed, if its file is readable
+    fn line(&mut self, source: &SourceLine) -> Option<&str> {
+        if !self.files.contains_key(&source.file) {
+            let lines = self
+                .resolve(&source.file)
+                .and_then(|path| fs::read_to_string(path).ok())
+                .map(|contents| contents.lines().map(str::to_string).collect());
+            self.files.insert(source.file.clone(), lines);
+        }
+        let lines = self.files[&source.file].as_ref()?;
+        lines.get(source.line as usize - 1).map(|line| line.trim())
+    }
+}
+
+/// Print the disassembly of every function in `program`, or only `function` (mangled, or
+/// demangled without the hash), interleaved with source lines when `source` is set
+pub fn dump(project_root: &Path, program: &Path, function: Option<&str>, source: bool) -> Result<()> {
+    let data = fs::read(program).with_context(|| format!("failed to read {}", program.display()))?;
+    let mut functions: Vec<_> = vm::function_instructions(&data)?
+        .into_iter()
+        .map(|(name, instructions)| (format!("{:#}", rustc_demangle::demangle(&name)), name, instructions))
+        .collect();
+    if let Some(function) = function {
+        functions.retain(|(demangled, name, _)| name == function || demangled == function);
+        if functions.is_empty() {
+            bail!("no function named {function} in {}", program.display());
+        }
+    }
+
+    let source_lines = if source {
+        let info = elf::inspect(&data)?;
+        let text = info.section(".text").context("the program has no .text section")?;
+        let addresses: Vec<_> = functions
+            .iter()
+            .flat_map(|(_, _, instructions)| instructions)
+            .map(|insn| text.address + (insn.pc * solana_sbpf::ebpf::INSN_SIZE) as u64)
+            .collect();
+        addr2line::source_lines(program, &info, &addresses)?
+    } else {
+        Vec::new()
+    };
+    let mut source_lines = source_lines.into_iter();
+    let mut sources = source.then(|| Sources::new(project_root));
+
+    for (demangled, _, instructions) in &functions {
+        println!("{demangled}:");
+        let mut last = None;
+        for insn in instructions {
+            if let Some(location) = source_lines.next().flatten() {
+                if last.as_ref() != Some(&location) {
+                    println!("    ; {}:{}", location.file, location.line);
+                    if let Some(line) = sources.as_mut().and_then(|sources| sources.line(&location)) {
+                        println!("    ;   {line}");
+                    }
+                    last = Some(location);
+                }
+            }
+            println!("    {:#06x}  {}", insn.offset, insn.text);
+        }
+        println!();
+    }
+    Ok(())
+}
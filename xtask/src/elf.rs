@@ -0,0 +1,348 @@
+//! Static inspection of the emitted `.so`: sections, symbols, dynamic relocations, and
+//! per-function instruction counts.
+
+use anyhow::{anyhow, Context, Result};
+use clap::ValueEnum;
+use object::{
+    FileFlags, Object, ObjectSection, ObjectSymbol, ObjectSymbolTable, RelocationFlags,
+    RelocationTarget, SymbolKind,
+};
+
+/// Runtime helpers LLVM lowers 128-bit arithmetic to when it doesn't expand it inline
+pub const I128_LIBCALLS: &[&str] = &[
+    "__multi3",
+    "__muloti4",
+    "__divti3",
+    "__udivti3",
+    "__modti3",
+    "__umodti3",
+    "__udivmodti4",
+    "__ashlti3",
+    "__ashrti3",
+    "__lshrti3",
+];
+
+/// Syscalls the Solana runtime registers, which are legitimately left undefined at link time
+pub const KNOWN_SYSCALLS: &[&str] = &[
+    "abort",
+    "sol_panic_",
+    "sol_log_",
+    "sol_log_64_",
+    "sol_log_compute_units_",
+    "sol_log_pubkey",
+    "sol_log_data",
+    "sol_create_program_address",
+    "sol_try_find_program_address",
+    "sol_sha256",
+    "sol_keccak256",
+    "sol_blake3",
+    "sol_poseidon",
+    "sol_secp256k1_recover",
+    "sol_curve_validate_point",
+    "sol_curve_group_op",
+    "sol_curve_multiscalar_mul",
+    "sol_alt_bn128_group_op",
+    "sol_alt_bn128_compression",
+    "sol_big_mod_exp",
+    "sol_get_clock_sysvar",
+    "sol_get_epoch_schedule_sysvar",
+    "sol_get_rent_sysvar",
+    "sol_get_fees_sysvar",
+    "sol_get_last_restart_slot",
+    "sol_get_epoch_rewards_sysvar",
+    "sol_get_sysvar",
+    "sol_get_epoch_stake",
+    "sol_memcpy_",
+    "sol_memmove_",
+    "sol_memcmp_",
+    "sol_memset_",
+    "sol_invoke_signed_c",
+    "sol_invoke_signed_rust",
+    "sol_alloc_free_",
+    "sol_set_return_data",
+    "sol_get_return_data",
+    "sol_get_stack_height",
+    "sol_get_processed_sibling_instruction",
+    "sol_remaining_compute_units",
+];
+
+const R_BPF_NONE: u32 = 0;
+const R_BPF_64_64: u32 = 1;
+const R_BPF_64_RELATIVE: u32 = 8;
+const R_BPF_64_32: u32 = 10;
+
+/// SBPF version, as selected by the ELF header's `e_flags`
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord, ValueEnum)]
+pub enum SbpfVersion {
+    V0,
+    V1,
+    V2,
+    V3,
+}
+
+impl SbpfVersion {
+    fn from_e_flags(e_flags: u32) -> Option<Self> {
+        match e_flags {
+            0 => Some(SbpfVersion::V0),
+            1 => Some(SbpfVersion::V1),
+            2 => Some(SbpfVersion::V2),
+            3 => Some(SbpfVersion::V3),
+            _ => None,
+        }
+    }
+
+    /// Whether this version's loader applies relocations of type `r_type`. From V3 on, calls
+    /// and syscalls are static and the strict ELF parser applies no relocations at all.
+    pub fn accepts_relocation(self, r_type: u32) -> bool {
+        self < SbpfVersion::V3 && matches!(r_type, R_BPF_64_64 | R_BPF_64_RELATIVE | R_BPF_64_32)
+    }
+}
+
+impl From<SbpfVersion> for solana_sbpf::program::SBPFVersion {
+    fn from(version: SbpfVersion) -> Self {
+        match version {
+            SbpfVersion::V0 => Self::V0,
+            SbpfVersion::V1 => Self::V1,
+            SbpfVersion::V2 => Self::V2,
+            SbpfVersion::V3 => Self::V3,
+        }
+    }
+}
+
+/// Name of a BPF relocation type
+pub fn relocation_type_name(r_type: u32) -> &'static str {
+    match r_type {
+        R_BPF_NONE => "R_BPF_NONE",
+        R_BPF_64_64 => "R_BPF_64_64",
+        2 => "R_BPF_64_ABS64",
+        3 => "R_BPF_64_ABS32",
+        4 => "R_BPF_64_NODYLD32",
+        R_BPF_64_RELATIVE => "R_BPF_64_RELATIVE",
+        R_BPF_64_32 => "R_BPF_64_32",
+        _ => "unknown",
+    }
+}
+
+/// Size of one BPF instruction slot
+const INSN_SIZE: usize = 8;
+/// `lddw`, the only instruction that occupies two slots
+const LD_DW_IMM: u8 = 0x18;
+
+pub struct Section {
+    pub name: String,
+    pub address: u64,
+    pub size: u64,
+}
+
+pub struct Symbol {
+    pub name: String,
+    pub address: u64,
+    pub size: u64,
+    pub is_function: bool,
+    /// False for symbols the object imports rather than defines
+    pub defined: bool,
+}
+
+pub struct Relocation {
+    pub offset: u64,
+    /// ELF `r_type`, e.g. `R_BPF_64_32` for calls
+    pub r_type: u32,
+    /// Dynamic symbol the relocation refers to, if any
+    pub symbol: Option<String>,
+}
+
+pub struct Function {
+    pub name: String,
+    pub address: u64,
+    pub size: u64,
+    pub instruction_count: usize,
+}
+
+/// Everything the xtask commands need to know about a program binary
+pub struct ElfInfo {
+    /// Raw `e_flags` of the ELF header
+    pub e_flags: u32,
+    pub sections: Vec<Section>,
+    pub symbols: Vec<Symbol>,
+    pub relocations: Vec<Relocation>,
+    pub functions: Vec<Function>,
+}
+
+/// How one i128 libcall shows up in a binary
+pub struct LibcallUse<'a> {
+    pub name: &'static str,
+    pub relocations: Vec<&'a Relocation>,
+    /// Whether the linker pulled a definition into the binary; relocations against an
+    /// undefined libcall are left for the loader, which rejects them as unknown syscalls
+    pub defined: bool,
+}
+
+impl ElfInfo {
+    /// SBPF version the header declares, or `None` for flags the loader doesn't know
+    pub fn sbpf_version(&self) -> Option<SbpfVersion> {
+        SbpfVersion::from_e_flags(self.e_flags)
+    }
+
+    pub fn section(&self, name: &str) -> Option<&Section> {
+        self.sections.iter().find(|section| section.name == name)
+    }
+
+    /// Undefined symbols the runtime won't provide, which make the loader reject the program
+    pub fn unresolved_symbols(&self) -> Vec<&str> {
+        self.symbols
+            .iter()
+            .filter(|symbol| !symbol.defined && !KNOWN_SYSCALLS.contains(&symbol.name.as_str()))
+            .map(|symbol| symbol.name.as_str())
+            .collect()
+    }
+
+    /// Every i128 libcall the binary references or defines
+    pub fn libcalls(&self) -> Vec<LibcallUse<'_>> {
+        I128_LIBCALLS
+            .iter()
+            .filter_map(|&name| {
+                let relocations: Vec<_> = self
+                    .relocations
+                    .iter()
+                    .filter(|relocation| relocation.symbol.as_deref() == Some(name))
+                    .collect();
+                let defined = self.symbols.iter().any(|symbol| symbol.name == name && symbol.defined);
+                (defined || !relocations.is_empty()).then_some(LibcallUse {
+                    name,
+                    relocations,
+                    defined,
+                })
+            })
+            .collect()
+    }
+}
+
+/// Parse `elf` and collect its sections, symbols, dynamic relocations, and functions
+pub fn inspect(elf: &[u8]) -> Result<ElfInfo> {
+    let file = object::File::parse(elf).context("failed to parse ELF")?;
+
+    let sections = file
+        .sections()
+        .map(|section| {
+            Ok(Section {
+                name: section.name()?.to_string(),
+                address: section.address(),
+                size: section.size(),
+            })
+        })
+        .collect::<Result<Vec<_>>>()?;
+
+    let mut symbols = Vec::new();
+    for symbol in file.symbols().chain(file.dynamic_symbols()) {
+        if matches!(symbol.kind(), SymbolKind::File | SymbolKind::Section) {
+            continue;
+        }
+        let name = symbol.name()?;
+        if name.is_empty() || symbols.iter().any(|s: &Symbol| s.name == name) {
+            continue;
+        }
+        symbols.push(Symbol {
+            name: name.to_string(),
+            address: symbol.address(),
+            size: symbol.size(),
+            is_function: symbol.kind() == SymbolKind::Text,
+            defined: !symbol.is_undefined(),
+        });
+    }
+
+    let mut relocations = Vec::new();
+    let dynamic_symbols = file.dynamic_symbol_table();
+    for (offset, relocation) in file.dynamic_relocations().into_iter().flatten() {
+        let RelocationFlags::Elf { r_type } = relocation.flags() else {
+            continue;
+        };
+        let symbol = match (relocation.target(), &dynamic_symbols) {
+            (RelocationTarget::Symbol(index), Some(table)) => table
+                .symbol_by_index(index)
+                .and_then(|symbol| symbol.name().map(str::to_string))
+                .ok(),
+            _ => None,
+        };
+        relocations.push(Relocation {
+            offset,
+            r_type,
+            symbol,
+        });
+    }
+
+    let mut functions = Vec::new();
+    for symbol in file.symbols().filter(|s| s.kind() == SymbolKind::Text && s.is_definition()) {
+        let Some(index) = symbol.section_index() else {
+            continue;
+        };
+        let name = symbol.name()?;
+        let section = file.section_by_index(index)?;
+        let data = section.data()?;
+        let start = (symbol.address() - section.address()) as usize;
+        let code = data
+            .get(start..start + symbol.size() as usize)
+            .ok_or_else(|| anyhow!("function {name} lies outside its section"))?;
+        functions.push(Function {
+            name: name.to_string(),
+            address: symbol.address(),
+            size: symbol.size(),
+            instruction_count: count_instructions(code),
+        });
+    }
+    functions.sort_by_key(|function| function.address);
+
+    let e_flags = match file.flags() {
+        FileFlags::Elf { e_flags, .. } => e_flags,
+        _ => 0,
+    };
+
+    Ok(ElfInfo {
+        e_flags,
+        sections,
+        symbols,
+        relocations,
+        functions,
+    })
+}
+
+/// Number of instructions in `code`, counting each two-slot `lddw` once
+pub fn count_instructions(code: &[u8]) -> usize {
+    let mut count = 0;
+    let mut offset = 0;
+    while offset < code.len() {
+        offset += if code[offset] == LD_DW_IMM { 2 * INSN_SIZE } else { INSN_SIZE };
+        count += 1;
+    }
+    count
+}
+
+/// The toolchain record the fixture embeds (`llvm=<commit> linker=<commit> built=<seconds>`), if
+/// `elf` carries one
+pub fn toolchain_record(elf: &[u8]) -> Option<&str> {
+    let marker = u128_bpf_fixture::build_info::MARKER.as_bytes();
+    let start = elf.windows(marker.len()).position(|window| window == marker)? + marker.len();
+    let record = elf[start..].split(|&byte| byte == 0).next()?;
+    std::str::from_utf8(record).ok()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{count_instructions, toolchain_record};
+
+    #[test]
+    fn lddw_counts_as_one_instruction() {
+        let mut code = Vec::new();
+        code.extend([0xb7, 0, 0, 0, 0, 0, 0, 0]); // mov64 r0, 0
+        code.extend([0x18, 0, 0, 0, 1, 0, 0, 0]); // lddw r0, 1 (first slot)
+        code.extend([0x00, 0, 0, 0, 0, 0, 0, 0]); // lddw (second slot)
+        code.extend([0x95, 0, 0, 0, 0, 0, 0, 0]); // exit
+        assert_eq!(count_instructions(&code), 3);
+    }
+
+    #[test]
+    fn finds_the_nul_terminated_toolchain_record() {
+        let elf = b"\x7fELF...u128-fixture toolchain: llvm=abc linker=unknown built=1700000000\0panicked";
+        assert_eq!(toolchain_record(elf), Some("llvm=abc linker=unknown built=1700000000"));
+        assert_eq!(toolchain_record(b"\x7fELF..."), None);
+    }
+}
@@ -0,0 +1,39 @@
+//! Host-side decoding of the program's error codes (`u128_bpf_fixture::error`), so a failing
+//! return code reads as the error the program meant rather than a bare number.
+
+use u128_bpf_fixture::error::{ErrorClass, FixtureError};
+
+/// What `code` means as an error of the program, e.g. `InvalidInstructionData (input error)`, or
+/// `None` outside the taxonomy's ranges, where the program keeps every successful result. A code
+/// the class reserves but no error uses yet is still named by its class, since a newer program may
+/// return it.
+pub fn describe(code: u64) -> Option<String> {
+    let class = ErrorClass::of(code)?;
+    let class_name = match class {
+        ErrorClass::Input => "input error",
+        ErrorClass::Arithmetic => "arithmetic error",
+        ErrorClass::Internal => "internal invariant failure",
+    };
+    Some(match FixtureError::from_code(code) {
+        Some(error) => format!("{error:?} ({class_name})"),
+        None => format!("unknown {class_name}"),
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn every_error_decodes_in_its_class() {
+        for error in FixtureError::ALL {
+            let described = describe(error.code()).unwrap();
+            assert!(described.starts_with(&format!("{error:?} (")), "{described}");
+        }
+        assert_eq!(describe(100).as_deref(), Some("InvalidInstructionData (input error)"));
+        assert_eq!(describe(150).as_deref(), Some("unknown input error"));
+        assert_eq!(describe(399).as_deref(), Some("unknown internal invariant failure"));
+        assert_eq!(describe(0), None);
+        assert_eq!(describe(400), None);
+    }
+}
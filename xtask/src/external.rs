@@ -0,0 +1,136 @@
+//! Run the op x boundary-value matrix under another SVM implementation (a Firedancer harness, a
+//! custom VM, ...) by handing it a fixture file, and check what it reports against the native
+//! reference.
+//!
+//! The runner is invoked as `<command> <fixture.json>`. The fixture holds the absolute path of
+//! the program, the program id, and per vector its name, instruction data, and serialized input
+//! region (hex). The runner prints a JSON array to stdout with one entry per vector it ran:
+//! `{"name": ..., "return_code": <u64 or null>, "error": <string, optional>,
+//! "compute_units": <u64, optional>}`.
+
+use crate::{fixtures, ops, vm};
+use anyhow::{bail, Context, Result};
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, BTreeSet};
+use std::fs;
+use std::path::Path;
+use std::process::{Command, Stdio};
+
+const FIXTURE_FILE: &str = "target/external/fixture.json";
+
+#[derive(Serialize)]
+struct Fixture {
+    program: String,
+    program_id: String,
+    vectors: Vec<FixtureVector>,
+}
+
+#[derive(Serialize)]
+struct FixtureVector {
+    name: String,
+    instruction_data: String,
+    input: String,
+}
+
+/// What the runner reports for one vector
+#[derive(Deserialize)]
+struct RunnerResult {
+    name: String,
+    return_code: Option<u64>,
+    error: Option<String>,
+    compute_units: Option<u64>,
+}
+
+/// Write the fixture, run `runner` on it, and compare every reported return code with the native
+/// reference
+pub fn run_external(project_root: &Path, program: &Path, runner: &str) -> Result<()> {
+    // Fail early with the usual hint rather than leaving the runner to report a missing file
+    fixtures::read_program(program)?;
+    let program = fs::canonicalize(program)?;
+
+    let mut expected = BTreeMap::new();
+    let mut vectors = Vec::new();
+    for op in ops::OPS {
+        for operands in op.boundary_inputs() {
+            let Some(reference) = op.reference(&operands) else {
+                continue;
+            };
+            let name = format!(
+                "{}({})",
+                op.name,
+                operands.iter().map(|x| format!("{x:#x}")).collect::<Vec<_>>().join(", ")
+            );
+            let data = op.encode(&operands);
+            vectors.push(FixtureVector {
+                name: name.clone(),
+                instruction_data: vm::encode_hex(&data),
+                input: vm::encode_hex(&vm::serialize_input(&data)),
+            });
+            expected.insert(name, reference);
+        }
+    }
+    let fixture = Fixture {
+        program: program.display().to_string(),
+        program_id: vm::encode_hex(&vm::PROGRAM_ID),
+        vectors,
+    };
+    let fixture_path = project_root.join(FIXTURE_FILE);
+    fs::create_dir_all(fixture_path.parent().unwrap())?;
+    fs::write(&fixture_path, serde_json::to_string_pretty(&fixture)?)
+        .with_context(|| format!("failed to write {}", fixture_path.display()))?;
+
+    println!("Running {} vectors under `{runner}`...", fixture.vectors.len());
+    // Through the shell so the runner can be a command line with its own arguments
+    let output = Command::new("sh")
+        .arg("-c")
+        .arg(format!("{runner} \"$1\""))
+        .arg("xtask")
+        .arg(&fixture_path)
+        .stderr(Stdio::inherit())
+        .output()
+        .with_context(|| format!("failed to run `{runner}`"))?;
+    if !output.status.success() {
+        bail!("`{runner}` exited with {}", output.status);
+    }
+    let results: Vec<RunnerResult> = serde_json::from_slice(&output.stdout)
+        .with_context(|| format!("`{runner}` did not print a JSON array of results"))?;
+
+    let mut mismatches = 0;
+    let mut reported = BTreeSet::new();
+    for result in &results {
+        let Some(&reference) = expected.get(&result.name) else {
+            bail!("`{runner}` reported a result for unknown vector {}", result.name);
+        };
+        if !reported.insert(&result.name) {
+            bail!("`{runner}` reported {} more than once", result.name);
+        }
+        let cu = result.compute_units.map_or(String::new(), |cu| format!(" ({cu} CU)"));
+        match (result.return_code, &result.error) {
+            (Some(code), None) if code == reference => {}
+            (Some(code), None) => {
+                mismatches += 1;
+                println!("MISMATCH {}: expected {reference:#x}, runner returned {code:#x}{cu}", result.name);
+            }
+            (_, Some(error)) => {
+                mismatches += 1;
+                println!("MISMATCH {}: expected {reference:#x}, runner failed: {error}{cu}", result.name);
+            }
+            (None, None) => {
+                mismatches += 1;
+                println!("MISMATCH {}: expected {reference:#x}, runner reported neither a result nor an error", result.name);
+            }
+        }
+    }
+    let missing = expected.len() - reported.len();
+
+    println!(
+        "{} of {} vectors match the native reference ({} not reported)",
+        reported.len() - mismatches,
+        expected.len(),
+        missing
+    );
+    if mismatches > 0 || missing > 0 {
+        bail!("the external runner disagrees with the native reference on {mismatches} vectors and skipped {missing}");
+    }
+    Ok(())
+}
@@ -0,0 +1,284 @@
+use crate::{errors, ops, vm};
+use anyhow::{bail, Context, Result};
+use serde::Serialize;
+use sha2::{Digest, Sha256};
+use std::fs;
+use std::path::Path;
+use u128_bpf_fixture::instruction::FixtureInstruction;
+
+/// Operands of the echo a matrix run starts with. Every byte differs, so an operand read at the
+/// wrong offset or in the wrong byte order can't come back looking right.
+const ECHO_OPERANDS: (u128, u128) = (
+    0x1f1e_1d1c_1b1a_1918_1716_1514_1312_1110,
+    0x2f2e_2d2c_2b2a_2928_2726_2524_2322_2120,
+);
+
+/// A single conformance vector, agreed on by the native reference and the VM
+#[derive(Serialize)]
+struct Vector {
+    opcode: &'static str,
+    /// Operands as decimal strings, since JSON numbers cannot hold a u128
+    inputs: Vec<String>,
+    /// Serialized instruction data as hex
+    data: String,
+    expected_output: Expected,
+    compute_units: u64,
+}
+
+#[derive(Serialize)]
+struct Expected {
+    return_code: u64,
+}
+
+/// Self-contained vectors in a form that can be proposed to the Agave/solana-sbpf conformance
+/// suites: the exact program, the exact input region, and everything observable about the run
+#[derive(Serialize)]
+struct ConformanceSuite {
+    program: ConformanceProgram,
+    /// Program id the input region is serialized with
+    program_id: String,
+    vectors: Vec<ConformanceVector>,
+}
+
+#[derive(Serialize)]
+struct ConformanceProgram {
+    file: String,
+    sha256: String,
+}
+
+#[derive(Serialize)]
+struct ConformanceVector {
+    name: String,
+    instruction_data: String,
+    /// Full input region the program is entered with (aligned loader serialization, no accounts)
+    input: String,
+    expected: ConformanceExpected,
+}
+
+#[derive(Serialize)]
+struct ConformanceExpected {
+    return_code: u64,
+    return_data: String,
+    logs: Vec<String>,
+    compute_units: u64,
+}
+
+/// One input of the op x boundary-value matrix on which the VM agreed with the reference
+pub struct Case {
+    pub op: &'static ops::Op,
+    pub operands: Vec<u128>,
+    data: Vec<u8>,
+    pub outcome: vm::RunOutcome,
+    expected: u64,
+}
+
+/// One input of the matrix on which the VM disagreed with the reference
+pub struct Mismatch {
+    pub op: &'static ops::Op,
+    pub operands: Vec<u128>,
+    pub message: String,
+    pub compute_units: u64,
+}
+
+/// Every input of the matrix, split by how the VM fared on it
+pub struct Matrix {
+    pub cases: Vec<Case>,
+    /// Inputs the reference leaves undefined
+    pub skipped: Vec<(&'static ops::Op, Vec<u128>)>,
+    /// Inputs the VM disagreed on, each already reported
+    pub mismatches: Vec<Mismatch>,
+}
+
+impl Matrix {
+    pub fn check(&self) -> Result<()> {
+        if !self.mismatches.is_empty() {
+            bail!(
+                "{} vectors disagree between the native reference and the VM",
+                self.mismatches.len()
+            );
+        }
+        Ok(())
+    }
+}
+
+/// Send the program an echo and check it sees instruction data the way the harness encodes it:
+/// the raw bytes come back unchanged as return data, and the operands it logs are the values
+/// encoded. A broken assumption about offsets, endianness, or serialization then fails once,
+/// here, instead of as a mismatch on every arithmetic op.
+pub fn check_echo(elf: &[u8]) -> Result<()> {
+    let (a, b) = ECHO_OPERANDS;
+    let data = FixtureInstruction::Echo { a, b }.pack();
+    let outcome = vm::execute(elf, &data, None)?;
+    match outcome.result {
+        Ok(0) => {}
+        Ok(code) => bail!("the echo returned {code:#x} instead of 0"),
+        Err(err) => bail!("the echo failed: {err}"),
+    }
+    if outcome.return_data != data {
+        bail!(
+            "the program echoed {} for the instruction data {}",
+            vm::encode_hex(&outcome.return_data),
+            vm::encode_hex(&data)
+        );
+    }
+    let decoded = format!(
+        "Program log: {:#x}, {:#x}, {:#x}, {:#x}, {:#x}",
+        a as u64,
+        (a >> 64) as u64,
+        b as u64,
+        (b >> 64) as u64,
+        data.len()
+    );
+    if !outcome.logs.contains(&decoded) {
+        bail!(
+            "the program decoded the echo operands differently than they were encoded; expected \
+             `{decoded}`, logs:\n{}",
+            outcome.logs.join("\n")
+        );
+    }
+    Ok(())
+}
+
+/// Run every op over the boundary-value matrix in the VM and compare each result with the
+/// native reference, after checking the echo
+pub fn run_matrix(elf: &[u8]) -> Result<Matrix> {
+    check_echo(elf).context("the harness's view of the instruction data is off")?;
+    let mut cases = Vec::new();
+    let mut skipped = Vec::new();
+    let mut mismatches = Vec::new();
+    for op in ops::OPS {
+        for operands in op.boundary_inputs() {
+            let Some(expected) = op.reference(&operands) else {
+                skipped.push((op, operands));
+                continue;
+            };
+            let data = op.encode(&operands);
+            let outcome = vm::execute(elf, &data, None)?;
+            let message = match &outcome.result {
+                Ok(code) if *code == expected => None,
+                Ok(code) => Some(match errors::describe(*code) {
+                    Some(error) => format!("expected {expected:#x}, VM returned {code:#x} ({error})"),
+                    None => format!("expected {expected:#x}, VM returned {code:#x}"),
+                }),
+                Err(err) => Some(format!("expected {expected:#x}, VM failed: {err}")),
+            };
+            if let Some(message) = message {
+                println!("MISMATCH {} {operands:?}: {message}", op.name);
+                mismatches.push(Mismatch {
+                    op,
+                    operands,
+                    message,
+                    compute_units: outcome.instruction_count,
+                });
+                continue;
+            }
+            cases.push(Case {
+                op,
+                operands,
+                data,
+                outcome,
+                expected,
+            });
+        }
+    }
+
+    Ok(Matrix {
+        cases,
+        skipped,
+        mismatches,
+    })
+}
+
+pub fn read_program(program: &Path) -> Result<Vec<u8>> {
+    fs::read(program).with_context(|| {
+        format!(
+            "failed to read {}. Run `cargo xtask build` first.",
+            program.display()
+        )
+    })
+}
+
+/// Run every op over the boundary-value matrix and write the agreed-upon vectors as JSON
+pub fn gen_fixtures(program: &Path, out: &Path) -> Result<()> {
+    let elf = read_program(program)?;
+    let matrix = run_matrix(&elf)?;
+
+    let vectors: Vec<_> = matrix
+        .cases
+        .iter()
+        .map(|case| Vector {
+            opcode: case.op.name,
+            inputs: case.operands.iter().map(u128::to_string).collect(),
+            data: vm::encode_hex(&case.data),
+            expected_output: Expected {
+                return_code: case.expected,
+            },
+            compute_units: case.outcome.instruction_count,
+        })
+        .collect();
+
+    if let Some(parent) = out.parent() {
+        fs::create_dir_all(parent)?;
+    }
+    fs::write(out, serde_json::to_string_pretty(&vectors)?)
+        .with_context(|| format!("failed to write {}", out.display()))?;
+    println!(
+        "Wrote {} vectors to {} ({} undefined inputs skipped)",
+        vectors.len(),
+        out.display(),
+        matrix.skipped.len()
+    );
+    matrix.check()
+}
+
+/// Write the program and the agreed-upon vectors to `out_dir` in the conformance export format
+pub fn export_conformance(program: &Path, out_dir: &Path) -> Result<()> {
+    let elf = read_program(program)?;
+    let matrix = run_matrix(&elf)?;
+    // Only vectors the toolchain gets right are worth proposing upstream
+    matrix.check()?;
+
+    fs::create_dir_all(out_dir)?;
+    let file_name = "program.so";
+    let program_path = out_dir.join(file_name);
+    fs::write(&program_path, &elf).with_context(|| format!("failed to write {}", program_path.display()))?;
+
+    let vectors = matrix
+        .cases
+        .iter()
+        .map(|case| ConformanceVector {
+            name: format!(
+                "{}({})",
+                case.op.name,
+                case.operands.iter().map(|x| format!("{x:#x}")).collect::<Vec<_>>().join(", ")
+            ),
+            instruction_data: vm::encode_hex(&case.data),
+            input: vm::encode_hex(&vm::serialize_input(&case.data)),
+            expected: ConformanceExpected {
+                return_code: case.expected,
+                return_data: vm::encode_hex(&case.outcome.return_data),
+                logs: case.outcome.logs.clone(),
+                compute_units: case.outcome.instruction_count,
+            },
+        })
+        .collect();
+    let suite = ConformanceSuite {
+        program: ConformanceProgram {
+            file: file_name.to_string(),
+            sha256: format!("{:x}", Sha256::digest(&elf)),
+        },
+        program_id: vm::encode_hex(&vm::PROGRAM_ID),
+        vectors,
+    };
+
+    let suite_path = out_dir.join("vectors.json");
+    fs::write(&suite_path, serde_json::to_string_pretty(&suite)?)
+        .with_context(|| format!("failed to write {}", suite_path.display()))?;
+    println!(
+        "Exported {} conformance vectors to {} ({} undefined inputs skipped)",
+        suite.vectors.len(),
+        out_dir.display(),
+        matrix.skipped.len()
+    );
+    Ok(())
+}
@@ -0,0 +1,199 @@
+//! The no_std footprint audit. The fixture's panic handler never looks at its `PanicInfo`, so in a
+//! release build every panic path folds away along with core's formatting machinery. A `format!`,
+//! an `unwrap` on a `Result` whose error is printed, or a float conversion brings them back,
+//! costing kilobytes of .text. Release builds fail when the symbol table shows any of it, unless
+//! the package opts in with `[package.metadata.footprint] allow = [...]`.
+
+use crate::elf;
+use anyhow::{bail, Context, Result};
+use serde::Deserialize;
+use std::cmp::Reverse;
+use std::collections::BTreeMap;
+use std::fs;
+use std::path::Path;
+
+/// Heavyweight core machinery, by the name a package opts in with, and the demangled paths of the
+/// symbols that give it away
+const HEAVYWEIGHT: &[(&str, &[&str])] = &[
+    ("fmt", &["core::fmt::"]),
+    (
+        "panic",
+        &[
+            "core::panicking::",
+            "core::result::unwrap_failed",
+            "core::option::expect_failed",
+            "core::slice::index::slice_",
+            "core::str::slice_error_fail",
+        ],
+    ),
+    ("unicode", &["core::unicode::"]),
+    ("float", &["core::num::flt2dec::", "core::num::dec2flt::"]),
+];
+
+/// Symbols listed per category when the audit fails
+const LISTED_SYMBOLS: usize = 5;
+
+/// The category of the heavyweight machinery `symbol` (demangled) belongs to, if any
+fn category_of(symbol: &str) -> Option<&'static str> {
+    HEAVYWEIGHT
+        .iter()
+        .find(|(_, paths)| paths.iter().any(|path| symbol.contains(path)))
+        .map(|&(category, _)| category)
+}
+
+/// The crate a demangled symbol's code comes from: the first path segment, or for a trait impl
+/// like `<u128 as core::fmt::Display>::fmt`, the trait's. Symbols that aren't Rust paths, like
+/// `entrypoint` or `__multi3`, have none.
+fn crate_of(symbol: &str) -> Option<&str> {
+    let path = match symbol.strip_prefix('<') {
+        Some(qualified) => qualified.split_once(" as ").map_or(qualified, |(_, tr)| tr),
+        None => symbol,
+    };
+    path.split_once("::").map(|(krate, _)| krate)
+}
+
+/// `[package.metadata.footprint]` of a package manifest
+#[derive(Deserialize, Default)]
+struct FootprintMetadata {
+    /// Categories of `HEAVYWEIGHT` the package may pull in
+    #[serde(default)]
+    allow: Vec<String>,
+}
+
+/// The categories the package at `manifest` opts in to
+fn allowed(manifest: &Path) -> Result<Vec<String>> {
+    #[derive(Deserialize)]
+    struct Manifest {
+        package: Package,
+    }
+    #[derive(Deserialize)]
+    struct Package {
+        metadata: Option<Metadata>,
+    }
+    #[derive(Deserialize)]
+    struct Metadata {
+        #[serde(default)]
+        footprint: FootprintMetadata,
+    }
+
+    let contents =
+        fs::read_to_string(manifest).with_context(|| format!("failed to read {}", manifest.display()))?;
+    let parsed: Manifest =
+        toml::from_str(&contents).with_context(|| format!("failed to parse {}", manifest.display()))?;
+    let allow = parsed.package.metadata.map(|metadata| metadata.footprint.allow).unwrap_or_default();
+    for category in &allow {
+        if !HEAVYWEIGHT.iter().any(|&(name, _)| name == category) {
+            let names: Vec<_> = HEAVYWEIGHT.iter().map(|&(name, _)| name).collect();
+            bail!(
+                "{} allows unknown footprint category `{category}` (known: {})",
+                manifest.display(),
+                names.join(", ")
+            );
+        }
+    }
+    Ok(allow)
+}
+
+/// Where a program's .text goes
+struct Footprint {
+    text: u64,
+    /// Bytes of function symbols per crate, `None` for symbols that aren't Rust paths
+    crates: BTreeMap<Option<String>, u64>,
+    /// Function symbols of each heavyweight category, demangled, with their sizes
+    heavyweight: BTreeMap<&'static str, Vec<(String, u64)>>,
+}
+
+fn measure(program: &Path) -> Result<Footprint> {
+    let data = fs::read(program).with_context(|| format!("failed to read {}", program.display()))?;
+    let info = elf::inspect(&data)?;
+    let mut footprint = Footprint {
+        text: info.section(".text").map_or(0, |section| section.size),
+        crates: BTreeMap::new(),
+        heavyweight: BTreeMap::new(),
+    };
+    for symbol in info.symbols.iter().filter(|symbol| symbol.defined && symbol.is_function) {
+        let name = format!("{:#}", rustc_demangle::demangle(&symbol.name));
+        *footprint.crates.entry(crate_of(&name).map(str::to_string)).or_default() += symbol.size;
+        if let Some(category) = category_of(&name) {
+            footprint.heavyweight.entry(category).or_default().push((name, symbol.size));
+        }
+    }
+    for symbols in footprint.heavyweight.values_mut() {
+        symbols.sort_by_key(|&(_, size)| Reverse(size));
+    }
+    Ok(footprint)
+}
+
+/// Fail if `program` pulls in heavyweight core machinery its package at `manifest` hasn't opted
+/// in to, listing the symbols and what they cost
+pub fn check(program: &Path, manifest: &Path) -> Result<()> {
+    let allow = allowed(manifest)?;
+    let footprint = measure(program)?;
+    let mut report = Vec::new();
+    for (category, symbols) in &footprint.heavyweight {
+        if allow.iter().any(|allowed| allowed == category) {
+            continue;
+        }
+        let bytes: u64 = symbols.iter().map(|&(_, size)| size).sum();
+        report.push(format!("  {category}: {} symbols, {bytes} bytes", symbols.len()));
+        for (name, size) in symbols.iter().take(LISTED_SYMBOLS) {
+            report.push(format!("    {size:>6}  {name}"));
+        }
+    }
+    if !report.is_empty() {
+        bail!(
+            "{} pulls in heavyweight core machinery:\n{}\nFind the `format!`, `unwrap`, or conversion that \
+             brings it in, or opt in with `[package.metadata.footprint] allow = [...]` in {}.",
+            program.display(),
+            report.join("\n"),
+            manifest.display()
+        );
+    }
+    Ok(())
+}
+
+/// `cargo xtask footprint`: how much of `program`'s .text each crate takes, the heavyweight core
+/// machinery it pulls in, and whether its package at `manifest` allows that
+pub fn footprint(program: &Path, manifest: &Path) -> Result<()> {
+    let footprint = measure(program)?;
+    println!("{}: .text {} bytes", program.display(), footprint.text);
+    println!("  {:<32} {:>8} {:>7}", "crate", "bytes", "share");
+    let mut crates: Vec<_> = footprint.crates.iter().collect();
+    crates.sort_by_key(|&(_, &bytes)| Reverse(bytes));
+    for (krate, &bytes) in crates {
+        println!(
+            "  {:<32} {bytes:>8} {:>6.1}%",
+            krate.as_deref().unwrap_or("(not a Rust path)"),
+            100.0 * bytes as f64 / footprint.text.max(1) as f64
+        );
+    }
+    println!();
+    for &(category, _) in HEAVYWEIGHT {
+        let symbols = footprint.heavyweight.get(category).map_or(&[][..], Vec::as_slice);
+        let bytes: u64 = symbols.iter().map(|&(_, size)| size).sum();
+        println!("  {category:<10} {:>4} symbols {bytes:>8} bytes", symbols.len());
+    }
+    println!();
+    check(program, manifest)?;
+    println!("No heavyweight core machinery beyond what {} allows", manifest.display());
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn flags_formatting_and_panic_paths() {
+        assert_eq!(category_of("core::fmt::write"), Some("fmt"));
+        assert_eq!(category_of("<u128 as core::fmt::Display>::fmt"), Some("fmt"));
+        assert_eq!(category_of("core::result::unwrap_failed"), Some("panic"));
+        assert_eq!(category_of("core::slice::index::slice_end_index_len_fail"), Some("panic"));
+        assert_eq!(category_of("u128_bpf_fixture::muldiv::mul_div_floor"), None);
+        assert_eq!(category_of("__udivti3"), None);
+
+        assert_eq!(crate_of("<u128 as core::fmt::Display>::fmt"), Some("core"));
+        assert_eq!(crate_of("u128_bpf_fixture::fold::fold"), Some("u128_bpf_fixture"));
+        assert_eq!(crate_of("entrypoint"), None);
+    }
+}
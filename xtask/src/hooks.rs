@@ -0,0 +1,60 @@
+use anyhow::{bail, Context, Result};
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::process::Command;
+
+/// Marker identifying hooks written by this command, so reinstalling can overwrite them
+const HOOK_MARKER: &str = "# Installed by `cargo xtask install-hooks`";
+
+/// Commands the pre-push hook runs, in order: the CI pipeline itself, so the hook checks exactly
+/// what CI gates on
+const PRE_PUSH_STEPS: &[&str] = &["cargo xtask ci"];
+
+fn hooks_dir(project_root: &Path) -> Result<PathBuf> {
+    let output = Command::new("git")
+        .args(["rev-parse", "--git-path", "hooks"])
+        .current_dir(project_root)
+        .output()
+        .context("failed to run git rev-parse")?;
+    if !output.status.success() {
+        bail!("{} is not inside a git repository", project_root.display());
+    }
+    let path = PathBuf::from(String::from_utf8_lossy(&output.stdout).trim());
+    Ok(project_root.join(path))
+}
+
+/// Install a pre-push hook that runs the CI pipeline before anything is pushed
+pub fn install_hooks(project_root: &Path, force: bool) -> Result<()> {
+    let hooks_dir = hooks_dir(project_root)?;
+    fs::create_dir_all(&hooks_dir)?;
+    let hook_path = hooks_dir.join("pre-push");
+
+    if hook_path.exists() && !force {
+        let existing = fs::read_to_string(&hook_path).unwrap_or_default();
+        if !existing.contains(HOOK_MARKER) {
+            bail!(
+                "{} already exists and was not installed by xtask. Re-run with --force to replace it.",
+                hook_path.display()
+            );
+        }
+    }
+
+    let mut script = format!("#!/bin/sh\n{HOOK_MARKER}\nset -e\ncd \"$(git rev-parse --show-toplevel)\"\n");
+    for step in PRE_PUSH_STEPS {
+        script.push_str(&format!("echo \"pre-push: {step}\"\n{step}\n"));
+    }
+    fs::write(&hook_path, script)
+        .with_context(|| format!("failed to write {}", hook_path.display()))?;
+
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::PermissionsExt;
+        fs::set_permissions(&hook_path, fs::Permissions::from_mode(0o755))?;
+    }
+
+    println!("Installed pre-push hook at {}", hook_path.display());
+    for step in PRE_PUSH_STEPS {
+        println!("  {step}");
+    }
+    Ok(())
+}
@@ -0,0 +1,172 @@
+//! Scan of the program's bytecode for anything outside the instruction set of the SBPF version it
+//! targets. A backend that can't select an instruction falls back to another lowering, and some
+//! of those emit encodings SBPF doesn't define (eBPF's `jmp32` class, atomics) or reach for
+//! floating point. eBPF has no floating-point instructions, so floats show up as calls to
+//! compiler-rt's soft-float routines instead, and those are rejected too.
+
+use crate::{elf, fixtures};
+use anyhow::{bail, Context, Result};
+use object::{Object, ObjectSection};
+use solana_sbpf::ebpf::*;
+use solana_sbpf::program::SBPFVersion;
+use std::path::Path;
+
+/// Opcodes every SBPF version accepts
+const COMMON: &[u8] = &[
+    ADD32_IMM, ADD32_REG, SUB32_IMM, SUB32_REG, OR32_IMM, OR32_REG, AND32_IMM, AND32_REG, LSH32_IMM, LSH32_REG,
+    RSH32_IMM, RSH32_REG, XOR32_IMM, XOR32_REG, MOV32_IMM, MOV32_REG, ARSH32_IMM, ARSH32_REG, BE, ADD64_IMM,
+    ADD64_REG, SUB64_IMM, SUB64_REG, OR64_IMM, OR64_REG, AND64_IMM, AND64_REG, LSH64_IMM, LSH64_REG, RSH64_IMM,
+    RSH64_REG, XOR64_IMM, XOR64_REG, MOV64_IMM, MOV64_REG, ARSH64_IMM, ARSH64_REG, JA, JEQ_IMM, JEQ_REG, JGT_IMM,
+    JGT_REG, JGE_IMM, JGE_REG, JLT_IMM, JLT_REG, JLE_IMM, JLE_REG, JSET_IMM, JSET_REG, JNE_IMM, JNE_REG,
+    JSGT_IMM, JSGT_REG, JSGE_IMM, JSGE_REG, JSLT_IMM, JSLT_REG, JSLE_IMM, JSLE_REG, CALL_IMM, CALL_REG,
+];
+
+/// Loads and stores in the classes eBPF puts them in, before SBPF v2 moved them
+const LEGACY_MEMORY: &[u8] = &[
+    LD_B_REG, LD_H_REG, LD_W_REG, LD_DW_REG, ST_B_IMM, ST_H_IMM, ST_W_IMM, ST_DW_IMM, ST_B_REG, ST_H_REG, ST_W_REG,
+    ST_DW_REG,
+];
+
+/// Loads and stores from SBPF v2 on, reusing the freed multiply, divide, and modulo encodings
+const MOVED_MEMORY: &[u8] = &[
+    LD_1B_REG, LD_2B_REG, LD_4B_REG, LD_8B_REG, ST_1B_IMM, ST_2B_IMM, ST_4B_IMM, ST_8B_IMM, ST_1B_REG, ST_2B_REG,
+    ST_4B_REG, ST_8B_REG,
+];
+
+/// Multiply, divide, and modulo in the ALU classes, replaced by the PQR class from SBPF v2 on
+const LEGACY_MULTIPLY_DIVIDE: &[u8] = &[
+    MUL32_IMM, MUL32_REG, DIV32_IMM, DIV32_REG, MOD32_IMM, MOD32_REG, MUL64_IMM, MUL64_REG, DIV64_IMM, DIV64_REG,
+    MOD64_IMM, MOD64_REG,
+];
+
+/// The product, quotient, and remainder class of SBPF v2 on
+const PQR: &[u8] = &[
+    LMUL32_IMM, LMUL32_REG, LMUL64_IMM, LMUL64_REG, UHMUL64_IMM, UHMUL64_REG, SHMUL64_IMM, SHMUL64_REG,
+    UDIV32_IMM, UDIV32_REG, UDIV64_IMM, UDIV64_REG, UREM32_IMM, UREM32_REG, UREM64_IMM, UREM64_REG, SDIV32_IMM,
+    SDIV32_REG, SDIV64_IMM, SDIV64_REG, SREM32_IMM, SREM32_REG, SREM64_IMM, SREM64_REG,
+];
+
+/// Prefixes of compiler-rt's soft-float routines, after the leading `__`
+const SOFT_FLOAT_OPERATIONS: &[&str] = &[
+    "add", "sub", "mul", "div", "neg", "pow", "cmp", "eq", "ne", "ge", "gt", "le", "lt", "unord", "float",
+    "fix", "extend", "trunc",
+];
+
+/// Floating-point machine modes in compiler-rt names: half, single, double, x87, and quad
+const FLOAT_MODES: &[&str] = &["hf", "sf", "df", "xf", "tf"];
+
+/// Whether `opcode` is an instruction `version` defines
+fn allowed(opcode: u8, version: SBPFVersion) -> bool {
+    // Several encodings mean different instructions in different versions, hence sets rather
+    // than one match
+    let versioned: [(&[u8], bool); 10] = [
+        (LEGACY_MEMORY, !version.move_memory_instruction_classes()),
+        (MOVED_MEMORY, version.move_memory_instruction_classes()),
+        (LEGACY_MULTIPLY_DIVIDE, !version.enable_pqr()),
+        (PQR, version.enable_pqr()),
+        (&[LD_DW_IMM], !version.disable_lddw()),
+        (&[HOR64_IMM], version.disable_lddw()),
+        (&[NEG32, NEG64], !version.disable_neg()),
+        (&[LE], !version.disable_le()),
+        (&[EXIT], !version.static_syscalls()),
+        (&[RETURN, SYSCALL], version.static_syscalls()),
+    ];
+    COMMON.contains(&opcode) || versioned.iter().any(|(opcodes, enabled)| *enabled && opcodes.contains(&opcode))
+}
+
+/// Byte offsets and opcodes of the instructions in `code` that `version` doesn't define
+fn unsupported(code: &[u8], version: SBPFVersion) -> Vec<(usize, u8)> {
+    let mut found = Vec::new();
+    let mut offset = 0;
+    while offset < code.len() {
+        let opcode = code[offset];
+        if !allowed(opcode, version) {
+            found.push((offset, opcode));
+        }
+        // The second slot of an `lddw` carries the high immediate, not an instruction
+        offset += if opcode == LD_DW_IMM && !version.disable_lddw() { 2 * INSN_SIZE } else { INSN_SIZE };
+    }
+    found
+}
+
+/// Whether `name` is one of compiler-rt's soft-float routines, like `__adddf3` or `__floatuntidf`
+fn is_soft_float(name: &str) -> bool {
+    let Some(rest) = name.strip_prefix("__") else {
+        return false;
+    };
+    SOFT_FLOAT_OPERATIONS.iter().any(|operation| {
+        rest.strip_prefix(operation)
+            .is_some_and(|modes| FLOAT_MODES.iter().any(|mode| modes.contains(mode)))
+    })
+}
+
+/// Check every instruction in .text against the SBPF version the ELF header declares, and check
+/// that no soft-float routine is defined or referenced
+pub fn isa_check(program: &Path) -> Result<()> {
+    let elf = fixtures::read_program(program)?;
+    let info = elf::inspect(&elf)?;
+    let version = info
+        .sbpf_version()
+        .with_context(|| format!("unknown SBPF e_flags {:#x}", info.e_flags))?;
+    let file = object::File::parse(&*elf).context("failed to parse ELF")?;
+    let text = file.section_by_name(".text").context("the program has no .text section")?;
+    let code = text.data()?;
+
+    let mut problems = Vec::new();
+    for (offset, opcode) in unsupported(code, version.into()) {
+        let address = text.address() + offset as u64;
+        let site = info
+            .functions
+            .iter()
+            .find(|function| (function.address..function.address + function.size).contains(&address))
+            .map_or_else(|| format!("{address:#x}"), |function| {
+                format!("{}+{:#x}", function.name, address - function.address)
+            });
+        problems.push(format!("opcode {opcode:#04x} at {site} is not an SBPF {version:?} instruction"));
+    }
+    for symbol in info.symbols.iter().filter(|symbol| is_soft_float(&symbol.name)) {
+        let how = if symbol.defined { "linked in" } else { "called" };
+        problems.push(format!("soft-float routine {} is {how}", symbol.name));
+    }
+
+    println!(
+        "{} instructions in .text checked against SBPF {version:?}",
+        elf::count_instructions(code)
+    );
+    if !problems.is_empty() {
+        for problem in &problems {
+            println!("  {problem}");
+        }
+        bail!("{} instruction set problem(s)", problems.len());
+    }
+    println!("No unsupported or floating-point instructions");
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn flags_what_the_version_lacks() {
+        let mut code = Vec::new();
+        code.extend([0xb7, 0, 0, 0, 0, 0, 0, 0]); // mov64 r0, 0
+        code.extend([0x18, 0, 0, 0, 1, 0, 0, 0]); // lddw r0, 1 (first slot)
+        code.extend([0x00, 0, 0, 0, 0, 0, 0, 0]); // lddw (second slot)
+        code.extend([0x16, 0, 1, 0, 0, 0, 0, 0]); // jeq32 r0, 0, +1: eBPF's jmp32 class
+        code.extend([0x95, 0, 0, 0, 0, 0, 0, 0]); // exit
+        assert_eq!(unsupported(&code, SBPFVersion::V0), [(24, 0x16)]);
+        // No lddw from v2 on, so both of its slots are foreign
+        assert_eq!(unsupported(&code, SBPFVersion::V3), [(8, 0x18), (16, 0x00), (24, 0x16)]);
+    }
+
+    #[test]
+    fn recognizes_soft_float_routines() {
+        for name in ["__adddf3", "__mulsf3", "__floatuntidf", "__fixunsdfti", "__truncdfsf2", "__unordsf2"] {
+            assert!(is_soft_float(name), "{name}");
+        }
+        for name in ["__multi3", "__udivti3", "__muloti4", "__ashlti3", "__lshrti3", "entrypoint"] {
+            assert!(!is_soft_float(name), "{name}");
+        }
+    }
+}
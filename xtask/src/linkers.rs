@@ -0,0 +1,333 @@
+//! `cargo xtask build --linker`: the program linked by something other than sbpf-linker, from the
+//! same bitcode rustc hands sbpf-linker, to compare link strategies. Each linker builds into its
+//! own target directory, with rustflags replacing the ones in .cargo/config.toml:
+//!
+//! - `bpf-linker`: aya's bpf-linker, installed against the selected LLVM
+//! - `lld`: `ld.lld` from the selected LLVM, built from its checkout if missing. rustc only drives
+//!   linkers of the `bpf` flavor for this target, so it invokes xtask itself, which translates the
+//!   bpf-linker style arguments and runs ld.lld with LTO over the bitcode.
+//!
+//! Any of them, sbpf-linker included, can instead be built against the stock LLVM of an installed
+//! platform-tools release, to tell the effect of the LLVM patches from that of the linker.
+
+use crate::cargo_config::DEFAULT_STACK_SIZE;
+use crate::{LlvmDirs, Profile};
+use anyhow::{bail, Context, Result};
+use clap::ValueEnum;
+use std::ffi::OsString;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::process::Command;
+use walkdir::WalkDir;
+
+/// Set for the rustc invocations of an lld build to the ld.lld to run, which turns xtask into
+/// the linker shim
+pub const LLD_SHIM_ENV: &str = "U128_XTASK_LLD";
+
+#[derive(Clone, Copy, PartialEq, Eq, ValueEnum)]
+pub enum Linker {
+    /// The custom sbpf-linker from setup, as configured in .cargo/config.toml
+    Sbpf,
+    /// aya's bpf-linker, built against the selected LLVM
+    #[value(name = "bpf-linker")]
+    Bpf,
+    /// ld.lld from the selected LLVM, with LTO over the bitcode
+    Lld,
+}
+
+impl Linker {
+    fn name(self) -> &'static str {
+        match self {
+            Linker::Sbpf => "sbpf",
+            Linker::Bpf => "bpf-linker",
+            Linker::Lld => "lld",
+        }
+    }
+}
+
+/// The LLVM a linker is built against, or for lld, taken from
+pub struct Llvm {
+    /// Install prefix, with `bin/llvm-config`
+    install: PathBuf,
+    /// Names the linker builds against this LLVM in the cache, like `llvm-<commit>`
+    key: String,
+    /// Checkout and build tree of the patched LLVM, which lld can be built in
+    dirs: Option<LlvmDirs>,
+}
+
+impl Llvm {
+    /// The patched LLVM selected by the last `setup` or `build-llvm`
+    pub fn patched() -> Result<Self> {
+        let dirs = LlvmDirs::selected()?;
+        Ok(Self {
+            install: dirs.install.clone(),
+            key: format!("llvm-{}", crate::short_commit(&dirs.commit)),
+            dirs: Some(dirs),
+        })
+    }
+
+    /// The stock LLVM of the newest platform-tools release under `root`, by default where
+    /// `cargo build-sbf` installs them
+    pub fn platform_tools(root: Option<&Path>) -> Result<Self> {
+        let root = root.map_or_else(crate::builtins::default_platform_tools_root, Path::to_path_buf);
+        let install = WalkDir::new(&root)
+            .follow_links(true)
+            .max_depth(5)
+            .into_iter()
+            .filter_map(Result::ok)
+            .filter(|entry| entry.path().ends_with("llvm/bin/llvm-config"))
+            .max_by_key(|entry| entry.metadata().ok().and_then(|metadata| metadata.modified().ok()))
+            .map(|entry| entry.path().parent().unwrap().parent().unwrap().to_path_buf())
+            .with_context(|| {
+                format!(
+                    "no platform-tools LLVM with bin/llvm-config under {}. Install platform-tools with \
+                     `cargo build-sbf --force-tools-install` or pass --platform-tools.",
+                    root.display()
+                )
+            })?;
+        // Releases install to <root>/<version>/platform-tools/llvm
+        let release = install
+            .ancestors()
+            .skip(1)
+            .find(|dir| !dir.ends_with("platform-tools"))
+            .and_then(Path::file_name)
+            .map_or_else(|| "unknown".to_string(), |name| name.to_string_lossy().into_owned());
+        println!("Using the LLVM of platform-tools {release} at {}", install.display());
+        Ok(Self {
+            install,
+            key: format!("platform-tools-{release}"),
+            dirs: None,
+        })
+    }
+}
+
+/// Where the program linked with `linker` against `llvm` is built
+fn target_dir(project_root: &Path, linker: Linker, llvm: &Llvm) -> PathBuf {
+    let name = match llvm.dirs {
+        Some(_) => linker.name().to_string(),
+        None => format!("{}-{}", linker.name(), llvm.key),
+    };
+    project_root.join("target/linkers").join(name)
+}
+
+/// Major version of the LLVM at `install`, from its llvm-config
+fn llvm_major_version(install: &Path) -> Result<u32> {
+    let llvm_config = install.join("bin/llvm-config");
+    let output = Command::new(&llvm_config)
+        .arg("--version")
+        .output()
+        .with_context(|| format!("failed to run {}. Run `cargo xtask setup` first.", llvm_config.display()))?;
+    let version = String::from_utf8_lossy(&output.stdout);
+    version
+        .split('.')
+        .next()
+        .and_then(|major| major.trim().parse().ok())
+        .with_context(|| format!("unexpected llvm-config version: {}", version.trim()))
+}
+
+/// sbpf-linker from the selected checkout built against `llvm`, building it on first use
+fn sbpf_linker(llvm: &Llvm) -> Result<PathBuf> {
+    let linker_commit = crate::selected_commit("sbpf-linker", crate::LINKER_BRANCH)?;
+    let target_dir = crate::linker_src_dir(&linker_commit).join(format!("target-{}", llvm.key));
+    let bin = target_dir.join("release/sbpf-linker");
+    if !bin.exists() {
+        println!("Building sbpf-linker against {}...", llvm.install.display());
+        crate::compile_linker(&linker_commit, &llvm.install, &target_dir)?;
+    }
+    Ok(bin)
+}
+
+/// bpf-linker installed against `llvm`, installing it on first use
+fn bpf_linker(llvm: &Llvm) -> Result<PathBuf> {
+    let root = crate::cache_dir().join("bpf-linker").join(&llvm.key);
+    let bin = root.join("bin/bpf-linker");
+    if bin.exists() {
+        return Ok(bin);
+    }
+    let major = llvm_major_version(&llvm.install)?;
+    println!("Installing bpf-linker against LLVM {major} ({})...", llvm.install.display());
+    crate::run_command_prefixed(
+        Command::new("cargo")
+            .args(["install", "bpf-linker", "--locked", "--no-default-features"])
+            .arg("--features")
+            .arg(format!("llvm-{major}"))
+            .arg("--root")
+            .arg(&root)
+            // llvm-sys names its prefix variable after the version it binds, like 211 for 21.1
+            .env(format!("LLVM_SYS_{major}1_PREFIX"), &llvm.install),
+        "install bpf-linker",
+        "bpf-linker",
+    )?;
+    Ok(bin)
+}
+
+/// ld.lld of `llvm`, building lld in the patched LLVM's build tree on first use
+fn lld(llvm: &Llvm) -> Result<PathBuf> {
+    let bin = llvm.install.join("bin/ld.lld");
+    if bin.exists() {
+        return Ok(bin);
+    }
+    let Some(dirs) = &llvm.dirs else {
+        bail!("{} has no ld.lld", llvm.install.display());
+    };
+    if !dirs.build.join("CMakeCache.txt").exists() {
+        bail!(
+            "{} has no LLVM build tree to build lld in (a toolchain imported from the cache has \
+             none). Run `cargo xtask rebuild-llvm` first.",
+            dirs.root().display()
+        );
+    }
+    println!("Building lld in {}...", dirs.build.display());
+    crate::run_command_prefixed(
+        Command::new("cmake")
+            .arg("-S")
+            .arg(dirs.src.join("llvm"))
+            .arg("-B")
+            .arg(&dirs.build)
+            .arg("-DLLVM_ENABLE_PROJECTS=lld"),
+        "configure lld",
+        "lld",
+    )?;
+    crate::run_command_prefixed(
+        Command::new("cmake").arg("--build").arg(&dirs.build).args(["--target", "lld"]),
+        "build lld",
+        "lld",
+    )?;
+    // lld picks its flavor from the name it is run as
+    fs::copy(dirs.build.join("bin/lld"), &bin)
+        .with_context(|| format!("failed to install {}", bin.display()))?;
+    Ok(bin)
+}
+
+/// Build the program with `linker` built against `llvm` into its own target directory, and
+/// return the artifact
+pub fn build(project_root: &Path, linker: Linker, llvm: &Llvm, profile: Profile) -> Result<PathBuf> {
+    let stack_size = format!("--llvm-args=-bpf-stack-size={DEFAULT_STACK_SIZE}");
+    let mut cmd = crate::cargo_build_bpf(project_root, crate::FIXTURE_PACKAGE, profile)?;
+    let linker_flag = match linker {
+        Linker::Sbpf => format!("linker={}", sbpf_linker(llvm)?.display()),
+        Linker::Bpf => format!("linker={}", bpf_linker(llvm)?.display()),
+        Linker::Lld => {
+            cmd.env(LLD_SHIM_ENV, lld(llvm)?);
+            format!("linker={}", std::env::current_exe()?.display())
+        }
+    };
+    let rustflags = [
+        "-C",
+        &linker_flag,
+        "-C",
+        "panic=abort",
+        "-C",
+        &format!("link-arg={stack_size}"),
+        "-C",
+        "relocation-model=static",
+    ];
+    // Record the toolchain actually linking this build, not the selected one
+    cmd.env("U128_LLVM_COMMIT", &llvm.key);
+    if linker != Linker::Sbpf {
+        cmd.env("U128_LINKER_COMMIT", linker.name());
+    }
+    let target_dir = target_dir(project_root, linker, llvm);
+    cmd.env("CARGO_ENCODED_RUSTFLAGS", rustflags.join("\x1f"))
+        .arg("--target-dir")
+        .arg(&target_dir);
+
+    crate::run_command_prefixed(&mut cmd, &format!("build with {}", linker.name()), "build")?;
+    Ok(target_dir
+        .join(crate::BPF_TARGET)
+        .join(profile.dir_name())
+        .join(crate::PROGRAM_SO_NAME))
+}
+
+/// Arguments of a bpf-linker style invocation, expanding `@file` response files
+fn linker_args(args: impl Iterator<Item = OsString>) -> Result<Vec<String>> {
+    let mut expanded = Vec::new();
+    for arg in args {
+        let arg = arg.into_string().map_err(|arg| anyhow::anyhow!("non-UTF-8 linker argument {arg:?}"))?;
+        match arg.strip_prefix('@') {
+            Some(file) => {
+                let contents =
+                    fs::read_to_string(file).with_context(|| format!("failed to read response file {file}"))?;
+                expanded.extend(contents.lines().map(str::to_string));
+            }
+            None => expanded.push(arg),
+        }
+    }
+    Ok(expanded)
+}
+
+/// The ld.lld arguments for a bpf-linker style invocation by rustc
+fn lld_args(args: &[String]) -> Result<Vec<String>> {
+    let mut lld = vec!["-shared".to_string(), "--entry=entrypoint".to_string(), "-z".into(), "notext".into()];
+    let mut args = args.iter();
+    while let Some(arg) = args.next() {
+        let mut value = || args.next().with_context(|| format!("{arg} takes a value"));
+        match arg.as_str() {
+            "-o" | "-L" => lld.extend([arg.clone(), value()?.clone()]),
+            "--export-symbols" => {
+                let file = value()?;
+                let symbols = fs::read_to_string(file).with_context(|| format!("failed to read {file}"))?;
+                lld.extend(symbols.lines().map(|symbol| format!("--export-dynamic-symbol={symbol}")));
+            }
+            "--cpu" => lld.push(format!("--plugin-opt=mcpu={}", value()?)),
+            "--cpu-features" => {
+                let features = value()?;
+                if !features.is_empty() {
+                    lld.extend(["-mllvm".to_string(), format!("-mattr={features}")]);
+                }
+            }
+            "-O0" | "-O1" | "-O2" | "-O3" => lld.push(format!("--lto-{}", &arg[1..])),
+            "-Os" | "-Oz" => lld.push("--lto-O2".to_string()),
+            "--debug" => {}
+            _ => match arg.strip_prefix("--llvm-args=") {
+                Some(option) => lld.extend(["-mllvm".to_string(), option.to_string()]),
+                None if arg.starts_with('-') => eprintln!("lld shim: ignoring {arg}"),
+                None => lld.push(arg.clone()),
+            },
+        }
+    }
+    Ok(lld)
+}
+
+/// Entry point of xtask when rustc runs it as the linker of an lld build
+pub fn lld_shim(lld: &Path) -> Result<()> {
+    let args = lld_args(&linker_args(std::env::args_os().skip(1))?)?;
+    let status = Command::new(lld)
+        .args(&args)
+        .status()
+        .with_context(|| format!("failed to run {}", lld.display()))?;
+    if !status.success() {
+        bail!("{} failed: {status}", lld.display());
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn translates_bpf_linker_arguments() {
+        let args = ["--cpu", "v3", "--cpu-features", "", "-O3", "--debug", "a.o", "-o", "out.so"]
+            .into_iter()
+            .chain(["--llvm-args=-bpf-stack-size=4096", "libcore.rlib"])
+            .map(str::to_string)
+            .collect::<Vec<_>>();
+        let expected = [
+            "-shared",
+            "--entry=entrypoint",
+            "-z",
+            "notext",
+            "--plugin-opt=mcpu=v3",
+            "--lto-O3",
+            "a.o",
+            "-o",
+            "out.so",
+            "-mllvm",
+            "-bpf-stack-size=4096",
+            "libcore.rlib",
+        ];
+        assert_eq!(lld_args(&args).unwrap(), expected);
+        assert!(lld_args(&["-o".to_string()]).is_err());
+    }
+}
@@ -1,16 +1,74 @@
-use anyhow::{bail, Context, Result};
-use clap::{Parser, Subcommand};
+use anyhow::{anyhow, bail, Context, Result};
+use clap::{Args, Parser, Subcommand, ValueEnum};
+use serde::Deserialize;
+use sha2::{Digest, Sha256};
 use std::ffi::OsString;
 use std::fs;
 use std::path::{Path, PathBuf};
-use std::process::Command;
+use std::io::{BufRead, BufReader, Read};
+use std::process::{Command, ExitStatus, Stdio};
+use std::sync::mpsc::{self, RecvTimeoutError};
+use std::thread;
+use std::time::{Duration, Instant};
 use walkdir::WalkDir;
 
+mod addr2line;
+mod agave;
+mod bench;
+mod builtins;
+mod bytediff;
+mod cache;
+mod cargo_config;
+mod ci;
+mod codegen;
+mod compare;
+mod coverage;
+mod dump;
+mod elf;
+mod errors;
+mod external;
+mod fixtures;
+mod footprint;
+mod hooks;
+mod isa;
+mod linkers;
+mod mutation;
+mod ops;
+mod package;
+mod panic_log;
+mod profile;
+mod purity;
+mod results;
+mod rodata;
+mod scaffold;
+mod smoke;
+mod snapshot;
+#[cfg(test)]
+mod soak;
+mod strip;
+mod summary;
+mod toolchain;
+mod verify;
+mod vm;
+
 const LLVM_REPO: &str = "https://github.com/blueshift-gg/llvm-project.git";
 const LLVM_BRANCH: &str = "BPF_i128_ret";
 const LINKER_REPO: &str = "https://github.com/blueshift-gg/sbpf-linker";
 const LINKER_BRANCH: &str = "u128_mul_libcall";
 const GIT_DEPTH: &str = "1";
+const BPF_TARGET: &str = "bpfel-unknown-none";
+/// Package holding the on-chain program, and its directory in the workspace
+const FIXTURE_PACKAGE: &str = "u128-bpf-fixture";
+const FIXTURE_DIR: &str = "fixture";
+const PROGRAM_SO_NAME: &str = "libu128_bpf_fixture.so";
+const FIXTURES_JSON: &str = "target/fixtures/vectors.json";
+const MOLLUSK_FIXTURES_DIR: &str = "target/mollusk-fixtures";
+const COMPARE_REPORT: &str = "target/compare/report.md";
+const TEST_RESULTS_DIR: &str = "target/test-results";
+const COST_MODEL_DIR: &str = "target/bench";
+const CU_BASELINE: &str = "snapshots/compute-units.json";
+/// Instruction data used when a command is not given any: `mul_loop` on the operands 10 and 20
+const DEFAULT_DATA: &str = "00000000000000000a00000000000000000000000000000014000000000000000000000000000000";
 
 /// xtask for setting up custom Rust compiler with i128 BPF support
 #[derive(Parser)]
@@ -21,47 +79,790 @@ struct Cli {
     command: Commands,
 }
 
+/// Cargo profile the BPF program is built with
+#[derive(Clone, Copy, ValueEnum)]
+enum Profile {
+    Release,
+    /// Unoptimized build, for debugging miscompiles
+    Debug,
+    /// Release codegen with DWARF kept in the ELF, for `cargo xtask addr2line`
+    ReleaseDwarf,
+}
+
+impl Profile {
+    /// Name cargo knows the profile by
+    fn cargo_name(self) -> &'static str {
+        match self {
+            Profile::Release => "release",
+            Profile::Debug => "dev",
+            Profile::ReleaseDwarf => "release-dwarf",
+        }
+    }
+
+    /// Directory under `target/bpfel-unknown-none` the profile's artifacts land in
+    fn dir_name(self) -> &'static str {
+        match self {
+            Profile::Release => "release",
+            Profile::Debug => "debug",
+            Profile::ReleaseDwarf => "release-dwarf",
+        }
+    }
+
+    /// Path of the program built with this profile
+    fn artifact(self, project_root: &Path) -> PathBuf {
+        project_root
+            .join("target")
+            .join(BPF_TARGET)
+            .join(self.dir_name())
+            .join(PROGRAM_SO_NAME)
+    }
+
+    /// Path of another package's program built with this profile
+    fn package_artifact(self, project_root: &Path, package: &str) -> PathBuf {
+        self.artifact(project_root)
+            .with_file_name(format!("lib{}.so", package.replace('-', "_")))
+    }
+}
+
+/// Options shared by the commands that configure LLVM
+#[derive(Args)]
+struct LlvmArgs {
+    /// Extra argument appended to the LLVM cmake configure command (repeatable),
+    /// e.g. `--llvm-cmake-arg=-DLLVM_USE_LINKER=lld`
+    #[arg(long = "llvm-cmake-arg", value_name = "ARG", allow_hyphen_values = true)]
+    cmake_args: Vec<String>,
+}
+
 #[derive(Subcommand)]
 enum Commands {
     /// Set up the complete toolchain (LLVM + sbpf linker)
-    Setup,
+    Setup {
+        /// Report a missing nightly or rust-src instead of installing it with rustup
+        #[arg(long)]
+        no_modify_toolchains: bool,
+        #[command(flatten)]
+        llvm: LlvmArgs,
+    },
     /// Clone and build the SBPF linker only
     BuildLinker,
     /// Clone and build LLVM with modified BPF backend
-    BuildLlvm,
+    BuildLlvm {
+        #[command(flatten)]
+        llvm: LlvmArgs,
+        /// Rebuild and reinstall only the BPF backend and what links it, without reconfiguring,
+        /// if the selected llvm-project checkout changed since the last build
+        #[arg(long, conflicts_with = "cmake_args")]
+        incremental: bool,
+    },
     /// Incremental rebuild + reinstall LLVM (clears install dir, keeps build cache)
-    RebuildLlvm,
-    /// Build the example project with the custom toolchain
-    Build,
+    RebuildLlvm {
+        #[command(flatten)]
+        llvm: LlvmArgs,
+    },
+    /// Compile and run a small u128 probe to check the installed toolchain works
+    SmokeTest,
+    /// Run the LLVM BPF backend lit tests against the built LLVM
+    LlvmTest {
+        /// Only run lit tests whose path matches this regex (e.g. `i128`)
+        #[arg(long)]
+        filter: Option<String>,
+    },
+    /// Build the example project, and any fixtures registered with `new-fixture`, with the custom
+    /// toolchain
+    Build {
+        #[arg(long, value_enum, default_value_t = Profile::Release)]
+        profile: Profile,
+        /// Linker to link the program with. Other than sbpf, the program alone is built into
+        /// target/linkers/<linker>/, to compare against the sbpf-linker build.
+        #[arg(long, value_enum, default_value_t = linkers::Linker::Sbpf)]
+        linker: linkers::Linker,
+        /// Build the linker against the stock LLVM of an installed platform-tools release instead
+        /// of the patched one, into target/linkers/<linker>-platform-tools-<release>/
+        #[arg(long)]
+        stock_llvm: bool,
+        /// Directory to search for platform-tools (defaults to the `cargo build-sbf` install location)
+        #[arg(long, value_name = "DIR", requires = "stock_llvm")]
+        platform_tools: Option<PathBuf>,
+        /// Strip debug info, .comment, and unneeded symbols from the built programs, as shipped
+        /// to production, keeping an unstripped copy next to each as `<name>.unstripped.so`
+        #[arg(long)]
+        strip: bool,
+        /// Also append a markdown summary of the program's size against its budget to this file
+        #[arg(long, value_name = "PATH")]
+        summary_md: Option<PathBuf>,
+    },
+    /// Execute the built program once in the solana-sbpf VM
+    Run {
+        /// Instruction data as hex
+        #[arg(long)]
+        data: String,
+        /// Program to load (defaults to the release build of this project)
+        #[arg(long)]
+        program: Option<PathBuf>,
+        /// Write the per-instruction register trace to this file
+        #[arg(long, value_name = "FILE")]
+        trace: Option<PathBuf>,
+    },
+    /// Print the sections, functions, and dynamic relocations of the built program
+    Inspect {
+        /// Program to inspect (defaults to the release build of this project)
+        #[arg(long)]
+        program: Option<PathBuf>,
+    },
+    /// List relocations against i128 libcalls and whether the linker resolved them
+    Libcalls {
+        /// Program to check (defaults to the release build of this project)
+        #[arg(long)]
+        program: Option<PathBuf>,
+    },
+    /// List the compiler-rt i128 builtins each op needs that stock platform-tools don't provide
+    LibcallGaps {
+        /// Program to check (defaults to the release build of this project)
+        #[arg(long)]
+        program: Option<PathBuf>,
+        /// Directory to search for platform-tools (defaults to the `cargo build-sbf` install location)
+        #[arg(long, value_name = "DIR")]
+        platform_tools: Option<PathBuf>,
+    },
+    /// Emit the control-flow graph of one function as Graphviz DOT
+    Cfg {
+        /// Function to graph
+        #[arg(long, default_value = "entrypoint")]
+        symbol: String,
+        /// Program to load (defaults to the release build of this project)
+        #[arg(long)]
+        program: Option<PathBuf>,
+        /// Write the graph to this file instead of stdout
+        #[arg(long, value_name = "FILE")]
+        out: Option<PathBuf>,
+    },
+    /// Diff two builds instruction by instruction, matching functions by symbol
+    Bytediff {
+        /// Baseline program
+        old: PathBuf,
+        /// Program to compare against the baseline
+        new: PathBuf,
+    },
+    /// Load the program with the ELF loader and verifier Agave uses on deployment
+    AgaveCheck {
+        /// Program to check (defaults to the release build of this project)
+        #[arg(long)]
+        program: Option<PathBuf>,
+        /// Newest SBPF version the feature set being checked against allows
+        #[arg(long, value_enum, default_value_t = elf::SbpfVersion::V3)]
+        max_sbpf_version: elf::SbpfVersion,
+    },
+    /// Print the dynamic relocations and flag types the program's SBPF loader rejects
+    Relocs {
+        /// Program to report on (defaults to the release build of this project)
+        #[arg(long)]
+        program: Option<PathBuf>,
+        /// Check against this SBPF version instead of the one in the ELF header
+        #[arg(long, value_enum)]
+        sbpf_version: Option<elf::SbpfVersion>,
+    },
+    /// Check the layout of u128 constants the program loads from .rodata
+    RodataCheck {
+        /// Program to check (defaults to the release build of this project)
+        #[arg(long)]
+        program: Option<PathBuf>,
+        /// A u128 constant the program uses (decimal or 0x hex, repeatable); checked for byte order
+        #[arg(long = "expect", value_name = "U128", value_parser = rodata::parse_u128)]
+        expected: Vec<u128>,
+    },
+    /// Check the bytecode holds only instructions its SBPF version defines and no floating point
+    IsaCheck {
+        /// Program to check (defaults to the release build of this project)
+        #[arg(long)]
+        program: Option<PathBuf>,
+    },
+    /// Check the plain arithmetic ops make no syscall but logging and never touch the heap
+    PureCheck {
+        /// Program to check (defaults to the release build of this project)
+        #[arg(long)]
+        program: Option<PathBuf>,
+    },
+    /// Check the fixture's BPF assembly against the FileCheck patterns in codegen/
+    CodegenCheck,
+    /// Compare the disassembly of key functions against the snapshots in snapshots/
+    Snapshot {
+        /// Accept the current disassembly as the new snapshot
+        #[arg(long)]
+        bless: bool,
+        /// Snapshot the LLVM IR the linker dumps instead of the disassembly
+        #[arg(long)]
+        ir: bool,
+    },
+    /// Build with the patched and the stock toolchain and compare the results
+    Compare {
+        /// Instruction data as hex used for the CU measurement
+        #[arg(long, default_value = DEFAULT_DATA)]
+        data: String,
+    },
+    /// Run every op on the patched and the stock build and write a markdown report of what the
+    /// stock toolchain gets wrong
+    CompareReport {
+        /// Output file (defaults to target/compare/report.md)
+        #[arg(long)]
+        out: Option<PathBuf>,
+    },
+    /// Compare the CU cost of the fixture's u128 lowerings in the VM
+    Bench {
+        /// Program to load (defaults to the release build of this project). With --model, repeat
+        /// it to measure one build per SBPF version.
+        #[arg(long)]
+        program: Vec<PathBuf>,
+        /// Measure every operation under every lowering and write the CU cost model
+        #[arg(long, conflicts_with = "instructions")]
+        model: bool,
+        /// Report executed-instruction, call, and syscall counts per op instead of CU
+        #[arg(long)]
+        instructions: bool,
+        /// Sweep the batch size of the batched operations and fit the marginal CU per operation
+        #[arg(long, conflicts_with_all = ["model", "instructions"])]
+        batch: bool,
+        /// Run every op on each boundary input repeatedly and report the spread of its CU,
+        /// flagging ops whose cost depends on the operands
+        #[arg(long, conflicts_with_all = ["model", "instructions", "batch"])]
+        variance: bool,
+        /// Runs of each input with --variance
+        #[arg(long, value_name = "N", default_value_t = 16, requires = "variance")]
+        runs: usize,
+        /// Check each op's worst-case CU against snapshots/compute-units.json and fail on a
+        /// regression
+        #[arg(long, conflicts_with_all = ["model", "instructions", "batch", "variance"])]
+        baseline: bool,
+        /// Compare the program's two A/B entries, `entry_libcall` and `entry_soft`, on every
+        /// operation they share
+        #[arg(long, conflicts_with_all = ["model", "instructions", "batch", "variance", "baseline"])]
+        entries: bool,
+        /// Percentage over the baseline an op may cost before --baseline fails
+        #[arg(long, value_name = "PERCENT", default_value_t = 0.0, requires = "baseline")]
+        tolerance: f64,
+        /// Rewrite the baseline with the current CU instead of checking against it
+        #[arg(long, requires = "baseline")]
+        bless: bool,
+        /// Also append a markdown summary of the CU changes against the baseline to this file
+        #[arg(long, value_name = "PATH", requires = "baseline", conflicts_with = "bless")]
+        summary_md: Option<PathBuf>,
+        /// Output directory for the cost model (defaults to target/bench)
+        #[arg(long, value_name = "DIR", requires = "model")]
+        out: Option<PathBuf>,
+    },
+    /// Attribute each op's executed instructions to functions and rank the libcalls by cost
+    Profile {
+        /// Program to load (defaults to the release build of this project)
+        #[arg(long)]
+        program: Option<PathBuf>,
+        /// Profile only the op with this name
+        #[arg(long)]
+        op: Option<String>,
+        /// Also rank the functions inlined into each function, from the DWARF of a release-dwarf
+        /// build (the default program then)
+        #[arg(long)]
+        inlined: bool,
+        /// Write the call stacks in the folded format of flamegraph tools to this file
+        #[arg(long, value_name = "FILE")]
+        folded: Option<PathBuf>,
+    },
+    /// Run the op x boundary-value matrix natively and in the VM and emit JSON test vectors
+    GenFixtures {
+        /// Program to load (defaults to the release build of this project)
+        #[arg(long)]
+        program: Option<PathBuf>,
+        /// Output file (defaults to target/fixtures/vectors.json)
+        #[arg(long)]
+        out: Option<PathBuf>,
+        /// Instead, export the program and vectors for the Agave/solana-sbpf conformance suites
+        /// into this directory
+        #[arg(long, value_name = "DIR", conflicts_with = "out")]
+        conformance: Option<PathBuf>,
+    },
+    /// Run the op x boundary-value matrix under another SVM implementation and check its results
+    /// against the native reference
+    ExternalRun {
+        /// Command that runs a fixture file and prints the results as JSON (see the README)
+        #[arg(long)]
+        runner: String,
+        /// Program to hand to the runner (defaults to the release build of this project)
+        #[arg(long)]
+        program: Option<PathBuf>,
+    },
+    /// Flip each operand bit of every passing matrix input in turn and check the VM still agrees
+    /// with the native reference
+    BitFlip {
+        /// Program to load (defaults to the release build of this project)
+        #[arg(long)]
+        program: Option<PathBuf>,
+        /// Mutate only the vectors of the op with this name
+        #[arg(long)]
+        op: Option<String>,
+    },
+    /// Run the op x boundary-value matrix in the VM and write JUnit XML and JSON results
+    TestReport {
+        /// Program to load (defaults to the release build of this project)
+        #[arg(long)]
+        program: Option<PathBuf>,
+        /// Output directory (defaults to target/test-results)
+        #[arg(long, value_name = "DIR")]
+        out: Option<PathBuf>,
+        /// Also append a markdown summary of the pass counts to this file
+        #[arg(long, value_name = "PATH")]
+        summary_md: Option<PathBuf>,
+    },
+    /// Measure the line coverage of the fixture's arithmetic modules with their host tests, using
+    /// cargo-llvm-cov, and write an lcov report to target/coverage
+    Coverage {
+        /// Also write an HTML report
+        #[arg(long)]
+        html: bool,
+        /// Fail if the total line coverage is below this percentage
+        #[arg(long, value_name = "PERCENT")]
+        fail_under: Option<f64>,
+    },
+    /// Run the Mollusk tests and dump every case as a portable `.fix` protobuf fixture
+    MolluskFixtures {
+        /// Output directory (defaults to target/mollusk-fixtures)
+        #[arg(long)]
+        out: Option<PathBuf>,
+        /// Also write each fixture as JSON
+        #[arg(long)]
+        json: bool,
+        /// Also write solana-conformance instruction fixtures, which Firedancer's VM can execute,
+        /// to a `firedancer` subdirectory
+        #[arg(long)]
+        firedancer: bool,
+    },
+    /// Build the program and package it with a provenance record
+    Package,
+    /// Rebuild the program in the pinned container and check it matches the packaged artifact
+    VerifyBuild,
+    /// Generate a new fixture crate under fixtures/ and register it with the workspace and
+    /// `cargo xtask build`
+    NewFixture {
+        /// Name of the fixture, in lowercase with dashes; the package is `<name>-fixture`
+        name: String,
+    },
+    /// Render .cargo/config.toml from xtask/templates/cargo-config.toml
+    GenConfig {
+        /// Linker to point the config at (defaults to the sbpf-linker setup built)
+        #[arg(long, value_name = "PATH")]
+        linker: Option<PathBuf>,
+        /// BPF stack size in bytes
+        #[arg(long, default_value_t = cargo_config::DEFAULT_STACK_SIZE)]
+        stack_size: u32,
+        /// Extra `-C` codegen option for the BPF target, like `target-cpu=v3` (repeatable)
+        #[arg(long = "sbpf-flag", value_name = "FLAG")]
+        sbpf_flags: Vec<String>,
+    },
+    /// Map program counters the VM reported back to Rust source lines, with every inlined frame,
+    /// using the DWARF of a `--profile release-dwarf` build
+    Addr2line {
+        /// Instruction index into .text, in decimal or 0x hex (repeatable)
+        #[arg(required = true, value_parser = addr2line::parse_pc)]
+        pcs: Vec<u64>,
+        /// Treat the values as ELF virtual addresses instead of instruction indices
+        #[arg(long)]
+        address: bool,
+        /// Program to symbolize (defaults to the release-dwarf build)
+        #[arg(long)]
+        program: Option<PathBuf>,
+    },
+    /// Print the disassembly of the built program function by function, optionally interleaved
+    /// with the Rust source line behind each run of instructions
+    Dump {
+        /// Only this function, by mangled or demangled name
+        #[arg(long)]
+        function: Option<String>,
+        /// Head each run of instructions with its source line from the DWARF line tables
+        #[arg(long)]
+        source: bool,
+        /// Program to disassemble (defaults to the release build, or the release-dwarf build with
+        /// --source)
+        #[arg(long)]
+        program: Option<PathBuf>,
+    },
+    /// Break the release program's .text down by crate and audit it for core's formatting and
+    /// panic machinery
+    Footprint {
+        /// Package to audit, the main program or a fixture registered with `new-fixture`
+        #[arg(long, default_value = FIXTURE_PACKAGE)]
+        package: String,
+        /// Program to audit (defaults to the package's release build)
+        #[arg(long)]
+        program: Option<PathBuf>,
+    },
+    /// Build the program with the `panic-log` feature into target/panic-log/ and check that a
+    /// deliberate panic logs where it happened before aborting
+    PanicLog {
+        #[arg(long, value_enum, default_value_t = Profile::Release)]
+        profile: Profile,
+        /// Check this program instead of building one
+        #[arg(long)]
+        program: Option<PathBuf>,
+    },
+    /// Try a newer nightly: pin it in rust-toolchain.toml if the program builds, passes the smoke
+    /// test, and passes the tests with it, and keep the current pin otherwise
+    BumpNightly {
+        /// Date of the nightly, like 2026-06-01
+        date: String,
+    },
+    /// Export the built toolchain to an archive, or import one, so CI can cache it between runs
+    Cache {
+        #[command(subcommand)]
+        command: CacheCommand,
+    },
+    /// Run the CI pipeline: toolchain check, build, bytecode checks, host tests, the no_std
+    /// check, and the CU baseline gate, with a summary of every step
+    Ci {
+        /// Percentage over the CU baseline an op may cost before the gate fails
+        #[arg(long, value_name = "PERCENT", default_value_t = 0.0)]
+        tolerance: f64,
+    },
+    /// Install a git pre-push hook that runs the CI pipeline (`cargo xtask ci`)
+    InstallHooks {
+        /// Replace an existing pre-push hook that was not installed by xtask
+        #[arg(long)]
+        force: bool,
+    },
+}
+
+#[derive(Subcommand)]
+enum CacheCommand {
+    /// Pack the selected LLVM install, sbpf-linker, and their refs, with a manifest of hashes
+    Export {
+        /// Archive to write; tar picks the compression from the extension (e.g. `.tar.zst`)
+        archive: PathBuf,
+    },
+    /// Unpack an exported toolchain into the cache after checking it against its manifest
+    Import {
+        /// Archive written by `cache export`
+        archive: PathBuf,
+    },
 }
 
 fn main() -> Result<()> {
+    if let Some(lld) = std::env::var_os(linkers::LLD_SHIM_ENV) {
+        return linkers::lld_shim(Path::new(&lld));
+    }
     let cli = Cli::parse();
     let project_root = project_root()?;
 
     match cli.command {
-        Commands::Setup => {
-            setup_llvm()?;
-            setup_linker(&project_root)?;
+        Commands::Setup {
+            no_modify_toolchains,
+            llvm,
+        } => {
+            setup_rust_toolchain(&project_root, !no_modify_toolchains)?;
+            // Resolve both refs up front so a missing branch fails before anything is built
+            let llvm_dirs = LlvmDirs::for_commit(resolve_commit("llvm", LLVM_REPO, LLVM_BRANCH)?);
+            let linker_commit = resolve_commit("sbpf-linker", LINKER_REPO, LINKER_BRANCH)?;
+            // The linker clone and dependency fetch don't need LLVM, so overlap them with the
+            // LLVM build; only the linker build itself has to wait for the install prefix
+            thread::scope(|scope| -> Result<()> {
+                let fetch = scope.spawn(|| fetch_linker(&linker_commit));
+                let llvm_result = setup_llvm(&llvm_dirs, &llvm.cmake_args);
+                let fetch_result = fetch
+                    .join()
+                    .map_err(|_| anyhow!("sbpf-linker fetch thread panicked"))?;
+                llvm_result?;
+                fetch_result
+            })?;
+            build_linker(&project_root, &linker_commit, &llvm_dirs)?;
+            println!();
+            smoke::smoke_test(&project_root)?;
             println!();
             println!("==========================================");
             println!("Setup complete!");
             println!();
             println!("Build this project with:");
-            println!("  cargo +nightly build-bpf");
+            println!("  cargo build-bpf");
             println!("==========================================");
         }
         Commands::BuildLinker => {
             setup_linker(&project_root)?;
         }
-        Commands::BuildLlvm => {
-            setup_llvm()?;
+        Commands::BuildLlvm { incremental: true, .. } => {
+            rebuild_llvm_backend()?;
+        }
+        Commands::BuildLlvm { llvm, .. } => {
+            let llvm_dirs = LlvmDirs::for_commit(resolve_commit("llvm", LLVM_REPO, LLVM_BRANCH)?);
+            setup_llvm(&llvm_dirs, &llvm.cmake_args)?;
+        }
+        Commands::RebuildLlvm { llvm } => {
+            rebuild_llvm(&llvm.cmake_args)?;
+        }
+        Commands::SmokeTest => {
+            smoke::smoke_test(&project_root)?;
+        }
+        Commands::LlvmTest { filter } => {
+            llvm_test(filter.as_deref())?;
+        }
+        Commands::Build {
+            profile,
+            linker,
+            stock_llvm,
+            platform_tools,
+            strip,
+            summary_md,
+        } => {
+            let artifact = if linker == linkers::Linker::Sbpf && !stock_llvm {
+                build_all(&project_root, profile)?;
+                if strip {
+                    for package in scaffold::registered_fixtures(&project_root)? {
+                        strip::strip(&profile.package_artifact(&project_root, &package))?;
+                    }
+                }
+                profile.artifact(&project_root)
+            } else {
+                let llvm = if stock_llvm {
+                    linkers::Llvm::platform_tools(platform_tools.as_deref())?
+                } else {
+                    linkers::Llvm::patched()?
+                };
+                let artifact = linkers::build(&project_root, linker, &llvm, profile)?;
+                // A finding to compare rather than a broken build
+                if let Err(err) = check_undefined_symbols(&artifact) {
+                    println!("warning: {err:#}");
+                }
+                println!("Build complete: {}", artifact.display());
+                artifact
+            };
+            if strip {
+                strip::strip(&artifact)?;
+            }
+            if let Some(path) = summary_md {
+                summary::append(&path, &size_summary(&project_root, &artifact)?)?;
+            }
+        }
+        Commands::Run { data, program, trace } => {
+            let program = program.unwrap_or_else(|| Profile::Release.artifact(&project_root));
+            run_program(&program, &data, trace.as_deref())?;
+        }
+        Commands::Inspect { program } => {
+            let program = program.unwrap_or_else(|| Profile::Release.artifact(&project_root));
+            inspect_program(&program)?;
+        }
+        Commands::Libcalls { program } => {
+            let program = program.unwrap_or_else(|| Profile::Release.artifact(&project_root));
+            check_libcalls(&program)?;
+        }
+        Commands::LibcallGaps {
+            program,
+            platform_tools,
+        } => {
+            let program = program.unwrap_or_else(|| Profile::Release.artifact(&project_root));
+            builtins::libcall_gaps(&program, platform_tools.as_deref())?;
+        }
+        Commands::Cfg { symbol, program, out } => {
+            let program = program.unwrap_or_else(|| Profile::Release.artifact(&project_root));
+            let elf = fs::read(&program).with_context(|| {
+                format!(
+                    "failed to read {}. Run `cargo xtask build` first.",
+                    program.display()
+                )
+            })?;
+            let dot = vm::function_cfg_dot(&elf, &symbol)?;
+            match out {
+                Some(out) => {
+                    fs::write(&out, dot).with_context(|| format!("failed to write {}", out.display()))?;
+                    println!("Wrote the CFG of {symbol} to {}", out.display());
+                }
+                None => print!("{dot}"),
+            }
+        }
+        Commands::Bytediff { old, new } => {
+            bytediff::bytediff(&old, &new)?;
+        }
+        Commands::AgaveCheck {
+            program,
+            max_sbpf_version,
+        } => {
+            let program = program.unwrap_or_else(|| Profile::Release.artifact(&project_root));
+            agave::agave_check(&program, max_sbpf_version)?;
+        }
+        Commands::Relocs {
+            program,
+            sbpf_version,
+        } => {
+            let program = program.unwrap_or_else(|| Profile::Release.artifact(&project_root));
+            report_relocations(&program, sbpf_version)?;
+        }
+        Commands::RodataCheck { program, expected } => {
+            let program = program.unwrap_or_else(|| Profile::Release.artifact(&project_root));
+            rodata::check_rodata(&program, &expected)?;
+        }
+        Commands::IsaCheck { program } => {
+            let program = program.unwrap_or_else(|| Profile::Release.artifact(&project_root));
+            isa::isa_check(&program)?;
+        }
+        Commands::PureCheck { program } => {
+            let program = program.unwrap_or_else(|| Profile::Release.artifact(&project_root));
+            purity::pure_check(&program)?;
+        }
+        Commands::CodegenCheck => {
+            codegen::codegen_check(&project_root)?;
         }
-        Commands::RebuildLlvm => {
-            rebuild_llvm()?;
+        Commands::Snapshot { bless, ir } => {
+            if ir {
+                snapshot::ir_snapshot(&project_root, bless)?;
+            } else {
+                snapshot::snapshot(&project_root, bless)?;
+            }
         }
-        Commands::Build => {
-            build_project(&project_root)?;
+        Commands::Compare { data } => {
+            compare::compare(&project_root, &vm::decode_hex(&data)?)?;
+        }
+        Commands::CompareReport { out } => {
+            let out = out.unwrap_or_else(|| project_root.join(COMPARE_REPORT));
+            compare::report(&project_root, &out)?;
+        }
+        Commands::Bench {
+            program,
+            model,
+            instructions,
+            batch,
+            variance,
+            runs,
+            baseline,
+            entries,
+            tolerance,
+            bless,
+            summary_md,
+            out,
+        } => {
+            let mut programs = program;
+            if programs.is_empty() {
+                programs.push(Profile::Release.artifact(&project_root));
+            }
+            if model {
+                let out = out.unwrap_or_else(|| project_root.join(COST_MODEL_DIR));
+                let programs: Vec<_> = programs.iter().map(PathBuf::as_path).collect();
+                bench::cost_model(&programs, &out)?;
+            } else if let [program] = &programs[..] {
+                if instructions {
+                    bench::instruction_counts(program)?;
+                } else if batch {
+                    bench::batch_sweep(program)?;
+                } else if variance {
+                    bench::variance(program, runs)?;
+                } else if baseline {
+                    let baseline = project_root.join(CU_BASELINE);
+                    bench::check_baseline(program, &baseline, tolerance, bless, summary_md.as_deref())?;
+                } else if entries {
+                    bench::entries(program)?;
+                } else {
+                    bench::bench(program)?;
+                }
+            } else {
+                bail!("--program can only be repeated with --model");
+            }
+        }
+        Commands::Profile {
+            program,
+            op,
+            inlined,
+            folded,
+        } => {
+            let build_profile = if inlined { Profile::ReleaseDwarf } else { Profile::Release };
+            let program = program.unwrap_or_else(|| build_profile.artifact(&project_root));
+            profile::profile(&program, op.as_deref(), inlined, folded.as_deref())?;
+        }
+        Commands::GenFixtures {
+            program,
+            out,
+            conformance,
+        } => {
+            let program = program.unwrap_or_else(|| Profile::Release.artifact(&project_root));
+            match conformance {
+                Some(out_dir) => fixtures::export_conformance(&program, &out_dir)?,
+                None => {
+                    let out = out.unwrap_or_else(|| project_root.join(FIXTURES_JSON));
+                    fixtures::gen_fixtures(&program, &out)?;
+                }
+            }
+        }
+        Commands::ExternalRun { runner, program } => {
+            let program = program.unwrap_or_else(|| Profile::Release.artifact(&project_root));
+            external::run_external(&project_root, &program, &runner)?;
+        }
+        Commands::BitFlip { program, op } => {
+            let program = program.unwrap_or_else(|| Profile::Release.artifact(&project_root));
+            mutation::bit_flip(&program, op.as_deref())?;
+        }
+        Commands::TestReport {
+            program,
+            out,
+            summary_md,
+        } => {
+            let program = program.unwrap_or_else(|| Profile::Release.artifact(&project_root));
+            let out = out.unwrap_or_else(|| project_root.join(TEST_RESULTS_DIR));
+            results::write_results(&program, &out, summary_md.as_deref())?;
+        }
+        Commands::Coverage { html, fail_under } => {
+            coverage::coverage(&project_root, html, fail_under)?;
+        }
+        Commands::MolluskFixtures {
+            out,
+            json,
+            firedancer,
+        } => {
+            let out = out.unwrap_or_else(|| project_root.join(MOLLUSK_FIXTURES_DIR));
+            eject_mollusk_fixtures(&project_root, &out, json, firedancer)?;
+        }
+        Commands::Package => {
+            package::package(&project_root)?;
+        }
+        Commands::VerifyBuild => {
+            verify::verify_build(&project_root)?;
+        }
+        Commands::NewFixture { name } => {
+            scaffold::new_fixture(&project_root, &name)?;
+        }
+        Commands::GenConfig {
+            linker,
+            stack_size,
+            sbpf_flags,
+        } => {
+            cargo_config::gen_config(&project_root, linker, stack_size, &sbpf_flags)?;
+        }
+        Commands::Addr2line { pcs, address, program } => {
+            let program = program.unwrap_or_else(|| Profile::ReleaseDwarf.artifact(&project_root));
+            addr2line::addr2line(&program, &pcs, address)?;
+        }
+        Commands::Dump {
+            function,
+            source,
+            program,
+        } => {
+            let build_profile = if source { Profile::ReleaseDwarf } else { Profile::Release };
+            let program = program.unwrap_or_else(|| build_profile.artifact(&project_root));
+            dump::dump(&project_root, &program, function.as_deref(), source)?;
+        }
+        Commands::Footprint { package, program } => {
+            let program =
+                program.unwrap_or_else(|| Profile::Release.package_artifact(&project_root, &package));
+            footprint::footprint(&program, &scaffold::package_manifest(&project_root, &package))?;
+        }
+        Commands::PanicLog { profile, program } => {
+            let program = match program {
+                Some(program) => program,
+                None => panic_log::build(&project_root, profile)?,
+            };
+            panic_log::check(&program)?;
+        }
+        Commands::BumpNightly { date } => {
+            toolchain::bump_nightly(&project_root, &date)?;
+        }
+        Commands::Cache { command } => match command {
+            CacheCommand::Export { archive } => cache::export(&archive)?,
+            CacheCommand::Import { archive } => cache::import(&project_root, &archive)?,
+        },
+        Commands::Ci { tolerance } => {
+            ci::ci(&project_root, tolerance)?;
+        }
+        Commands::InstallHooks { force } => {
+            hooks::install_hooks(&project_root, force)?;
         }
     }
 
@@ -88,36 +889,185 @@ fn cache_dir() -> PathBuf {
         .join("u128-bpf-toolchain")
 }
 
+/// Leading hex digits of a commit used to name cache directories
+fn short_commit(commit: &str) -> &str {
+    &commit[..commit.len().min(12)]
+}
+
+/// Where the branch and commit last resolved for `component` are recorded
+fn ref_file(component: &str) -> PathBuf {
+    cache_dir().join(format!("{component}.ref"))
+}
+
+/// Look up the commit `branch` of `repo` points at and record it as the selected ref for
+/// `component`. When the remote can't be reached, fall back to the commit recorded for the
+/// same branch by an earlier run.
+fn resolve_commit(component: &str, repo: &str, branch: &str) -> Result<String> {
+    let commit = match resolve_remote_branch(repo, branch) {
+        Ok(Some(commit)) => commit,
+        Ok(None) => bail!("branch `{branch}` not found in {repo}"),
+        Err(err) => match recorded_commit(component, branch) {
+            Some(commit) => {
+                println!("  Could not reach {repo} ({err:#}); using recorded {branch} at {}", short_commit(&commit));
+                return Ok(commit);
+            }
+            None => return Err(err),
+        },
+    };
+    fs::create_dir_all(cache_dir())?;
+    fs::write(ref_file(component), format!("{branch} {commit}\n"))
+        .with_context(|| format!("failed to write {}", ref_file(component).display()))?;
+    println!("  {component}: {branch} at {}", short_commit(&commit));
+    Ok(commit)
+}
+
+/// Commit recorded for `component` by the last setup, provided it was resolved from `branch`
+fn recorded_commit(component: &str, branch: &str) -> Option<String> {
+    let contents = fs::read_to_string(ref_file(component)).ok()?;
+    let (recorded_branch, commit) = contents.trim().split_once(' ')?;
+    (recorded_branch == branch).then(|| commit.to_string())
+}
+
+/// Commit selected for `component` by the last setup, for commands that use the toolchain
+/// without rebuilding it
+fn selected_commit(component: &str, branch: &str) -> Result<String> {
+    recorded_commit(component, branch).ok_or_else(|| {
+        anyhow!(
+            "no {component} build recorded for branch `{branch}` in {}. Run `cargo xtask setup` first.",
+            cache_dir().display()
+        )
+    })
+}
+
+/// Source, build, and install directories of the LLVM built from one commit
+struct LlvmDirs {
+    commit: String,
+    src: PathBuf,
+    build: PathBuf,
+    install: PathBuf,
+}
+
+impl LlvmDirs {
+    fn for_commit(commit: String) -> Self {
+        let root = cache_dir().join("llvm").join(short_commit(&commit));
+        Self {
+            commit,
+            src: root.join("llvm-project"),
+            build: root.join("llvm-build"),
+            install: root.join("llvm-install"),
+        }
+    }
+
+    /// The LLVM selected by the last `setup` or `build-llvm`
+    fn selected() -> Result<Self> {
+        Ok(Self::for_commit(selected_commit("llvm", LLVM_BRANCH)?))
+    }
+
+    fn root(&self) -> &Path {
+        self.src.parent().unwrap()
+    }
+}
+
+/// Checkout of the SBPF linker at `commit`
+fn linker_src_dir(commit: &str) -> PathBuf {
+    cache_dir().join("sbpf-linker").join(short_commit(commit))
+}
+
+/// Cargo target directory for a linker build, keyed by the LLVM it links so that linkers
+/// built against different LLVM commits don't overwrite each other
+fn linker_target_dir(linker_commit: &str, llvm_commit: &str) -> PathBuf {
+    linker_src_dir(linker_commit).join(format!("target-llvm-{}", short_commit(llvm_commit)))
+}
+
+/// The linker binary built from `linker_commit` against the LLVM at `llvm_commit`
+fn linker_bin(linker_commit: &str, llvm_commit: &str) -> PathBuf {
+    linker_target_dir(linker_commit, llvm_commit).join("release/sbpf-linker")
+}
+
+fn setup_rust_toolchain(project_root: &Path, modify: bool) -> Result<()> {
+    let pin = toolchain::pinned(project_root)?;
+    let missing = toolchain::missing_components(&pin)?;
+    let components = pin.components.join(", ");
+    if missing.as_ref().is_none_or(|missing| !missing.is_empty()) {
+        let install = format!(
+            "rustup toolchain install {} --profile minimal{}",
+            pin.channel,
+            pin.components.iter().map(|component| format!(" --component {component}")).collect::<String>()
+        );
+        if !modify {
+            bail!("{} with {components} is required. Install it with `{install}`.", pin.channel);
+        }
+        println!("Installing {} with {components}...", pin.channel);
+        // `toolchain install` adds missing components to an already installed toolchain too
+        run_command(
+            Command::new("rustup")
+                .args(["toolchain", "install", &pin.channel, "--profile", "minimal"])
+                .args(pin.components.iter().flat_map(|component| ["--component", component])),
+            "install nightly toolchain",
+        )?;
+    } else {
+        println!("  {} with {components} already installed", pin.channel);
+    }
+    Ok(())
+}
+
 fn setup_linker(project_root: &Path) -> Result<()> {
-    let base_dir = cache_dir();
-    let linker_dir = base_dir.join("sbpf-linker");
-    let linker_bin = linker_dir.join("target/release/sbpf-linker");
+    let llvm_dirs = LlvmDirs::selected()?;
+    let linker_commit = resolve_commit("sbpf-linker", LINKER_REPO, LINKER_BRANCH)?;
+    fetch_linker(&linker_commit)?;
+    build_linker(project_root, &linker_commit, &llvm_dirs)
+}
 
-    println!("  SBPF linker will be built in: {}", linker_dir.display());
+/// Clone the SBPF linker at `commit` and fetch its crate dependencies, neither of which needs LLVM
+fn fetch_linker(commit: &str) -> Result<()> {
+    let linker_dir = linker_src_dir(commit);
 
-    // Ensure cache directory exists
-    std::fs::create_dir_all(&base_dir)?;
+    println!("  SBPF linker will be built in: {}", linker_dir.display());
 
     // 1. Clone SBPF linker if needed
     println!("[1/3] Cloning SBPF linker...");
     if linker_dir.exists() {
-        println!("  sbpf-linker directory already exists, skipping clone");
+        println!("  sbpf-linker {} already cloned, skipping", short_commit(commit));
     } else {
-        run_command(
-            Command::new("git")
-                .args(["clone", "--depth", GIT_DEPTH, "--branch", LINKER_BRANCH, LINKER_REPO])
-                .arg(&linker_dir),
-            "clone sbpf-linker",
-        )?;
+        std::fs::create_dir_all(linker_dir.parent().unwrap())?;
+        clone_repo(LINKER_REPO, LINKER_BRANCH, commit, &linker_dir, "linker")?;
     }
 
+    retry_network("fetch sbpf-linker dependencies", || {
+        run_command_prefixed(
+            Command::new("cargo").arg("fetch").current_dir(&linker_dir),
+            "fetch sbpf-linker dependencies",
+            "linker",
+        )
+    })
+}
+
+/// Build the SBPF linker against the installed LLVM and point .cargo/config.toml at it
+fn build_linker(project_root: &Path, linker_commit: &str, llvm_dirs: &LlvmDirs) -> Result<()> {
+    let target_dir = linker_target_dir(linker_commit, &llvm_dirs.commit);
+    let linker_bin = linker_bin(linker_commit, &llvm_dirs.commit);
+
     // 2. Build SBPF linker with LLVM_PREFIX pointing to our custom LLVM
-    let llvm_install_dir = base_dir.join("llvm-install");
-    println!("[2/3] Building SBPF linker (LLVM_PREFIX={})...", llvm_install_dir.display());
+    println!("[2/3] Building SBPF linker (LLVM_PREFIX={})...", llvm_dirs.install.display());
+    compile_linker(linker_commit, &llvm_dirs.install, &target_dir)?;
 
+    // 3. Update .cargo/config.toml with linker path
+    println!("[3/3] Updating .cargo/config.toml with linker path...");
+    cargo_config::CargoConfig::new(&linker_bin, FIXTURE_PACKAGE).write(project_root)?;
+
+    println!("  SBPF linker ready at: {}", linker_bin.display());
+    Ok(())
+}
+
+/// Build the checkout of the SBPF linker at `linker_commit` against the LLVM installed at
+/// `llvm_install_dir`, into `target_dir`
+fn compile_linker(linker_commit: &str, llvm_install_dir: &Path, target_dir: &Path) -> Result<()> {
+    let linker_dir = linker_src_dir(linker_commit);
     let mut cmd = Command::new("cargo");
     cmd.args(["install", "--path", "."])
-        .env("LLVM_PREFIX", &llvm_install_dir)
+        .arg("--target-dir")
+        .arg(target_dir)
+        .env("LLVM_PREFIX", llvm_install_dir)
         .current_dir(&linker_dir);
 
     // On macOS, use Homebrew's llvm for libc++, zlib, and zstd
@@ -171,81 +1121,124 @@ fn setup_linker(project_root: &Path) -> Result<()> {
         cmd.env("LIBZSTD_PATH", format!("{}/lib", zstd_prefix));
     }
 
-    run_command(&mut cmd, "build sbpf-linker")?;
-
-    // 3. Update .cargo/config.toml with linker path
-    println!("[3/3] Updating .cargo/config.toml with linker path...");
-    let cargo_config_dir = project_root.join(".cargo");
-    std::fs::create_dir_all(&cargo_config_dir)?;
-
-    let config_content = format!(
-        r#"[target.bpfel-unknown-none]
-rustflags = [
-    "-C", "linker={}",
-    "-C", "panic=abort",
-    "-C", "link-arg=--dump-module=llvm_dump",
-    "-C", "link-arg=--llvm-args=-bpf-stack-size=4096",
-    "-C", "relocation-model=static",
-]
-
-[alias]
-build-bpf = "build --release --target bpfel-unknown-none -Zbuild-std=core,alloc"
-"#,
-        linker_bin.display()
-    );
-
-    std::fs::write(cargo_config_dir.join("config.toml"), config_content)
-        .context("failed to write .cargo/config.toml")?;
-
-    println!("  SBPF linker ready at: {}", linker_bin.display());
-    Ok(())
+    run_command_prefixed(&mut cmd, "build sbpf-linker", "linker")
 }
 
-fn setup_llvm() -> Result<()> {
-    let base_dir = cache_dir();
-    let llvm_src_dir = base_dir.join("llvm-project");
-
-    println!("  LLVM will be built in: {}", base_dir.display());
+fn setup_llvm(dirs: &LlvmDirs, extra_cmake_args: &[String]) -> Result<()> {
+    println!("  Host: {}", host_triple()?);
+    check_build_tools()?;
+    println!("  LLVM will be built in: {}", dirs.root().display());
 
     // Ensure cache directory exists
-    std::fs::create_dir_all(&base_dir)?;
+    std::fs::create_dir_all(dirs.root())?;
+
+    let llvm_config = dirs.install.join("bin/llvm-config");
+    if !llvm_config.exists() {
+        preflight_llvm_build(dirs)?;
+    }
 
     // 1. Clone LLVM repo if needed
     println!("[1/2] Cloning LLVM...");
-    if llvm_src_dir.exists() {
-        println!("  llvm-project directory already exists, skipping clone");
+    if dirs.src.exists() {
+        println!("  llvm-project {} already cloned, skipping", short_commit(&dirs.commit));
     } else {
-        run_command(
-            Command::new("git")
-                .args(["clone", "--depth", GIT_DEPTH, "--branch", LLVM_BRANCH, LLVM_REPO])
-                .arg(&llvm_src_dir),
-            "clone llvm-project",
-        )?;
+        clone_repo(LLVM_REPO, LLVM_BRANCH, &dirs.commit, &dirs.src, "llvm")?;
     }
 
     // 2. Build LLVM from source (skip if already built)
-    let llvm_build_dir = base_dir.join("llvm-build");
-    let llvm_install_dir = base_dir.join("llvm-install");
-    let llvm_config = llvm_install_dir.join("bin/llvm-config");
-
     if llvm_config.exists() {
         println!("[2/2] LLVM already built (found {}), skipping", llvm_config.display());
+        if !extra_cmake_args.is_empty() {
+            println!("  --llvm-cmake-arg ignored for an existing build; use `rebuild-llvm` to apply it");
+        }
     } else {
         println!("[2/2] Building LLVM (this may take a while)...");
-        std::fs::create_dir_all(&llvm_build_dir)?;
-        std::fs::create_dir_all(&llvm_install_dir)?;
-        build_llvm(&llvm_src_dir, &llvm_build_dir, &llvm_install_dir)?;
+        std::fs::create_dir_all(&dirs.build)?;
+        std::fs::create_dir_all(&dirs.install)?;
+        build_llvm(&dirs.src, &dirs.build, &dirs.install, extra_cmake_args)?;
     }
 
-    println!("  LLVM installed to: {}", llvm_install_dir.display());
+    println!("  LLVM installed to: {}", dirs.install.display());
     Ok(())
 }
 
-fn rebuild_llvm() -> Result<()> {
-    let base_dir = cache_dir();
-    let llvm_src_dir = base_dir.join("llvm-project");
-    let llvm_build_dir = base_dir.join("llvm-build");
-    let llvm_install_dir = base_dir.join("llvm-install");
+const GIB: u64 = 1024 * 1024 * 1024;
+/// Rough footprint of a shallow llvm-project clone
+const LLVM_SRC_BYTES: u64 = 3 * GIB;
+/// Rough footprint of a fresh BPF-only release build with assertions
+const LLVM_BUILD_BYTES: u64 = 8 * GIB;
+/// Rough footprint of the install prefix
+const LLVM_INSTALL_BYTES: u64 = GIB;
+/// Approximate CPU time of a fresh BPF-only LLVM build, in core-minutes
+const LLVM_BUILD_CORE_MINUTES: u64 = 300;
+
+/// Check there is room for the LLVM clone and build and print how long it is likely to take,
+/// so a full disk is reported up front rather than as a ninja error 40 minutes in
+fn preflight_llvm_build(dirs: &LlvmDirs) -> Result<()> {
+    let base_dir = dirs.root();
+    let src_exists = dirs.src.exists();
+    let build_configured = dirs.build.join("CMakeCache.txt").exists();
+
+    let mut required = LLVM_INSTALL_BYTES;
+    if !src_exists {
+        required += LLVM_SRC_BYTES;
+    }
+    if !build_configured {
+        required += LLVM_BUILD_BYTES;
+    }
+
+    match free_space(base_dir) {
+        Some(available) if available < required => {
+            bail!(
+                "not enough disk space for the LLVM build: {:.1} GiB free in {}, about {:.1} GiB needed. \
+                 Free up space{}.",
+                available as f64 / GIB as f64,
+                base_dir.display(),
+                required as f64 / GIB as f64,
+                if cfg!(target_os = "linux") {
+                    " or point XDG_CACHE_HOME at a larger disk"
+                } else {
+                    ""
+                }
+            );
+        }
+        Some(available) => println!(
+            "  Disk: {:.1} GiB free, about {:.1} GiB needed",
+            available as f64 / GIB as f64,
+            required as f64 / GIB as f64
+        ),
+        None => println!("  Disk: could not determine free space, continuing"),
+    }
+
+    if build_configured {
+        println!("  Existing build directory found, the build will be incremental");
+    } else {
+        let cores = std::thread::available_parallelism().map_or(1, |n| n.get()) as u64;
+        let minutes = (LLVM_BUILD_CORE_MINUTES / cores).max(5);
+        println!("  Estimated LLVM build time: ~{minutes} minutes on {cores} cores");
+    }
+    Ok(())
+}
+
+/// Free bytes on the filesystem holding `path`, as reported by `df`
+fn free_space(path: &Path) -> Option<u64> {
+    let output = Command::new("df").arg("-Pk").arg(path).output().ok()?;
+    if !output.status.success() {
+        return None;
+    }
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    // POSIX format: Filesystem 1024-blocks Used Available Capacity Mounted-on
+    let available_kib: u64 = stdout.lines().nth(1)?.split_whitespace().nth(3)?.parse().ok()?;
+    Some(available_kib * 1024)
+}
+
+fn rebuild_llvm(extra_cmake_args: &[String]) -> Result<()> {
+    let LlvmDirs {
+        src: llvm_src_dir,
+        build: llvm_build_dir,
+        install: llvm_install_dir,
+        ..
+    } = LlvmDirs::selected()?;
 
     if !llvm_src_dir.exists() {
         bail!("llvm-project not found at {}. Run `setup` or `build-llvm` first.", llvm_src_dir.display());
@@ -259,16 +1252,215 @@ fn rebuild_llvm() -> Result<()> {
     fs::create_dir_all(&llvm_install_dir)?;
 
     println!("Rebuilding LLVM (incremental)...");
-    build_llvm(&llvm_src_dir, &llvm_build_dir, &llvm_install_dir)?;
+    build_llvm(&llvm_src_dir, &llvm_build_dir, &llvm_install_dir, extra_cmake_args)?;
     println!("  LLVM installed to: {}", llvm_install_dir.display());
     Ok(())
 }
 
-fn build_llvm(src_dir: &Path, build_dir: &Path, install_prefix: &Path) -> Result<()> {
-    // Skip cmake configure if already configured
+/// Targets `build-llvm --incremental` rebuilds and reinstalls: the BPF backend libraries, the
+/// libLLVM dylib the tools link, and llc
+const LLVM_BACKEND_TARGETS: &[&str] = &[
+    "LLVMBPFAsmParser",
+    "LLVMBPFCodeGen",
+    "LLVMBPFDesc",
+    "LLVMBPFDisassembler",
+    "LLVMBPFInfo",
+    "LLVM",
+    "llc",
+];
+/// Fingerprint of the llvm-project checkout the build tree was last built from, in the build tree
+const LLVM_SOURCE_STAMP: &str = "xtask-source.stamp";
+
+/// sha256 of the checkout's HEAD, uncommitted changes, and untracked files, which changes
+/// whenever a backend developer edits or checks out something
+fn llvm_source_fingerprint(src_dir: &Path) -> Result<String> {
+    let mut hasher = Sha256::new();
+    for args in [
+        &["rev-parse", "HEAD"][..],
+        &["diff", "HEAD", "--binary"],
+        &["ls-files", "--others", "--exclude-standard"],
+    ] {
+        let output = Command::new("git")
+            .arg("-C")
+            .arg(src_dir)
+            .args(args)
+            .output()
+            .context("failed to run git")?;
+        if !output.status.success() {
+            bail!("git {} failed in {}", args.join(" "), src_dir.display());
+        }
+        hasher.update(&output.stdout);
+    }
+    Ok(format!("{:x}", hasher.finalize()))
+}
+
+/// Rebuild and reinstall just `LLVM_BACKEND_TARGETS` of the selected LLVM if its checkout changed
+/// since the last build. The build tree keeps its configuration; the build system reruns cmake
+/// by itself if a CMakeLists.txt changed.
+fn rebuild_llvm_backend() -> Result<()> {
+    let dirs = LlvmDirs::selected()?;
+    if !dirs.build.join("CMakeCache.txt").exists() || !dirs.install.join("bin/llvm-config").exists() {
+        bail!(
+            "no LLVM build to update in {}. Run `build-llvm` without --incremental first.",
+            dirs.root().display()
+        );
+    }
+    let fingerprint = llvm_source_fingerprint(&dirs.src)?;
+    let stamp = dirs.build.join(LLVM_SOURCE_STAMP);
+    if fs::read_to_string(&stamp).is_ok_and(|built| built == fingerprint) {
+        println!(
+            "llvm-project {} is unchanged since the last build, nothing to do",
+            short_commit(&dirs.commit)
+        );
+        return Ok(());
+    }
+
+    let mut cmake_build = Command::new("cmake");
+    cmake_build
+        .arg("--build")
+        .arg(&dirs.build)
+        .arg("--target")
+        .args(LLVM_BACKEND_TARGETS.iter().map(|target| format!("install-{target}")));
+    if configured_generator(&dirs.build).as_deref() == Some("Unix Makefiles") {
+        let jobs = std::thread::available_parallelism().map_or(1, |n| n.get());
+        cmake_build.args(["--parallel", &jobs.to_string()]);
+    }
+    println!("Rebuilding the BPF backend with command {cmake_build:?}");
+    let start = Instant::now();
+    run_command_prefixed(&mut cmake_build, "rebuild the BPF backend", "llvm")?;
+    fs::write(&stamp, fingerprint).with_context(|| format!("failed to write {}", stamp.display()))?;
+    println!(
+        "  BPF backend reinstalled to {} in {:.0?}",
+        dirs.install.display(),
+        start.elapsed()
+    );
+    Ok(())
+}
+
+/// Configure flags for the LLVM build, independent of the host and generator
+const LLVM_CMAKE_ARGS: &[&str] = &[
+    "-DCMAKE_BUILD_TYPE=Release",
+    "-DLLVM_BUILD_LLVM_DYLIB=ON",
+    "-DLLVM_ENABLE_ASSERTIONS=ON",
+    "-DLLVM_ENABLE_PROJECTS=",
+    "-DLLVM_ENABLE_RUNTIMES=",
+    "-DLLVM_INSTALL_UTILS=ON",
+    "-DLLVM_LINK_LLVM_DYLIB=ON",
+    "-DLLVM_TARGETS_TO_BUILD=BPF",
+];
+
+/// Target triple of the machine building the toolchain.
+///
+/// Only hosts the setup flow has been exercised on are accepted, so an unsupported host fails
+/// before cloning anything rather than halfway through the LLVM build.
+fn host_triple() -> Result<&'static str> {
+    match (std::env::consts::ARCH, std::env::consts::OS) {
+        ("x86_64", "linux") => Ok("x86_64-unknown-linux-gnu"),
+        ("aarch64", "linux") => Ok("aarch64-unknown-linux-gnu"),
+        ("x86_64", "macos") => Ok("x86_64-apple-darwin"),
+        ("aarch64", "macos") => Ok("arm64-apple-darwin"),
+        (arch, os) => bail!("unsupported host {arch}-{os}; supported hosts are x86_64/aarch64 Linux and macOS"),
+    }
+}
+
+/// Host-specific configure flags appended after `LLVM_CMAKE_ARGS`
+fn host_cmake_args() -> Result<Vec<String>> {
+    let triple = host_triple()?;
+    let mut args = Vec::new();
+    if cfg!(target_os = "linux") {
+        // On Linux, explicitly use clang to avoid C++ ABI mismatches with GCC
+        args.push("-DCMAKE_C_COMPILER=clang".to_string());
+        args.push("-DCMAKE_CXX_COMPILER=clang++".to_string());
+        // config.guess reports distro-specific vendors on some aarch64 systems (e.g.
+        // aarch64-redhat-linux on Asahi), so pin the triple rustc and the linker expect
+        args.push(format!("-DLLVM_HOST_TRIPLE={triple}"));
+    }
+    Ok(args)
+}
+
+/// Fail early with an install hint if a tool the LLVM build needs is missing
+fn check_build_tools() -> Result<()> {
+    let mut tools = vec!["git", "cmake"];
+    if cfg!(target_os = "linux") {
+        tools.extend(["clang", "clang++"]);
+    }
+    let missing: Vec<&str> = tools.into_iter().filter(|tool| !tool_available(tool)).collect();
+    if missing.is_empty() {
+        return Ok(());
+    }
+
+    let hint = if cfg!(target_os = "macos") {
+        "brew install cmake"
+    } else if Path::new("/etc/fedora-release").exists() {
+        "sudo dnf install cmake clang"
+    } else {
+        "sudo apt-get install cmake clang"
+    };
+    bail!("missing build tools: {}. Install them with `{hint}`.", missing.join(", "))
+}
+
+fn tool_available(tool: &str) -> bool {
+    Command::new(tool).arg("--version").output().is_ok()
+}
+
+/// Pick the CMake generator, preferring Ninja and falling back to Makefiles when it is missing
+fn cmake_generator() -> &'static str {
+    if tool_available("ninja") {
+        return "Ninja";
+    }
+    let hint = if cfg!(target_os = "macos") {
+        "brew install ninja"
+    } else if Path::new("/etc/fedora-release").exists() {
+        "sudo dnf install ninja-build"
+    } else {
+        "sudo apt-get install ninja-build"
+    };
+    println!("  ninja not found, falling back to Unix Makefiles (install it with `{hint}` for faster builds)");
+    "Unix Makefiles"
+}
+
+/// Full configure flags for the LLVM build: generator, `LLVM_CMAKE_ARGS`, and host flags
+fn llvm_configure_args(generator: &str) -> Result<Vec<String>> {
+    let mut args = vec!["-G".to_string(), generator.to_string()];
+    args.extend(LLVM_CMAKE_ARGS.iter().map(|arg| arg.to_string()));
+    args.extend(host_cmake_args()?);
+    Ok(args)
+}
+
+/// Generator an existing build directory was configured with
+fn configured_generator(build_dir: &Path) -> Option<String> {
+    fs::read_to_string(build_dir.join("CMakeCache.txt"))
+        .ok()?
+        .lines()
+        .find_map(|line| line.strip_prefix("CMAKE_GENERATOR:INTERNAL=").map(str::to_string))
+}
+
+fn build_llvm(
+    src_dir: &Path,
+    build_dir: &Path,
+    install_prefix: &Path,
+    extra_cmake_args: &[String],
+) -> Result<()> {
+    // Skip cmake configure if already configured, unless there are new cache entries to apply
     let cmake_cache = build_dir.join("CMakeCache.txt");
-    if cmake_cache.exists() {
+    if cmake_cache.exists() && extra_cmake_args.is_empty() {
         println!("  CMake already configured (found CMakeCache.txt), skipping configure");
+    } else if cmake_cache.exists() {
+        // The generator and base flags are already in the cache; only apply the additions
+        let mut cmake_reconfigure = Command::new("cmake");
+        let cmake_reconfigure = cmake_reconfigure
+            .arg("-S")
+            .arg(src_dir.join("llvm"))
+            .arg("-B")
+            .arg(build_dir)
+            .args(extra_cmake_args);
+        println!("Reconfiguring LLVM with command {cmake_reconfigure:?}");
+        let status = stream_command(cmake_reconfigure, "llvm").with_context(|| {
+            format!("failed to reconfigure LLVM build with command {cmake_reconfigure:?}")
+        })?;
+        if !status.success() {
+            anyhow::bail!("failed to reconfigure LLVM build with command {cmake_reconfigure:?}: {status}");
+        }
     } else {
         let mut install_arg = OsString::from("-DCMAKE_INSTALL_PREFIX=");
         install_arg.push(install_prefix.as_os_str());
@@ -278,29 +1470,12 @@ fn build_llvm(src_dir: &Path, build_dir: &Path, install_prefix: &Path) -> Result
             .arg(src_dir.join("llvm"))
             .arg("-B")
             .arg(build_dir)
-            .args([
-                "-G",
-                "Ninja",
-                "-DCMAKE_BUILD_TYPE=Release",
-                "-DLLVM_BUILD_LLVM_DYLIB=ON",
-                "-DLLVM_ENABLE_ASSERTIONS=ON",
-                "-DLLVM_ENABLE_PROJECTS=",
-                "-DLLVM_ENABLE_RUNTIMES=",
-                "-DLLVM_INSTALL_UTILS=ON",
-                "-DLLVM_LINK_LLVM_DYLIB=ON",
-                "-DLLVM_TARGETS_TO_BUILD=BPF",
-            ])
-            .arg(install_arg);
-
-        // On Linux, explicitly use clang to avoid C++ ABI mismatches with GCC
-        if cfg!(target_os = "linux") {
-            cmake_configure
-                .arg("-DCMAKE_C_COMPILER=clang")
-                .arg("-DCMAKE_CXX_COMPILER=clang++");
-        }
+            .args(llvm_configure_args(cmake_generator())?)
+            .arg(install_arg)
+            .args(extra_cmake_args);
 
         println!("Configuring LLVM with command {cmake_configure:?}");
-        let status = cmake_configure.status().with_context(|| {
+        let status = stream_command(cmake_configure, "llvm").with_context(|| {
             format!("failed to configure LLVM build with command {cmake_configure:?}")
         })?;
         if !status.success() {
@@ -320,9 +1495,13 @@ fn build_llvm(src_dir: &Path, build_dir: &Path, install_prefix: &Path) -> Result
         // does not turn those into symlinks-to-symlinks), use absolute
         // symlinks so we can distinguish the two cases.
         .env("CMAKE_INSTALL_MODE", "ABS_SYMLINK");
+    // Ninja parallelizes by default, make needs to be told
+    if configured_generator(build_dir).as_deref() == Some("Unix Makefiles") {
+        let jobs = std::thread::available_parallelism().map_or(1, |n| n.get());
+        cmake_build.args(["--parallel", &jobs.to_string()]);
+    }
     println!("Building LLVM with command {cmake_build:?}");
-    let status = cmake_build
-        .status()
+    let status = stream_command(cmake_build, "llvm")
         .with_context(|| format!("failed to build LLVM with command {cmake_build:?}"))?;
     if !status.success() {
         anyhow::bail!("failed to build LLVM with command {cmake_build:?}: {status}");
@@ -357,18 +1536,638 @@ fn build_llvm(src_dir: &Path, build_dir: &Path, install_prefix: &Path) -> Result
         }
     }
 
+    let stamp = build_dir.join(LLVM_SOURCE_STAMP);
+    fs::write(&stamp, llvm_source_fingerprint(src_dir)?)
+        .with_context(|| format!("failed to write {}", stamp.display()))?;
     Ok(())
 }
 
-fn build_project(project_root: &Path) -> Result<()> {
-    println!("Building project with cargo +nightly...");
-    run_command(
-        Command::new("cargo")
-            .args(["+nightly", "build-bpf"])
-            .current_dir(project_root),
-        "build project",
+fn llvm_test(filter: Option<&str>) -> Result<()> {
+    let LlvmDirs {
+        src: llvm_src_dir,
+        build: llvm_build_dir,
+        ..
+    } = LlvmDirs::selected()?;
+
+    if !llvm_build_dir.join("CMakeCache.txt").exists() {
+        bail!("llvm-build not found at {}. Run `setup` or `build-llvm` first.", llvm_build_dir.display());
+    }
+
+    match filter {
+        // Run lit directly so a subset (e.g. the i128 tests) can be iterated on quickly
+        Some(filter) => {
+            run_command_prefixed(
+                Command::new("cmake")
+                    .arg("--build")
+                    .arg(&llvm_build_dir)
+                    .args(["--target", "llvm-test-depends"]),
+                "build lit test dependencies",
+                "llvm",
+            )?;
+            println!("Running BPF lit tests matching {filter:?}...");
+            run_command_prefixed(
+                Command::new(llvm_build_dir.join("bin/llvm-lit"))
+                    .args(["-sv", "--filter", filter])
+                    .arg(llvm_src_dir.join("llvm/test/CodeGen/BPF")),
+                "run llvm-lit",
+                "lit",
+            )?;
+        }
+        None => {
+            println!("Running check-llvm-codegen-bpf...");
+            run_command_prefixed(
+                Command::new("cmake")
+                    .arg("--build")
+                    .arg(&llvm_build_dir)
+                    .args(["--target", "check-llvm-codegen-bpf"]),
+                "check-llvm-codegen-bpf",
+                "lit",
+            )?;
+        }
+    }
+    println!("LLVM BPF lit tests passed");
+    Ok(())
+}
+
+fn build_project(project_root: &Path, profile: Profile) -> Result<()> {
+    build_package(project_root, FIXTURE_PACKAGE, profile)?;
+    let artifact = profile.artifact(project_root);
+    check_undefined_symbols(&artifact)?;
+    // Unoptimized builds are expected to be large and to keep their panic paths, so only release
+    // artifacts are held to the budget and audited
+    if let Profile::Release = profile {
+        check_size_limits(project_root, &artifact)?;
+        footprint::check(&artifact, &scaffold::package_manifest(project_root, FIXTURE_PACKAGE))?;
+    }
+    println!("Build complete: {}", artifact.display());
+    Ok(())
+}
+
+/// Build the program, then every fixture registered with `new-fixture`
+fn build_all(project_root: &Path, profile: Profile) -> Result<()> {
+    build_project(project_root, profile)?;
+    for package in scaffold::registered_fixtures(project_root)? {
+        build_package(project_root, &package, profile)?;
+        let artifact = profile.package_artifact(project_root, &package);
+        check_undefined_symbols(&artifact)?;
+        if let Profile::Release = profile {
+            check_program_size(&artifact, None)?;
+            footprint::check(&artifact, &scaffold::package_manifest(project_root, &package))?;
+        }
+        println!("Build complete: {}", artifact.display());
+    }
+    Ok(())
+}
+
+/// Build one package of the workspace for BPF
+fn build_package(project_root: &Path, package: &str, profile: Profile) -> Result<()> {
+    let mut cmd = cargo_build_bpf(project_root, package, profile)?;
+    run_command_prefixed(
+        &mut cmd,
+        &format!("build {package}"),
+        "build",
     )?;
-    println!("Build complete!");
+    Ok(())
+}
+
+/// The cargo command building `package` for BPF.
+/// Spelled out rather than going through the `build-bpf` alias, which is release-only.
+/// Fails if the toolchain isn't the pinned nightly.
+fn cargo_build_bpf(project_root: &Path, package: &str, profile: Profile) -> Result<Command> {
+    toolchain::check_active(project_root)?;
+    let nightly = toolchain::nightly(project_root)?;
+    println!("Building {package} ({}) with cargo +{nightly}...", profile.dir_name());
+    let mut cmd = Command::new("cargo");
+    cmd.arg(format!("+{nightly}"))
+        .args(["build", "--package", package])
+        .args(["--profile", profile.cargo_name(), "--target", BPF_TARGET])
+        .arg("-Zbuild-std=core,alloc")
+        .envs(toolchain_record_env(project_root))
+        .current_dir(project_root);
+    Ok(cmd)
+}
+
+/// Variables the fixture's build script embeds as the record of the toolchain that built it
+/// (see fixture/src/build_info.rs)
+const TOOLCHAIN_RECORD_ENV: [&str; 3] = ["U128_LLVM_COMMIT", "U128_LINKER_COMMIT", "SOURCE_DATE_EPOCH"];
+
+/// The values of [`TOOLCHAIN_RECORD_ENV`] for a build of this checkout: the selected LLVM and
+/// sbpf-linker commits, and `SOURCE_DATE_EPOCH` if set, or else the commit time of HEAD, so that
+/// rebuilding a commit embeds the same record. Whatever can't be determined is left unset.
+fn toolchain_record_env(project_root: &Path) -> Vec<(&'static str, String)> {
+    let epoch = std::env::var("SOURCE_DATE_EPOCH").ok().or_else(|| git_commit_time(project_root));
+    [
+        ("U128_LLVM_COMMIT", selected_commit("llvm", LLVM_BRANCH).ok()),
+        ("U128_LINKER_COMMIT", selected_commit("sbpf-linker", LINKER_BRANCH).ok()),
+        ("SOURCE_DATE_EPOCH", epoch),
+    ]
+    .into_iter()
+    .filter_map(|(name, value)| Some((name, value?)))
+    .collect()
+}
+
+/// `[package.metadata.size-limits]` of the fixture manifest
+#[derive(Deserialize)]
+struct SizeLimits {
+    /// Maximum size of the .text section
+    text: Option<u64>,
+    /// Maximum size of the whole ELF
+    elf: Option<u64>,
+}
+
+fn size_limits(project_root: &Path) -> Result<Option<SizeLimits>> {
+    #[derive(Deserialize)]
+    struct Manifest {
+        package: Package,
+    }
+    #[derive(Deserialize)]
+    struct Package {
+        metadata: Option<Metadata>,
+    }
+    #[derive(Deserialize)]
+    struct Metadata {
+        #[serde(rename = "size-limits")]
+        size_limits: Option<SizeLimits>,
+    }
+
+    let path = project_root.join(FIXTURE_DIR).join("Cargo.toml");
+    let contents = fs::read_to_string(&path).with_context(|| format!("failed to read {}", path.display()))?;
+    let manifest: Manifest =
+        toml::from_str(&contents).with_context(|| format!("failed to parse {}", path.display()))?;
+    Ok(manifest.package.metadata.and_then(|metadata| metadata.size_limits))
+}
+
+/// Largest ELF a program can be deployed with: the 10 MiB limit on account data, less the 45-byte
+/// header of the ProgramData account that holds the ELF
+const MAX_DEPLOY_SIZE: u64 = 10 * 1024 * 1024 - 45;
+
+/// Fail if the program is too large to deploy or has outgrown the size budget in the fixture
+/// manifest
+fn check_size_limits(project_root: &Path, program: &Path) -> Result<()> {
+    check_program_size(program, size_limits(project_root)?.as_ref())
+}
+
+/// Fail if `program` is too large to deploy or has outgrown `budget`, with the size of every
+/// section so the growth can be traced
+fn check_program_size(program: &Path, budget: Option<&SizeLimits>) -> Result<()> {
+    let mut exceeded = Vec::new();
+    for (name, size, limit) in program_sizes(program, budget)? {
+        match limit {
+            Some(limit) if size > limit => exceeded.push(format!("{name} is {size} bytes (limit {limit})")),
+            Some(limit) => println!("  {name}: {size} of {limit} bytes"),
+            None => {}
+        }
+    }
+    if !exceeded.is_empty() {
+        let hint = if exceeded.iter().any(|exceeded| exceeded.starts_with("deployable")) {
+            "The program can't be deployed; move ops into a fixture of their own or cut the expansion."
+        } else {
+            "Raise [package.metadata.size-limits] in the package's Cargo.toml if the growth is intended."
+        };
+        bail!(
+            "{} exceeds its size budget: {}. {hint}\n{}",
+            program.display(),
+            exceeded.join(", "),
+            section_breakdown(program)?
+        );
+    }
+    Ok(())
+}
+
+/// What was measured, its size, and its limit if there is one
+type SizeRow = (&'static str, u64, Option<u64>);
+
+/// Size of the program's .text and of the whole ELF against `budget`, and of the ELF against
+/// the deployment limit
+fn program_sizes(program: &Path, budget: Option<&SizeLimits>) -> Result<Vec<SizeRow>> {
+    let elf = fs::read(program).with_context(|| format!("failed to read {}", program.display()))?;
+    let text = elf::inspect(&elf)?.section(".text").map_or(0, |section| section.size);
+    Ok(vec![
+        (".text", text, budget.and_then(|budget| budget.text)),
+        ("ELF", elf.len() as u64, budget.and_then(|budget| budget.elf)),
+        ("deployable ELF", elf.len() as u64, Some(MAX_DEPLOY_SIZE)),
+    ])
+}
+
+/// The program's sections from largest to smallest, with their share of the file
+fn section_breakdown(program: &Path) -> Result<String> {
+    let elf = fs::read(program).with_context(|| format!("failed to read {}", program.display()))?;
+    let mut sections = elf::inspect(&elf)?.sections;
+    sections.retain(|section| section.size > 0);
+    sections.sort_by(|a, b| b.size.cmp(&a.size).then_with(|| a.name.cmp(&b.name)));
+    let mut breakdown = format!("{:<24} {:>10} {:>7}\n", "section", "bytes", "share");
+    let total = elf.len() as u64;
+    let in_sections: u64 = sections.iter().map(|section| section.size).sum();
+    let rows = sections
+        .iter()
+        .map(|section| (section.name.as_str(), section.size))
+        .chain([("(headers and padding)", total.saturating_sub(in_sections))]);
+    for (name, size) in rows {
+        let share = 100.0 * size as f64 / total as f64;
+        breakdown.push_str(&format!("{name:<24} {size:>10} {share:>6.1}%\n"));
+    }
+    Ok(breakdown)
+}
+
+/// Markdown table of the program's sizes against the budget, for `build --summary-md`
+fn size_summary(project_root: &Path, program: &Path) -> Result<String> {
+    let rows: Vec<_> = program_sizes(program, size_limits(project_root)?.as_ref())?
+        .into_iter()
+        .map(|(name, size, limit)| {
+            let (limit, headroom) = match limit {
+                Some(limit) => (limit.to_string(), format!("{:+}", limit as i64 - size as i64)),
+                None => ("-".to_string(), "-".to_string()),
+            };
+            vec![name.to_string(), size.to_string(), limit, headroom]
+        })
+        .collect();
+    let name = program.file_name().unwrap_or_default().to_string_lossy();
+    Ok(format!(
+        "### Program size\n\n`{name}` against `[package.metadata.size-limits]` and the deployment limit, in \
+         bytes\n\n{}",
+        summary::table(&["", "size", "limit", "headroom"], &rows)
+    ))
+}
+
+/// Fail if linking left references to anything other than runtime syscalls, e.g. an i128
+/// libcall nothing provided
+fn check_undefined_symbols(program: &Path) -> Result<()> {
+    let elf = fs::read(program).with_context(|| format!("failed to read {}", program.display()))?;
+    let unresolved = elf::inspect(&elf)?.unresolved_symbols().join(", ");
+    if !unresolved.is_empty() {
+        bail!(
+            "{} has undefined symbols that are not syscalls: {unresolved}. \
+             The loader will reject it; check that the linker provides them.",
+            program.display()
+        );
+    }
+    Ok(())
+}
+
+fn inspect_program(program: &Path) -> Result<()> {
+    let elf = fs::read(program).with_context(|| {
+        format!(
+            "failed to read {}. Run `cargo xtask build` first.",
+            program.display()
+        )
+    })?;
+    let info = elf::inspect(&elf)?;
+
+    println!("Toolchain: {}", elf::toolchain_record(&elf).unwrap_or("(no record)"));
+    println!();
+    println!("Sections:");
+    for section in info.sections.iter().filter(|section| !section.name.is_empty()) {
+        println!("  {:<20} {:#010x} {:>8} bytes", section.name, section.address, section.size);
+    }
+    println!();
+    println!("Functions:");
+    for function in &info.functions {
+        println!(
+            "  {:<32} {:#010x} {:>6} bytes {:>6} instructions",
+            function.name, function.address, function.size, function.instruction_count
+        );
+    }
+    println!();
+    println!("Symbols:");
+    for symbol in &info.symbols {
+        let kind = if symbol.is_function { "func" } else { "data" };
+        if symbol.defined {
+            println!("  {:<32} {:#010x} {:>6} bytes {kind}", symbol.name, symbol.address, symbol.size);
+        } else {
+            println!("  {:<32} {:>10}", symbol.name, "undefined");
+        }
+    }
+    println!();
+    println!("Dynamic relocations:");
+    if info.relocations.is_empty() {
+        println!("  (none)");
+    }
+    for relocation in &info.relocations {
+        println!(
+            "  {:#010x} type {:<3} {}",
+            relocation.offset,
+            relocation.r_type,
+            relocation.symbol.as_deref().unwrap_or("-")
+        );
+    }
+    Ok(())
+}
+
+fn check_libcalls(program: &Path) -> Result<()> {
+    let elf = fs::read(program).with_context(|| {
+        format!(
+            "failed to read {}. Run `cargo xtask build` first.",
+            program.display()
+        )
+    })?;
+    let info = elf::inspect(&elf)?;
+    let libcalls = info.libcalls();
+
+    if libcalls.is_empty() {
+        println!("No i128 libcalls referenced: 128-bit arithmetic is expanded inline");
+        return Ok(());
+    }
+
+    let mut unresolved = Vec::new();
+    for libcall in &libcalls {
+        let status = if libcall.defined { "resolved" } else { "UNRESOLVED" };
+        println!("{:<14} {:>3} relocations  {status}", libcall.name, libcall.relocations.len());
+        for relocation in &libcall.relocations {
+            println!("  {:#010x} type {}", relocation.offset, relocation.r_type);
+        }
+        if !libcall.defined {
+            unresolved.push(libcall.name);
+        }
+    }
+    if !unresolved.is_empty() {
+        bail!(
+            "the linker left {} unresolved; the loader will reject the program",
+            unresolved.join(", ")
+        );
+    }
+    println!("i128 arithmetic goes through the libcall path");
+    Ok(())
+}
+
+fn report_relocations(program: &Path, sbpf_version: Option<elf::SbpfVersion>) -> Result<()> {
+    let elf = fs::read(program).with_context(|| {
+        format!(
+            "failed to read {}. Run `cargo xtask build` first.",
+            program.display()
+        )
+    })?;
+    let info = elf::inspect(&elf)?;
+    let version = match (sbpf_version, info.sbpf_version()) {
+        (Some(version), _) | (None, Some(version)) => version,
+        (None, None) => bail!("unknown SBPF e_flags {:#x}; pass --sbpf-version", info.e_flags),
+    };
+
+    println!("Dynamic sections (checked against the SBPF {version:?} loader):");
+    for section in info
+        .sections
+        .iter()
+        .filter(|section| section.name.starts_with(".dyn") || section.name.starts_with(".rel"))
+    {
+        println!("  {:<12} {:>8} bytes", section.name, section.size);
+    }
+    println!();
+
+    println!("{:<12} {:<24} {:<32} status", "offset", "type", "target");
+    let mut rejected = 0;
+    for relocation in &info.relocations {
+        let status = if version.accepts_relocation(relocation.r_type) {
+            "ok"
+        } else {
+            rejected += 1;
+            "REJECTED"
+        };
+        println!(
+            "{:<#12x} {:<24} {:<32} {status}",
+            relocation.offset,
+            format!("{} ({})", elf::relocation_type_name(relocation.r_type), relocation.r_type),
+            relocation.symbol.as_deref().unwrap_or("-")
+        );
+    }
+    println!();
+    println!("{} relocations, {rejected} rejected by the SBPF {version:?} loader", info.relocations.len());
+    if rejected > 0 {
+        bail!("{rejected} relocations would make the SBPF {version:?} loader reject {}", program.display());
+    }
+    Ok(())
+}
+
+/// Run `cargo test` with Mollusk's fixture ejection turned on, so each
+/// `process_and_validate_instruction` call is written to `out` as a `.fix` file
+fn eject_mollusk_fixtures(project_root: &Path, out: &Path, json: bool, firedancer: bool) -> Result<()> {
+    fs::create_dir_all(out)?;
+    let firedancer_out = out.join("firedancer");
+    let mut cmd = Command::new("cargo");
+    cmd.arg("test").env("EJECT_FUZZ_FIXTURES", out).current_dir(project_root);
+    if json {
+        cmd.env("EJECT_FUZZ_FIXTURES_JSON", out);
+    }
+    if firedancer {
+        fs::create_dir_all(&firedancer_out)?;
+        cmd.env("EJECT_FUZZ_FIXTURES_FD", &firedancer_out);
+    }
+    run_command_prefixed(&mut cmd, "run tests with fixture ejection", "test")?;
+
+    let count_fixtures = |dir: &Path| -> Result<usize> {
+        Ok(fs::read_dir(dir)?
+            .filter_map(|entry| entry.ok())
+            .filter(|entry| entry.path().extension().is_some_and(|ext| ext == "fix"))
+            .count())
+    };
+    println!("Wrote {} Mollusk fixtures to {}", count_fixtures(out)?, out.display());
+    if firedancer {
+        println!(
+            "Wrote {} Firedancer instruction fixtures to {}",
+            count_fixtures(&firedancer_out)?,
+            firedancer_out.display()
+        );
+    }
+    Ok(())
+}
+
+fn run_program(program: &Path, data: &str, trace: Option<&Path>) -> Result<()> {
+    let elf = fs::read(program).with_context(|| {
+        format!(
+            "failed to read {}. Run `cargo xtask build` first.",
+            program.display()
+        )
+    })?;
+    let data = vm::decode_hex(data)?;
+    let outcome = vm::execute(&elf, &data, trace)?;
+
+    for log in &outcome.logs {
+        println!("{log}");
+    }
+    match &outcome.result {
+        Ok(code) => match errors::describe(*code) {
+            Some(error) => println!("Return code: {code} ({code:#x}), {error}"),
+            None => println!("Return code: {code} ({code:#x})"),
+        },
+        Err(err) => println!("Program failed: {err}"),
+    }
+    println!("Instructions executed: {}", outcome.instruction_count);
+    if let Some(trace) = trace {
+        println!("Trace written to {}", trace.display());
+    }
+    if !outcome.return_data.is_empty() {
+        println!("Return data ({} bytes):", outcome.return_data.len());
+        for (i, chunk) in outcome.return_data.chunks(16).enumerate() {
+            if let Ok(bytes) = <[u8; 16]>::try_from(chunk) {
+                let value = u128::from_le_bytes(bytes);
+                println!("  u128[{i}] = {value} ({value:#x})");
+            } else {
+                println!("  trailing bytes: {chunk:02x?}");
+            }
+        }
+    }
+
+    if outcome.result.is_err() {
+        bail!("program execution failed");
+    }
+    Ok(())
+}
+
+/// Attempts for network-bound steps before giving up
+const NETWORK_ATTEMPTS: u32 = 4;
+/// Delay before the first retry; doubled after every failed attempt
+const NETWORK_BACKOFF: Duration = Duration::from_secs(5);
+
+/// Run a network-bound step, retrying with exponential backoff on failure
+fn retry_network<T>(description: &str, mut step: impl FnMut() -> Result<T>) -> Result<T> {
+    let mut delay = NETWORK_BACKOFF;
+    for attempt in 1..NETWORK_ATTEMPTS {
+        match step() {
+            Ok(value) => return Ok(value),
+            Err(err) => {
+                println!(
+                    "  {description} failed (attempt {attempt}/{NETWORK_ATTEMPTS}): {err:#}; retrying in {}s",
+                    delay.as_secs()
+                );
+                thread::sleep(delay);
+                delay *= 2;
+            }
+        }
+    }
+    step().with_context(|| {
+        format!("{description} failed {NETWORK_ATTEMPTS} times; this looks like a network problem")
+    })
+}
+
+/// Commit `branch` of `repo` currently points at, or `None` if the branch doesn't exist
+fn resolve_remote_branch(repo: &str, branch: &str) -> Result<Option<String>> {
+    retry_network(&format!("look up {branch} on {repo}"), || {
+        let output = Command::new("git")
+            .args(["ls-remote", "--exit-code", "--heads", repo, branch])
+            .output()
+            .context("failed to run git ls-remote")?;
+        match output.status.code() {
+            Some(0) => {
+                let stdout = String::from_utf8_lossy(&output.stdout);
+                let commit = stdout
+                    .split_whitespace()
+                    .next()
+                    .ok_or_else(|| anyhow!("git ls-remote printed no commit for {branch}"))?;
+                Ok(Some(commit.to_string()))
+            }
+            // --exit-code reports "no matching refs" as 2; anything else is a transport failure
+            Some(2) => Ok(None),
+            _ => bail!("{}", String::from_utf8_lossy(&output.stderr).trim()),
+        }
+    })
+}
+
+/// Shallow-clone `branch` of `repo` into `dest`, retrying transient failures, and check the
+/// checkout is at `commit` so the directory it lands in is keyed correctly
+fn clone_repo(repo: &str, branch: &str, commit: &str, dest: &Path, prefix: &str) -> Result<()> {
+    retry_network(&format!("clone {repo}"), || {
+        // A failed clone can leave a partial checkout behind that would make git refuse to retry
+        if dest.exists() {
+            fs::remove_dir_all(dest)?;
+        }
+        run_command_prefixed(
+            Command::new("git")
+                .args(["clone", "--depth", GIT_DEPTH, "--branch", branch, repo])
+                .arg(dest),
+            &format!("clone {repo}"),
+            prefix,
+        )
+    })?;
+
+    let head = git_head(dest).with_context(|| format!("failed to read HEAD of {}", dest.display()))?;
+    if head != commit {
+        fs::remove_dir_all(dest)?;
+        bail!("{branch} moved from {commit} to {head} while cloning; re-run to pick up the new commit");
+    }
+    Ok(())
+}
+
+/// Commit checked out in `repo`, if it is a git checkout
+fn git_head(repo: &Path) -> Option<String> {
+    let output = Command::new("git")
+        .args(["rev-parse", "HEAD"])
+        .current_dir(repo)
+        .output()
+        .ok()?;
+    output
+        .status
+        .success()
+        .then(|| String::from_utf8_lossy(&output.stdout).trim().to_string())
+}
+
+/// Commit time of HEAD in `repo`, in seconds since the Unix epoch
+fn git_commit_time(repo: &Path) -> Option<String> {
+    let output = Command::new("git")
+        .args(["log", "-1", "--format=%ct"])
+        .current_dir(repo)
+        .output()
+        .ok()?;
+    output
+        .status
+        .success()
+        .then(|| String::from_utf8_lossy(&output.stdout).trim().to_string())
+}
+
+/// How long a streamed command may stay silent before a "still running" line is printed
+const HEARTBEAT: Duration = Duration::from_secs(30);
+
+/// Run `cmd`, echoing its stdout and stderr line by line as `[prefix mm:ss] line` so that
+/// long cmake/cargo invocations show progress instead of appearing hung
+fn stream_command(cmd: &mut Command, prefix: &str) -> std::io::Result<ExitStatus> {
+    let start = Instant::now();
+    let mut child = cmd.stdout(Stdio::piped()).stderr(Stdio::piped()).spawn()?;
+
+    let (tx, rx) = mpsc::channel();
+    let stdout = child.stdout.take().map(|out| Box::new(out) as Box<dyn Read + Send>);
+    let stderr = child.stderr.take().map(|err| Box::new(err) as Box<dyn Read + Send>);
+    let readers: Vec<_> = [stdout, stderr]
+        .into_iter()
+        .flatten()
+        .map(|stream| {
+            let tx = tx.clone();
+            thread::spawn(move || {
+                for line in BufReader::new(stream).lines().map_while(Result::ok) {
+                    if tx.send(line).is_err() {
+                        break;
+                    }
+                }
+            })
+        })
+        .collect();
+    drop(tx);
+
+    let elapsed = |start: Instant| {
+        let secs = start.elapsed().as_secs();
+        format!("{:02}:{:02}", secs / 60, secs % 60)
+    };
+    loop {
+        match rx.recv_timeout(HEARTBEAT) {
+            Ok(line) => println!("[{prefix} {}] {line}", elapsed(start)),
+            Err(RecvTimeoutError::Timeout) => println!("[{prefix} {}] still running...", elapsed(start)),
+            Err(RecvTimeoutError::Disconnected) => break,
+        }
+    }
+    for reader in readers {
+        let _ = reader.join();
+    }
+
+    let status = child.wait()?;
+    println!("[{prefix} {}] finished ({status})", elapsed(start));
+    Ok(status)
+}
+
+/// Like `run_command`, but streams the output with a `[prefix]` and elapsed time
+fn run_command_prefixed(cmd: &mut Command, description: &str, prefix: &str) -> Result<()> {
+    let status = stream_command(cmd, prefix)
+        .with_context(|| format!("failed to run: {}", description))?;
+
+    if !status.success() {
+        bail!("command failed: {}", description);
+    }
+
     Ok(())
 }
 
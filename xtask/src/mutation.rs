@@ -0,0 +1,70 @@
+//! Bit-flip mutation of the conformance vectors: every input the VM already agrees with the
+//! reference on is perturbed one operand bit at a time, and each neighbor is checked too. The
+//! curated boundary values sit on the edges where lowerings go wrong; their one-bit neighbors
+//! cover the carries, borrows, and normalization shifts just beside them for the price of a VM
+//! run each.
+
+use crate::{fixtures, ops, vm};
+use anyhow::{bail, Result};
+use std::collections::HashSet;
+use std::path::Path;
+
+/// Flip each operand bit of every passing vector of the selected ops, in turn, and check the VM
+/// result on each neighbor against the native reference
+pub fn bit_flip(program: &Path, only: Option<&str>) -> Result<()> {
+    let elf = fixtures::read_program(program)?;
+    if only.is_some_and(|name| !ops::OPS.iter().any(|op| op.name == name)) {
+        let names: Vec<_> = ops::OPS.iter().map(|op| op.name).collect();
+        bail!("no op named {} (available: {})", only.unwrap_or_default(), names.join(", "));
+    }
+    let matrix = fixtures::run_matrix(&elf)?;
+    let loaded = vm::LoadedProgram::load(&elf)?;
+
+    println!("{:<24} {:>8} {:>9} {:>9} {:>11}", "op", "vectors", "mutants", "skipped", "mismatches");
+    let mut failed = Vec::new();
+    for op in ops::OPS.iter().filter(|op| only.is_none_or(|name| op.name == name)) {
+        let vectors: Vec<_> = matrix.cases.iter().filter(|case| case.op.name == op.name).collect();
+        // Neighbors of different vectors often coincide, like the flips of 0 and of 1
+        let mut seen = HashSet::new();
+        let (mut mutants, mut skipped, mut mismatches) = (0, 0, Vec::new());
+        for case in &vectors {
+            for operand in 0..case.operands.len() {
+                for bit in 0..u128::BITS {
+                    let mut operands = case.operands.clone();
+                    operands[operand] ^= 1 << bit;
+                    if !seen.insert(operands.clone()) {
+                        continue;
+                    }
+                    let Some(expected) = op.reference(&operands) else {
+                        skipped += 1;
+                        continue;
+                    };
+                    mutants += 1;
+                    let outcome = loaded.execute(&op.encode(&operands))?;
+                    if outcome.result.as_ref().ok() != Some(&expected) {
+                        mismatches.push(format!(
+                            "{operands:#x?} (bit {bit} of operand {operand} flipped): expected {expected:#x}, got {:?}",
+                            outcome.result
+                        ));
+                    }
+                }
+            }
+        }
+        println!(
+            "{:<24} {:>8} {mutants:>9} {skipped:>9} {:>11}",
+            op.name,
+            vectors.len(),
+            mismatches.len()
+        );
+        if let Some(first) = mismatches.first() {
+            println!("  first mismatch: {first}");
+            failed.push(op.name);
+        }
+    }
+    println!();
+    if !failed.is_empty() {
+        bail!("the VM disagrees with the reference on one-bit neighbors for {}", failed.join(", "));
+    }
+    println!("The VM agrees with the reference on every one-bit neighbor with a defined result");
+    matrix.check()
+}
@@ -0,0 +1,638 @@
+//! Host-side description of the operations the fixture program implements.
+
+use u128_bpf_fixture::instruction::{BatchedOp, FixtureInstruction, Lowering, Region, Rounding};
+use u128_bpf_fixture::orderbook::Order;
+
+/// Operand values that sit on the edges of the 64- and 128-bit ranges
+pub const BOUNDARY_VALUES: &[u128] = &[
+    0,
+    1,
+    2,
+    u64::MAX as u128,
+    u64::MAX as u128 + 1,
+    i128::MAX as u128,
+    i128::MIN as u128,
+    u128::MAX - 1,
+    u128::MAX,
+];
+
+/// Operations per invocation of the batch ops in the matrix
+pub const BATCH_COUNT: u32 = 16;
+
+/// u128 values the copy ops in the matrix move per invocation
+pub const COPY_COUNT: u8 = 16;
+
+/// Rate and periods of the interest accrual op in the matrix: 5% compounded 64 times
+pub const INTEREST_RATE_BPS: u16 = 500;
+pub const INTEREST_PERIODS: u32 = 64;
+
+/// Periods of the compound factor op in the matrix, whose operand is the Q64.64 rate
+pub const COMPOUND_PERIODS: u32 = 365;
+
+/// Liquidity of the sqrt-price step ops in the matrix, whose operands are the price and the
+/// amount in
+pub const SQRT_PRICE_LIQUIDITY: u64 = 1 << 40;
+
+/// Fee of the constant-product op in the matrix, whose operands are the reserves and the amount in
+pub const CONSTANT_PRODUCT_FEE_BPS: u16 = 30;
+
+/// An operation exposed by the fixture, over operands given as a slice of `arity` values
+pub struct Op {
+    pub name: &'static str,
+    /// The u128 operation the op measures, shared by its lowerings
+    pub operation: &'static str,
+    pub lowering: Lowering,
+    pub arity: usize,
+    /// compiler-rt routines LLVM lowers the op's 128-bit arithmetic to when the target has no
+    /// native support for it
+    pub builtins: &'static [&'static str],
+    pub instruction: fn(&[u128]) -> FixtureInstruction,
+}
+
+impl Op {
+    /// Instruction data the program expects for `operands`
+    pub fn encode(&self, operands: &[u128]) -> Vec<u8> {
+        (self.instruction)(operands).pack()
+    }
+
+    /// Expected program return code, or `None` when the result is undefined
+    /// (e.g. the operation overflows and hits the abort path)
+    pub fn reference(&self, operands: &[u128]) -> Option<u64> {
+        (self.instruction)(operands).expected_return()
+    }
+
+    /// Every combination of boundary values for this operation's operands
+    pub fn boundary_inputs(&self) -> Vec<Vec<u128>> {
+        let mut inputs = vec![Vec::new()];
+        for _ in 0..self.arity {
+            inputs = inputs
+                .into_iter()
+                .flat_map(|prefix| {
+                    BOUNDARY_VALUES.iter().map(move |&value| {
+                        let mut operands = prefix.clone();
+                        operands.push(value);
+                        operands
+                    })
+                })
+                .collect();
+        }
+        inputs
+    }
+}
+
+pub const OPS: &[Op] = &[
+    Op {
+        name: "mul_loop",
+        operation: "mul_loop",
+        lowering: Lowering::Native,
+        arity: 2,
+        builtins: &["__multi3"],
+        instruction: |operands| FixtureInstruction::MulLoop {
+            a: operands[0],
+            b: operands[1],
+        },
+    },
+    Op {
+        name: "mul_native",
+        operation: "mul",
+        lowering: Lowering::Native,
+        arity: 2,
+        builtins: &["__multi3"],
+        instruction: |operands| FixtureInstruction::Mul {
+            a: operands[0],
+            b: operands[1],
+            lowering: Lowering::Native,
+        },
+    },
+    Op {
+        name: "mul_libcall",
+        operation: "mul",
+        lowering: Lowering::Libcall,
+        arity: 2,
+        builtins: &["__multi3"],
+        instruction: |operands| FixtureInstruction::Mul {
+            a: operands[0],
+            b: operands[1],
+            lowering: Lowering::Libcall,
+        },
+    },
+    Op {
+        name: "mul_limbs",
+        operation: "mul",
+        lowering: Lowering::Limbs,
+        arity: 2,
+        builtins: &[],
+        instruction: |operands| FixtureInstruction::Mul {
+            a: operands[0],
+            b: operands[1],
+            lowering: Lowering::Limbs,
+        },
+    },
+    Op {
+        name: "mul_pair",
+        operation: "mul",
+        lowering: Lowering::Pair,
+        arity: 2,
+        builtins: &[],
+        instruction: |operands| FixtureInstruction::Mul {
+            a: operands[0],
+            b: operands[1],
+            lowering: Lowering::Pair,
+        },
+    },
+    Op {
+        name: "div_native",
+        operation: "div",
+        lowering: Lowering::Native,
+        arity: 2,
+        builtins: &["__udivti3"],
+        instruction: |operands| FixtureInstruction::Div {
+            a: operands[0],
+            b: operands[1],
+            lowering: Lowering::Native,
+        },
+    },
+    Op {
+        name: "div_libcall",
+        operation: "div",
+        lowering: Lowering::Libcall,
+        arity: 2,
+        builtins: &["__udivti3"],
+        instruction: |operands| FixtureInstruction::Div {
+            a: operands[0],
+            b: operands[1],
+            lowering: Lowering::Libcall,
+        },
+    },
+    Op {
+        name: "div_pair",
+        operation: "div",
+        lowering: Lowering::Pair,
+        arity: 2,
+        builtins: &[],
+        instruction: |operands| FixtureInstruction::Div {
+            a: operands[0],
+            b: operands[1],
+            lowering: Lowering::Pair,
+        },
+    },
+    Op {
+        name: "div_floor",
+        operation: "div_floor",
+        lowering: Lowering::Native,
+        arity: 2,
+        builtins: &["__udivti3", "__umodti3"],
+        instruction: |operands| FixtureInstruction::DivRound {
+            a: operands[0],
+            b: operands[1],
+            rounding: Rounding::Floor,
+        },
+    },
+    Op {
+        name: "div_ceil",
+        operation: "div_ceil",
+        lowering: Lowering::Native,
+        arity: 2,
+        builtins: &["__udivti3", "__umodti3"],
+        instruction: |operands| FixtureInstruction::DivRound {
+            a: operands[0],
+            b: operands[1],
+            rounding: Rounding::Ceil,
+        },
+    },
+    Op {
+        name: "div_round",
+        operation: "div_round",
+        lowering: Lowering::Native,
+        arity: 2,
+        builtins: &["__udivti3", "__umodti3"],
+        instruction: |operands| FixtureInstruction::DivRound {
+            a: operands[0],
+            b: operands[1],
+            rounding: Rounding::Nearest,
+        },
+    },
+    Op {
+        name: "mul_div_floor",
+        operation: "mul_div_floor",
+        lowering: Lowering::Native,
+        arity: 3,
+        builtins: &["__multi3"],
+        instruction: |operands| FixtureInstruction::MulDiv {
+            a: operands[0],
+            b: operands[1],
+            c: operands[2],
+            ceil: false,
+        },
+    },
+    Op {
+        name: "mul_div_ceil",
+        operation: "mul_div_ceil",
+        lowering: Lowering::Native,
+        arity: 3,
+        builtins: &["__multi3"],
+        instruction: |operands| FixtureInstruction::MulDiv {
+            a: operands[0],
+            b: operands[1],
+            c: operands[2],
+            ceil: true,
+        },
+    },
+    Op {
+        name: "accrue_interest",
+        operation: "accrue_interest",
+        lowering: Lowering::Native,
+        arity: 1,
+        builtins: &["__multi3"],
+        instruction: |operands| FixtureInstruction::AccrueInterest {
+            principal: operands[0],
+            rate_bps: INTEREST_RATE_BPS,
+            periods: INTEREST_PERIODS,
+        },
+    },
+    Op {
+        name: "compound_factor",
+        operation: "compound_factor",
+        lowering: Lowering::Native,
+        arity: 1,
+        builtins: &["__multi3"],
+        instruction: |operands| FixtureInstruction::CompoundFactor {
+            rate: operands[0],
+            periods: COMPOUND_PERIODS,
+        },
+    },
+    Op {
+        name: "abi_torture",
+        operation: "abi_torture",
+        lowering: Lowering::Native,
+        arity: 1,
+        builtins: &["__multi3"],
+        instruction: |operands| FixtureInstruction::AbiTorture { seed: operands[0] },
+    },
+    Op {
+        name: "self_test",
+        operation: "self_test",
+        lowering: Lowering::Native,
+        arity: 0,
+        builtins: &["__multi3", "__udivti3", "__umodti3", "__ashlti3", "__lshrti3"],
+        instruction: |_| FixtureInstruction::SelfTest,
+    },
+    Op {
+        name: "sqrt_price_step_a_to_b",
+        operation: "sqrt_price_step_a_to_b",
+        lowering: Lowering::Native,
+        arity: 2,
+        builtins: &["__multi3", "__udivti3"],
+        instruction: |operands| FixtureInstruction::SqrtPriceStep {
+            sqrt_price: operands[0],
+            liquidity: SQRT_PRICE_LIQUIDITY,
+            amount_in: operands[1],
+            a_to_b: true,
+        },
+    },
+    Op {
+        name: "sqrt_price_step_b_to_a",
+        operation: "sqrt_price_step_b_to_a",
+        lowering: Lowering::Native,
+        arity: 2,
+        builtins: &["__multi3", "__udivti3"],
+        instruction: |operands| FixtureInstruction::SqrtPriceStep {
+            sqrt_price: operands[0],
+            liquidity: SQRT_PRICE_LIQUIDITY,
+            amount_in: operands[1],
+            a_to_b: false,
+        },
+    },
+    Op {
+        name: "constant_product",
+        operation: "constant_product",
+        lowering: Lowering::Native,
+        arity: 3,
+        builtins: &["__multi3", "__udivti3"],
+        instruction: |operands| FixtureInstruction::ConstantProduct {
+            reserve_in: operands[0],
+            reserve_out: operands[1],
+            amount_in: operands[2],
+            fee_bps: CONSTANT_PRODUCT_FEE_BPS,
+        },
+    },
+    Op {
+        name: "notional",
+        operation: "notional",
+        lowering: Lowering::Native,
+        arity: 2,
+        builtins: &["__multi3"],
+        // Each operand is an order, the price in its low half and the size in its high half
+        instruction: |operands| {
+            let orders: Vec<Order> = operands.iter().map(|&order| order.into()).collect();
+            FixtureInstruction::notional(&orders).expect("one order per operand")
+        },
+    },
+    Op {
+        name: "rem_native",
+        operation: "rem",
+        lowering: Lowering::Native,
+        arity: 2,
+        builtins: &["__umodti3"],
+        instruction: |operands| FixtureInstruction::Rem {
+            a: operands[0],
+            b: operands[1],
+            lowering: Lowering::Native,
+        },
+    },
+    Op {
+        name: "rem_libcall",
+        operation: "rem",
+        lowering: Lowering::Libcall,
+        arity: 2,
+        builtins: &["__umodti3"],
+        instruction: |operands| FixtureInstruction::Rem {
+            a: operands[0],
+            b: operands[1],
+            lowering: Lowering::Libcall,
+        },
+    },
+    Op {
+        name: "rem_pair",
+        operation: "rem",
+        lowering: Lowering::Pair,
+        arity: 2,
+        builtins: &[],
+        instruction: |operands| FixtureInstruction::Rem {
+            a: operands[0],
+            b: operands[1],
+            lowering: Lowering::Pair,
+        },
+    },
+    Op {
+        name: "shl_native",
+        operation: "shl",
+        lowering: Lowering::Native,
+        arity: 2,
+        builtins: &["__ashlti3"],
+        instruction: |operands| FixtureInstruction::Shl {
+            a: operands[0],
+            b: operands[1],
+            lowering: Lowering::Native,
+        },
+    },
+    Op {
+        name: "shl_libcall",
+        operation: "shl",
+        lowering: Lowering::Libcall,
+        arity: 2,
+        builtins: &["__ashlti3"],
+        instruction: |operands| FixtureInstruction::Shl {
+            a: operands[0],
+            b: operands[1],
+            lowering: Lowering::Libcall,
+        },
+    },
+    Op {
+        name: "shl_pair",
+        operation: "shl",
+        lowering: Lowering::Pair,
+        arity: 2,
+        builtins: &[],
+        instruction: |operands| FixtureInstruction::Shl {
+            a: operands[0],
+            b: operands[1],
+            lowering: Lowering::Pair,
+        },
+    },
+    Op {
+        name: "shr_native",
+        operation: "shr",
+        lowering: Lowering::Native,
+        arity: 2,
+        builtins: &["__lshrti3"],
+        instruction: |operands| FixtureInstruction::Shr {
+            a: operands[0],
+            b: operands[1],
+            lowering: Lowering::Native,
+        },
+    },
+    Op {
+        name: "shr_libcall",
+        operation: "shr",
+        lowering: Lowering::Libcall,
+        arity: 2,
+        builtins: &["__lshrti3"],
+        instruction: |operands| FixtureInstruction::Shr {
+            a: operands[0],
+            b: operands[1],
+            lowering: Lowering::Libcall,
+        },
+    },
+    Op {
+        name: "shr_pair",
+        operation: "shr",
+        lowering: Lowering::Pair,
+        arity: 2,
+        builtins: &[],
+        instruction: |operands| FixtureInstruction::Shr {
+            a: operands[0],
+            b: operands[1],
+            lowering: Lowering::Pair,
+        },
+    },
+    Op {
+        name: "cmp_native",
+        operation: "cmp",
+        lowering: Lowering::Native,
+        arity: 2,
+        builtins: &[],
+        instruction: |operands| FixtureInstruction::Cmp {
+            a: operands[0],
+            b: operands[1],
+            lowering: Lowering::Native,
+        },
+    },
+    Op {
+        name: "cmp_pair",
+        operation: "cmp",
+        lowering: Lowering::Pair,
+        arity: 2,
+        builtins: &[],
+        instruction: |operands| FixtureInstruction::Cmp {
+            a: operands[0],
+            b: operands[1],
+            lowering: Lowering::Pair,
+        },
+    },
+    Op {
+        name: "add_native",
+        operation: "add",
+        lowering: Lowering::Native,
+        arity: 2,
+        builtins: &[],
+        instruction: |operands| FixtureInstruction::Add {
+            a: operands[0],
+            b: operands[1],
+            lowering: Lowering::Native,
+        },
+    },
+    Op {
+        name: "add_pair",
+        operation: "add",
+        lowering: Lowering::Pair,
+        arity: 2,
+        builtins: &[],
+        instruction: |operands| FixtureInstruction::Add {
+            a: operands[0],
+            b: operands[1],
+            lowering: Lowering::Pair,
+        },
+    },
+    Op {
+        name: "sub_native",
+        operation: "sub",
+        lowering: Lowering::Native,
+        arity: 2,
+        builtins: &[],
+        instruction: |operands| FixtureInstruction::Sub {
+            a: operands[0],
+            b: operands[1],
+            lowering: Lowering::Native,
+        },
+    },
+    Op {
+        name: "sub_pair",
+        operation: "sub",
+        lowering: Lowering::Pair,
+        arity: 2,
+        builtins: &[],
+        instruction: |operands| FixtureInstruction::Sub {
+            a: operands[0],
+            b: operands[1],
+            lowering: Lowering::Pair,
+        },
+    },
+    Op {
+        name: "batch_mul",
+        operation: "batch_mul",
+        lowering: Lowering::Native,
+        arity: 2,
+        builtins: &["__multi3"],
+        instruction: |operands| FixtureInstruction::Batch {
+            op: BatchedOp::Mul,
+            count: BATCH_COUNT,
+            a: operands[0],
+            b: operands[1],
+            lowering: Lowering::Native,
+        },
+    },
+    Op {
+        name: "batch_div",
+        operation: "batch_div",
+        lowering: Lowering::Native,
+        arity: 2,
+        builtins: &["__udivti3"],
+        instruction: |operands| FixtureInstruction::Batch {
+            op: BatchedOp::Div,
+            count: BATCH_COUNT,
+            a: operands[0],
+            b: operands[1],
+            lowering: Lowering::Native,
+        },
+    },
+    Op {
+        name: "return_data",
+        operation: "return_data",
+        lowering: Lowering::Native,
+        arity: 1,
+        builtins: &[],
+        instruction: |operands| FixtureInstruction::ReturnData { a: operands[0] },
+    },
+    Op {
+        name: "memcmp",
+        operation: "memcmp",
+        lowering: Lowering::Native,
+        arity: 2,
+        builtins: &[],
+        instruction: |operands| FixtureInstruction::MemCmp {
+            a: operands[0],
+            b: operands[1],
+        },
+    },
+    Op {
+        name: "decode_endian",
+        operation: "decode_endian",
+        lowering: Lowering::Native,
+        arity: 1,
+        builtins: &[],
+        instruction: |operands| FixtureInstruction::DecodeEndian { a: operands[0] },
+    },
+    Op {
+        name: "narrow_u64",
+        operation: "narrow_u64",
+        lowering: Lowering::Native,
+        arity: 1,
+        builtins: &[],
+        instruction: |operands| FixtureInstruction::Narrow {
+            a: operands[0],
+            signed: false,
+        },
+    },
+    Op {
+        name: "narrow_i64",
+        operation: "narrow_i64",
+        lowering: Lowering::Native,
+        arity: 1,
+        builtins: &[],
+        instruction: |operands| FixtureInstruction::Narrow {
+            a: operands[0],
+            signed: true,
+        },
+    },
+    Op {
+        name: "memcpy_stack_to_heap",
+        operation: "memcpy_stack_to_heap",
+        lowering: Lowering::Native,
+        arity: 1,
+        builtins: &[],
+        instruction: |operands| FixtureInstruction::Memcpy {
+            src: Region::Stack,
+            dst: Region::Heap,
+            count: COPY_COUNT,
+            a: operands[0],
+        },
+    },
+    Op {
+        name: "memcpy_heap_to_stack",
+        operation: "memcpy_heap_to_stack",
+        lowering: Lowering::Native,
+        arity: 1,
+        builtins: &[],
+        instruction: |operands| FixtureInstruction::Memcpy {
+            src: Region::Heap,
+            dst: Region::Stack,
+            count: COPY_COUNT,
+            a: operands[0],
+        },
+    },
+    Op {
+        name: "memmove_stack",
+        operation: "memmove_stack",
+        lowering: Lowering::Native,
+        arity: 1,
+        builtins: &[],
+        instruction: |operands| FixtureInstruction::Memmove {
+            region: Region::Stack,
+            count: COPY_COUNT,
+            a: operands[0],
+        },
+    },
+    Op {
+        name: "memmove_heap",
+        operation: "memmove_heap",
+        lowering: Lowering::Native,
+        arity: 1,
+        builtins: &[],
+        instruction: |operands| FixtureInstruction::Memmove {
+            region: Region::Heap,
+            count: COPY_COUNT,
+            a: operands[0],
+        },
+    },
+];
@@ -0,0 +1,130 @@
+use anyhow::{Context, Result};
+use serde::Serialize;
+use sha2::{Digest, Sha256};
+use std::collections::BTreeMap;
+use std::fs;
+use std::path::Path;
+use std::process::Command;
+
+const PACKAGE_DIR: &str = "target/package";
+
+/// Environment variables that change what the toolchain or the program build produces
+const TRACKED_ENV: &[&str] = &[
+    "CC",
+    "CXX",
+    "CFLAGS",
+    "CXXFLAGS",
+    "LDFLAGS",
+    "RUSTFLAGS",
+    "CARGO_ENCODED_RUSTFLAGS",
+    "RUSTUP_TOOLCHAIN",
+    "LLVM_PREFIX",
+    "CXXSTDLIB_PATH",
+    "ZLIB_PATH",
+    "LIBZSTD_PATH",
+];
+
+#[derive(Serialize)]
+struct Provenance {
+    artifact: Artifact,
+    llvm: Source,
+    linker: Source,
+    cmake_flags: Vec<String>,
+    host_triple: Option<String>,
+    rustc_version: Option<String>,
+    clang_version: Option<String>,
+    /// Variables xtask sets on the toolchain builds, plus tracked variables present in the caller's environment
+    env: BTreeMap<String, String>,
+    cargo_config: Option<String>,
+}
+
+#[derive(Serialize)]
+struct Artifact {
+    file: String,
+    size: u64,
+    sha256: String,
+}
+
+#[derive(Serialize)]
+struct Source {
+    repo: &'static str,
+    branch: &'static str,
+    commit: Option<String>,
+}
+
+/// Build the program and copy it to target/package alongside a provenance record
+pub fn package(project_root: &Path) -> Result<()> {
+    crate::build_project(project_root, crate::Profile::Release)?;
+
+    let package_dir = project_root.join(PACKAGE_DIR);
+    fs::create_dir_all(&package_dir)?;
+    let so = crate::Profile::Release.artifact(project_root);
+    let file_name = so.file_name().unwrap().to_string_lossy().into_owned();
+    let elf = fs::read(&so).with_context(|| format!("failed to read {}", so.display()))?;
+    let packaged = package_dir.join(&file_name);
+    fs::write(&packaged, &elf).with_context(|| format!("failed to write {}", packaged.display()))?;
+
+    let llvm_dirs = crate::LlvmDirs::selected()?;
+    let linker_commit = crate::selected_commit("sbpf-linker", crate::LINKER_BRANCH)?;
+    let llvm_install_dir = &llvm_dirs.install;
+    let generator = crate::configured_generator(&llvm_dirs.build);
+    let mut cmake_flags = crate::llvm_configure_args(generator.as_deref().unwrap_or("Ninja"))?;
+    cmake_flags.push(format!("-DCMAKE_INSTALL_PREFIX={}", llvm_install_dir.display()));
+
+    let mut env = BTreeMap::new();
+    env.insert("CMAKE_INSTALL_MODE".to_string(), "ABS_SYMLINK".to_string());
+    env.insert("LLVM_PREFIX".to_string(), llvm_install_dir.display().to_string());
+    for name in TRACKED_ENV {
+        if let Ok(value) = std::env::var(name) {
+            env.insert(name.to_string(), value);
+        }
+    }
+    // What the program embeds as its toolchain record, which a rebuild must embed too
+    for (name, value) in crate::toolchain_record_env(project_root) {
+        env.insert(name.to_string(), value);
+    }
+
+    let nightly = crate::toolchain::nightly(project_root)?;
+    let provenance = Provenance {
+        artifact: Artifact {
+            file: file_name,
+            size: elf.len() as u64,
+            sha256: format!("{:x}", Sha256::digest(&elf)),
+        },
+        llvm: Source {
+            repo: crate::LLVM_REPO,
+            branch: crate::LLVM_BRANCH,
+            commit: crate::git_head(&llvm_dirs.src),
+        },
+        linker: Source {
+            repo: crate::LINKER_REPO,
+            branch: crate::LINKER_BRANCH,
+            commit: crate::git_head(&crate::linker_src_dir(&linker_commit)),
+        },
+        cmake_flags,
+        host_triple: command_output("rustc", &["-vV"])
+            .and_then(|out| out.lines().find_map(|l| l.strip_prefix("host: ").map(str::to_string))),
+        rustc_version: command_output("rustc", &[&format!("+{nightly}"), "--version"]),
+        clang_version: command_output("clang", &["--version"])
+            .and_then(|out| out.lines().next().map(str::to_string)),
+        env,
+        cargo_config: fs::read_to_string(project_root.join(".cargo/config.toml")).ok(),
+    };
+
+    let provenance_path = package_dir.join("provenance.json");
+    fs::write(&provenance_path, serde_json::to_string_pretty(&provenance)?)
+        .with_context(|| format!("failed to write {}", provenance_path.display()))?;
+
+    println!("Packaged {}", packaged.display());
+    println!("  sha256: {}", provenance.artifact.sha256);
+    println!("Provenance written to {}", provenance_path.display());
+    Ok(())
+}
+
+fn command_output(program: &str, args: &[&str]) -> Option<String> {
+    let output = Command::new(program).args(args).output().ok()?;
+    output
+        .status
+        .success()
+        .then(|| String::from_utf8_lossy(&output.stdout).trim().to_string())
+}
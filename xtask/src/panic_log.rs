@@ -0,0 +1,69 @@
+//! `cargo xtask panic-log`: build the program with the `panic-log` feature, whose panic handler
+//! logs where it panicked before aborting, and check a deliberate panic shows up in the program
+//! log. The build goes to target/panic-log/, apart from the release artifact it must not replace.
+
+use crate::{vm, Profile};
+use anyhow::{bail, Context, Result};
+use std::fs;
+use std::path::{Path, PathBuf};
+use u128_bpf_fixture::instruction::{opcode, Lowering, HEADER_LEN};
+
+/// Kept apart from target/bpfel-unknown-none so the shipped build is left untouched
+const PANIC_LOG_TARGET_DIR: &str = "target/panic-log";
+
+/// Where the panic handler's diagnostic starts, and the file the deliberate panic is in
+const EXPECTED_LOG: &[&str] = &["Program log: panicked at ", "entrypoint.rs"];
+
+/// Path of the `panic-log` build with `profile`
+pub fn artifact(project_root: &Path, profile: Profile) -> PathBuf {
+    let default = profile.artifact(project_root);
+    let relative = default.strip_prefix(project_root.join("target")).expect("artifacts are under target/");
+    project_root.join(PANIC_LOG_TARGET_DIR).join(relative)
+}
+
+/// Build the program with the `panic-log` feature
+pub fn build(project_root: &Path, profile: Profile) -> Result<PathBuf> {
+    let mut cmd = crate::cargo_build_bpf(project_root, crate::FIXTURE_PACKAGE, profile)?;
+    cmd.args(["--features", "panic-log", "--target-dir", PANIC_LOG_TARGET_DIR]);
+    crate::run_command_prefixed(&mut cmd, "build the panic-log program", "build")?;
+    let program = artifact(project_root, profile);
+    crate::check_undefined_symbols(&program)?;
+    println!("Build complete: {}", program.display());
+    Ok(program)
+}
+
+/// Run the deliberate panic in `program` and fail unless it aborted and logged where it panicked
+pub fn check(program: &Path) -> Result<()> {
+    let elf = fs::read(program).with_context(|| format!("failed to read {}", program.display()))?;
+    let mut data = vec![0; HEADER_LEN + 16];
+    data[0] = opcode::PANIC;
+    data[1] = Lowering::Native as u8;
+    let outcome = vm::execute(&elf, &data, None)?;
+    if let Ok(code) = outcome.result {
+        bail!(
+            "{} returned {code:#x} instead of aborting on the deliberate panic. Was it built with \
+             `--features panic-log`?",
+            program.display()
+        );
+    }
+    let logged = outcome
+        .logs
+        .iter()
+        .find(|log| EXPECTED_LOG.iter().all(|expected| log.contains(expected)));
+    match logged {
+        Some(log) => println!("{}: {log}", program.display()),
+        None => bail!(
+            "{} aborted without logging where it panicked. Logs:\n{}",
+            program.display(),
+            outcome.logs.join("\n")
+        ),
+    }
+    Ok(())
+}
+
+#[test]
+#[ignore = "needs `cargo xtask panic-log`"]
+fn deliberate_panic_is_logged() {
+    let project_root = Path::new(env!("CARGO_MANIFEST_DIR")).parent().unwrap();
+    check(&artifact(project_root, Profile::Release)).unwrap();
+}
@@ -0,0 +1,185 @@
+//! Attribute each op's executed instructions to the functions they ran in, from the VM's
+//! instruction trace, to show which linker-provided libcalls dominate its cost. The VM charges one
+//! CU per instruction, so instructions are CU. With DWARF the attribution extends to the math
+//! inlined into each function, and the call stacks can be written in the folded format flamegraph
+//! tools read.
+
+use crate::{addr2line, elf, fixtures, ops, vm};
+use anyhow::{bail, Context, Result};
+use std::cmp::Reverse;
+use std::collections::{BTreeMap, BTreeSet, HashMap};
+use std::fmt::Write as _;
+use std::fs;
+use std::path::Path;
+
+/// Functions listed per op, by inclusive share
+const TOP_FUNCTIONS: usize = 8;
+
+/// One op's instructions summed over the boundary inputs it was run on
+struct OpProfile {
+    name: &'static str,
+    samples: u64,
+    instructions: u64,
+    functions: BTreeMap<String, vm::FunctionProfile>,
+    stacks: BTreeMap<(Vec<String>, usize), u64>,
+}
+
+/// A frame name as the folded format allows it, without the `;` that separates frames
+fn folded_frame(name: &str) -> String {
+    name.replace(';', ",")
+}
+
+/// Print, per op, where its instructions went (averaged over the boundary inputs with a defined
+/// result), followed by the libcalls ranked by their share of everything executed. With
+/// `inlined`, also rank the functions inlined into them by their own instructions, read from
+/// the program's DWARF; with `folded`, write every op's call stacks there, one
+/// `op;entrypoint;...;function count` line each.
+pub fn profile(program: &Path, only: Option<&str>, inlined: bool, folded: Option<&Path>) -> Result<()> {
+    let elf = fixtures::read_program(program)?;
+    let selected: Vec<_> = ops::OPS.iter().filter(|op| only.is_none_or(|name| op.name == name)).collect();
+    if selected.is_empty() {
+        let names: Vec<_> = ops::OPS.iter().map(|op| op.name).collect();
+        bail!("no op named {} (available: {})", only.unwrap_or_default(), names.join(", "));
+    }
+
+    let mut profiles = Vec::new();
+    for op in selected {
+        let mut profile = OpProfile {
+            name: op.name,
+            samples: 0,
+            instructions: 0,
+            functions: BTreeMap::new(),
+            stacks: BTreeMap::new(),
+        };
+        for operands in op.boundary_inputs() {
+            let Some(expected) = op.reference(&operands) else {
+                continue;
+            };
+            let run = vm::profile(&elf, &op.encode(&operands))?;
+            if run.result.as_ref().ok() != Some(&expected) {
+                bail!(
+                    "{} computed {} wrong on {operands:#x?}: expected {expected:#x}, got {:?}",
+                    program.display(),
+                    op.name,
+                    run.result
+                );
+            }
+            profile.samples += 1;
+            profile.instructions += run.instructions;
+            for (name, function) in run.functions {
+                let total = profile.functions.entry(name).or_default();
+                total.calls += function.calls;
+                total.self_instructions += function.self_instructions;
+                total.inclusive_instructions += function.inclusive_instructions;
+            }
+            for (stack, count) in run.stacks {
+                *profile.stacks.entry(stack).or_default() += count;
+            }
+        }
+        if profile.samples > 0 {
+            profiles.push(profile);
+        }
+    }
+
+    // The functions inlined at every pc that ran, outermost first
+    let inlined_at: Option<HashMap<usize, Vec<String>>> = if inlined {
+        let info = elf::inspect(&elf)?;
+        let text = info.section(".text").context("the program has no .text section")?;
+        let pcs: Vec<_> = profiles
+            .iter()
+            .flat_map(|profile| profile.stacks.keys().map(|&(_, pc)| pc))
+            .collect::<BTreeSet<_>>()
+            .into_iter()
+            .collect();
+        let addresses: Vec<_> = pcs
+            .iter()
+            .map(|&pc| text.address + (pc * solana_sbpf::ebpf::INSN_SIZE) as u64)
+            .collect();
+        let frames = addr2line::inlined_functions(program, &info, &addresses)?;
+        Some(pcs.into_iter().zip(frames).collect())
+    } else {
+        None
+    };
+
+    let mut total_instructions = 0;
+    let mut libcall_totals: BTreeMap<String, u64> = BTreeMap::new();
+    for profile in &profiles {
+        total_instructions += profile.instructions;
+        for (name, function) in &profile.functions {
+            if elf::I128_LIBCALLS.contains(&name.as_str()) {
+                *libcall_totals.entry(name.clone()).or_default() += function.inclusive_instructions;
+            }
+        }
+
+        let per_invocation = |count: u64| count as f64 / profile.samples as f64;
+        let share = |count: u64| 100.0 * count as f64 / profile.instructions as f64;
+        println!("{} ({:.1} instructions per invocation)", profile.name, per_invocation(profile.instructions));
+        println!("  {:<40} {:>8} {:>10} {:>10} {:>7}", "function", "calls", "self", "inclusive", "share");
+        let mut ranked: Vec<_> = profile.functions.iter().collect();
+        ranked.sort_by_key(|(_, function)| Reverse(function.inclusive_instructions));
+        for (name, function) in ranked.into_iter().take(TOP_FUNCTIONS) {
+            let marker = if elf::I128_LIBCALLS.contains(&name.as_str()) { "*" } else { " " };
+            println!(
+                "{marker} {name:<40} {:>8.1} {:>10.1} {:>10.1} {:>6.1}%",
+                per_invocation(function.calls),
+                per_invocation(function.self_instructions),
+                per_invocation(function.inclusive_instructions),
+                share(function.inclusive_instructions)
+            );
+        }
+
+        if let Some(inlined_at) = &inlined_at {
+            // Each instruction belongs to the innermost function inlined where it ran
+            let mut innermost: BTreeMap<&str, u64> = BTreeMap::new();
+            for ((_, pc), &count) in &profile.stacks {
+                let name = inlined_at[pc].last().map_or("<no line info>", String::as_str);
+                *innermost.entry(name).or_default() += count;
+            }
+            let mut ranked: Vec<_> = innermost.into_iter().collect();
+            ranked.sort_by_key(|&(_, count)| Reverse(count));
+            println!("  {:<51} {:>10} {:>7}", "innermost inlined function", "self", "share");
+            for (name, count) in ranked.into_iter().take(TOP_FUNCTIONS) {
+                println!("  {name:<51} {:>10.1} {:>6.1}%", per_invocation(count), share(count));
+            }
+        }
+        println!();
+    }
+
+    let mut ranked: Vec<_> = libcall_totals.into_iter().collect();
+    ranked.sort_by_key(|&(_, inclusive)| Reverse(inclusive));
+    if ranked.is_empty() {
+        println!("No i128 libcall executed");
+    } else {
+        println!("Libcalls (*) by share of all instructions executed above:");
+        for (name, inclusive) in ranked {
+            println!(
+                "  {name:<14} {inclusive:>10} {:>6.1}%",
+                100.0 * inclusive as f64 / total_instructions as f64
+            );
+        }
+    }
+
+    if let Some(path) = folded {
+        let mut lines: BTreeMap<String, u64> = BTreeMap::new();
+        for profile in &profiles {
+            for ((stack, pc), &count) in &profile.stacks {
+                let mut frames = vec![profile.name.to_string()];
+                frames.extend(stack.iter().map(|name| format!("{:#}", rustc_demangle::demangle(name))));
+                // The outermost inlined frame is the function itself, already on the stack
+                if let Some(inlined) = inlined_at.as_ref().map(|inlined_at| &inlined_at[pc]) {
+                    frames.extend(inlined.iter().skip(1).cloned());
+                }
+                let frames: Vec<_> = frames.iter().map(|frame| folded_frame(frame)).collect();
+                *lines.entry(frames.join(";")).or_default() += count;
+            }
+        }
+        let mut out = String::new();
+        for (stack, count) in &lines {
+            writeln!(out, "{stack} {count}")?;
+        }
+        fs::write(path, out).with_context(|| format!("failed to write {}", path.display()))?;
+        println!();
+        println!("Wrote {} folded stacks to {}", lines.len(), path.display());
+    }
+    Ok(())
+}
@@ -0,0 +1,76 @@
+//! Checks that the plain arithmetic ops stay plain: run in the VM, they may log but make no
+//! other syscall and never touch the heap. A stray `format!`, a panic message, or a `Vec`
+//! pulls core's formatting machinery or the allocator into the hot path, and these catch it
+//! where the CU numbers would only drift.
+
+use crate::{fixtures, ops, vm};
+use anyhow::{bail, Result};
+use std::collections::BTreeSet;
+use std::path::Path;
+
+/// Operations that compute on their operands and nothing else
+const PURE_OPERATIONS: &[&str] = &[
+    "mul",
+    "div",
+    "rem",
+    "shl",
+    "shr",
+    "cmp",
+    "add",
+    "sub",
+    "div_floor",
+    "div_ceil",
+    "div_round",
+    "mul_div_floor",
+    "mul_div_ceil",
+    "mul_loop",
+    "batch_mul",
+    "batch_div",
+];
+
+/// The syscalls a pure op may make
+const LOGGING_SYSCALLS: &[&str] = &["sol_log_", "sol_log_64_", "sol_log_compute_units_", "sol_log_pubkey", "sol_log_data"];
+
+/// Run every pure op on each boundary input and fail if any made a syscall other than logging or
+/// loaded from or stored to the heap
+pub fn pure_check(program: &Path) -> Result<()> {
+    let elf = fixtures::read_program(program)?;
+
+    println!("{:<16} {:>8} {:>14}  other syscalls", "op", "inputs", "heap accesses");
+    let mut impure = Vec::new();
+    for op in ops::OPS.iter().filter(|op| PURE_OPERATIONS.contains(&op.operation)) {
+        let (mut inputs, mut heap_accesses, mut syscalls) = (0, 0, BTreeSet::new());
+        for operands in op.boundary_inputs() {
+            let Some(expected) = op.reference(&operands) else {
+                continue;
+            };
+            let effects = vm::side_effects(&elf, &op.encode(&operands))?;
+            if effects.result.as_ref().ok() != Some(&expected) {
+                bail!(
+                    "{} computed {} wrong on {operands:#x?}: expected {expected:#x}, got {:?}",
+                    program.display(),
+                    op.name,
+                    effects.result
+                );
+            }
+            inputs += 1;
+            heap_accesses += effects.heap_accesses;
+            syscalls.extend(effects.syscalls.into_iter().filter(|name| !LOGGING_SYSCALLS.contains(&name.as_str())));
+        }
+        let syscalls: Vec<_> = syscalls.into_iter().collect();
+        println!(
+            "{:<16} {inputs:>8} {heap_accesses:>14}  {}",
+            op.name,
+            if syscalls.is_empty() { "-".to_string() } else { syscalls.join(", ") }
+        );
+        if heap_accesses > 0 || !syscalls.is_empty() {
+            impure.push(op.name);
+        }
+    }
+    println!();
+    if !impure.is_empty() {
+        bail!("{} pure op(s) touched the heap or made a non-logging syscall: {}", impure.len(), impure.join(", "));
+    }
+    println!("No pure op touched the heap or made a syscall other than logging");
+    Ok(())
+}
@@ -0,0 +1,231 @@
+//! Machine-readable results of the op x boundary-value matrix, for CI dashboards and reviewers
+//! who shouldn't have to read logs: a JUnit XML report and a JSON summary with CU numbers.
+
+use crate::fixtures::{self, Matrix};
+use crate::{ops, summary};
+use anyhow::{Context, Result};
+use serde::Serialize;
+use sha2::{Digest, Sha256};
+use std::fmt::Write as _;
+use std::fs;
+use std::path::Path;
+
+#[derive(Serialize)]
+struct Results {
+    program: String,
+    sha256: String,
+    passed: usize,
+    failed: usize,
+    skipped: usize,
+    opcodes: Vec<OpcodeResults>,
+}
+
+#[derive(Serialize)]
+struct OpcodeResults {
+    name: &'static str,
+    passed: usize,
+    failed: usize,
+    skipped: usize,
+    /// Highest CU among the inputs that ran
+    max_compute_units: Option<u64>,
+    cases: Vec<CaseResult>,
+}
+
+#[derive(Serialize)]
+struct CaseResult {
+    /// Operands as decimal strings, since JSON numbers cannot hold a u128
+    inputs: Vec<String>,
+    status: Status,
+    compute_units: Option<u64>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    message: Option<String>,
+}
+
+#[derive(Serialize, PartialEq)]
+#[serde(rename_all = "lowercase")]
+enum Status {
+    Pass,
+    Fail,
+    Skip,
+}
+
+impl OpcodeResults {
+    fn collect(op: &'static ops::Op, matrix: &Matrix) -> Self {
+        let is_op = |other: &ops::Op| other.name == op.name;
+        let inputs = |operands: &[u128]| operands.iter().map(u128::to_string).collect();
+        let mut cases: Vec<_> = matrix
+            .cases
+            .iter()
+            .filter(|case| is_op(case.op))
+            .map(|case| CaseResult {
+                inputs: inputs(&case.operands),
+                status: Status::Pass,
+                compute_units: Some(case.outcome.instruction_count),
+                message: None,
+            })
+            .collect();
+        cases.extend(
+            matrix
+                .mismatches
+                .iter()
+                .filter(|mismatch| is_op(mismatch.op))
+                .map(|mismatch| CaseResult {
+                    inputs: inputs(&mismatch.operands),
+                    status: Status::Fail,
+                    compute_units: Some(mismatch.compute_units),
+                    message: Some(mismatch.message.clone()),
+                }),
+        );
+        cases.extend(
+            matrix
+                .skipped
+                .iter()
+                .filter(|(skipped, _)| is_op(skipped))
+                .map(|(_, operands)| CaseResult {
+                    inputs: inputs(operands),
+                    status: Status::Skip,
+                    compute_units: None,
+                    message: Some("undefined for these inputs".to_string()),
+                }),
+        );
+        let count = |status: Status| cases.iter().filter(|case| case.status == status).count();
+        Self {
+            name: op.name,
+            passed: count(Status::Pass),
+            failed: count(Status::Fail),
+            skipped: count(Status::Skip),
+            max_compute_units: cases.iter().filter_map(|case| case.compute_units).max(),
+            cases,
+        }
+    }
+}
+
+/// Run the matrix against `program` and write `junit.xml` and `results.json` to `out_dir`, and
+/// append a markdown summary to `summary_md` if given. Fails after writing them if any input
+/// disagrees with the native reference.
+pub fn write_results(program: &Path, out_dir: &Path, summary_md: Option<&Path>) -> Result<()> {
+    let elf = fixtures::read_program(program)?;
+    let matrix = fixtures::run_matrix(&elf)?;
+
+    let opcodes: Vec<_> = ops::OPS
+        .iter()
+        .map(|op| OpcodeResults::collect(op, &matrix))
+        .collect();
+    let results = Results {
+        program: program.display().to_string(),
+        sha256: format!("{:x}", Sha256::digest(&elf)),
+        passed: opcodes.iter().map(|op| op.passed).sum(),
+        failed: opcodes.iter().map(|op| op.failed).sum(),
+        skipped: opcodes.iter().map(|op| op.skipped).sum(),
+        opcodes,
+    };
+
+    fs::create_dir_all(out_dir)?;
+    let json_path = out_dir.join("results.json");
+    fs::write(&json_path, serde_json::to_string_pretty(&results)?)
+        .with_context(|| format!("failed to write {}", json_path.display()))?;
+    let junit_path = out_dir.join("junit.xml");
+    fs::write(&junit_path, junit(&results)?).with_context(|| format!("failed to write {}", junit_path.display()))?;
+
+    println!(
+        "{} passed, {} failed, {} skipped; results written to {} and {}",
+        results.passed,
+        results.failed,
+        results.skipped,
+        junit_path.display(),
+        json_path.display()
+    );
+    if let Some(path) = summary_md {
+        summary::append(path, &markdown(&results))?;
+    }
+    matrix.check()
+}
+
+/// Pass counts, with the ops that failed up front and every op's counts folded away
+fn markdown(results: &Results) -> String {
+    let name = Path::new(&results.program).file_name().map_or(results.program.clone(), |name| {
+        name.to_string_lossy().into_owned()
+    });
+    let mut markdown = format!(
+        "### u128 conformance matrix\n\n**{} passed**, {} failed, {} skipped on `{name}` (sha256 `{}`)\n\n",
+        results.passed,
+        results.failed,
+        results.skipped,
+        &results.sha256[..12]
+    );
+    let row = |op: &OpcodeResults| {
+        let max_compute_units = op.max_compute_units.map_or("-".to_string(), |cu| cu.to_string());
+        vec![
+            format!("`{}`", op.name),
+            op.passed.to_string(),
+            op.failed.to_string(),
+            op.skipped.to_string(),
+            max_compute_units,
+        ]
+    };
+    let header = ["op", "passed", "failed", "skipped", "max CU"];
+    let failed: Vec<_> = results.opcodes.iter().filter(|op| op.failed > 0).map(row).collect();
+    if failed.is_empty() {
+        markdown.push_str("Every op agrees with the native reference.\n\n");
+    } else {
+        markdown.push_str(&summary::table(&header, &failed));
+        markdown.push('\n');
+    }
+    let all: Vec<_> = results.opcodes.iter().map(row).collect();
+    markdown.push_str("<details><summary>All ops</summary>\n\n");
+    markdown.push_str(&summary::table(&header, &all));
+    markdown.push_str("\n</details>\n");
+    markdown
+}
+
+/// Render the results as JUnit XML, one test suite per opcode and one test case per input
+fn junit(results: &Results) -> Result<String> {
+    let mut xml = String::new();
+    writeln!(xml, r#"<?xml version="1.0" encoding="UTF-8"?>"#)?;
+    writeln!(
+        xml,
+        r#"<testsuites name="u128-fixture" tests="{}" failures="{}" skipped="{}">"#,
+        results.passed + results.failed + results.skipped,
+        results.failed,
+        results.skipped
+    )?;
+    for op in &results.opcodes {
+        writeln!(
+            xml,
+            r#"  <testsuite name="{}" tests="{}" failures="{}" skipped="{}">"#,
+            escape(op.name),
+            op.cases.len(),
+            op.failed,
+            op.skipped
+        )?;
+        for case in &op.cases {
+            writeln!(
+                xml,
+                r#"    <testcase classname="{}" name="{}({})">"#,
+                escape(op.name),
+                escape(op.name),
+                case.inputs.join(", ")
+            )?;
+            if let Some(cu) = case.compute_units {
+                writeln!(xml, r#"      <properties><property name="compute_units" value="{cu}"/></properties>"#)?;
+            }
+            let message = escape(case.message.as_deref().unwrap_or_default());
+            match case.status {
+                Status::Pass => {}
+                Status::Fail => writeln!(xml, r#"      <failure message="{message}"/>"#)?,
+                Status::Skip => writeln!(xml, r#"      <skipped message="{message}"/>"#)?,
+            }
+            writeln!(xml, "    </testcase>")?;
+        }
+        writeln!(xml, "  </testsuite>")?;
+    }
+    writeln!(xml, "</testsuites>")?;
+    Ok(xml)
+}
+
+fn escape(text: &str) -> String {
+    text.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+}
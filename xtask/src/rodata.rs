@@ -0,0 +1,209 @@
+//! Checks that 128-bit constants the backend places in .rodata are laid out the way the code
+//! loading them expects: little-endian, low 64 bits first, and 16-byte aligned.
+
+use anyhow::{bail, Context, Result};
+use object::{Object, ObjectSection, ObjectSymbol, SymbolKind};
+use std::collections::BTreeMap;
+use std::fs;
+use std::path::Path;
+
+/// Where the loader maps .rodata from SBPF v1 on; v0 programs use the ELF addresses directly
+const MM_RODATA_START: u64 = 0x1_0000_0000;
+/// ABI alignment of i128/u128 in the BPF data layout (`i128:128`)
+const U128_ALIGN: u64 = 16;
+
+const LD_DW_IMM: u8 = 0x18;
+const LDX_DW: u8 = 0x79;
+const CALL: u8 = 0x85;
+/// Instruction classes that write their destination register
+const CLASS_LD: u8 = 0x00;
+const CLASS_LDX: u8 = 0x01;
+const CLASS_ALU: u8 = 0x04;
+const CLASS_ALU64: u8 = 0x07;
+
+/// A read-only data section and its contents
+struct Rodata<'a> {
+    name: String,
+    address: u64,
+    data: &'a [u8],
+}
+
+/// A 16-byte value the code reads as two adjacent 64-bit loads
+struct LoadedConstant {
+    /// ELF address of the low half
+    address: u64,
+    section: String,
+    value: u128,
+    /// Function and byte offset of the first load
+    site: String,
+}
+
+/// Parse a u128 given in decimal or as 0x-prefixed hex
+pub fn parse_u128(value: &str) -> Result<u128, String> {
+    match value.strip_prefix("0x") {
+        Some(hex) => u128::from_str_radix(&hex.replace('_', ""), 16),
+        None => value.replace('_', "").parse(),
+    }
+    .map_err(|err| format!("invalid u128 {value:?}: {err}"))
+}
+
+/// Locate the u128 constants the code loads from .rodata, check their alignment, and check that
+/// each `expected` constant is stored little-endian with the low half first
+pub fn check_rodata(program: &Path, expected: &[u128]) -> Result<()> {
+    let elf = fs::read(program).with_context(|| {
+        format!(
+            "failed to read {}. Run `cargo xtask build` first.",
+            program.display()
+        )
+    })?;
+    let file = object::File::parse(&*elf).context("failed to parse ELF")?;
+
+    let mut rodata = Vec::new();
+    for section in file.sections() {
+        let name = section.name()?;
+        if name.starts_with(".rodata") || name.starts_with(".data.rel.ro") {
+            rodata.push(Rodata {
+                name: name.to_string(),
+                address: section.address(),
+                data: section.data()?,
+            });
+        }
+    }
+
+    let mut problems = Vec::new();
+    let constants = loaded_constants(&file, &rodata)?;
+    println!("u128 constants loaded from read-only data:");
+    if constants.is_empty() {
+        println!("  (none)");
+    }
+    for constant in &constants {
+        let aligned = constant.address % U128_ALIGN == 0;
+        println!(
+            "  {:#010x} {:<10} {:#034x}  {}{}",
+            constant.address,
+            constant.section,
+            constant.value,
+            constant.site,
+            if aligned { "" } else { "  MISALIGNED" }
+        );
+        if !aligned {
+            problems.push(format!(
+                "constant at {:#x} is only {}-byte aligned",
+                constant.address,
+                1 << constant.address.trailing_zeros().min(4)
+            ));
+        }
+    }
+
+    if !expected.is_empty() {
+        println!();
+        println!("Expected constants:");
+    }
+    for &value in expected {
+        let swapped = value.rotate_left(64);
+        if let Some(address) = find(&rodata, &value.to_le_bytes()) {
+            println!("  {value:#034x}  ok at {address:#x}");
+        } else if let Some(address) = find(&rodata, &value.to_be_bytes()) {
+            println!("  {value:#034x}  BIG-ENDIAN at {address:#x}");
+            problems.push(format!("{value:#x} is stored big-endian"));
+        } else if let Some(address) = find(&rodata, &swapped.to_le_bytes()).filter(|_| swapped != value) {
+            println!("  {value:#034x}  HALVES SWAPPED at {address:#x}");
+            problems.push(format!("{value:#x} is stored high 64 bits first"));
+        } else {
+            println!("  {value:#034x}  not in read-only data (materialized inline or folded away)");
+        }
+    }
+
+    if !problems.is_empty() {
+        bail!("{} .rodata layout problem(s): {}", problems.len(), problems.join("; "));
+    }
+    Ok(())
+}
+
+/// ELF address of the first occurrence of `bytes` in read-only data
+fn find(rodata: &[Rodata], bytes: &[u8]) -> Option<u64> {
+    rodata.iter().find_map(|section| {
+        section
+            .data
+            .windows(bytes.len())
+            .position(|window| window == bytes)
+            .map(|offset| section.address + offset as u64)
+    })
+}
+
+/// Track registers holding a .rodata address (set by `lddw`) through each function and collect
+/// the addresses read with pairs of `ldxdw` 8 bytes apart
+fn loaded_constants(file: &object::File, rodata: &[Rodata]) -> Result<Vec<LoadedConstant>> {
+    let locate = |address: u64| {
+        let address = if address >= MM_RODATA_START { address - MM_RODATA_START } else { address };
+        rodata
+            .iter()
+            .find(|section| (section.address..section.address + section.data.len() as u64).contains(&address))
+            .map(|section| (address, section))
+    };
+
+    // address -> first load site
+    let mut loads: BTreeMap<u64, String> = BTreeMap::new();
+    for symbol in file.symbols().filter(|s| s.kind() == SymbolKind::Text && s.is_definition()) {
+        let Some(index) = symbol.section_index() else {
+            continue;
+        };
+        let section = file.section_by_index(index)?;
+        let start = (symbol.address() - section.address()) as usize;
+        let Some(code) = section.data()?.get(start..start + symbol.size() as usize) else {
+            continue;
+        };
+        let name = symbol.name()?;
+
+        let mut bases: [Option<u64>; 11] = [None; 11];
+        let mut offset = 0;
+        while offset + 8 <= code.len() {
+            let insn = &code[offset..offset + 8];
+            let (opcode, dst, src) = (insn[0], (insn[1] & 0x0f) as usize, (insn[1] >> 4) as usize);
+            let imm = u32::from_le_bytes(insn[4..8].try_into().unwrap());
+            if opcode == LD_DW_IMM && offset + 16 <= code.len() {
+                let high = u32::from_le_bytes(code[offset + 12..offset + 16].try_into().unwrap());
+                let address = (u64::from(high) << 32) | u64::from(imm);
+                if dst < bases.len() {
+                    bases[dst] = locate(address).map(|(address, _)| address);
+                }
+                offset += 16;
+                continue;
+            }
+            if opcode == LDX_DW {
+                let off = i16::from_le_bytes([insn[2], insn[3]]);
+                if let Some(base) = bases.get(src).copied().flatten() {
+                    let address = base.wrapping_add_signed(off.into());
+                    loads.entry(address).or_insert_with(|| format!("{name}+{offset:#x}"));
+                }
+            }
+            if opcode == CALL {
+                bases[..6].fill(None);
+            } else if matches!(opcode & 0x07, CLASS_LD | CLASS_LDX | CLASS_ALU | CLASS_ALU64) && dst < bases.len() {
+                bases[dst] = None;
+            }
+            offset += 8;
+        }
+    }
+
+    let mut constants = Vec::new();
+    for (&address, site) in &loads {
+        if !loads.contains_key(&(address + 8)) || constants.iter().any(|c: &LoadedConstant| c.address + 8 == address) {
+            continue;
+        }
+        let Some((_, section)) = locate(address) else {
+            continue;
+        };
+        let start = (address - section.address) as usize;
+        let Some(bytes) = section.data.get(start..start + 16) else {
+            continue;
+        };
+        constants.push(LoadedConstant {
+            address,
+            section: section.name.clone(),
+            value: u128::from_le_bytes(bytes.try_into().unwrap()),
+            site: site.clone(),
+        });
+    }
+    Ok(constants)
+}
@@ -0,0 +1,280 @@
+//! `cargo xtask new-fixture`: a new fixture crate for probing another codegen area, laid out like
+//! `fixture` (an entrypoint behind `no-entrypoint`, the instruction encoding, and Mollusk tests)
+//! and registered so `cargo xtask build` builds it along with the main program.
+
+use anyhow::{bail, Context, Result};
+use serde::Deserialize;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+/// Directory the generated crates go in, one subdirectory per fixture
+const FIXTURES_DIR: &str = "fixtures";
+
+const CARGO_TOML: &str = r#"[package]
+name = "{package}"
+version = "0.1.0"
+edition = "2021"
+license = "MIT"
+publish = false
+
+[lib]
+crate-type = ["cdylib", "rlib"]
+
+[features]
+# Leave out the program entrypoint and panic handler, for crates that only need the instruction API
+no-entrypoint = []
+
+[dev-dependencies]
+mollusk-svm = "0.9.0"
+solana-instruction = "3.1.0"
+solana-program-error = "3.0"
+"#;
+
+const LIB_RS: &str = r#"//! The `{name}` fixture program. To cover an operation, give it an opcode in
+//! [`instruction::opcode`], dispatch on it in the entrypoint, and test it in `tests/mollusk.rs`.
+#![cfg_attr(target_arch = "bpf", no_std)]
+
+#[cfg(not(feature = "no-entrypoint"))]
+mod entrypoint;
+pub mod instruction;
+"#;
+
+const INSTRUCTION_RS: &str = r#"//! Instruction data the program accepts, encoded like `u128-bpf-fixture`'s: an 8-byte header
+//! starting with the opcode, then the u128 operands, 16 bytes little-endian each.
+
+/// Length of the header in front of the operands
+pub const HEADER_LEN: usize = 8;
+
+/// Offset of the instruction data in the input region of an invocation without accounts: the
+/// program skips the account count and the data length, 8 bytes each
+pub const INSTRUCTION_DATA_OFFSET: usize = 0x10;
+
+/// The return code for instruction data the program doesn't accept, which the runtime reports as
+/// `InvalidInstructionData`
+pub const INVALID_INSTRUCTION_DATA: u64 = 3 << 32;
+
+/// The opcodes the program accepts, in the first header byte
+pub mod opcode {}
+
+/// The u128 operand at `index` in instruction data, if the data is long enough
+pub fn operand(data: &[u8], index: usize) -> Option<u128> {
+    let start = HEADER_LEN + 16 * index;
+    Some(u128::from_le_bytes(data.get(start..start + 16)?.try_into().ok()?))
+}
+"#;
+
+const ENTRYPOINT_RS: &str = r#"use crate::instruction::{HEADER_LEN, INSTRUCTION_DATA_OFFSET, INVALID_INSTRUCTION_DATA};
+
+#[cfg(target_arch = "bpf")]
+#[panic_handler]
+fn panic(_: &core::panic::PanicInfo) -> ! {
+    unsafe { core::hint::unreachable_unchecked() }
+}
+
+/// Called by the loader with the serialized input region. The program takes no accounts, so the
+/// instruction data is at a fixed offset.
+#[unsafe(no_mangle)]
+#[allow(clippy::not_unsafe_ptr_arg_deref)]
+pub fn entrypoint(input: *mut u8) -> u64 {
+    let data = unsafe {
+        if input.cast::<u64>().read_unaligned() != 0 {
+            return INVALID_INSTRUCTION_DATA;
+        }
+        let len = input.add(8).cast::<u64>().read_unaligned() as usize;
+        core::slice::from_raw_parts(input.add(INSTRUCTION_DATA_OFFSET), len)
+    };
+    dispatch(data).unwrap_or(INVALID_INSTRUCTION_DATA)
+}
+
+// Until the first opcode, the match has nothing but its fallback
+#[allow(clippy::match_single_binding)]
+fn dispatch(data: &[u8]) -> Option<u64> {
+    let header = data.get(..HEADER_LEN)?;
+    match header[0] {
+        // One arm per entry of `instruction::opcode`, reading its operands with
+        // `instruction::operand` and returning the result
+        _ => None,
+    }
+}
+"#;
+
+const MOLLUSK_RS: &str = r#"//! Mollusk tests of the `{name}` fixture program. Build it first with `cargo xtask build`.
+
+use mollusk_svm::{result::Check, Mollusk};
+use solana_instruction::Instruction;
+use solana_program_error::ProgramError;
+use {crate_name}::instruction::HEADER_LEN;
+
+const PROGRAM_ID: [u8; 32] = [0x02; 32];
+
+/// Built program for the profile named by `BPF_PROFILE` (`release`, `debug`, or `release-dwarf`),
+/// release by default
+fn program_path() -> String {
+    let profile = std::env::var("BPF_PROFILE").unwrap_or_else(|_| "release".to_string());
+    format!("{}/../../target/bpfel-unknown-none/{profile}/lib{crate_name}", env!("CARGO_MANIFEST_DIR"))
+}
+
+#[test]
+fn rejects_unknown_opcodes() {
+    let mollusk = Mollusk::new(&PROGRAM_ID.into(), &program_path());
+    let instruction = Instruction {
+        program_id: PROGRAM_ID.into(),
+        accounts: vec![],
+        data: vec![0xff; HEADER_LEN],
+    };
+    mollusk.process_and_validate_instruction(
+        &instruction,
+        &[],
+        &[Check::err(ProgramError::InvalidInstructionData)],
+    );
+}
+"#;
+
+/// `[workspace.metadata]` of the root manifest
+#[derive(Deserialize, Default)]
+struct WorkspaceMetadata {
+    /// Packages of the fixtures `cargo xtask build` builds besides the main program
+    #[serde(default)]
+    fixtures: Vec<String>,
+}
+
+/// The package names of the fixtures registered in the root manifest
+pub fn registered_fixtures(project_root: &Path) -> Result<Vec<String>> {
+    #[derive(Deserialize)]
+    struct Manifest {
+        workspace: Workspace,
+    }
+    #[derive(Deserialize)]
+    struct Workspace {
+        #[serde(default)]
+        metadata: WorkspaceMetadata,
+    }
+
+    let path = project_root.join("Cargo.toml");
+    let contents = fs::read_to_string(&path).with_context(|| format!("failed to read {}", path.display()))?;
+    let manifest: Manifest =
+        toml::from_str(&contents).with_context(|| format!("failed to parse {}", path.display()))?;
+    Ok(manifest.workspace.metadata.fixtures)
+}
+
+/// The manifest of `package`: the main program's, or a generated fixture's in `fixtures/<name>`
+pub fn package_manifest(project_root: &Path, package: &str) -> PathBuf {
+    match package.strip_suffix("-fixture") {
+        Some(name) if package != crate::FIXTURE_PACKAGE => project_root.join(FIXTURES_DIR).join(name),
+        _ => project_root.join(crate::FIXTURE_DIR),
+    }
+    .join("Cargo.toml")
+}
+
+/// Generate the `fixtures/<name>` crate, add it to the workspace members, and register its
+/// package with the fixtures `cargo xtask build` builds
+pub fn new_fixture(project_root: &Path, name: &str) -> Result<()> {
+    let valid = name.starts_with(|c: char| c.is_ascii_lowercase())
+        && !name.ends_with('-')
+        && name.chars().all(|c| c.is_ascii_lowercase() || c.is_ascii_digit() || c == '-');
+    if !valid {
+        bail!("fixture names are lowercase letters, digits, and dashes, starting with a letter: {name}");
+    }
+    let package = format!("{name}-fixture");
+    let crate_name = package.replace('-', "_");
+    let dir = Path::new(FIXTURES_DIR).join(name);
+    let root = project_root.join(&dir);
+    if root.exists() {
+        bail!("{} already exists", root.display());
+    }
+
+    let manifest_path = project_root.join("Cargo.toml");
+    let manifest = fs::read_to_string(&manifest_path)
+        .with_context(|| format!("failed to read {}", manifest_path.display()))?;
+    let member = dir.to_string_lossy().replace('\\', "/");
+    let manifest = append_to_array(&manifest, "members", &member)?;
+    let manifest = append_to_array(&manifest, "fixtures", &package)?;
+
+    let fill = |template: &str| {
+        template
+            .replace("{name}", name)
+            .replace("{package}", &package)
+            .replace("{crate_name}", &crate_name)
+    };
+    let files = [
+        ("Cargo.toml", CARGO_TOML),
+        ("src/lib.rs", LIB_RS),
+        ("src/instruction.rs", INSTRUCTION_RS),
+        ("src/entrypoint.rs", ENTRYPOINT_RS),
+        ("tests/mollusk.rs", MOLLUSK_RS),
+    ];
+    for (file, template) in files {
+        let path = root.join(file);
+        fs::create_dir_all(path.parent().unwrap())?;
+        fs::write(&path, fill(template)).with_context(|| format!("failed to write {}", path.display()))?;
+        println!("  wrote   {}", dir.join(file).display());
+    }
+    fs::write(&manifest_path, manifest)
+        .with_context(|| format!("failed to write {}", manifest_path.display()))?;
+    println!("  updated Cargo.toml (workspace member and registered fixture)");
+    println!();
+    println!("Created {package} in {}. Build it with `cargo xtask build` and test it with", dir.display());
+    println!("`cargo test -p {package}`.");
+    Ok(())
+}
+
+/// `manifest` with `value` appended to the single-line string array assigned to `key`, which
+/// keeps the rest of the file, comments included, as it is
+fn append_to_array(manifest: &str, key: &str, value: &str) -> Result<String> {
+    let mut found = false;
+    let mut out = String::new();
+    for line in manifest.lines() {
+        let items = line
+            .strip_prefix(key)
+            .map(str::trim_start)
+            .and_then(|rest| rest.strip_prefix('='))
+            .map(str::trim)
+            .and_then(|rest| rest.strip_prefix('['))
+            .and_then(|rest| rest.strip_suffix(']'));
+        match items {
+            Some(items) if !found => {
+                found = true;
+                let items = items.trim().trim_end_matches(',');
+                let separator = if items.is_empty() { "" } else { ", " };
+                out.push_str(&format!("{key} = [{items}{separator}\"{value}\"]"));
+            }
+            _ => out.push_str(line),
+        }
+        out.push('\n');
+    }
+    if !found {
+        bail!("the root Cargo.toml has no single-line `{key} = [...]` to add {value} to");
+    }
+    Ok(out)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn appends_to_single_line_arrays() {
+        let manifest = [
+            "[workspace]",
+            "members = [\"fixture\", \"xtask\"]",
+            "",
+            "# Fixtures besides `fixture`",
+            "[workspace.metadata]",
+            "fixtures = []",
+        ]
+        .join("\n");
+        let manifest = append_to_array(&manifest, "members", "fixtures/shifts").unwrap();
+        let manifest = append_to_array(&manifest, "fixtures", "shifts-fixture").unwrap();
+        let expected = [
+            "[workspace]",
+            "members = [\"fixture\", \"xtask\", \"fixtures/shifts\"]",
+            "",
+            "# Fixtures besides `fixture`",
+            "[workspace.metadata]",
+            "fixtures = [\"shifts-fixture\"]\n",
+        ]
+        .join("\n");
+        assert_eq!(manifest, expected);
+        assert!(append_to_array(&manifest, "exclude", "x").is_err());
+    }
+}
@@ -0,0 +1,93 @@
+use crate::cargo_config::CargoConfig;
+use crate::vm;
+use anyhow::{bail, Context, Result};
+use std::fs;
+use std::path::Path;
+use std::process::Command;
+
+/// Operands chosen so the product carries into the high 64 bits, which a truncating or
+/// miscompiled 128-bit multiply gets wrong
+const LHS: u128 = 0x0000_0001_0000_0003_ffff_ffff_0000_0007;
+const RHS: u128 = 0x0000_0000_0000_0002_0000_0005_8000_0001;
+
+const PROBE_MANIFEST: &str = r#"[package]
+name = "u128-probe"
+version = "0.1.0"
+edition = "2021"
+
+[lib]
+crate-type = ["cdylib"]
+
+[profile.release]
+overflow-checks = false
+
+[workspace]
+"#;
+
+const PROBE_SOURCE: &str = r#"#![no_std]
+
+#[panic_handler]
+fn panic(_: &core::panic::PanicInfo) -> ! {
+    loop {}
+}
+
+#[unsafe(no_mangle)]
+pub fn entrypoint(input: *mut u8) -> u64 {
+    let a = unsafe { *(input.add(0x10) as *const u128) };
+    let b = unsafe { *(input.add(0x20) as *const u128) };
+    let product = a.wrapping_mul(b);
+    (product >> 64) as u64 ^ product as u64
+}
+"#;
+
+/// What the probe returns for `LHS * RHS`, computed natively
+fn expected() -> u64 {
+    let product = LHS.wrapping_mul(RHS);
+    (product >> 64) as u64 ^ product as u64
+}
+
+/// Compile a tiny u128 multiply with the selected toolchain and check its result in the VM
+pub fn smoke_test(project_root: &Path) -> Result<()> {
+    let llvm_commit = crate::selected_commit("llvm", crate::LLVM_BRANCH)?;
+    let linker_commit = crate::selected_commit("sbpf-linker", crate::LINKER_BRANCH)?;
+    let linker_bin = crate::linker_bin(&linker_commit, &llvm_commit);
+    if !linker_bin.exists() {
+        bail!("sbpf-linker not found at {}. Run `cargo xtask setup` first.", linker_bin.display());
+    }
+
+    println!("Smoke test: compiling a u128 multiply probe...");
+    let probe_dir = crate::cache_dir().join("smoke-probe");
+    fs::create_dir_all(probe_dir.join("src"))?;
+    fs::write(probe_dir.join("Cargo.toml"), PROBE_MANIFEST)?;
+    fs::write(probe_dir.join("src/lib.rs"), PROBE_SOURCE)?;
+    CargoConfig::new(&linker_bin, "u128-probe").write(&probe_dir)?;
+
+    crate::run_command_prefixed(
+        Command::new("cargo")
+            .arg(format!("+{}", crate::toolchain::nightly(project_root)?))
+            .args(["build", "--release", "--target", crate::BPF_TARGET])
+            .arg("-Zbuild-std=core,alloc")
+            .current_dir(&probe_dir),
+        "build smoke-test probe",
+        "smoke",
+    )
+    .context("smoke test FAILED: the toolchain could not compile the probe")?;
+
+    let so = probe_dir.join("target").join(crate::BPF_TARGET).join("release/libu128_probe.so");
+    let elf = fs::read(&so).with_context(|| format!("failed to read {}", so.display()))?;
+    let data = [LHS.to_le_bytes(), RHS.to_le_bytes()].concat();
+    let outcome = vm::execute(&elf, &data, None).context("smoke test FAILED: the probe did not load")?;
+
+    let expected = expected();
+    match outcome.result {
+        Ok(code) if code == expected => {
+            println!(
+                "Smoke test passed: {LHS:#x} * {RHS:#x} matched the native result ({} instructions)",
+                outcome.instruction_count
+            );
+            Ok(())
+        }
+        Ok(code) => bail!("smoke test FAILED: probe returned {code:#x}, expected {expected:#x}"),
+        Err(err) => bail!("smoke test FAILED: probe aborted in the VM: {err}"),
+    }
+}
@@ -0,0 +1,339 @@
+use crate::{codegen, elf, vm};
+use anyhow::{bail, Context, Result};
+use std::collections::BTreeMap;
+use std::fmt::Write as _;
+use std::fs;
+use std::path::Path;
+use std::process::Command;
+
+/// Directory holding the golden disassembly, one `<function>.s` per snapshotted function
+const SNAPSHOT_DIR: &str = "snapshots";
+/// Where the current disassembly is written when it differs from the snapshot
+const ACTUAL_DIR: &str = "target/snapshots";
+/// Subdirectory of both holding the IR snapshots, one `<function>.ll` per function plus the
+/// module's declarations
+const IR_DIR: &str = "ir";
+/// Modules of the fixture whose functions the IR snapshots keep, when LTO leaves them uninlined
+const IR_MODULES: &[&str] = &["intrinsics", "pair", "muldiv"];
+/// Prefix of the fixture's demangled symbols
+const FIXTURE_CRATE: &str = "u128_bpf_fixture::";
+
+/// Compare the disassembly of `entrypoint` and any i128 libcalls linked into the program
+/// against the checked-in snapshots, or rewrite the snapshots when `bless` is set
+pub fn snapshot(project_root: &Path, bless: bool) -> Result<()> {
+    crate::build_project(project_root, crate::Profile::Release)?;
+    let so = crate::Profile::Release.artifact(project_root);
+    let elf = fs::read(&so).with_context(|| format!("failed to read {}", so.display()))?;
+    let functions = vm::disassemble_functions(&elf)?;
+
+    let mut names = vec!["entrypoint"];
+    names.extend(elf::I128_LIBCALLS.iter().filter(|name| functions.contains_key(**name)));
+    let mut snapshots = BTreeMap::new();
+    for name in names {
+        let Some(actual) = functions.get(name) else {
+            bail!("{name} not found in {}", so.display());
+        };
+        snapshots.insert(format!("{name}.s"), actual.clone());
+    }
+
+    let snapshot_dir = project_root.join(SNAPSHOT_DIR);
+    if !bless && !has_snapshots(&snapshot_dir, "s") {
+        println!(
+            "No bytecode snapshots in {} yet. Record them for the pinned toolchain with `cargo xtask \
+             snapshot --bless` and commit them to check the disassembly.",
+            snapshot_dir.display()
+        );
+        return Ok(());
+    }
+    let failed = compare(&snapshot_dir, &project_root.join(ACTUAL_DIR), &snapshots, bless)?;
+    if !failed.is_empty() {
+        bail!(
+            "{} bytecode snapshot(s) differ, are missing, or are gone: {}. Review the diff and re-run with --bless \
+             to accept it.",
+            failed.len(),
+            failed.join(", ")
+        );
+    }
+    if !bless {
+        println!("Bytecode snapshots match");
+    }
+    Ok(())
+}
+
+/// Compare the IR the linker dumps for `entrypoint`, the fixture's arithmetic helpers, and the
+/// module's external declarations against the checked-in snapshots, or rewrite them when `bless`
+/// is set. Value names are normalized first, so only a change in what reaches the linker shows.
+pub fn ir_snapshot(project_root: &Path, bless: bool) -> Result<()> {
+    crate::build_project(project_root, crate::Profile::Release)?;
+    let dump = project_root.join(codegen::LLVM_DUMP);
+    let module = fs::read_to_string(&dump).with_context(|| {
+        format!("failed to read {}; check the --dump-module link arg", dump.display())
+    })?;
+
+    let mut snapshots = BTreeMap::new();
+    let mut declarations = String::new();
+    let mut lines = module.lines();
+    while let Some(line) = lines.next() {
+        if line.starts_with("declare ") {
+            writeln!(declarations, "{}", normalize(line))?;
+            continue;
+        }
+        if !line.starts_with("define ") {
+            continue;
+        }
+        let mut function = format!("{line}\n");
+        for line in lines.by_ref() {
+            writeln!(function, "{line}")?;
+            if line == "}" {
+                break;
+            }
+        }
+        let Some(name) = snapshot_name(&demangled_name(line)) else {
+            continue;
+        };
+        snapshots.insert(format!("{name}.ll"), normalize(&function));
+    }
+    if !snapshots.contains_key("entrypoint.ll") {
+        bail!("entrypoint not found in {}", dump.display());
+    }
+    snapshots.insert("declarations.ll".to_string(), declarations);
+
+    let snapshot_dir = project_root.join(SNAPSHOT_DIR).join(IR_DIR);
+    if !bless && !has_snapshots(&snapshot_dir, "ll") {
+        println!(
+            "No IR snapshots in {} yet. Record them for the pinned toolchain with `cargo xtask snapshot \
+             --ir --bless` and commit them to check the IR.",
+            snapshot_dir.display()
+        );
+        return Ok(());
+    }
+    let failed = compare(
+        &snapshot_dir,
+        &project_root.join(ACTUAL_DIR).join(IR_DIR),
+        &snapshots,
+        bless,
+    )?;
+    if !failed.is_empty() {
+        bail!(
+            "{} IR snapshot(s) differ, are missing, or are gone: {}. Review the diff and re-run with --ir --bless \
+             to accept it.",
+            failed.len(),
+            failed.join(", ")
+        );
+    }
+    if !bless {
+        println!("IR snapshots match");
+    }
+    Ok(())
+}
+
+/// Whether `dir` holds any snapshot with `extension`, i.e. a baseline has been recorded there
+fn has_snapshots(dir: &Path, extension: &str) -> bool {
+    fs::read_dir(dir).is_ok_and(|entries| {
+        entries.flatten().any(|entry| entry.path().extension().is_some_and(|ext| ext == extension))
+    })
+}
+
+/// Check each snapshot against its file in `snapshot_dir`, writing the ones that differ to
+/// `actual_dir` and showing the diff, and return the names that failed. With `bless`, write them
+/// to `snapshot_dir` instead. A file in `snapshot_dir` with the same extension but no snapshot
+/// any more fails too, or is removed when blessing.
+fn compare(
+    snapshot_dir: &Path,
+    actual_dir: &Path,
+    snapshots: &BTreeMap<String, String>,
+    bless: bool,
+) -> Result<Vec<String>> {
+    let mut failed = Vec::new();
+    for (file, actual) in snapshots {
+        let expected_path = snapshot_dir.join(file);
+
+        if bless {
+            fs::create_dir_all(snapshot_dir)?;
+            fs::write(&expected_path, actual)
+                .with_context(|| format!("failed to write {}", expected_path.display()))?;
+            println!("  wrote   {}", expected_path.display());
+            continue;
+        }
+
+        let Ok(expected) = fs::read_to_string(&expected_path) else {
+            println!("  MISSING {file} (no {})", expected_path.display());
+            failed.push(file.clone());
+            continue;
+        };
+        if &expected == actual {
+            println!("  ok      {file}");
+            continue;
+        }
+
+        fs::create_dir_all(actual_dir)?;
+        let actual_path = actual_dir.join(file);
+        fs::write(&actual_path, actual)
+            .with_context(|| format!("failed to write {}", actual_path.display()))?;
+        println!("  CHANGED {file}");
+        // diff exits 1 when the files differ, which is expected here
+        let _ = Command::new("diff")
+            .arg("-u")
+            .arg(&expected_path)
+            .arg(&actual_path)
+            .status();
+        failed.push(file.clone());
+    }
+
+    let extensions: Vec<_> = snapshots.keys().filter_map(|file| Path::new(file).extension()).collect();
+    for entry in fs::read_dir(snapshot_dir).into_iter().flatten().flatten() {
+        let path = entry.path();
+        let file = entry.file_name().to_string_lossy().into_owned();
+        if snapshots.contains_key(&file) || !path.extension().is_some_and(|ext| extensions.contains(&ext)) {
+            continue;
+        }
+        if bless {
+            fs::remove_file(&path).with_context(|| format!("failed to remove {}", path.display()))?;
+            println!("  removed {}", path.display());
+        } else {
+            println!("  GONE    {file} (the build no longer has it)");
+            failed.push(file);
+        }
+    }
+    Ok(failed)
+}
+
+/// The demangled name, without the hash, of the function a `define` line defines
+fn demangled_name(define: &str) -> String {
+    let Some((_, rest)) = define.split_once('@') else {
+        return String::new();
+    };
+    let name = match rest.strip_prefix('"') {
+        Some(quoted) => quoted.split('"').next().unwrap_or_default(),
+        None => rest.split('(').next().unwrap_or_default(),
+    };
+    format!("{:#}", rustc_demangle::demangle(name))
+}
+
+/// File stem of a function's snapshot, or `None` if it isn't one of the functions kept
+fn snapshot_name(function: &str) -> Option<String> {
+    if function == "entrypoint" {
+        return Some(function.to_string());
+    }
+    let path = function.strip_prefix(FIXTURE_CRATE)?;
+    let module = path.split("::").next()?;
+    // Closures and generic instantiations come and go with inlining decisions
+    if !IR_MODULES.contains(&module) || path.contains(['{', '<']) {
+        return None;
+    }
+    Some(path.replace("::", "."))
+}
+
+fn is_identifier(c: char) -> bool {
+    c.is_ascii_alphanumeric() || matches!(c, '.' | '_' | '$' | '-')
+}
+
+/// IR with everything that changes between otherwise identical builds renamed: local values
+/// and block labels numbered in order of appearance, symbol hashes and anonymous constant names
+/// dropped, and metadata and attribute group ids blanked. Comments go too, as they repeat the
+/// value names. Quoted `%"..."` names are types and stay as they are.
+fn normalize(ir: &str) -> String {
+    let mut locals: BTreeMap<String, usize> = BTreeMap::new();
+    let mut local = |name: &str| {
+        let next = locals.len();
+        *locals.entry(name.to_string()).or_insert(next)
+    };
+
+    let mut out = String::new();
+    for line in ir.lines() {
+        let line = line.split(';').next().unwrap_or_default().trim_end();
+        if line.is_empty() {
+            continue;
+        }
+        if let Some(label) = line.strip_suffix(':').filter(|label| label.chars().all(is_identifier)) {
+            out.push_str(&format!("v{}:\n", local(label)));
+            continue;
+        }
+
+        let mut chars = line.chars().peekable();
+        while let Some(c) = chars.next() {
+            let type_name = c == '%' && chars.peek() == Some(&'"');
+            if !matches!(c, '%' | '@' | '!' | '#') || type_name {
+                out.push(c);
+                continue;
+            }
+            let mut name = String::new();
+            if chars.peek() == Some(&'"') {
+                chars.next();
+                name.extend(chars.by_ref().take_while(|&c| c != '"'));
+            } else {
+                while let Some(&next) = chars.peek().filter(|&&next| is_identifier(next)) {
+                    name.push(next);
+                    chars.next();
+                }
+            }
+            match c {
+                _ if name.is_empty() => out.push(c),
+                '%' => out.push_str(&format!("%v{}", local(&name))),
+                '@' => out.push_str(&format!("@{}", global(&name))),
+                _ if name.chars().all(|c| c.is_ascii_digit()) => out.push_str(&format!("{c}N")),
+                _ => out.push_str(&format!("{c}{name}")),
+            }
+        }
+        out.push('\n');
+    }
+    out
+}
+
+/// A global's name without the parts derived from hashes
+fn global(name: &str) -> String {
+    if name.starts_with("_ZN") || name.starts_with("_R") {
+        return format!("{:#}", rustc_demangle::demangle(name));
+    }
+    // alloc_<hash> and anon.<hash>.<n> name the crate's anonymous constants
+    for prefix in ["alloc_", "anon."] {
+        if let Some(rest) = name.strip_prefix(prefix) {
+            let suffix = rest.trim_start_matches(|c: char| c.is_ascii_hexdigit());
+            return format!("{prefix}*{suffix}");
+        }
+    }
+    name.to_string()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn normalize_drops_what_builds_rename() {
+        let ir = [
+            "define internal fastcc i128 @_ZN16u128_bpf_fixture6muldiv13mul_div_floor17h0123456789abcdefE(i128 %a, \
+             i128 %b) unnamed_addr #4 {",
+            "start:",
+            "  %_3 = tail call i128 @__multi3(i128 %a, i128 %b), !dbg !12 ; a comment",
+            "  br label %bb2",
+            "",
+            "bb2:  ; preds = %start",
+            "  store ptr @alloc_9f8e7d, ptr %_3",
+            "  %x = load %\"core::fmt::Arguments<'_>\", ptr %_3",
+            "  ret i128 %_3",
+            "}",
+        ]
+        .join("\n");
+        let expected = [
+            "define internal fastcc i128 @u128_bpf_fixture::muldiv::mul_div_floor(i128 %v0, i128 %v1) unnamed_addr \
+             #N {",
+            "v2:",
+            "  %v3 = tail call i128 @__multi3(i128 %v0, i128 %v1), !dbg !N",
+            "  br label %v4",
+            "v4:",
+            "  store ptr @alloc_*, ptr %v3",
+            "  %v5 = load %\"core::fmt::Arguments<'_>\", ptr %v3",
+            "  ret i128 %v3",
+            "}\n",
+        ]
+        .join("\n");
+        assert_eq!(normalize(&ir), expected);
+        // The same function with other value names normalizes the same way
+        assert_eq!(normalize(&ir.replace("%_3", "%7").replace("bb2", "bb9")), expected);
+        assert_eq!(
+            snapshot_name(&demangled_name(ir.lines().next().unwrap())).as_deref(),
+            Some("muldiv.mul_div_floor")
+        );
+    }
+}
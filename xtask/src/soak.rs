@@ -0,0 +1,116 @@
+//! Randomized soak test of the built program, for overnight runs before a toolchain release:
+//!
+//! ```text
+//! U128_SOAK_SECONDS=600 cargo test -p xtask -- --ignored --nocapture soak
+//! ```
+//!
+//! Every op runs on random operands in the VM, round after round, until the time is up, and each
+//! result is checked against the native reference. The seed is printed, and `U128_SOAK_SEED`
+//! replays a run.
+
+use crate::{fixtures, ops, vm, Profile};
+use std::env;
+use std::fs;
+use std::path::Path;
+use std::time::{Duration, Instant, SystemTime, UNIX_EPOCH};
+
+/// How long the soak runs without `U128_SOAK_SECONDS`
+const DEFAULT_SECONDS: u64 = 60;
+/// Divergences listed in the failure message; the count covers all of them
+const REPORTED_DIVERGENCES: usize = 20;
+
+/// splitmix64, as in the fixture's build script
+struct Rng(u64);
+
+impl Rng {
+    fn next(&mut self) -> u64 {
+        self.0 = self.0.wrapping_add(0x9e37_79b9_7f4a_7c15);
+        let mut z = self.0;
+        z = (z ^ (z >> 30)).wrapping_mul(0xbf58_476d_1ce4_e5b9);
+        z = (z ^ (z >> 27)).wrapping_mul(0x94d0_49bb_1331_11eb);
+        z ^ (z >> 31)
+    }
+
+    fn next_u128(&mut self) -> u128 {
+        (u128::from(self.next()) << 64) | u128::from(self.next())
+    }
+
+    /// Uniform bits hit the edges too rarely, so a quarter of the operands are small, a quarter
+    /// have a random magnitude, and a quarter sit just beside a boundary value
+    fn operand(&mut self) -> u128 {
+        match self.next() % 4 {
+            0 => self.next_u128(),
+            1 => u128::from(self.next() % 1024),
+            2 => self.next_u128() >> (self.next() % 128),
+            _ => {
+                let boundary = ops::BOUNDARY_VALUES[self.next() as usize % ops::BOUNDARY_VALUES.len()];
+                let delta = (self.next() % 33) as i128 - 16;
+                boundary.wrapping_add_signed(delta)
+            }
+        }
+    }
+}
+
+fn env_u64(name: &str) -> Option<u64> {
+    let value = env::var(name).ok()?;
+    let parsed = match value.strip_prefix("0x") {
+        Some(hex) => u64::from_str_radix(hex, 16),
+        None => value.parse(),
+    };
+    Some(parsed.unwrap_or_else(|_| panic!("{name} is not a u64: {value}")))
+}
+
+#[test]
+#[ignore = "long-running; set U128_SOAK_SECONDS and run with --ignored"]
+fn soak() {
+    let seconds = env_u64("U128_SOAK_SECONDS").unwrap_or(DEFAULT_SECONDS);
+    let seed = env_u64("U128_SOAK_SEED")
+        .unwrap_or_else(|| SystemTime::now().duration_since(UNIX_EPOCH).unwrap().as_nanos() as u64);
+    let project_root = Path::new(env!("CARGO_MANIFEST_DIR")).parent().unwrap();
+    let program = Profile::Release.artifact(project_root);
+    let elf = fs::read(&program)
+        .unwrap_or_else(|err| panic!("failed to read {}: {err}. Run `cargo xtask build` first.", program.display()));
+    fixtures::check_echo(&elf).unwrap();
+    let loaded = vm::LoadedProgram::load(&elf).unwrap();
+    println!("Soaking {} for {seconds}s with U128_SOAK_SEED={seed:#x}", program.display());
+
+    let mut rng = Rng(seed);
+    let mut runs = vec![0u64; ops::OPS.len()];
+    let (mut undefined, mut divergences) = (0u64, Vec::new());
+    let start = Instant::now();
+    while start.elapsed() < Duration::from_secs(seconds) {
+        for (index, op) in ops::OPS.iter().enumerate() {
+            let operands: Vec<u128> = (0..op.arity).map(|_| rng.operand()).collect();
+            let Some(expected) = op.reference(&operands) else {
+                undefined += 1;
+                continue;
+            };
+            runs[index] += 1;
+            let outcome = loaded.execute(&op.encode(&operands)).unwrap();
+            if outcome.result.as_ref().ok() != Some(&expected) {
+                divergences.push(format!(
+                    "{} {operands:#x?}: expected {expected:#x}, got {:?}",
+                    op.name, outcome.result
+                ));
+            }
+        }
+    }
+
+    let elapsed = start.elapsed().as_secs_f64();
+    let total: u64 = runs.iter().sum();
+    println!("{:<24} {:>10}", "op", "runs");
+    for (op, runs) in ops::OPS.iter().zip(&runs) {
+        println!("{:<24} {runs:>10}", op.name);
+    }
+    println!(
+        "{total} runs in {elapsed:.1}s ({:.0}/s), {undefined} undefined inputs skipped, {} divergences",
+        total as f64 / elapsed,
+        divergences.len()
+    );
+    assert!(
+        divergences.is_empty(),
+        "{} divergences with U128_SOAK_SEED={seed:#x}:\n{}",
+        divergences.len(),
+        divergences[..divergences.len().min(REPORTED_DIVERGENCES)].join("\n")
+    );
+}
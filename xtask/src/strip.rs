@@ -0,0 +1,61 @@
+//! `cargo xtask build --strip`: the program as production programs are shipped, without debug
+//! info, the .comment section, or symbols nothing needs. The unstripped program is kept next to
+//! it for symbolizing traces and profiles.
+
+use crate::elf;
+use anyhow::{bail, Context, Result};
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::process::Command;
+
+/// The unstripped copy kept next to `program`, like `libu128_bpf_fixture.unstripped.so`
+fn unstripped_path(program: &Path) -> PathBuf {
+    program.with_extension("unstripped.so")
+}
+
+/// Strip `program` in place with the patched LLVM's llvm-objcopy, saving the unstripped copy
+/// first, and print the size before and after
+pub fn strip(program: &Path) -> Result<()> {
+    let objcopy = crate::LlvmDirs::selected()?.install.join("bin/llvm-objcopy");
+    if !objcopy.exists() {
+        bail!("{} not found. Run `cargo xtask build-llvm` first.", objcopy.display());
+    }
+    let stripped = program.with_extension("stripped.so");
+    crate::run_command(
+        Command::new(&objcopy)
+            .args(["--strip-unneeded", "--remove-section=.comment"])
+            .arg(program)
+            .arg(&stripped),
+        "strip the program",
+    )?;
+
+    let before = fs::read(program).with_context(|| format!("failed to read {}", program.display()))?;
+    let after = fs::read(&stripped).with_context(|| format!("failed to read {}", stripped.display()))?;
+    if after.len() == before.len() {
+        // Cargo didn't relink, so this is the program stripped by an earlier build
+        fs::remove_file(&stripped)?;
+        println!("{} is already stripped", program.display());
+        return Ok(());
+    }
+    let kept = elf::inspect(&after)?;
+    let removed: Vec<_> = elf::inspect(&before)?
+        .sections
+        .into_iter()
+        .map(|section| section.name)
+        .filter(|name| kept.section(name).is_none())
+        .collect();
+
+    let unstripped = unstripped_path(program);
+    fs::write(&unstripped, &before).with_context(|| format!("failed to write {}", unstripped.display()))?;
+    fs::rename(&stripped, program).with_context(|| format!("failed to replace {}", program.display()))?;
+    println!(
+        "Stripped {}: {} -> {} bytes ({:.1}% smaller), removed {}",
+        program.display(),
+        before.len(),
+        after.len(),
+        100.0 * (1.0 - after.len() as f64 / before.len() as f64),
+        if removed.is_empty() { "symbols only".to_string() } else { removed.join(", ") }
+    );
+    println!("  unstripped copy: {}", unstripped.display());
+    Ok(())
+}
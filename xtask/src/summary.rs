@@ -0,0 +1,49 @@
+//! Compact markdown reports for PR comments and GitHub step summaries. Each command appends its
+//! section, so several can share one file, like `$GITHUB_STEP_SUMMARY`.
+
+use anyhow::{Context, Result};
+use std::fs::{self, OpenOptions};
+use std::io::Write as _;
+use std::path::Path;
+
+/// Append `markdown` to the summary at `path`, creating it if needed
+pub fn append(path: &Path, markdown: &str) -> Result<()> {
+    if let Some(dir) = path.parent().filter(|dir| !dir.as_os_str().is_empty()) {
+        fs::create_dir_all(dir)?;
+    }
+    let mut file = OpenOptions::new()
+        .create(true)
+        .append(true)
+        .open(path)
+        .with_context(|| format!("failed to open {}", path.display()))?;
+    writeln!(file, "{markdown}").with_context(|| format!("failed to write {}", path.display()))?;
+    println!("Markdown summary appended to {}", path.display());
+    Ok(())
+}
+
+/// A markdown table with the first column left-aligned and the others, numbers, right-aligned
+pub fn table(header: &[&str], rows: &[Vec<String>]) -> String {
+    let line = |cells: &[&str]| format!("| {} |\n", cells.join(" | "));
+    let mut table = line(header);
+    let alignment: Vec<_> = (0..header.len()).map(|column| if column == 0 { ":--" } else { "--:" }).collect();
+    table.push_str(&line(&alignment));
+    for row in rows {
+        table.push_str(&line(&row.iter().map(String::as_str).collect::<Vec<_>>()));
+    }
+    table
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn renders_tables() {
+        let rows = vec![
+            vec!["mul_native".to_string(), "120".to_string()],
+            vec!["div_native".to_string(), "1,024".to_string()],
+        ];
+        let expected = "| op | CU |\n| :-- | --: |\n| mul_native | 120 |\n| div_native | 1,024 |\n";
+        assert_eq!(table(&["op", "CU"], &rows), expected);
+    }
+}
@@ -0,0 +1,156 @@
+//! The nightly the custom flow is known to work with, pinned with its components in
+//! `rust-toolchain.toml`. Every command that runs cargo for BPF uses the pin, builds check the
+//! toolchain they run under matches it, and `cargo xtask bump-nightly` moves it deliberately.
+
+use crate::Profile;
+use anyhow::{bail, Context, Result};
+use serde::Deserialize;
+use std::fs;
+use std::path::Path;
+use std::process::Command;
+
+const TOOLCHAIN_FILE: &str = "rust-toolchain.toml";
+
+/// `[toolchain]` of rust-toolchain.toml
+#[derive(Deserialize)]
+pub struct Pin {
+    /// The pinned nightly, like `nightly-2026-05-19`
+    pub channel: String,
+    #[serde(default)]
+    pub components: Vec<String>,
+}
+
+/// The toolchain pinned in the project's rust-toolchain.toml
+pub fn pinned(project_root: &Path) -> Result<Pin> {
+    #[derive(Deserialize)]
+    struct ToolchainFile {
+        toolchain: Pin,
+    }
+
+    let path = project_root.join(TOOLCHAIN_FILE);
+    let contents = fs::read_to_string(&path).with_context(|| format!("failed to read {}", path.display()))?;
+    let file: ToolchainFile =
+        toml::from_str(&contents).with_context(|| format!("failed to parse {}", path.display()))?;
+    if !file.toolchain.channel.starts_with("nightly-") {
+        bail!(
+            "{} pins `{}`; the BPF build needs a dated nightly like `nightly-2026-05-19`",
+            path.display(),
+            file.toolchain.channel
+        );
+    }
+    Ok(file.toolchain)
+}
+
+/// The pinned nightly, for `cargo +<nightly>`
+pub fn nightly(project_root: &Path) -> Result<String> {
+    Ok(pinned(project_root)?.channel)
+}
+
+/// The components of `pin` missing from its installed toolchain, or `None` if the toolchain
+/// itself isn't installed
+pub fn missing_components(pin: &Pin) -> Result<Option<Vec<String>>> {
+    let toolchains = Command::new("rustup")
+        .args(["toolchain", "list"])
+        .output()
+        .context("failed to run rustup (is it installed?)")?;
+    let installed = String::from_utf8_lossy(&toolchains.stdout)
+        .lines()
+        .any(|line| line.starts_with(&pin.channel));
+    if !installed {
+        return Ok(None);
+    }
+    let components = Command::new("rustup")
+        .args(["component", "list", "--installed", "--toolchain", &pin.channel])
+        .output()
+        .context("failed to list rustup components")?;
+    let components = String::from_utf8_lossy(&components.stdout);
+    Ok(Some(
+        pin.components
+            .iter()
+            .filter(|component| !components.lines().any(|line| line.starts_with(component.as_str())))
+            .cloned()
+            .collect(),
+    ))
+}
+
+/// Fail unless the pinned nightly is installed with its components and xtask runs under it.
+/// rustup passes the toolchain it picked on to xtask in `RUSTUP_TOOLCHAIN`, so a `+toolchain` or
+/// `rustup override` that shadows the pin shows up there.
+pub fn check_active(project_root: &Path) -> Result<()> {
+    let pin = pinned(project_root)?;
+    match missing_components(&pin)? {
+        None => bail!("{} is not installed. Run `cargo xtask setup` first.", pin.channel),
+        Some(missing) if !missing.is_empty() => {
+            bail!("{} lacks {}. Run `cargo xtask setup` first.", pin.channel, missing.join(", "))
+        }
+        Some(_) => {}
+    }
+    if let Ok(active) = std::env::var("RUSTUP_TOOLCHAIN") {
+        if !active.starts_with(&pin.channel) {
+            bail!(
+                "xtask runs under {active}, but {TOOLCHAIN_FILE} pins {}. Drop the `+toolchain`, \
+                 RUSTUP_TOOLCHAIN, or `rustup override` that selects it, or move the pin with \
+                 `cargo xtask bump-nightly`.",
+                pin.channel
+            );
+        }
+    }
+    Ok(())
+}
+
+/// Pin `nightly-<date>` if the program builds, passes the smoke test, and passes the tests with
+/// it, and leave the pin as it was otherwise
+pub fn bump_nightly(project_root: &Path, date: &str) -> Result<()> {
+    let date = date.strip_prefix("nightly-").unwrap_or(date);
+    let digits = date.split('-').map(str::len).collect::<Vec<_>>();
+    if digits != [4, 2, 2] || !date.chars().all(|c| c.is_ascii_digit() || c == '-') {
+        bail!("expected a nightly date like 2026-05-19, not {date}");
+    }
+    let pin = pinned(project_root)?;
+    let candidate = format!("nightly-{date}");
+    if candidate == pin.channel {
+        println!("{TOOLCHAIN_FILE} already pins {candidate}");
+        return Ok(());
+    }
+
+    println!("Installing {candidate} with {}...", pin.components.join(", "));
+    crate::run_command(
+        Command::new("rustup")
+            .args(["toolchain", "install", &candidate, "--profile", "minimal"])
+            .args(pin.components.iter().flat_map(|component| ["--component", component])),
+        &format!("install {candidate}"),
+    )?;
+
+    let path = project_root.join(TOOLCHAIN_FILE);
+    let original = fs::read_to_string(&path).with_context(|| format!("failed to read {}", path.display()))?;
+    let bumped = original.replace(&format!("\"{}\"", pin.channel), &format!("\"{candidate}\""));
+    fs::write(&path, bumped).with_context(|| format!("failed to write {}", path.display()))?;
+    // The cargo processes below would otherwise follow the toolchain xtask was started with
+    std::env::set_var("RUSTUP_TOOLCHAIN", &candidate);
+
+    let steps: [(&str, &dyn Fn() -> Result<()>); 3] = [
+        ("build", &|| crate::build_all(project_root, Profile::Release)),
+        ("smoke-test", &|| crate::smoke::smoke_test(project_root)),
+        ("tests", &|| {
+            crate::run_command_prefixed(
+                Command::new("cargo").args(["test", "--workspace"]).current_dir(project_root),
+                "run the tests",
+                "test",
+            )
+        }),
+    ];
+    for (name, step) in steps {
+        println!("==> {name} with {candidate}");
+        if let Err(err) = step() {
+            fs::write(&path, &original).with_context(|| format!("failed to restore {}", path.display()))?;
+            return Err(err.context(format!(
+                "{candidate} fails the {name} step; {TOOLCHAIN_FILE} still pins {}",
+                pin.channel
+            )));
+        }
+    }
+    println!();
+    println!("{candidate} builds, runs, and passes the tests. {TOOLCHAIN_FILE} now pins it instead of");
+    println!("{}; commit the change.", pin.channel);
+    Ok(())
+}
@@ -0,0 +1,106 @@
+//! Rebuild the program inside the pinned container image and check it is bit-for-bit the
+//! artifact `xtask package` produced.
+
+use anyhow::{bail, Context, Result};
+use serde::Deserialize;
+use sha2::{Digest, Sha256};
+use std::collections::BTreeMap;
+use std::fs;
+use std::path::Path;
+use std::process::Command;
+
+const IMAGE: &str = "upstream-u128-fixture-build";
+const DOCKERFILE: &str = "docker/Dockerfile";
+/// Kept apart from target/bpfel-unknown-none so the host build is left untouched
+const VERIFY_TARGET_DIR: &str = "target/verify";
+const PROVENANCE: &str = "target/package/provenance.json";
+
+/// The part of provenance.json written by `xtask package` this check needs
+#[derive(Deserialize)]
+struct Provenance {
+    artifact: Artifact,
+    #[serde(default)]
+    env: BTreeMap<String, String>,
+}
+
+#[derive(Deserialize)]
+struct Artifact {
+    file: String,
+    sha256: String,
+}
+
+/// Build the image from docker/Dockerfile, build the program in it, and compare the hash of the
+/// result with the packaged artifact
+pub fn verify_build(project_root: &Path) -> Result<()> {
+    let provenance_path = project_root.join(PROVENANCE);
+    let provenance: Provenance = serde_json::from_str(
+        &fs::read_to_string(&provenance_path).with_context(|| {
+            format!(
+                "failed to read {}. Run `cargo xtask package` first.",
+                provenance_path.display()
+            )
+        })?,
+    )
+    .with_context(|| format!("failed to parse {}", provenance_path.display()))?;
+
+    println!("Building the {IMAGE} image...");
+    crate::run_command_prefixed(
+        Command::new("docker")
+            .args(["build", "--tag", IMAGE, "--file", DOCKERFILE, "."])
+            .current_dir(project_root),
+        "build the container image",
+        "docker",
+    )?;
+
+    println!("Building the program in the container...");
+    // The checkout's .cargo/config.toml points at the host's linker, so the flags it sets are
+    // replaced with ones using the linker installed in the image
+    let rustflags = [
+        "-C linker=sbpf-linker".to_string(),
+        "-C panic=abort".to_string(),
+        format!("-C link-arg=--llvm-args=-bpf-stack-size={}", crate::cargo_config::DEFAULT_STACK_SIZE),
+        "-C relocation-model=static".to_string(),
+    ]
+    .join(" ");
+    // The program embeds the toolchain record of the packaged build, which the rebuild must
+    // reproduce
+    let record_env = crate::TOOLCHAIN_RECORD_ENV.iter().filter_map(|&name| {
+        let value = provenance.env.get(name)?;
+        Some(["--env".to_string(), format!("{name}={value}")])
+    });
+    crate::run_command_prefixed(
+        Command::new("docker")
+            .args(["run", "--rm", "--volume"])
+            .arg(format!("{}:/work", project_root.display()))
+            .args(record_env.flatten())
+            .args(["--env", &format!("RUSTFLAGS={rustflags}"), IMAGE])
+            .args(["cargo", &format!("+{}", crate::toolchain::nightly(project_root)?)])
+            .args(["build", "--package", crate::FIXTURE_PACKAGE])
+            .args(["--release", "--target", crate::BPF_TARGET])
+            .args(["-Zbuild-std=core,alloc", "--target-dir", VERIFY_TARGET_DIR])
+            .current_dir(project_root),
+        "build the program in the container",
+        "verify",
+    )?;
+
+    let rebuilt = project_root
+        .join(VERIFY_TARGET_DIR)
+        .join(crate::BPF_TARGET)
+        .join("release")
+        .join(&provenance.artifact.file);
+    let elf = fs::read(&rebuilt).with_context(|| format!("failed to read {}", rebuilt.display()))?;
+    let sha256 = format!("{:x}", Sha256::digest(&elf));
+
+    println!();
+    println!("  packaged: {}  {}", provenance.artifact.sha256, provenance.artifact.file);
+    println!("  rebuilt:  {sha256}  {}", rebuilt.display());
+    if sha256 != provenance.artifact.sha256 {
+        bail!(
+            "the container build does not reproduce the packaged artifact. Compare them with `cargo xtask bytediff target/package/{} {}`.",
+            provenance.artifact.file,
+            rebuilt.display()
+        );
+    }
+    println!("The packaged artifact is reproducible");
+    Ok(())
+}
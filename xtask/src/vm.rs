@@ -0,0 +1,835 @@
+use anyhow::{anyhow, Context, Result};
+use solana_sbpf::{
+    declare_builtin_function, ebpf,
+    elf::Executable,
+    error::EbpfError,
+    memory_region::{AccessType, MemoryMapping, MemoryRegion},
+    program::{BuiltinProgram, SBPFVersion},
+    static_analysis::{Analysis, TraceLogEntry},
+    verifier::RequisiteVerifier,
+    vm::{Config, ContextObject, EbpfVm},
+};
+use std::collections::{BTreeMap, BTreeSet};
+use std::fs::File;
+use std::io::{BufWriter, Write};
+use std::path::Path;
+use std::sync::Arc;
+use u128_bpf_fixture::clock::Clock;
+
+/// Program id the fixture is serialized with (matches the Mollusk tests)
+pub const PROGRAM_ID: [u8; 32] = [0x02; 32];
+/// Maximum compute budget a single transaction can request
+pub const COMPUTE_BUDGET: u64 = 1_400_000;
+const HEAP_SIZE: usize = 32 * 1024;
+/// Clock sysvar the fixture reads: slot 1000, 400 seconds into epoch 2
+pub const CLOCK: Clock = Clock {
+    slot: 1_000,
+    epoch_start_timestamp: 1_700_000_000,
+    epoch: 2,
+    leader_schedule_epoch: 3,
+    unix_timestamp: 1_700_000_400,
+};
+
+/// Host-side state shared with the syscalls during a run
+pub struct RunContext {
+    remaining: u64,
+    pub logs: Vec<String>,
+    pub return_data: Vec<u8>,
+    pub trace_log: Vec<TraceLogEntry>,
+}
+
+impl RunContext {
+    fn new(budget: u64) -> Self {
+        Self {
+            remaining: budget,
+            logs: Vec::new(),
+            return_data: Vec::new(),
+            trace_log: Vec::new(),
+        }
+    }
+}
+
+impl ContextObject for RunContext {
+    fn trace(&mut self, state: [u64; 12]) {
+        self.trace_log.push(state);
+    }
+
+    fn consume(&mut self, amount: u64) {
+        self.remaining = self.remaining.saturating_sub(amount);
+    }
+
+    fn get_remaining(&self) -> u64 {
+        self.remaining
+    }
+}
+
+/// Outcome of executing the fixture once
+pub struct RunOutcome {
+    pub instruction_count: u64,
+    pub result: Result<u64, EbpfError>,
+    pub logs: Vec<String>,
+    pub return_data: Vec<u8>,
+}
+
+fn translate_slice<'a>(
+    memory_mapping: &MemoryMapping,
+    access_type: AccessType,
+    vm_addr: u64,
+    len: u64,
+) -> Result<&'a mut [u8], EbpfError> {
+    let host_addr: Result<u64, EbpfError> = memory_mapping.map(access_type, vm_addr, len).into();
+    Ok(unsafe { std::slice::from_raw_parts_mut(host_addr? as *mut u8, len as usize) })
+}
+
+declare_builtin_function!(
+    /// sol_log_
+    SyscallLog,
+    fn rust(
+        context: &mut RunContext,
+        addr: u64,
+        len: u64,
+        _arg3: u64,
+        _arg4: u64,
+        _arg5: u64,
+        memory_mapping: &mut MemoryMapping,
+    ) -> Result<u64, Box<dyn std::error::Error>> {
+        let bytes = translate_slice(memory_mapping, AccessType::Load, addr, len)?;
+        context.logs.push(format!("Program log: {}", String::from_utf8_lossy(bytes)));
+        Ok(0)
+    }
+);
+
+declare_builtin_function!(
+    /// sol_log_64_
+    SyscallLog64,
+    fn rust(
+        context: &mut RunContext,
+        arg1: u64,
+        arg2: u64,
+        arg3: u64,
+        arg4: u64,
+        arg5: u64,
+        _memory_mapping: &mut MemoryMapping,
+    ) -> Result<u64, Box<dyn std::error::Error>> {
+        context.logs.push(format!(
+            "Program log: {arg1:#x}, {arg2:#x}, {arg3:#x}, {arg4:#x}, {arg5:#x}"
+        ));
+        Ok(0)
+    }
+);
+
+declare_builtin_function!(
+    /// sol_log_compute_units_
+    SyscallLogComputeUnits,
+    fn rust(
+        context: &mut RunContext,
+        _arg1: u64,
+        _arg2: u64,
+        _arg3: u64,
+        _arg4: u64,
+        _arg5: u64,
+        _memory_mapping: &mut MemoryMapping,
+    ) -> Result<u64, Box<dyn std::error::Error>> {
+        let remaining = context.get_remaining();
+        context.logs.push(format!("Program consumption: {remaining} units remaining"));
+        Ok(0)
+    }
+);
+
+declare_builtin_function!(
+    /// sol_set_return_data
+    SyscallSetReturnData,
+    fn rust(
+        context: &mut RunContext,
+        addr: u64,
+        len: u64,
+        _arg3: u64,
+        _arg4: u64,
+        _arg5: u64,
+        memory_mapping: &mut MemoryMapping,
+    ) -> Result<u64, Box<dyn std::error::Error>> {
+        context.return_data = translate_slice(memory_mapping, AccessType::Load, addr, len)?.to_vec();
+        Ok(0)
+    }
+);
+
+declare_builtin_function!(
+    /// sol_get_return_data
+    SyscallGetReturnData,
+    fn rust(
+        context: &mut RunContext,
+        addr: u64,
+        len: u64,
+        program_id_addr: u64,
+        _arg4: u64,
+        _arg5: u64,
+        memory_mapping: &mut MemoryMapping,
+    ) -> Result<u64, Box<dyn std::error::Error>> {
+        // Like the runtime: copy what fits, and leave both buffers alone when there is nothing
+        let copied = len.min(context.return_data.len() as u64);
+        if copied > 0 {
+            translate_slice(memory_mapping, AccessType::Store, addr, copied)?
+                .copy_from_slice(&context.return_data[..copied as usize]);
+            translate_slice(memory_mapping, AccessType::Store, program_id_addr, PROGRAM_ID.len() as u64)?
+                .copy_from_slice(&PROGRAM_ID);
+        }
+        Ok(context.return_data.len() as u64)
+    }
+);
+
+declare_builtin_function!(
+    /// sol_memcmp_
+    SyscallMemcmp,
+    fn rust(
+        _context: &mut RunContext,
+        s1: u64,
+        s2: u64,
+        n: u64,
+        result_addr: u64,
+        _arg5: u64,
+        memory_mapping: &mut MemoryMapping,
+    ) -> Result<u64, Box<dyn std::error::Error>> {
+        let s1 = translate_slice(memory_mapping, AccessType::Load, s1, n)?;
+        let s2 = translate_slice(memory_mapping, AccessType::Load, s2, n)?;
+        // The difference of the first bytes that differ, as the runtime reports it
+        let result = s1
+            .iter()
+            .zip(s2.iter())
+            .find(|(x, y)| x != y)
+            .map_or(0, |(&x, &y)| i32::from(x) - i32::from(y));
+        translate_slice(memory_mapping, AccessType::Store, result_addr, 4)?.copy_from_slice(&result.to_le_bytes());
+        Ok(0)
+    }
+);
+
+declare_builtin_function!(
+    /// sol_memcpy_
+    SyscallMemcpy,
+    fn rust(
+        _context: &mut RunContext,
+        dst: u64,
+        src: u64,
+        n: u64,
+        _arg4: u64,
+        _arg5: u64,
+        memory_mapping: &mut MemoryMapping,
+    ) -> Result<u64, Box<dyn std::error::Error>> {
+        if src < dst.saturating_add(n) && dst < src.saturating_add(n) {
+            return Err("sol_memcpy_ called with overlapping buffers".into());
+        }
+        let src = translate_slice(memory_mapping, AccessType::Load, src, n)?;
+        translate_slice(memory_mapping, AccessType::Store, dst, n)?.copy_from_slice(src);
+        Ok(0)
+    }
+);
+
+declare_builtin_function!(
+    /// sol_memmove_
+    SyscallMemmove,
+    fn rust(
+        _context: &mut RunContext,
+        dst: u64,
+        src: u64,
+        n: u64,
+        _arg4: u64,
+        _arg5: u64,
+        memory_mapping: &mut MemoryMapping,
+    ) -> Result<u64, Box<dyn std::error::Error>> {
+        // The buffers may overlap, so copy through host pointers rather than slices
+        let src = translate_slice(memory_mapping, AccessType::Load, src, n)?.as_ptr();
+        let dst = translate_slice(memory_mapping, AccessType::Store, dst, n)?.as_mut_ptr();
+        unsafe { std::ptr::copy(src, dst, n as usize) };
+        Ok(0)
+    }
+);
+
+declare_builtin_function!(
+    /// sol_get_clock_sysvar
+    SyscallGetClockSysvar,
+    fn rust(
+        _context: &mut RunContext,
+        addr: u64,
+        _arg2: u64,
+        _arg3: u64,
+        _arg4: u64,
+        _arg5: u64,
+        memory_mapping: &mut MemoryMapping,
+    ) -> Result<u64, Box<dyn std::error::Error>> {
+        let clock = [
+            CLOCK.slot.to_le_bytes(),
+            CLOCK.epoch_start_timestamp.to_le_bytes(),
+            CLOCK.epoch.to_le_bytes(),
+            CLOCK.leader_schedule_epoch.to_le_bytes(),
+            CLOCK.unix_timestamp.to_le_bytes(),
+        ];
+        translate_slice(memory_mapping, AccessType::Store, addr, size_of::<Clock>() as u64)?
+            .copy_from_slice(clock.as_flattened());
+        Ok(0)
+    }
+);
+
+declare_builtin_function!(
+    /// sol_invoke_signed_c
+    SyscallInvokeSignedC,
+    fn rust(
+        _context: &mut RunContext,
+        _instruction: u64,
+        _account_infos: u64,
+        _account_infos_len: u64,
+        _signers_seeds: u64,
+        _signers_seeds_len: u64,
+        _memory_mapping: &mut MemoryMapping,
+    ) -> Result<u64, Box<dyn std::error::Error>> {
+        // A single program in a bare VM has no runtime to dispatch the callee; the Mollusk tests
+        // cover CPI
+        Err("cross-program invocation is not supported outside a runtime".into())
+    }
+);
+
+declare_builtin_function!(
+    /// sol_create_program_address and sol_try_find_program_address
+    SyscallProgramAddress,
+    fn rust(
+        _context: &mut RunContext,
+        _seeds: u64,
+        _seeds_len: u64,
+        _program_id: u64,
+        _address: u64,
+        _bump_seed: u64,
+        _memory_mapping: &mut MemoryMapping,
+    ) -> Result<u64, Box<dyn std::error::Error>> {
+        // Telling a PDA from an on-curve point takes an ed25519 implementation the xtask doesn't
+        // carry; the Mollusk tests cover PDA derivation
+        Err("program address derivation is not supported outside a runtime".into())
+    }
+);
+
+declare_builtin_function!(
+    /// abort
+    SyscallAbort,
+    fn rust(
+        _context: &mut RunContext,
+        _arg1: u64,
+        _arg2: u64,
+        _arg3: u64,
+        _arg4: u64,
+        _arg5: u64,
+        _memory_mapping: &mut MemoryMapping,
+    ) -> Result<u64, Box<dyn std::error::Error>> {
+        Err("program aborted".into())
+    }
+);
+
+declare_builtin_function!(
+    /// sol_panic_
+    SyscallPanic,
+    fn rust(
+        _context: &mut RunContext,
+        file: u64,
+        len: u64,
+        line: u64,
+        column: u64,
+        _arg5: u64,
+        memory_mapping: &mut MemoryMapping,
+    ) -> Result<u64, Box<dyn std::error::Error>> {
+        let file = translate_slice(memory_mapping, AccessType::Load, file, len)?;
+        Err(format!(
+            "program panicked at {}:{line}:{column}",
+            String::from_utf8_lossy(file)
+        )
+        .into())
+    }
+);
+
+fn loader(trace: bool) -> Result<Arc<BuiltinProgram<RunContext>>> {
+    let config = Config {
+        enable_instruction_tracing: trace,
+        // Keep function names so disassembly and snapshots are keyed by symbol, not hash
+        enable_symbol_and_section_labels: true,
+        enabled_sbpf_versions: SBPFVersion::V0..=SBPFVersion::V3,
+        ..Config::default()
+    };
+    let mut loader = BuiltinProgram::new_loader(config);
+    loader.register_function("sol_log_", SyscallLog::vm)?;
+    loader.register_function("sol_log_64_", SyscallLog64::vm)?;
+    loader.register_function("sol_log_compute_units_", SyscallLogComputeUnits::vm)?;
+    loader.register_function("sol_set_return_data", SyscallSetReturnData::vm)?;
+    loader.register_function("sol_get_return_data", SyscallGetReturnData::vm)?;
+    loader.register_function("sol_memcmp_", SyscallMemcmp::vm)?;
+    loader.register_function("sol_memcpy_", SyscallMemcpy::vm)?;
+    loader.register_function("sol_memmove_", SyscallMemmove::vm)?;
+    loader.register_function("sol_get_clock_sysvar", SyscallGetClockSysvar::vm)?;
+    loader.register_function("sol_invoke_signed_c", SyscallInvokeSignedC::vm)?;
+    loader.register_function("sol_create_program_address", SyscallProgramAddress::vm)?;
+    loader.register_function("sol_try_find_program_address", SyscallProgramAddress::vm)?;
+    loader.register_function("abort", SyscallAbort::vm)?;
+    loader.register_function("sol_panic_", SyscallPanic::vm)?;
+    Ok(Arc::new(loader))
+}
+
+fn load(elf: &[u8], trace: bool) -> Result<Executable<RunContext>> {
+    let executable = Executable::from_elf(elf, loader(trace)?)
+        .map_err(|err| anyhow!("failed to load ELF: {err}"))?;
+    executable
+        .verify::<RequisiteVerifier>()
+        .map_err(|err| anyhow!("ELF failed verification: {err}"))?;
+    Ok(executable)
+}
+
+/// Static facts about a program as the loader sees it
+pub struct ProgramSummary {
+    pub text_size: usize,
+    pub instruction_count: usize,
+    pub disassembly: String,
+}
+
+/// Load `elf` without executing it and disassemble its text section
+pub fn summarize(elf: &[u8]) -> Result<ProgramSummary> {
+    let executable = load(elf, false)?;
+    let analysis = Analysis::from_executable(&executable)
+        .map_err(|err| anyhow!("failed to analyze executable: {err}"))?;
+    let mut disassembly = Vec::new();
+    analysis.disassemble(&mut disassembly)?;
+    Ok(ProgramSummary {
+        text_size: executable.get_text_bytes().1.len(),
+        instruction_count: analysis.instructions.len(),
+        disassembly: String::from_utf8_lossy(&disassembly).into_owned(),
+    })
+}
+
+/// Disassembly of each function the loader registered, keyed by name
+pub fn disassemble_functions(elf: &[u8]) -> Result<BTreeMap<String, String>> {
+    let executable = load(elf, false)?;
+    let analysis = Analysis::from_executable(&executable)
+        .map_err(|err| anyhow!("failed to analyze executable: {err}"))?;
+
+    let starts: Vec<_> = analysis.functions.keys().copied().collect();
+    let mut functions = BTreeMap::new();
+    for (i, (&start, (_, name))) in analysis.functions.iter().enumerate() {
+        let end = starts.get(i + 1).copied().unwrap_or(usize::MAX);
+        let mut listing = Vec::new();
+        let mut last_basic_block = usize::MAX;
+        for (index, insn) in analysis.instructions.iter().enumerate() {
+            if insn.ptr < start || insn.ptr >= end {
+                continue;
+            }
+            analysis.disassemble_label(&mut listing, true, insn.ptr, &mut last_basic_block)?;
+            writeln!(listing, "    {}", analysis.disassemble_instruction(insn, index))?;
+        }
+        functions.insert(name.clone(), String::from_utf8_lossy(&listing).into_owned());
+    }
+    Ok(functions)
+}
+
+/// One disassembled instruction and its offset in bytes from the start of its function
+pub struct ListedInstruction {
+    /// Instruction index into .text
+    pub pc: usize,
+    pub offset: usize,
+    pub text: String,
+}
+
+/// The instructions of each function the loader registered, keyed by name
+pub fn function_instructions(elf: &[u8]) -> Result<BTreeMap<String, Vec<ListedInstruction>>> {
+    let executable = load(elf, false)?;
+    let analysis = Analysis::from_executable(&executable)
+        .map_err(|err| anyhow!("failed to analyze executable: {err}"))?;
+
+    let mut functions = BTreeMap::new();
+    for (&start, (_, name)) in &analysis.functions {
+        let end = analysis
+            .functions
+            .range(start + 1..)
+            .next()
+            .map_or(usize::MAX, |(&next, _)| next);
+        let instructions = analysis
+            .instructions
+            .iter()
+            .enumerate()
+            .filter(|(_, insn)| (start..end).contains(&insn.ptr))
+            .map(|(index, insn)| ListedInstruction {
+                pc: insn.ptr,
+                offset: (insn.ptr - start) * ebpf::INSN_SIZE,
+                text: analysis.disassemble_instruction(insn, index),
+            })
+            .collect();
+        functions.insert(name.clone(), instructions);
+    }
+    Ok(functions)
+}
+
+/// Control-flow graph of the function named `symbol` as Graphviz DOT, one node per basic block
+pub fn function_cfg_dot(elf: &[u8], symbol: &str) -> Result<String> {
+    let executable = load(elf, false)?;
+    let analysis = Analysis::from_executable(&executable)
+        .map_err(|err| anyhow!("failed to analyze executable: {err}"))?;
+
+    let start = analysis
+        .functions
+        .iter()
+        .find(|(_, (_, name))| name == symbol)
+        .map(|(&start, _)| start)
+        .ok_or_else(|| {
+            let names: Vec<_> = analysis.functions.values().map(|(_, name)| name.as_str()).collect();
+            anyhow!("no function named {symbol} (available: {})", names.join(", "))
+        })?;
+    let end = analysis
+        .functions
+        .range(start + 1..)
+        .next()
+        .map_or(usize::MAX, |(&next, _)| next);
+    let function = start..end;
+
+    let escape = |text: &str| text.replace('\\', "\\\\").replace('"', "\\\"");
+    let mut dot = format!("digraph \"{}\" {{\n    node [shape=box fontname=monospace];\n", escape(symbol));
+    // The analysis adds a virtual super root after the last instruction; it isn't code
+    let blocks: Vec<_> = analysis
+        .cfg_nodes
+        .range(function.clone())
+        .filter(|(&pc, _)| pc != analysis.super_root)
+        .collect();
+    for (i, (&pc, node)) in blocks.iter().enumerate() {
+        let mut label = format!("{}:\\l", escape(&node.label));
+        for index in node.instructions.clone() {
+            let insn = &analysis.instructions[index];
+            label.push_str(&format!("    {}\\l", escape(&analysis.disassemble_instruction(insn, index))));
+        }
+        dot.push_str(&format!("    b{pc} [label=\"{label}\"];\n"));
+
+        let fallthrough = blocks.get(i + 1).map(|(&next, _)| next);
+        for &destination in node.destinations.iter().filter(|d| function.contains(d)) {
+            let style = if node.destinations.len() > 1 && Some(destination) == fallthrough {
+                " [style=dashed]"
+            } else {
+                ""
+            };
+            dot.push_str(&format!("    b{pc} -> b{destination}{style};\n"));
+        }
+    }
+    dot.push_str("}\n");
+    Ok(dot)
+}
+
+/// Serialize `data` the way the aligned BPF loader does for an instruction without accounts
+pub fn serialize_input(data: &[u8]) -> Vec<u8> {
+    let mut input = Vec::with_capacity(16 + data.len() + PROGRAM_ID.len());
+    input.extend_from_slice(&0u64.to_le_bytes());
+    input.extend_from_slice(&(data.len() as u64).to_le_bytes());
+    input.extend_from_slice(data);
+    input.extend_from_slice(&PROGRAM_ID);
+    input
+}
+
+/// Execute a loaded program once with `data` as instruction data, returning the syscall context,
+/// the instructions the meter charged, and the result
+fn run(executable: &Executable<RunContext>, data: &[u8]) -> Result<(RunContext, u64, Result<u64, EbpfError>)> {
+    let config = executable.get_config();
+    let sbpf_version = executable.get_sbpf_version();
+    let mut stack = vec![0u8; config.stack_size()];
+    let mut heap = vec![0u8; HEAP_SIZE];
+    let mut input = serialize_input(data);
+    let stack_gap = if !sbpf_version.dynamic_stack_frames() && config.enable_stack_frame_gaps {
+        config.stack_frame_size as u64
+    } else {
+        0
+    };
+    let regions = vec![
+        executable.get_ro_region(),
+        MemoryRegion::new_writable_gapped(&mut stack, ebpf::MM_STACK_START, stack_gap),
+        MemoryRegion::new_writable(&mut heap, ebpf::MM_HEAP_START),
+        MemoryRegion::new_writable(&mut input, ebpf::MM_INPUT_START),
+    ];
+    let memory_mapping = MemoryMapping::new(regions, config, sbpf_version)
+        .map_err(|err| anyhow!("failed to create memory mapping: {err}"))?;
+
+    let mut context = RunContext::new(COMPUTE_BUDGET);
+    let mut vm = EbpfVm::new(
+        executable.get_loader().clone(),
+        sbpf_version,
+        &mut context,
+        memory_mapping,
+        config.stack_size(),
+    );
+    let (instruction_count, result) = vm.execute_program(executable, true);
+    Ok((context, instruction_count, result.into()))
+}
+
+/// Load `elf` into the interpreter and execute it once with `data` as instruction data.
+///
+/// When `trace_path` is set, every executed instruction is written there together with the
+/// register file before it ran.
+pub fn execute(elf: &[u8], data: &[u8], trace_path: Option<&Path>) -> Result<RunOutcome> {
+    let executable = load(elf, trace_path.is_some())?;
+    let (mut context, instruction_count, result) = run(&executable, data)?;
+
+    if let Some(trace_path) = trace_path {
+        let analysis = Analysis::from_executable(&executable)
+            .map_err(|err| anyhow!("failed to analyze executable: {err}"))?;
+        let file = File::create(trace_path)
+            .with_context(|| format!("failed to create {}", trace_path.display()))?;
+        let mut writer = BufWriter::new(file);
+        analysis
+            .disassemble_trace_log(&mut writer, &context.trace_log)
+            .and_then(|_| writer.flush())
+            .with_context(|| format!("failed to write trace to {}", trace_path.display()))?;
+    }
+
+    Ok(RunOutcome {
+        instruction_count,
+        result,
+        logs: std::mem::take(&mut context.logs),
+        return_data: std::mem::take(&mut context.return_data),
+    })
+}
+
+/// A program loaded and verified once, to execute on many inputs without reloading it
+pub struct LoadedProgram {
+    executable: Executable<RunContext>,
+}
+
+impl LoadedProgram {
+    pub fn load(elf: &[u8]) -> Result<Self> {
+        Ok(Self {
+            executable: load(elf, false)?,
+        })
+    }
+
+    /// Execute the program once with `data` as instruction data
+    pub fn execute(&self, data: &[u8]) -> Result<RunOutcome> {
+        let (mut context, instruction_count, result) = run(&self.executable, data)?;
+        Ok(RunOutcome {
+            instruction_count,
+            result,
+            logs: std::mem::take(&mut context.logs),
+            return_data: std::mem::take(&mut context.return_data),
+        })
+    }
+}
+
+/// How an executed instruction moves between functions
+#[derive(PartialEq)]
+enum Step {
+    Call,
+    Syscall,
+    Return,
+    Other,
+}
+
+/// Classify the instruction at `pc`, as the interpreter of `executable`'s SBPF version would run it
+fn step(executable: &Executable<RunContext>, pc: usize) -> Step {
+    let insn = ebpf::get_insn(executable.get_text_bytes().1, pc);
+    let static_syscalls = executable.get_sbpf_version().static_syscalls();
+    match insn.opc {
+        ebpf::CALL_IMM
+            if !static_syscalls
+                && executable
+                    .get_loader()
+                    .get_function_registry()
+                    .lookup_by_key(insn.imm as u32)
+                    .is_some() =>
+        {
+            Step::Syscall
+        }
+        ebpf::CALL_IMM | ebpf::CALL_REG => Step::Call,
+        ebpf::SYSCALL if static_syscalls => Step::Syscall,
+        ebpf::EXIT if !static_syscalls => Step::Return,
+        ebpf::RETURN if static_syscalls => Step::Return,
+        _ => Step::Other,
+    }
+}
+
+/// What one run executed, counted from the instruction trace rather than the compute meter, so
+/// it stays the same whatever the runtime charges for syscalls
+pub struct ExecutionCounts {
+    pub instructions: u64,
+    /// BPF-to-BPF calls, libcalls included
+    pub calls: u64,
+    pub syscalls: u64,
+    pub result: Result<u64, EbpfError>,
+}
+
+/// Execute `elf` once with `data` as instruction data and count what it executed
+pub fn count_executed(elf: &[u8], data: &[u8]) -> Result<ExecutionCounts> {
+    let executable = load(elf, true)?;
+    let (context, _, result) = run(&executable, data)?;
+
+    let mut counts = ExecutionCounts {
+        instructions: context.trace_log.len() as u64,
+        calls: 0,
+        syscalls: 0,
+        result,
+    };
+    // Register 11 of each trace entry is the pc of the instruction about to execute
+    for state in &context.trace_log {
+        match step(&executable, state[11] as usize) {
+            Step::Call => counts.calls += 1,
+            Step::Syscall => counts.syscalls += 1,
+            Step::Return | Step::Other => {}
+        }
+    }
+    Ok(counts)
+}
+
+/// The syscalls one run made and how often it loaded from or stored to the heap, from the
+/// instruction trace
+pub struct SideEffects {
+    pub syscalls: BTreeSet<String>,
+    pub heap_accesses: u64,
+    pub result: Result<u64, EbpfError>,
+}
+
+/// Address the load or store at `pc` accesses, from the registers before it ran, or `None` if
+/// the instruction doesn't access memory
+fn memory_address(executable: &Executable<RunContext>, pc: usize, registers: &[u64]) -> Option<u64> {
+    let insn = ebpf::get_insn(executable.get_text_bytes().1, pc);
+    // SBPF v2 moved loads and stores into the ALU classes
+    let moved = executable.get_sbpf_version().move_memory_instruction_classes();
+    let base = match insn.opc {
+        ebpf::LD_B_REG | ebpf::LD_H_REG | ebpf::LD_W_REG | ebpf::LD_DW_REG if !moved => insn.src,
+        ebpf::ST_B_IMM | ebpf::ST_H_IMM | ebpf::ST_W_IMM | ebpf::ST_DW_IMM if !moved => insn.dst,
+        ebpf::ST_B_REG | ebpf::ST_H_REG | ebpf::ST_W_REG | ebpf::ST_DW_REG if !moved => insn.dst,
+        ebpf::LD_1B_REG | ebpf::LD_2B_REG | ebpf::LD_4B_REG | ebpf::LD_8B_REG if moved => insn.src,
+        ebpf::ST_1B_IMM | ebpf::ST_2B_IMM | ebpf::ST_4B_IMM | ebpf::ST_8B_IMM if moved => insn.dst,
+        ebpf::ST_1B_REG | ebpf::ST_2B_REG | ebpf::ST_4B_REG | ebpf::ST_8B_REG if moved => insn.dst,
+        _ => return None,
+    };
+    Some(registers[base as usize].wrapping_add_signed(insn.off.into()))
+}
+
+/// Execute `elf` once with `data` as instruction data and record the syscalls it made by name
+/// and the loads and stores that landed in the heap region
+pub fn side_effects(elf: &[u8], data: &[u8]) -> Result<SideEffects> {
+    let executable = load(elf, true)?;
+    let (context, _, result) = run(&executable, data)?;
+    let heap = ebpf::MM_HEAP_START..ebpf::MM_HEAP_START + HEAP_SIZE as u64;
+
+    let mut effects = SideEffects {
+        syscalls: BTreeSet::new(),
+        heap_accesses: 0,
+        result,
+    };
+    for state in &context.trace_log {
+        let pc = state[11] as usize;
+        if step(&executable, pc) == Step::Syscall {
+            let insn = ebpf::get_insn(executable.get_text_bytes().1, pc);
+            let name = executable
+                .get_loader()
+                .get_function_registry()
+                .lookup_by_key(insn.imm as u32)
+                .map_or_else(|| format!("<unknown {:#x}>", insn.imm), |(name, _)| {
+                    String::from_utf8_lossy(name).into_owned()
+                });
+            effects.syscalls.insert(name);
+        }
+        if memory_address(&executable, pc, &state[..11]).is_some_and(|address| heap.contains(&address)) {
+            effects.heap_accesses += 1;
+        }
+    }
+    Ok(effects)
+}
+
+/// Executed instructions attributed to one function
+#[derive(Clone, Default)]
+pub struct FunctionProfile {
+    pub calls: u64,
+    /// Instructions executed in the function's own body
+    pub self_instructions: u64,
+    /// Instructions executed while the function was on the call stack, callees included
+    pub inclusive_instructions: u64,
+}
+
+/// Where one run spent its instructions, per function the loader registered
+pub struct Profile {
+    pub instructions: u64,
+    pub functions: BTreeMap<String, FunctionProfile>,
+    /// Instructions executed per call stack (outermost function first) and pc
+    pub stacks: BTreeMap<(Vec<String>, usize), u64>,
+    pub result: Result<u64, EbpfError>,
+}
+
+/// Execute `elf` once with `data` as instruction data and attribute every traced instruction to
+/// the function containing it and to the functions on the call stack
+pub fn profile(elf: &[u8], data: &[u8]) -> Result<Profile> {
+    let executable = load(elf, true)?;
+    let (context, _, result) = run(&executable, data)?;
+    let analysis = Analysis::from_executable(&executable)
+        .map_err(|err| anyhow!("failed to analyze executable: {err}"))?;
+    let function_of = |pc: usize| {
+        analysis
+            .functions
+            .range(..=pc)
+            .next_back()
+            .map_or("<unknown>", |(_, (_, name))| name.as_str())
+    };
+
+    let mut functions: BTreeMap<String, FunctionProfile> = BTreeMap::new();
+    let mut stacks: BTreeMap<(Vec<&str>, usize), u64> = BTreeMap::new();
+    let mut stack: Vec<&str> = Vec::new();
+    let mut entering = true;
+    for state in &context.trace_log {
+        let pc = state[11] as usize;
+        let function = function_of(pc);
+        if entering {
+            stack.push(function);
+            functions.entry(function.to_string()).or_default().calls += 1;
+            entering = false;
+        }
+        functions.entry(function.to_string()).or_default().self_instructions += 1;
+        *stacks.entry((stack.clone(), pc)).or_default() += 1;
+        // Count a recursive function once per instruction, not once per frame
+        for (depth, &caller) in stack.iter().enumerate() {
+            if !stack[..depth].contains(&caller) {
+                functions.entry(caller.to_string()).or_default().inclusive_instructions += 1;
+            }
+        }
+        match step(&executable, pc) {
+            Step::Call => entering = true,
+            Step::Return => {
+                stack.pop();
+            }
+            Step::Syscall | Step::Other => {}
+        }
+    }
+    let stacks = stacks
+        .into_iter()
+        .map(|((stack, pc), count)| ((stack.into_iter().map(str::to_string).collect(), pc), count))
+        .collect();
+    Ok(Profile {
+        instructions: context.trace_log.len() as u64,
+        functions,
+        stacks,
+        result,
+    })
+}
+
+/// Decode a hex string, tolerating an optional `0x` prefix and embedded whitespace
+pub fn decode_hex(hex: &str) -> Result<Vec<u8>> {
+    let digits = hex
+        .trim_start_matches("0x")
+        .chars()
+        .filter(|c| !c.is_whitespace())
+        .map(|c| c.to_digit(16).map(|digit| digit as u8).ok_or_else(|| anyhow!("invalid hex digit {c:?}")))
+        .collect::<Result<Vec<_>>>()?;
+    if !digits.len().is_multiple_of(2) {
+        anyhow::bail!("hex input has an odd number of digits");
+    }
+    Ok(digits.chunks(2).map(|pair| pair[0] << 4 | pair[1]).collect())
+}
+
+/// Encode bytes as lowercase hex without a prefix
+pub fn encode_hex(bytes: &[u8]) -> String {
+    bytes.iter().map(|b| format!("{b:02x}")).collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn decode_hex_rejects_non_hex_digits() {
+        assert_eq!(decode_hex("0x0a ff\n10").unwrap(), [0x0a, 0xff, 0x10]);
+        let error = decode_hex("aéa").unwrap_err().to_string();
+        assert!(error.contains("'é'"), "{error}");
+        assert!(decode_hex("abc").is_err());
+    }
+}